@@ -7,4 +7,27 @@ fn main() {
             String::from_utf8(output.stdout).unwrap()
         );
     }
+
+    // Without meson, the bundle has to be compiled here to be included in the binary
+    if std::env::var_os("CARGO_FEATURE_EMBED_RESOURCES").is_some() {
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        let target = std::path::Path::new(&out_dir).join("vimdicator.gresource");
+        let status = Command::new("glib-compile-resources")
+            .arg("--sourcedir=src")
+            .arg(format!("--target={}", target.display()))
+            .arg("src/vimdicator.gresource.xml")
+            .status()
+            .expect("glib-compile-resources is needed for the embed-resources feature");
+        assert!(status.success(), "Could not compile the resources");
+
+        // GSettings only finds the installed schemas, `settings::settings()` loads this one
+        let schemas = std::path::Path::new(&out_dir).join("schemas");
+        std::fs::create_dir_all(&schemas).unwrap();
+        let status = Command::new("glib-compile-schemas")
+            .arg(format!("--targetdir={}", schemas.display()))
+            .arg("data")
+            .status()
+            .expect("glib-compile-schemas is needed for the embed-resources feature");
+        assert!(status.success(), "Could not compile the settings schema");
+    }
 }
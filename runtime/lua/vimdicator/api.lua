@@ -0,0 +1,46 @@
+-- GUI state for plugins, available as `require("vimdicator.api")`. Each function waits for
+-- the GUI to answer and errors when nvim isn't attached to vimdicator, check `M.available()`.
+
+local vimdicator = require("vimdicator")
+
+local M = {}
+
+--- Whether nvim is attached to vimdicator
+---@return boolean
+function M.available()
+  return vim.g.vimdicator_channel ~= nil
+end
+
+--- Size of the grid in cells and of the window in pixels
+---@return { columns: integer, rows: integer, width: integer, height: integer }
+function M.window_size()
+  return vimdicator.rpcrequest("WindowSize")
+end
+
+--- Let the user pick a file in the native dialog, see `require("vimdicator.ui")` for options
+---@return string|nil path, nil when the dialog was dismissed
+function M.pick_file()
+  return require("vimdicator.ui").open_file_dialog()
+end
+
+--- Open or close a panel
+---@param panel "quickfix"|"registers"|"search"
+---@param open boolean|nil nil toggles the panel
+function M.set_panel(panel, open)
+  vimdicator.rpcrequest("Panel", panel, open)
+end
+
+--- Show a toast in the window, unlike `vim.notify` it isn't routed by the notify-* settings
+---@param message string
+---@param timeout integer|nil seconds, 0 keeps it until dismissed, 5 by default
+function M.toast(message, timeout)
+  vimdicator.rpcrequest("Toast", message, timeout)
+end
+
+--- Put text on the system clipboard, without going through a register
+---@param text string
+function M.set_clipboard(text)
+  vimdicator.rpcrequest("SetClipboard", text)
+end
+
+return M
@@ -0,0 +1,129 @@
+-- Images drawn by the GUI over the grid, available as `require("vimdicator.image")`.
+-- Each image is anchored to an extmark, so it moves with the text around it.
+
+local M = {}
+
+local ns = vim.api.nvim_create_namespace("vimdicator_image")
+
+--- Placed images by id: { buf, mark, path, width, height }
+local images = {}
+local next_id = 1
+
+--- Placements last sent to the GUI, nothing is sent while they stay the same
+local sent = {}
+
+local group = nil
+
+--- Screen positions of the placed images in the windows of the current tabpage, as sent in
+--- the "Images" notification: { id, path, row, col, width, height, clip } with 0-based grid
+--- cells, and clip the { row, col, rows, columns } of the window text area
+local function placements()
+  local list = {}
+  for _, win in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+    local buf = vim.api.nvim_win_get_buf(win)
+    local info = vim.fn.getwininfo(win)[1]
+    local clip = { info.winrow - 1, info.wincol - 1 + info.textoff, info.height, info.width - info.textoff }
+
+    for id, image in pairs(images) do
+      if image.buf == buf then
+        local pos = vim.api.nvim_buf_get_extmark_by_id(buf, ns, image.mark, {})
+        if pos[1] and pos[1] + 1 >= info.topline and pos[1] + 1 <= info.botline then
+          local screen = vim.fn.screenpos(win, pos[1] + 1, pos[2] + 1)
+          if screen.row > 0 then
+            table.insert(list, {
+              id,
+              image.path,
+              screen.row - 1,
+              screen.col - 1,
+              image.width or 0,
+              image.height or 0,
+              clip,
+            })
+          end
+        end
+      end
+    end
+  end
+  table.sort(list, function(a, b)
+    return a[1] < b[1]
+  end)
+  return list
+end
+
+--- Tell the GUI where the images are now, after scrolling, edits or layout changes
+function M.sync()
+  for id, image in pairs(images) do
+    if not vim.api.nvim_buf_is_valid(image.buf) then
+      images[id] = nil
+    end
+  end
+
+  local list = placements()
+  if not vim.deep_equal(list, sent) then
+    sent = list
+    require("vimdicator").rpcnotify("Images", list)
+  end
+end
+
+local function watch()
+  if group then
+    return
+  end
+  group = vim.api.nvim_create_augroup("VimdicatorImage", { clear = true })
+  vim.api.nvim_create_autocmd({
+    "WinScrolled",
+    "WinResized",
+    "BufWinEnter",
+    "BufWipeout",
+    "TabEnter",
+    "TextChanged",
+    "TextChangedI",
+    "VimResized",
+  }, {
+    group = group,
+    callback = function()
+      -- Window positions are only final once the autocommands are done
+      vim.schedule(M.sync)
+    end,
+  })
+end
+
+--- Show the image file `path` over buffer `buf` (0 for the current one) from the 0-based
+--- `row` and `col` on, like an extmark.
+---@param opts table|nil `width` and `height` in cells, with only one of them the aspect
+--- ratio is kept and without both the image has its own size. The image covers the text,
+--- add `virt_lines` to make room for it.
+---@return integer id for `clear()`
+function M.place(buf, row, col, path, opts)
+  opts = opts or {}
+  buf = buf == 0 and vim.api.nvim_get_current_buf() or buf
+
+  local id = next_id
+  next_id = next_id + 1
+  images[id] = {
+    buf = buf,
+    mark = vim.api.nvim_buf_set_extmark(buf, ns, row, col, {}),
+    path = vim.fn.fnamemodify(path, ":p"),
+    width = opts.width,
+    height = opts.height,
+  }
+
+  watch()
+  M.sync()
+  return id
+end
+
+--- Remove the image `id`, or all images without an id
+function M.clear(id)
+  for image_id, image in pairs(images) do
+    if id == nil or image_id == id then
+      if vim.api.nvim_buf_is_valid(image.buf) then
+        vim.api.nvim_buf_del_extmark(image.buf, ns, image.mark)
+      end
+      images[image_id] = nil
+    end
+  end
+  M.sync()
+end
+
+return M
@@ -0,0 +1,1039 @@
+-- Helpers for talking to the vimdicator GUI, available as `require("vimdicator")`
+
+local M = {}
+
+local channel = nil
+
+-- Root the GUI's path bar shows, "" while the file browser is closed
+local browsing = nil
+local browsing_subscribed = false
+
+--- Called by the GUI once the UI is attached
+function M._attach(chan)
+  channel = chan
+  vim.g.vimdicator_channel = chan
+  M._wrap_signature_help()
+
+  -- Signs drawn by the native gutter follow diagnostics
+  vim.api.nvim_create_autocmd("DiagnosticChanged", {
+    group = vim.api.nvim_create_augroup("VimdicatorGutter", { clear = true }),
+    callback = function()
+      M.rpcnotify("GutterChanged")
+    end,
+  })
+
+  -- The native quickfix panel follows :make, :grep, :vimgrep and friends
+  vim.api.nvim_create_autocmd("QuickFixCmdPost", {
+    group = vim.api.nvim_create_augroup("VimdicatorQuickfix", { clear = true }),
+    pattern = "[^l]*",
+    callback = function()
+      M.sync_quickfix()
+    end,
+  })
+
+  -- The native register panel follows yanks and recorded macros
+  vim.api.nvim_create_autocmd({ "TextYankPost", "RecordingLeave" }, {
+    group = vim.api.nvim_create_augroup("VimdicatorRegisters", { clear = true }),
+    callback = function()
+      -- The recorded register is only set once RecordingLeave returns
+      vim.schedule(M.sync_registers)
+    end,
+  })
+
+  -- Projects may override some GUI settings in a .vimdicator.toml
+  vim.api.nvim_create_autocmd("DirChanged", {
+    group = vim.api.nvim_create_augroup("VimdicatorProject", { clear = true }),
+    callback = function()
+      M.sync_project_config()
+    end,
+  })
+  vim.schedule(M.sync_project_config)
+
+  -- Images and PDFs selected in the file browser are previewed by the GUI
+  vim.api.nvim_create_autocmd({ "CursorMoved", "WinEnter", "BufEnter" }, {
+    group = vim.api.nvim_create_augroup("VimdicatorPreview", { clear = true }),
+    callback = function()
+      vim.schedule(M.sync_file_preview)
+    end,
+  })
+
+  -- The GUI's path bar shows the root of the file browser while it is open, Ctrl+L in the
+  -- browser types a path for it
+  local browser = vim.api.nvim_create_augroup("VimdicatorFileBrowser", { clear = true })
+  vim.api.nvim_create_autocmd({ "BufWinEnter", "WinClosed", "TabEnter" }, {
+    group = browser,
+    callback = function()
+      vim.schedule(M.sync_file_browser)
+    end,
+  })
+  vim.api.nvim_create_autocmd("FileType", {
+    group = browser,
+    pattern = "NvimTree",
+    callback = function(args)
+      -- After nvim-tree's own mappings, which win
+      vim.schedule(function()
+        if vim.api.nvim_buf_is_valid(args.buf) and vim.fn.maparg("<C-l>", "n", false, true).buffer ~= 1 then
+          vim.keymap.set("n", "<C-l>", function()
+            M.rpcnotify("EditPath")
+          end, { buffer = args.buf, desc = "Type the path of a directory to show" })
+        end
+      end)
+    end,
+  })
+  vim.api.nvim_create_autocmd("BufWritePost", {
+    group = browser,
+    callback = function()
+      if browsing and browsing ~= "" then
+        M.rpcnotify("GitChanged")
+      end
+    end,
+  })
+  vim.schedule(M.sync_file_browser)
+
+  -- Pinching sends these with the pinch-action setting at "keys". Unmapped, nvim would run
+  -- the "(VimdicatorScaleUp)" after <Plug> as typed keys.
+  for _, lhs in ipairs({ "<Plug>(VimdicatorScaleUp)", "<Plug>(VimdicatorScaleDown)" }) do
+    for _, mode in ipairs({ "n", "x", "s", "o", "i", "c", "t" }) do
+      if vim.fn.maparg(lhs, mode) == "" then
+        vim.keymap.set(mode, lhs, "<Nop>")
+      end
+    end
+  end
+
+  -- Messages end up as toasts or desktop notifications, see the notify-* settings
+  vim.notify = function(msg, level, _opts)
+    M.notify(msg, level)
+  end
+end
+
+--- Send a notification to the GUI, does nothing when not running under vimdicator
+function M.rpcnotify(event, ...)
+  if channel then
+    vim.rpcnotify(channel, "Gui", event, ...)
+  end
+end
+
+--- Ask the GUI and wait for its answer, errors when not running under vimdicator or when the
+--- GUI could not answer. Plugins should use `require("vimdicator.api")`.
+function M.rpcrequest(request, ...)
+  if not channel then
+    error("Not running under vimdicator")
+  end
+  return vim.rpcrequest(channel, "Gui", request, ...)
+end
+
+--- Show a message in the GUI
+---@param msg string
+---@param level integer|nil one of vim.log.levels
+function M.notify(msg, level)
+  M.rpcnotify("Notify", msg, level or vim.log.levels.INFO)
+end
+
+--- Report progress of a long-running job, shown as a progress bar in the header bar.
+--- Jobs are identified by `id`, "stop" removes the bar.
+---@param action "start"|"update"|"stop"
+---@param id string|integer
+---@param percent number|nil 0-100, nil pulses the bar when the progress is unknown
+---@param title string|nil kept from the previous call when nil
+function M.progress(action, id, percent, title)
+  M.rpcnotify("Progress", action, tostring(id), percent or -1, title or "")
+end
+
+--- Change the GUI font, accepts 'guifont' syntax e.g. "Source Code Pro:h11"
+---@param font string
+function M.set_font(font)
+  vim.o.guifont = font
+end
+
+--- Buffer position and window shown at a 0-based screen cell of the default grid.
+--- Folds and wrapped lines above the cell are not accounted for.
+local function screen_to_buffer(row, col)
+  for _, win in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+    local info = vim.fn.getwininfo(win)[1]
+    local top, left = info.winrow - 1, info.wincol - 1 + info.textoff
+
+    if row >= top and row < top + info.height and col >= left and col < info.wincol - 1 + info.width then
+      local lnum = info.topline + row - top
+      if lnum > vim.api.nvim_buf_line_count(info.bufnr) then
+        return nil
+      end
+
+      local leftcol = vim.api.nvim_win_call(win, function()
+        return vim.fn.winsaveview().leftcol
+      end)
+      local byte = vim.fn.virtcol2col(win, lnum, col - left + leftcol + 1)
+      if byte <= 0 then
+        return nil
+      end
+
+      return info.bufnr, lnum, byte, win
+    end
+  end
+end
+
+--- Jump to the match of the GUI find bar at a 0-based screen cell, or to the next match after
+--- the cursor without a cell. Leaves insert and terminal mode first, and makes `pattern` the
+--- last search pattern like `/` would.
+function M.find_jump(pattern, row, col)
+  vim.fn.setreg("/", pattern)
+  vim.fn.histadd("/", pattern)
+  vim.v.hlsearch = 1
+
+  local _, lnum, byte, win
+  if row then
+    _, lnum, byte, win = screen_to_buffer(row, col)
+  end
+
+  local function jump()
+    vim.cmd("normal! m'")
+    if win then
+      vim.api.nvim_set_current_win(win)
+      vim.api.nvim_win_set_cursor(win, { lnum, byte - 1 })
+    else
+      vim.fn.search(pattern)
+    end
+  end
+
+  -- Leaving insert mode moves the cursor left, so only jump once it's done
+  if vim.api.nvim_get_mode().mode:match("^[iRt]") then
+    vim.api.nvim_create_autocmd("ModeChanged", { pattern = "*:n*", once = true, callback = jump })
+    vim.cmd.stopinsert()
+  else
+    jump()
+  end
+end
+
+--- Request LSP hover documentation for a screen cell, answered with a "Hover" GUI event
+function M.hover(grid, row, col)
+  local buf, lnum, byte = screen_to_buffer(row, col)
+  if not buf then
+    return
+  end
+
+  local client = vim.lsp.get_active_clients({ bufnr = buf })[1]
+  if not client then
+    return
+  end
+
+  local params = {
+    textDocument = vim.lsp.util.make_text_document_params(buf),
+    position = {
+      line = lnum - 1,
+      character = vim.lsp.util.character_offset(buf, lnum - 1, byte - 1, client.offset_encoding),
+    },
+  }
+
+  vim.lsp.buf_request_all(buf, "textDocument/hover", params, function(results)
+    local lines = {}
+    for _, res in pairs(results) do
+      if res.result and res.result.contents then
+        vim.list_extend(lines, vim.lsp.util.convert_input_to_markdown_lines(res.result.contents))
+      end
+    end
+
+    local markdown = vim.trim(table.concat(lines, "\n"))
+    if markdown ~= "" then
+      M.rpcnotify("Hover", grid, row, col, markdown)
+    end
+  end)
+end
+
+--- Label of the active signature and the 0-based byte range of its active parameter
+local function active_signature(result)
+  local signature = result.signatures[(result.activeSignature or 0) + 1] or result.signatures[1]
+  local label = signature.label
+
+  local index = signature.activeParameter or result.activeParameter or 0
+  local param = signature.parameters and signature.parameters[index + 1]
+  if not param then
+    return label, -1, -1
+  end
+
+  if type(param.label) == "table" then
+    -- UTF-16 offsets into the signature label
+    return label, vim.str_byteindex(label, param.label[1], true), vim.str_byteindex(label, param.label[2], true)
+  end
+
+  local start, finish = label:find(param.label, 1, true)
+  if not start then
+    return label, -1, -1
+  end
+  return label, start - 1, finish
+end
+
+--- Show LSP signature help in the GUI instead of a floating window,
+--- answered with "SignatureHelp" GUI events, an empty label hides it
+function M._wrap_signature_help()
+  local method = "textDocument/signatureHelp"
+  local default = vim.lsp.handlers[method]
+
+  vim.lsp.handlers[method] = function(err, result, ctx, config)
+    if not channel then
+      return default(err, result, ctx, config)
+    end
+
+    if err or not result or not result.signatures or vim.tbl_isempty(result.signatures) then
+      M.rpcnotify("SignatureHelp", "", -1, -1)
+      return
+    end
+
+    M.rpcnotify("SignatureHelp", active_signature(result))
+  end
+end
+
+--- Highest priority sign of each line in `top`..`bot` of `buf` as { text, "#rrggbb" or "" }
+local function line_signs(buf, top, bot)
+  local signs = {}
+  local ok, marks = pcall(vim.api.nvim_buf_get_extmarks, buf, -1, { top - 1, 0 }, { bot - 1, -1 }, {
+    details = true,
+    type = "sign",
+  })
+  if not ok then
+    return signs
+  end
+
+  for _, mark in ipairs(marks) do
+    local lnum, details = mark[2] + 1, mark[4]
+    local priority = details.priority or 0
+    local current = signs[lnum]
+
+    if details.sign_text and (not current or priority > current.priority) then
+      local hl = details.sign_hl_group and vim.api.nvim_get_hl(0, { name = details.sign_hl_group, link = false }) or {}
+      signs[lnum] = {
+        text = vim.trim(details.sign_text),
+        color = hl.fg and string.format("#%06x", hl.fg) or "",
+        priority = priority,
+      }
+    end
+  end
+
+  return signs
+end
+
+--- Line number and sign of each screen row of the current window, for the native gutter.
+--- Returns the 0-based grid row of the window and { lnum, sign, color } per row,
+--- lnum is 0 on the continuation rows of wrapped lines.
+function M.gutter()
+  local win = vim.api.nvim_get_current_win()
+  local info = vim.fn.getwininfo(win)[1]
+  local signs = line_signs(info.bufnr, info.topline, info.botline)
+
+  local rows = {}
+  local lnum = info.topline
+  while #rows < info.height and lnum <= info.botline do
+    local fold_end = vim.fn.foldclosedend(lnum)
+    local height = 1
+    if fold_end == -1 and vim.wo[win].wrap and vim.api.nvim_win_text_height then
+      height = vim.api.nvim_win_text_height(win, { start_row = lnum - 1, end_row = lnum - 1 }).all
+    end
+
+    local sign = signs[lnum] or { text = "", color = "" }
+    table.insert(rows, { lnum, sign.text, sign.color })
+    for _ = 2, height do
+      table.insert(rows, { 0, "", "" })
+    end
+
+    lnum = fold_end == -1 and lnum + 1 or fold_end + 1
+  end
+
+  return { info.winrow - 1, rows }
+end
+
+--- Clicks on the native gutter move the cursor to `lnum`, clicking a sign shows its diagnostics
+function M.gutter_click(lnum, on_sign)
+  vim.api.nvim_win_set_cursor(0, { lnum, 0 })
+  if on_sign then
+    vim.diagnostic.open_float()
+  end
+end
+
+--- Rows of windows without 'wrap' whose line continues past the left or right edge, for the
+--- fade-out hints of the grid. Returns { row, col, width, flags } per window with such rows:
+--- the 0-based grid position and width of its text area, and per screen row 1 when text is
+--- scrolled off the left plus 2 when text continues on the right.
+function M.overflow()
+  local windows = {}
+  for _, win in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+    local info = vim.fn.getwininfo(win)[1]
+    if not vim.wo[win].wrap and vim.api.nvim_win_get_config(win).relative == "" then
+      local width = info.width - info.textoff
+      local flags, hidden = {}, false
+      vim.api.nvim_win_call(win, function()
+        local leftcol = vim.fn.winsaveview().leftcol
+        local lnum = info.topline
+        while #flags < info.height and lnum <= info.botline do
+          local fold_end = vim.fn.foldclosedend(lnum)
+          local flag = 0
+          if fold_end == -1 then
+            local line_width = vim.fn.virtcol({ lnum, "$" }) - 1
+            if leftcol > 0 and line_width > 0 then
+              flag = flag + 1
+            end
+            if line_width > leftcol + width then
+              flag = flag + 2
+            end
+          end
+          hidden = hidden or flag > 0
+          table.insert(flags, flag)
+          lnum = fold_end == -1 and lnum + 1 or fold_end + 1
+        end
+      end)
+
+      if hidden then
+        table.insert(windows, { info.winrow - 1, info.wincol - 1 + info.textoff, width, flags })
+      end
+    end
+  end
+  return windows
+end
+
+--- Send the quickfix list to the native panel
+---@param show boolean|nil open the panel too
+function M.sync_quickfix(show)
+  local list = vim.fn.getqflist({ title = 0, idx = 0, items = 0 })
+  local items = {}
+  for _, item in ipairs(list.items) do
+    local path = item.bufnr > 0 and vim.fn.fnamemodify(vim.api.nvim_buf_get_name(item.bufnr), ":.") or ""
+    table.insert(items, { path, item.lnum, item.col, item.type, item.text, item.valid == 1 })
+  end
+  M.rpcnotify("Quickfix", list.title, list.idx, items, show == true)
+end
+
+--- Report a failed command run for a GUI action, without the "Vim(cmd):" prefix
+local function notify_error(err)
+  vim.notify((tostring(err):gsub("^Vim%(%a+%):", "")), vim.log.levels.WARN)
+end
+
+--- Move in the quickfix list from the native panel, `cmd` is e.g. "cnext" or "cc 3"
+function M.quickfix_command(cmd)
+  local ok, err = pcall(vim.cmd, cmd)
+  if not ok then
+    notify_error(err)
+  end
+  M.sync_quickfix()
+end
+
+--- { label, path, lnum, text } of a mark or jump, the text is only known for loaded buffers
+local function location(label, bufnr, path, lnum)
+  local text = ""
+  if bufnr > 0 and vim.api.nvim_buf_is_loaded(bufnr) then
+    path = vim.api.nvim_buf_get_name(bufnr)
+    text = vim.api.nvim_buf_get_lines(bufnr, lnum - 1, lnum, false)[1] or ""
+  end
+  return { label, vim.fn.fnamemodify(path, ":~:."), lnum, vim.trim(text) }
+end
+
+--- Marks and jump list of the current window for the native navigator, as { marks, jumps }.
+--- Jumps are newest first and labeled with their count relative to the current position.
+function M.locations()
+  local marks = {}
+  local buf = vim.api.nvim_get_current_buf()
+  for _, mark in ipairs(vim.fn.getmarklist(buf)) do
+    local name = mark.mark:sub(2)
+    if name:match("^[a-z]$") then
+      table.insert(marks, location(name, buf, "", mark.pos[2]))
+    end
+  end
+  for _, mark in ipairs(vim.fn.getmarklist()) do
+    local name = mark.mark:sub(2)
+    if name:match("^[A-Z]$") then
+      table.insert(marks, location(name, mark.pos[1], mark.file, mark.pos[2]))
+    end
+  end
+
+  local jumps = {}
+  local list, current = unpack(vim.fn.getjumplist())
+  for i = #list, 1, -1 do
+    local jump = list[i]
+    local path = jump.filename or vim.fn.bufname(jump.bufnr)
+    table.insert(jumps, location(tostring(i - 1 - current), jump.bufnr, path, jump.lnum))
+  end
+
+  return { marks, jumps }
+end
+
+--- Go to a mark, or `count` entries along the jump list (backwards when negative)
+---@param mark string|nil
+---@param count integer|nil
+function M.goto_location(mark, count)
+  local keys
+  if mark then
+    keys = "`" .. mark
+  elseif count < 0 then
+    keys = -count .. vim.api.nvim_replace_termcodes("<C-o>", true, false, true)
+  elseif count > 0 then
+    keys = count .. vim.api.nvim_replace_termcodes("<C-i>", true, false, true)
+  else
+    return
+  end
+
+  local ok, err = pcall(vim.cmd.normal, { keys, bang = true })
+  if not ok then
+    notify_error(err)
+  end
+end
+
+-- The clipboard registers are left out, reading them goes through the (slow) clipboard provider
+local REGISTERS = '"0123456789abcdefghijklmnopqrstuvwxyz-.:%/#'
+
+--- Send the non-empty registers to the native register panel
+---@param show boolean|nil open the panel too
+function M.sync_registers(show)
+  local registers = {}
+  for name in REGISTERS:gmatch(".") do
+    local info = vim.fn.getreginfo(name)
+    if info.regcontents and #info.regcontents > 0 then
+      table.insert(registers, { name, info.regtype or "v", table.concat(info.regcontents, "\n") })
+    end
+  end
+  M.rpcnotify("Registers", registers, show == true)
+end
+
+--- Empty a register from the native panel
+function M.clear_register(name)
+  vim.fn.setreg(name, {})
+  M.sync_registers()
+end
+
+-- Groups of the colorscheme previews, the order `Colorscheme` expects their foregrounds in
+local PREVIEW_GROUPS = { "Normal", "Comment", "Statement", "String", "Function", "Type", "Constant" }
+
+--- Normal background and the foreground of each preview group as "#rrggbb" or ""
+local function preview_colors()
+  local function hex(color)
+    return color and string.format("#%06x", color) or ""
+  end
+
+  local foregrounds = {}
+  for _, group in ipairs(PREVIEW_GROUPS) do
+    table.insert(foregrounds, hex(vim.api.nvim_get_hl(0, { name = group, link = false }).fg))
+  end
+  return hex(vim.api.nvim_get_hl(0, { name = "Normal", link = false }).bg), foregrounds
+end
+
+--- Every installed colorscheme as { name, background, normal_bg, foregrounds }, found by
+--- applying each one in turn. The highlight groups, overrides included, and `background` are
+--- restored afterwards.
+function M.colorschemes()
+  local colors_name, background = vim.g.colors_name, vim.o.background
+  local current = colors_name or "default"
+  -- Every group as it is now, including :highlight overrides on top of the colorscheme
+  local highlights = vim.api.nvim_get_hl(0, {})
+  local eventignore = vim.o.eventignore
+  -- Statuslines and other plugins following the colorscheme don't need to see the previews
+  vim.o.eventignore = (eventignore == "" and "" or eventignore .. ",") .. "ColorSchemePre,ColorScheme"
+
+  local schemes = {}
+  for _, name in ipairs(vim.fn.getcompletion("", "color")) do
+    if pcall(vim.cmd.colorscheme, name) then
+      local normal_bg, foregrounds = preview_colors()
+      table.insert(schemes, { name, vim.o.background, normal_bg, foregrounds })
+    end
+  end
+
+  -- Loading the colorscheme again would lose the overrides, the groups are put back instead.
+  -- Without a colors_name, setting 'background' doesn't reload a colorscheme either.
+  vim.g.colors_name = nil
+  vim.o.background = background
+  vim.cmd.highlight("clear")
+  for name, attrs in pairs(highlights) do
+    vim.api.nvim_set_hl(0, name, attrs)
+  end
+  vim.g.colors_name = colors_name
+
+  vim.o.eventignore = eventignore
+  return { current, background, schemes }
+end
+
+-- Marks the line of ginit.lua the colorscheme dialog manages
+local COLORSCHEME_MARKER = "-- Set by the vimdicator colorscheme dialog"
+
+--- Apply `name` on the next starts too, by (re)writing its line in ginit.lua
+local function persist_colorscheme(name)
+  local path = vim.fn.stdpath("config") .. "/ginit.lua"
+  local lines = vim.fn.filereadable(path) == 1 and vim.fn.readfile(path) or {}
+  local line = string.format("vim.cmd.colorscheme(%q) %s", name, COLORSCHEME_MARKER)
+
+  local replaced = false
+  for i, existing in ipairs(lines) do
+    if existing:find(COLORSCHEME_MARKER, 1, true) then
+      lines[i] = line
+      replaced = true
+    end
+  end
+  if not replaced then
+    table.insert(lines, line)
+  end
+
+  vim.fn.mkdir(vim.fn.fnamemodify(path, ":h"), "p")
+  if vim.fn.writefile(lines, path) ~= 0 then
+    notify_error("Failed to write " .. path)
+  end
+end
+
+--- Apply a colorscheme from the colorscheme dialog
+---@param persist boolean|nil also apply it on the next starts
+---@param background string|nil "dark" or "light" to set afterwards
+function M.set_colorscheme(name, persist, background)
+  local ok, err = pcall(vim.cmd.colorscheme, name)
+  if not ok then
+    notify_error(err)
+    return
+  end
+  if background and vim.o.background ~= background then
+    vim.o.background = background
+  end
+  if persist then
+    persist_colorscheme(name)
+  end
+end
+
+-- Largest .vimdicator.toml read, anything bigger is not a settings file
+local PROJECT_CONFIG_MAX_SIZE = 64 * 1024
+
+-- Watches the .vimdicator.toml in effect, it is sent again when it changes
+local project_watcher = nil
+
+--- Send the .vimdicator.toml of the working directory or one of its parents to the GUI,
+--- with an empty path when there is none
+---@param reload boolean|nil report invalid settings again, for :VimdicatorReloadConfig
+function M.sync_project_config(reload)
+  local path = vim.fs.find(".vimdicator.toml", { upward = true, path = vim.fn.getcwd(), type = "file" })[1]
+  local contents = ""
+  if path and vim.fn.getfsize(path) > PROJECT_CONFIG_MAX_SIZE then
+    notify_error(path .. " is too large, ignoring it")
+    path = nil
+  elseif path then
+    contents = table.concat(vim.fn.readfile(path), "\n")
+  end
+
+  if project_watcher then
+    project_watcher:close()
+    project_watcher = nil
+  end
+  if path then
+    -- Saving may replace the file, each sync watches the current one
+    project_watcher = (vim.uv or vim.loop).new_fs_event()
+    project_watcher:start(path, {}, vim.schedule_wrap(function()
+      M.sync_project_config()
+    end))
+  end
+
+  M.rpcnotify("ProjectConfig", path or "", contents, reload == true)
+end
+
+-- Whether nvim-tree was visible before a project opened or closed it
+local sidebar_before = nil
+
+--- Open or close the nvim-tree file browser for a project, nil restores how it was before
+---@param open boolean|nil
+function M.set_sidebar(open)
+  local ok, api = pcall(require, "nvim-tree.api")
+  if not ok then
+    return
+  end
+
+  local visible = api.tree.is_visible()
+  if open == nil then
+    if sidebar_before == nil then
+      return
+    end
+    open, sidebar_before = sidebar_before, nil
+  elseif sidebar_before == nil then
+    sidebar_before = visible
+  end
+
+  if open ~= visible then
+    api.tree.toggle({ focus = false })
+  end
+end
+
+--- Files the GUI previews instead of opening them from the nvim-tree file browser
+local PREVIEW_EXTENSIONS = {
+  png = true,
+  jpg = true,
+  jpeg = true,
+  gif = true,
+  webp = true,
+  bmp = true,
+  svg = true,
+  pdf = true,
+}
+
+-- Path the GUI is previewing, nil when the preview is hidden
+local previewing = nil
+
+--- Path of the nvim-tree node under the cursor, if the GUI previews it
+local function previewable_node()
+  local ok, api = pcall(require, "nvim-tree.api")
+  if not ok or vim.bo.filetype ~= "NvimTree" then
+    return nil
+  end
+  local node = api.tree.get_node_under_cursor()
+  if not node or node.type ~= "file" then
+    return nil
+  end
+  local extension = vim.fn.fnamemodify(node.absolute_path, ":e"):lower()
+  return PREVIEW_EXTENSIONS[extension] and node.absolute_path or nil
+end
+
+--- Show the preview of the file under the cursor of the file browser, or hide it. While it is
+--- shown, opening the file from the browser keeps it out of nvim.
+function M.sync_file_preview()
+  local path = previewable_node()
+  if path == previewing then
+    return
+  end
+  previewing = path
+
+  local group = vim.api.nvim_create_augroup("VimdicatorPreviewOpen", { clear = true })
+  if not path then
+    M.rpcnotify("FilePreview", "", { 0, 0, 0, 0 })
+    return
+  end
+
+  local tree = vim.api.nvim_get_current_win()
+  local info = vim.fn.getwininfo(tree)[1]
+  M.rpcnotify("FilePreview", path, { info.winrow - 1, info.wincol - 1, info.height, info.width })
+
+  -- nvim-tree edits the file right after leaving the tree, this group is only cleared once
+  -- that is done, see the scheduled sync in `_attach`
+  vim.api.nvim_create_autocmd("BufReadCmd", {
+    group = group,
+    pattern = vim.fn.escape(path, "*?[]{},\\"),
+    callback = function(args)
+      vim.schedule(function()
+        if vim.api.nvim_buf_is_valid(args.buf) then
+          pcall(vim.api.nvim_buf_delete, args.buf, { force = true })
+        end
+        if vim.api.nvim_win_is_valid(tree) then
+          vim.api.nvim_set_current_win(tree)
+        end
+      end)
+    end,
+  })
+end
+
+--- Open the previewed file in nvim after all, from the button of the preview
+function M.open_previewed(path)
+  vim.api.nvim_create_augroup("VimdicatorPreviewOpen", { clear = true })
+  previewing = nil
+
+  local ok, api = pcall(require, "nvim-tree.api")
+  if ok and vim.bo.filetype == "NvimTree" then
+    local node = api.tree.get_node_under_cursor()
+    if node and node.absolute_path == path then
+      -- Where the browser would open it, e.g. the previous window
+      api.node.open.edit()
+      return
+    end
+  end
+  local edited, err = pcall(vim.cmd.edit, vim.fn.fnameescape(path))
+  if not edited then
+    notify_error(err)
+  end
+end
+
+--- Show the root of the nvim-tree file browser in the GUI's path bar while it is open
+function M.sync_file_browser()
+  local ok, api = pcall(require, "nvim-tree.api")
+  if not ok then
+    return
+  end
+  if not browsing_subscribed then
+    browsing_subscribed = true
+    -- Re-rooting from inside the tree doesn't enter any buffer or window
+    api.events.subscribe(api.events.Event.TreeRendered, function()
+      vim.schedule(M.sync_file_browser)
+    end)
+  end
+
+  local root = ""
+  if api.tree.is_visible() then
+    local core_ok, core = pcall(require, "nvim-tree.core")
+    root = core_ok and core.get_cwd() or ""
+  end
+  if root == browsing then
+    return
+  end
+  browsing = root
+  M.rpcnotify("FileBrowser", root)
+end
+
+--- Re-root the file browser at `path` from the GUI's path bar, relative paths are taken from
+--- the current root. Whether nvim's directory follows is up to nvim-tree's
+--- `sync_root_with_cwd` and `actions.change_dir` options.
+function M.change_root(path)
+  local ok, api = pcall(require, "nvim-tree.api")
+  if not ok then
+    return
+  end
+
+  path = vim.fs.normalize(path)
+  if not vim.startswith(path, "/") and browsing and browsing ~= "" then
+    path = browsing .. "/" .. path
+  end
+  path = vim.fn.fnamemodify(path, ":p")
+  if vim.fn.isdirectory(path) == 0 then
+    notify_error("Not a directory: " .. path)
+    return
+  end
+  api.tree.change_root(path)
+end
+
+-- Whether the GUI's filter entry narrowed the file browser, see M.filter_file_browser
+local filtering = false
+
+--- Narrow the file browser to entries whose names contain `text`, ignoring case, from the
+--- GUI's filter entry. An empty `text` shows every entry again.
+function M.filter_file_browser(text)
+  local ok, api = pcall(require, "nvim-tree.api")
+  if not ok then
+    return
+  end
+  if text == "" then
+    if filtering then
+      filtering = false
+      api.live_filter.clear()
+    end
+    return
+  end
+
+  -- The live filter only sees loaded directories, expanding them finds matches further down
+  -- and leaves their parents open
+  if not filtering then
+    filtering = true
+    api.tree.expand_all()
+  end
+
+  -- nvim-tree's own prompt can't be given text, so its filter is set the way the prompt does
+  local pattern = "\\c\\V" .. (text:gsub("\\", "\\\\"))
+  local core_ok, core = pcall(require, "nvim-tree.core")
+  local explorer = core_ok and core.get_explorer and core.get_explorer()
+  if explorer and explorer.live_filter then
+    explorer.live_filter.filter = pattern
+    explorer.live_filter:apply_filter()
+    explorer.renderer:draw()
+    return
+  end
+  local filter_ok, live_filter = pcall(require, "nvim-tree.live-filter")
+  if filter_ok and live_filter.apply_filter then
+    live_filter.filter = pattern
+    live_filter.apply_filter()
+    require("nvim-tree.renderer").draw()
+  end
+end
+
+--- Report of `:checkhealth`, run in a scratch tab that is closed again
+---@return string[]
+function M.checkhealth()
+  local tab = vim.api.nvim_get_current_tabpage()
+  vim.cmd("tabnew")
+  local scratch = vim.api.nvim_get_current_buf()
+
+  -- Depending on the version it reuses the empty tab or opens another one
+  local ok, err = pcall(vim.cmd, "silent checkhealth")
+  local report = vim.api.nvim_get_current_buf()
+  local lines = ok and vim.api.nvim_buf_get_lines(report, 0, -1, false) or {}
+
+  for _, buf in ipairs({ report, scratch }) do
+    if vim.api.nvim_buf_is_valid(buf) then
+      pcall(vim.api.nvim_buf_delete, buf, { force = true })
+    end
+  end
+  if vim.api.nvim_tabpage_is_valid(tab) then
+    vim.api.nvim_set_current_tabpage(tab)
+  end
+
+  if not ok then
+    error(err)
+  end
+  return lines
+end
+
+--- Open a file the GUI found too large to load comfortably. `plain` skips the swap file,
+--- filetype plugins and syntax highlighting, which are what make large files slow
+function M.open_large_file(path, line, plain)
+  local edit = "edit " .. vim.fn.fnameescape(path)
+  if plain then
+    local eventignore = vim.o.eventignore
+    vim.opt.eventignore:append({ "FileType", "Syntax" })
+    local ok, err = pcall(vim.cmd, "noswapfile " .. edit)
+    vim.o.eventignore = eventignore
+    if not ok then
+      error(err)
+    end
+    vim.bo.undolevels = -1
+  else
+    vim.cmd(edit)
+  end
+
+  if line > 0 then
+    pcall(vim.api.nvim_win_set_cursor, 0, { line, 0 })
+  end
+end
+
+--- Edit the binary file `path` as an xxd dump, from the hex viewer. The dump is converted
+--- back to binary when writing and dumped again afterwards.
+function M.open_hex(path)
+  if vim.fn.executable("xxd") == 0 then
+    notify_error("xxd is not installed")
+    return
+  end
+  local ok, err = pcall(vim.cmd, "edit ++bin " .. vim.fn.fnameescape(path))
+  if not ok then
+    notify_error(err)
+    return
+  end
+
+  local buf = vim.api.nvim_get_current_buf()
+  if vim.b[buf].vimdicator_hex then
+    return
+  end
+  vim.b[buf].vimdicator_hex = true
+  vim.cmd("silent %!xxd")
+  vim.bo[buf].filetype = "xxd"
+  vim.bo[buf].modified = false
+
+  local group = vim.api.nvim_create_augroup("VimdicatorHex" .. buf, { clear = true })
+  vim.api.nvim_create_autocmd("BufWritePre", {
+    group = group,
+    buffer = buf,
+    command = "silent %!xxd -r",
+  })
+  vim.api.nvim_create_autocmd("BufWritePost", {
+    group = group,
+    buffer = buf,
+    callback = function()
+      vim.cmd("silent %!xxd")
+      vim.bo[buf].modified = false
+    end,
+  })
+end
+
+--- Snapshot of the tabs, windows and open files the GUI takes every few seconds, restored by
+--- `restore_session` after a crash. Doesn't touch `v:this_session` of the user's sessions.
+function M.save_session(path)
+  if vim.fn.getcmdwintype() ~= "" then
+    return
+  end
+  local this_session = vim.v.this_session
+  local sessionoptions = vim.o.sessionoptions
+  -- Options and terminals don't survive a restore well
+  vim.opt.sessionoptions:remove({ "blank", "options", "localoptions", "terminal" })
+  -- Failures are skipped, reporting them every few seconds would be a nuisance
+  pcall(vim.cmd, "silent mksession! " .. vim.fn.fnameescape(path))
+  vim.o.sessionoptions = sessionoptions
+  vim.v.this_session = this_session
+end
+
+--- Bring back a snapshot of `save_session`
+function M.restore_session(path)
+  if vim.fn.filereadable(path) == 0 then
+    return
+  end
+  local ok, err = pcall(vim.cmd, "silent source " .. vim.fn.fnameescape(path))
+  if not ok then
+    notify_error(err)
+  end
+end
+
+--- Reread the current file as `encoding`, from the encoding menu of the status footer
+function M.reopen_with_encoding(encoding)
+  local ok, err = pcall(vim.cmd, "edit ++enc=" .. encoding)
+  if not ok then
+    notify_error(err)
+  end
+end
+
+--- Convert the line endings of the current buffer, written with the next save
+function M.set_fileformat(format)
+  local ok, err = pcall(function()
+    vim.bo.fileformat = format
+  end)
+  if not ok then
+    notify_error(err)
+  end
+end
+
+--- Directory "here" refers to: the node under the cursor in the nvim-tree file browser,
+--- otherwise the directory of the current file, otherwise the working directory
+function M.current_dir()
+  if vim.bo.filetype == "NvimTree" then
+    local ok, api = pcall(require, "nvim-tree.api")
+    local node = ok and api.tree.get_node_under_cursor()
+    if node and node.absolute_path then
+      return node.type == "directory" and node.absolute_path or vim.fs.dirname(node.absolute_path)
+    end
+  end
+
+  local name = vim.api.nvim_buf_get_name(0)
+  if vim.bo.buftype == "" and name ~= "" then
+    return vim.fs.dirname(name)
+  end
+
+  return vim.fn.getcwd()
+end
+
+--- Files "these" refers to: the marked nvim-tree nodes, else the node under the cursor,
+--- otherwise the current file
+---@param relative boolean|nil relative to the working directory where possible
+---@return string[]
+function M.selected_paths(relative)
+  local paths = {}
+
+  if vim.bo.filetype == "NvimTree" then
+    local ok, api = pcall(require, "nvim-tree.api")
+    if ok then
+      local nodes = api.marks.list()
+      if #nodes == 0 then
+        nodes = { api.tree.get_node_under_cursor() }
+      end
+      for _, node in ipairs(nodes) do
+        if node.absolute_path then
+          table.insert(paths, node.absolute_path)
+        end
+      end
+    end
+  else
+    local name = vim.api.nvim_buf_get_name(0)
+    if vim.bo.buftype == "" and name ~= "" then
+      table.insert(paths, name)
+    end
+  end
+
+  if relative then
+    for i, path in ipairs(paths) do
+      paths[i] = vim.fn.fnamemodify(path, ":.")
+    end
+  end
+  return paths
+end
+
+--- Open a terminal in `current_dir()`
+---@param argv string[]|nil external terminal command, opens a `:terminal` split when nil
+function M.open_terminal(argv)
+  local dir = M.current_dir()
+
+  if argv then
+    local ok, err = pcall(vim.fn.jobstart, argv, { cwd = dir, detach = true })
+    if not ok or err <= 0 then
+      vim.notify("Failed to start " .. argv[1], vim.log.levels.ERROR)
+    end
+  else
+    vim.cmd.split()
+    vim.cmd.lcd(vim.fn.fnameescape(dir))
+    vim.cmd.terminal()
+  end
+end
+
+-- Submodules are loaded on first use, e.g. `require("vimdicator").image.place(...)`
+setmetatable(M, {
+  __index = function(_, key)
+    if key == "image" then
+      return require("vimdicator.image")
+    end
+  end,
+})
+
+return M
@@ -0,0 +1,54 @@
+-- Native dialogs for plugins, available as `require("vimdicator.ui")`. They block like
+-- `vim.fn.input()` until the user is done, and error when nvim isn't attached to vimdicator.
+
+local vimdicator = require("vimdicator")
+
+local M = {}
+
+---@class vimdicator.FileDialogFilter
+---@field name string shown in the dialog
+---@field patterns string[] globs like "*.lua"
+
+---@class vimdicator.FileDialogOpts
+---@field title string|nil
+---@field folder string|nil shown first, the working directory by default
+---@field filters vimdicator.FileDialogFilter[]|nil
+
+local function file_dialog(kind, opts)
+  opts = vim.tbl_extend("keep", opts or {}, { folder = vim.fn.getcwd() })
+  local result = vimdicator.rpcrequest("FileDialog", kind, opts)
+  if result == vim.NIL then
+    return nil
+  end
+  return result
+end
+
+--- Let the user pick an existing file
+---@param opts (vimdicator.FileDialogOpts|{ multiple: boolean|nil })|nil
+---@return string|string[]|nil path, a list of them with `multiple`, nil when dismissed
+function M.open_file_dialog(opts)
+  return file_dialog("open", opts)
+end
+
+--- Let the user choose where to save a file, the dialog asks before overwriting
+---@param opts (vimdicator.FileDialogOpts|{ name: string|nil })|nil `name` is suggested
+---@return string|nil path, nil when dismissed
+function M.save_file_dialog(opts)
+  return file_dialog("save", opts)
+end
+
+--- Let the user pick a color
+---@param initial string|nil selected first, "#rrggbb" or any CSS color
+---@param opts { eyedropper: boolean|nil }|nil `eyedropper` picks from anywhere on screen
+--- where the desktop portal can, the dialog is shown elsewhere
+---@return string|nil color as "#rrggbb", nil when dismissed
+function M.color_picker(initial, opts)
+  opts = opts or {}
+  local color = vimdicator.rpcrequest("ColorPicker", initial, opts.eyedropper == true)
+  if color == vim.NIL then
+    return nil
+  end
+  return color
+end
+
+return M
@@ -0,0 +1,55 @@
+if vim.g.loaded_vimdicator then
+  return
+end
+vim.g.loaded_vimdicator = true
+
+vim.api.nvim_create_user_command("VimdicatorFont", function(opts)
+  require("vimdicator").set_font(opts.args)
+end, { nargs = 1, desc = "Set the vimdicator GUI font" })
+
+vim.api.nvim_create_user_command("VimdicatorCacheStats", function()
+  require("vimdicator").rpcnotify("CacheStats")
+end, { nargs = 0, desc = "Show memory usage and hit rates of the vimdicator render caches" })
+
+vim.api.nvim_create_user_command("VimdicatorReloadConfig", function()
+  require("vimdicator").sync_project_config(true)
+end, { nargs = 0, desc = "Read .vimdicator.toml again and report invalid GUI settings" })
+
+vim.api.nvim_create_user_command("VimdicatorQuickfix", function()
+  require("vimdicator").sync_quickfix(true)
+end, { nargs = 0, desc = "Show the quickfix list in the native panel instead of a :copen window" })
+
+vim.api.nvim_create_user_command("VimdicatorRegisters", function()
+  require("vimdicator").sync_registers(true)
+end, { nargs = 0, desc = "Show the registers and recorded macros in the native panel" })
+
+vim.api.nvim_create_user_command("VimdicatorTrash", function(opts)
+  require("vimdicator").rpcnotify("Trash", opts.bang)
+end, {
+  nargs = 0,
+  bang = true,
+  desc = "Move the marked nvim-tree files (or the current one) to the trash, ! deletes them for good",
+})
+
+vim.api.nvim_create_user_command("VimdicatorPresent", function()
+  require("vimdicator").rpcnotify("Presentation")
+end, { nargs = 0, desc = "Toggle the vimdicator presentation mode: bigger font, no panels" })
+
+vim.api.nvim_create_user_command("VimdicatorHexView", function(opts)
+  local path = opts.args ~= "" and opts.args or "%"
+  require("vimdicator").rpcnotify("HexView", vim.fn.fnamemodify(vim.fn.expand(path), ":p"))
+end, { nargs = "?", complete = "file", desc = "Show a file (or the current one) in the vimdicator hex viewer" })
+
+vim.api.nvim_create_user_command("VimdicatorCursor", function(opts)
+  require("vimdicator").rpcnotify("Cursor", opts.args)
+end, {
+  nargs = "+",
+  desc = "Override the vimdicator cursor, e.g. insert=block visual=underline size=2px color=#ff8800",
+  complete = function()
+    local keys = { "size=", "color=", "reset" }
+    for _, mode in ipairs({ "normal", "insert", "replace", "visual", "select", "operator", "command" }) do
+      table.insert(keys, mode .. "=")
+    end
+    return keys
+  end,
+})
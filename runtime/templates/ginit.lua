@@ -0,0 +1,6 @@
+-- Example ginit.lua, copy it to your nvim config directory.
+-- It is sourced by vimdicator after the GUI attaches.
+
+local vimdicator = require("vimdicator")
+
+vimdicator.set_font("Source Code Pro:h11")
@@ -18,21 +18,55 @@
  * SPDX-License-Identifier: GPL-3.0-or-later
  */
 
+use adw::prelude::{MessageDialogExt, MessageDialogExtManual};
 use adw::subclass::prelude::*;
 use gtk::prelude::*;
 use gtk::{gio, glib};
-use std::cell::OnceCell;
-use tokio::sync::mpsc::UnboundedSender;
+use std::cell::{Cell, OnceCell};
+
+use gettextrs::{gettext, ngettext};
 
 use crate::config::VERSION;
-use crate::{nvim::GtkToNvimEvent, widgets};
+use crate::{
+    locale,
+    nvim::{GtkToNvimEvent, NvimBridge},
+    options::{Options, WindowOptions},
+    settings, trash, widgets,
+};
+
+/// Id of the application unless `--app-id` or the `app-id` setting give another one
+pub const DEFAULT_APP_ID: &str = "io.github.polymeilex.vimdicator";
+
+/// The id given with `--app-id`, else the `app-id` setting, else [`DEFAULT_APP_ID`]. It's the
+/// Wayland app-id of the windows too, window managers can tell instances apart by it.
+pub fn app_id(options: &Options) -> String {
+    if let Some(id) = &options.app_id {
+        return id.clone();
+    }
+
+    let id = settings::settings().string("app-id");
+    if id.is_empty() {
+        DEFAULT_APP_ID.to_string()
+    } else if gio::Application::id_is_valid(&id) {
+        id.to_string()
+    } else {
+        log::warn!("Invalid app-id setting {id:?}, using {DEFAULT_APP_ID}");
+        DEFAULT_APP_ID.to_string()
+    }
+}
 
 mod imp {
     use super::*;
 
     #[derive(Debug, Default)]
     pub struct VimdicatorApplication {
-        pub nvim_tx: OnceCell<UnboundedSender<GtkToNvimEvent>>,
+        pub nvim: OnceCell<NvimBridge>,
+        /// Taken by the first window, later ones open the usual way
+        pub window_options: Cell<Option<WindowOptions>>,
+        /// Address of the `--server`, if attached to one
+        pub server: OnceCell<String>,
+        /// The last run did not exit cleanly, the first window offers to restore its session
+        pub offer_restore: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -48,10 +82,21 @@ mod imp {
             let obj = self.obj();
             obj.setup_gactions();
             obj.set_accels_for_action("app.quit", &["<primary>q"]);
+            obj.set_accels_for_action("app.open-file", &["<primary>o"]);
+            obj.set_accels_for_action("win.debug-overlay", &["<primary><shift>F12"]);
+            obj.set_accels_for_action("win.protocol-diagnostics", &["<primary><shift>F11"]);
+            obj.set_accels_for_action("win.find", &["<primary><shift>f"]);
+            obj.set_accels_for_action("win.search-panel", &["<primary><shift>g"]);
+            obj.set_accels_for_action("win.presentation", &["<primary><shift>F5"]);
         }
     }
 
     impl ApplicationImpl for VimdicatorApplication {
+        fn startup(&self) {
+            self.parent_startup();
+            crate::dbus::register(&self.obj());
+        }
+
         fn activate(&self) {
             let application = self.obj();
 
@@ -59,7 +104,16 @@ mod imp {
                 window
             } else {
                 let window = widgets::VimdicatorWindow::new(&*application);
-                window.connect(self.nvim_tx.get().unwrap().clone());
+                window.connect(self.nvim.get().unwrap().clone());
+                if let Some(addr) = self.server.get() {
+                    window.set_server(addr);
+                }
+                if let Some(options) = self.window_options.take() {
+                    window.apply_options(options);
+                }
+                if self.offer_restore.take() {
+                    window.offer_session_restore();
+                }
                 window.upcast()
             };
 
@@ -78,26 +132,306 @@ glib::wrapper! {
 }
 
 impl VimdicatorApplication {
-    pub fn new(
-        application_id: &str,
-        flags: &gio::ApplicationFlags,
-        input_tx: UnboundedSender<GtkToNvimEvent>,
-    ) -> Self {
+    pub fn new(application_id: &str, flags: &gio::ApplicationFlags, nvim: NvimBridge) -> Self {
         let this: Self = glib::Object::builder()
             .property("application-id", application_id)
             .property("flags", flags)
             .build();
 
-        this.imp().nvim_tx.set(input_tx).unwrap();
+        this.imp().nvim.set(nvim).unwrap();
 
         this
     }
 
+    /// `--maximized`, `--fullscreen` and `--geometry` of this launch
+    pub fn set_window_options(&self, options: WindowOptions) {
+        self.imp().window_options.set(Some(options));
+    }
+
+    /// See [`crate::session`]
+    pub fn set_offer_restore(&self, offer: bool) {
+        self.imp().offer_restore.set(offer);
+    }
+
+    pub fn set_server(&self, addr: &str) {
+        self.imp().server.set(addr.to_string()).unwrap();
+    }
+
+    pub fn nvim(&self) -> &NvimBridge {
+        self.imp().nvim.get().unwrap()
+    }
+
+    pub fn open_file(&self, path: &str, line: Option<u64>) {
+        if let Some(window) = self.window() {
+            window.open_file(path.to_string(), line);
+            return;
+        }
+
+        self.nvim()
+            .send(GtkToNvimEvent::OpenFile {
+                path: path.to_string(),
+                line,
+            })
+            .unwrap();
+    }
+
+    pub fn change_directory(&self, path: &str) {
+        self.nvim()
+            .send(GtkToNvimEvent::ChangeDirectory(path.to_string()))
+            .unwrap();
+    }
+
+    /// nvim's working directory, where file dialogs start
+    async fn nvim_cwd(&self) -> Option<gio::File> {
+        match self.nvim().eval_as::<String>("getcwd()").await {
+            Ok(cwd) => Some(gio::File::for_path(cwd)),
+            Err(err) => {
+                log::warn!("Could not get the working directory: {err}");
+                None
+            }
+        }
+    }
+
+    async fn open_file_dialog(&self) {
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Open File"))
+            .modal(true)
+            .build();
+        dialog.set_initial_folder(self.nvim_cwd().await.as_ref());
+
+        let app = self.clone();
+        dialog.open(
+            self.active_window().as_ref(),
+            gio::Cancellable::NONE,
+            move |res| {
+                if let Ok(file) = res {
+                    app.open_file(&file_to_nvim_path(&file), None);
+                }
+            },
+        );
+    }
+
+    async fn open_folder_dialog(&self) {
+        let dialog = gtk::FileDialog::builder()
+            .title(gettext("Open Folder"))
+            .modal(true)
+            .build();
+        dialog.set_initial_folder(self.nvim_cwd().await.as_ref());
+
+        let app = self.clone();
+        dialog.select_folder(
+            self.active_window().as_ref(),
+            gio::Cancellable::NONE,
+            move |res| {
+                if let Ok(file) = res {
+                    app.change_directory(&file_to_nvim_path(&file));
+                }
+            },
+        );
+    }
+
+    /// Open a terminal in the directory of the current file, or of the nvim-tree node under the cursor
+    fn open_terminal(&self) {
+        let settings = settings::settings();
+
+        let external = if settings.string("terminal") == "external" {
+            let Some(argv) = external_terminal(&settings.string("terminal-command")) else {
+                log::error!("No external terminal found, set terminal-command");
+                return;
+            };
+            Some(argv)
+        } else {
+            None
+        };
+
+        self.nvim()
+            .send(GtkToNvimEvent::OpenTerminal { external })
+            .unwrap();
+    }
+
+    /// Files the file browser actions act on, see `selected_paths()` in the runtime
+    async fn selected_paths(&self, relative: bool) -> Vec<String> {
+        let code = "return require('vimdicator').selected_paths(...)";
+        match self
+            .nvim()
+            .exec_lua_as::<Vec<String>>(code, vec![relative.into()])
+            .await
+        {
+            Ok(paths) => paths,
+            Err(err) => {
+                log::warn!("Could not get the selected files: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn copy_paths(&self, relative: bool) {
+        let paths = self.selected_paths(relative).await;
+        if let (Some(window), false) = (self.active_window(), paths.is_empty()) {
+            window.clipboard().set_text(&paths.join("\n"));
+        }
+    }
+
+    /// Show the selected files in the file manager, once per folder
+    async fn show_in_files(&self) {
+        let mut folders = Vec::new();
+        for path in self.selected_paths(false).await {
+            let folder = std::path::Path::new(&path).parent().map(|p| p.to_owned());
+            if folders.contains(&folder) {
+                continue;
+            }
+            folders.push(folder);
+
+            let launcher = gtk::FileLauncher::new(Some(&gio::File::for_path(&path)));
+            launcher.open_containing_folder(
+                self.active_window().as_ref(),
+                gio::Cancellable::NONE,
+                move |res| {
+                    if let Err(err) = res {
+                        log::error!("Could not show {path} in the file manager: {err}");
+                    }
+                },
+            );
+        }
+    }
+
+    /// Let the user pick an application for the selected files
+    // FileLauncher only learns to ask for the application in GTK 4.12
+    #[allow(deprecated)]
+    async fn open_with_dialog(&self) {
+        let files: Vec<gio::File> = self
+            .selected_paths(false)
+            .await
+            .iter()
+            .map(gio::File::for_path)
+            .collect();
+        let Some(first) = files.first() else {
+            return;
+        };
+
+        let dialog = gtk::AppChooserDialog::new(
+            self.active_window().as_ref(),
+            gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+            first,
+        );
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk::ResponseType::Ok {
+                if let Some(app_info) = dialog.app_info() {
+                    let context = WidgetExt::display(dialog).app_launch_context();
+                    if let Err(err) = app_info.launch(&files, Some(&context)) {
+                        log::error!("Could not start {}: {err}", app_info.name());
+                    }
+                }
+            }
+            dialog.destroy();
+        });
+        dialog.present();
+    }
+
+    /// The file browser doesn't watch the file system
+    fn reload_file_browser(&self) {
+        self.nvim()
+            .send(GtkToNvimEvent::ExecLua(
+                r#"pcall(function() require("nvim-tree.api").tree.reload() end)"#.to_string(),
+            ))
+            .unwrap();
+    }
+
+    fn window(&self) -> Option<widgets::VimdicatorWindow> {
+        self.active_window().and_downcast()
+    }
+
+    /// Move the selected files to the trash, with an undo toast
+    async fn trash_selected(&self) {
+        let paths = trash::trash(self.selected_paths(false).await).await;
+        if paths.is_empty() {
+            return;
+        }
+        self.reload_file_browser();
+
+        let title = match paths.as_slice() {
+            [path] => gettext("“{name}” moved to trash").replace("{name}", &file_name(path)),
+            _ => ngettext(
+                "{count} file moved to trash",
+                "{count} files moved to trash",
+                paths.len() as u32,
+            )
+            .replace("{count}", &locale::count(paths.len())),
+        };
+        let toast = adw::Toast::builder()
+            .title(glib::markup_escape_text(&title))
+            .button_label(gettext("Undo"))
+            .build();
+
+        let app = self.clone();
+        toast.connect_button_clicked(move |_| {
+            let (app, paths) = (app.clone(), paths.clone());
+            glib::MainContext::default().spawn_local(async move {
+                trash::restore(paths).await;
+                app.reload_file_browser();
+            });
+        });
+
+        if let Some(window) = self.window() {
+            window.add_toast(toast);
+        }
+    }
+
+    /// Delete the selected files for good, after asking
+    async fn delete_selected(&self) {
+        let paths = self.selected_paths(false).await;
+        let heading = match paths.as_slice() {
+            [] => return,
+            [path] => gettext("Permanently Delete “{name}”?").replace("{name}", &file_name(path)),
+            _ => ngettext(
+                "Permanently Delete {count} File?",
+                "Permanently Delete {count} Files?",
+                paths.len() as u32,
+            )
+            .replace("{count}", &locale::count(paths.len())),
+        };
+
+        let dialog = adw::MessageDialog::builder()
+            .heading(heading)
+            .body(gettext("Deleted files can't be restored."))
+            .modal(true)
+            .build();
+        dialog.set_transient_for(self.active_window().as_ref());
+        dialog.add_responses(&[
+            ("cancel", &gettext("_Cancel")),
+            ("delete", &gettext("_Delete")),
+        ]);
+        dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        if dialog.choose_future().await == "delete" {
+            trash::delete(paths).await;
+            self.reload_file_browser();
+        }
+    }
+
+    /// Start another vimdicator process with its own nvim, under the same application id
+    pub fn new_instance(&self) {
+        let res = std::env::current_exe().and_then(|exe| {
+            let mut command = std::process::Command::new(exe);
+            command.arg("--new-instance");
+            if let Some(id) = self.application_id() {
+                command.args(["--app-id", &id]);
+            }
+            command.spawn()
+        });
+
+        if let Err(err) = res {
+            log::error!("Failed to start a new instance: {err}");
+        }
+    }
+
     fn setup_gactions(&self) {
         let sidebar_action = gio::ActionEntry::builder("toggle_sidebar")
             .activate(move |app: &Self, _, _| {
                 app.imp()
-                    .nvim_tx
+                    .nvim
                     .get()
                     .unwrap()
                     .send(GtkToNvimEvent::ExecLua(
@@ -106,13 +440,91 @@ impl VimdicatorApplication {
                     .unwrap();
             })
             .build();
+        let open_file_action = gio::ActionEntry::builder("open-file")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default()
+                    .spawn_local(async move { app.open_file_dialog().await });
+            })
+            .build();
+        let open_folder_action = gio::ActionEntry::builder("open-folder")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default()
+                    .spawn_local(async move { app.open_folder_dialog().await });
+            })
+            .build();
+        let open_recent_action = gio::ActionEntry::builder("open-recent")
+            .parameter_type(Some(glib::VariantTy::STRING))
+            .activate(move |app: &Self, _, param| {
+                if let Some(path) = param.and_then(|param| param.get::<String>()) {
+                    app.open_file(&path, None);
+                }
+            })
+            .build();
+        let open_terminal_action = gio::ActionEntry::builder("open-terminal")
+            .activate(move |app: &Self, _, _| app.open_terminal())
+            .build();
+        let open_with_action = gio::ActionEntry::builder("open-with")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default()
+                    .spawn_local(async move { app.open_with_dialog().await });
+            })
+            .build();
+        let show_in_files_action = gio::ActionEntry::builder("show-in-files")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default().spawn_local(async move { app.show_in_files().await });
+            })
+            .build();
+        let copy_path_action = gio::ActionEntry::builder("copy-path")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default()
+                    .spawn_local(async move { app.copy_paths(false).await });
+            })
+            .build();
+        let copy_relative_path_action = gio::ActionEntry::builder("copy-relative-path")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default().spawn_local(async move { app.copy_paths(true).await });
+            })
+            .build();
+        let trash_action = gio::ActionEntry::builder("trash")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default().spawn_local(async move { app.trash_selected().await });
+            })
+            .build();
+        let delete_action = gio::ActionEntry::builder("delete")
+            .activate(move |app: &Self, _, _| {
+                let app = app.clone();
+                glib::MainContext::default()
+                    .spawn_local(async move { app.delete_selected().await });
+            })
+            .build();
         let quit_action = gio::ActionEntry::builder("quit")
             .activate(move |app: &Self, _, _| app.quit())
             .build();
         let about_action = gio::ActionEntry::builder("about")
             .activate(move |app: &Self, _, _| app.show_about())
             .build();
-        self.add_action_entries([quit_action, about_action, sidebar_action]);
+        self.add_action_entries([
+            quit_action,
+            about_action,
+            sidebar_action,
+            open_file_action,
+            open_folder_action,
+            open_recent_action,
+            open_terminal_action,
+            open_with_action,
+            show_in_files_action,
+            copy_path_action,
+            copy_relative_path_action,
+            trash_action,
+            delete_action,
+        ]);
     }
 
     fn show_about(&self) {
@@ -130,3 +542,49 @@ impl VimdicatorApplication {
         about.present();
     }
 }
+
+/// Terminals tried in order when no terminal command is configured
+const KNOWN_TERMINALS: &[&str] = &[
+    "xdg-terminal-exec",
+    "kgx",
+    "gnome-terminal",
+    "konsole",
+    "xfce4-terminal",
+    "alacritty",
+    "foot",
+    "xterm",
+];
+
+/// Argv of the external terminal, `command` is parsed with shell quoting rules
+fn external_terminal(command: &str) -> Option<Vec<String>> {
+    if !command.is_empty() {
+        return glib::shell_parse_argv(command).ok().map(|argv| {
+            argv.iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        });
+    }
+
+    KNOWN_TERMINALS
+        .iter()
+        .find(|terminal| glib::find_program_in_path(terminal).is_some())
+        .map(|terminal| vec![terminal.to_string()])
+}
+
+/// Path nvim can open for a file picked through the (possibly portal-backed) dialog.
+///
+/// Inside Flatpak/Snap the document portal hands out FUSE paths under
+/// `/run/user/<uid>/doc`, which are readable from the sandbox; files without a
+/// local path (e.g. `sftp://`) fall back to their URI, which netrw understands.
+fn file_to_nvim_path(file: &gio::File) -> String {
+    file.path()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file.uri().to_string())
+}
+
+fn file_name(path: &str) -> String {
+    std::path::Path::new(path).file_name().map_or_else(
+        || path.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
@@ -0,0 +1,85 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use gtk::{gio, prelude::*};
+
+use crate::{
+    clock::{self, Timer},
+    nvim::{BufferInfo, GtkToNvimEvent, NvimBridge},
+};
+
+/// Issues `:silent! update` when the window loses focus or the user stops typing
+#[derive(Debug)]
+pub struct Autosave {
+    settings: gio::Settings,
+    nvim_tx: NvimBridge,
+    buffer: RefCell<BufferInfo>,
+    idle_timer: RefCell<Option<Timer>>,
+}
+
+impl Autosave {
+    pub fn new(settings: gio::Settings, nvim_tx: NvimBridge) -> Self {
+        Self {
+            settings,
+            nvim_tx,
+            buffer: RefCell::new(BufferInfo::default()),
+            idle_timer: RefCell::new(None),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.boolean("autosave")
+    }
+
+    pub fn set_buffer(&self, buffer: &BufferInfo) {
+        *self.buffer.borrow_mut() = buffer.clone();
+    }
+
+    /// Restart the idle timer, called on every input
+    pub fn reset_idle(self: &Rc<Self>) {
+        if let Some(timer) = self.idle_timer.take() {
+            timer.cancel();
+        }
+
+        if !self.is_enabled() {
+            return;
+        }
+
+        let timeout = self.settings.uint("autosave-idle-timeout");
+        if timeout == 0 {
+            return;
+        }
+
+        let this = Rc::downgrade(self);
+        let timer = clock::timeout_once(Duration::from_secs(timeout.into()), move || {
+            if let Some(this) = this.upgrade() {
+                this.idle_timer.take();
+                this.save();
+            }
+        });
+
+        *self.idle_timer.borrow_mut() = Some(timer);
+    }
+
+    pub fn focus_lost(&self) {
+        if self.is_enabled() {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let buffer = self.buffer.borrow();
+
+        if !buffer.modified || buffer.name.is_empty() {
+            return;
+        }
+
+        let excluded = self.settings.strv("autosave-excluded-filetypes");
+        if excluded.iter().any(|ft| ft.to_str() == buffer.filetype) {
+            return;
+        }
+
+        self.nvim_tx
+            .send(GtkToNvimEvent::Command("silent! update".to_string()))
+            .unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+//! Time of the GUI's timers. They run on glib's main loop, unless a test swapped in a
+//! [`VirtualClock`] with [`use_virtual`], which only moves when the test steps it. Timers
+//! then fire in a fixed order and animations finish right away.
+//!
+//! UI tests start vimdicator with `VIMDICATOR_VIRTUAL_CLOCK=1` set and step the clock from
+//! nvim with `require("vimdicator").rpcnotify("AdvanceClock", milliseconds)`.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+    time::Duration,
+};
+
+use gtk::glib;
+
+type Callback = Box<dyn FnMut() -> glib::Continue>;
+
+#[derive(Clone, Default)]
+enum Clock {
+    #[default]
+    Glib,
+    Virtual(Rc<VirtualClock>),
+}
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+}
+
+/// A pending timer, cancelling one that already fired is a bug like removing its source
+#[derive(Debug)]
+pub struct Timer(TimerKind);
+
+#[derive(Debug)]
+enum TimerKind {
+    Glib(glib::SourceId),
+    Virtual(Weak<VirtualClock>, u64),
+}
+
+impl Timer {
+    pub fn cancel(self) {
+        match self.0 {
+            TimerKind::Glib(source) => source.remove(),
+            TimerKind::Virtual(clock, id) => {
+                if let Some(clock) = clock.upgrade() {
+                    clock.cancel(id);
+                }
+            }
+        }
+    }
+}
+
+/// Call `f` every `interval` until it returns `Continue(false)`
+pub fn timeout(interval: Duration, f: impl FnMut() -> glib::Continue + 'static) -> Timer {
+    let clock = CLOCK.with(|clock| clock.borrow().clone());
+    match clock {
+        Clock::Glib => Timer(TimerKind::Glib(glib::timeout_add_local(interval, f))),
+        Clock::Virtual(clock) => {
+            let id = clock.schedule(interval, Some(interval), Box::new(f));
+            Timer(TimerKind::Virtual(Rc::downgrade(&clock), id))
+        }
+    }
+}
+
+/// Call `f` once after `delay`
+pub fn timeout_once(delay: Duration, f: impl FnOnce() + 'static) -> Timer {
+    let clock = CLOCK.with(|clock| clock.borrow().clone());
+    match clock {
+        Clock::Glib => Timer(TimerKind::Glib(glib::timeout_add_local_once(delay, f))),
+        Clock::Virtual(clock) => {
+            let mut f = Some(f);
+            let once = move || {
+                if let Some(f) = f.take() {
+                    f();
+                }
+                glib::Continue(false)
+            };
+            let id = clock.schedule(delay, None, Box::new(once));
+            Timer(TimerKind::Virtual(Rc::downgrade(&clock), id))
+        }
+    }
+}
+
+/// Whether time only moves when a test steps it, animations should jump to their end
+pub fn is_virtual() -> bool {
+    CLOCK.with(|clock| matches!(*clock.borrow(), Clock::Virtual(_)))
+}
+
+/// Name of the environment variable that starts vimdicator with a virtual clock
+pub const VIRTUAL_ENV: &str = "VIMDICATOR_VIRTUAL_CLOCK";
+
+/// Run the timers created from now on with a virtual clock, for tests
+pub fn use_virtual() -> Rc<VirtualClock> {
+    let clock = Rc::new(VirtualClock::default());
+    CLOCK.with(|current| current.replace(Clock::Virtual(clock.clone())));
+    clock
+}
+
+/// Step the virtual clock, see [`VirtualClock::advance`]. Real time can't be stepped.
+pub fn advance(by: Duration) {
+    let clock = CLOCK.with(|clock| clock.borrow().clone());
+    match clock {
+        Clock::Glib => log::warn!("Only a virtual clock can be advanced, see {VIRTUAL_ENV}"),
+        Clock::Virtual(clock) => {
+            clock.advance(by);
+            log::debug!("Virtual clock at {:?}", clock.now());
+        }
+    }
+}
+
+struct Pending {
+    id: u64,
+    due: Duration,
+    interval: Option<Duration>,
+    callback: Callback,
+}
+
+/// Time that only passes with [`VirtualClock::advance`]
+#[derive(Default)]
+pub struct VirtualClock {
+    now: Cell<Duration>,
+    next_id: Cell<u64>,
+    pending: RefCell<Vec<Pending>>,
+}
+
+impl std::fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("now", &self.now.get())
+            .field("pending", &self.pending.borrow().len())
+            .finish()
+    }
+}
+
+impl VirtualClock {
+    fn schedule(&self, delay: Duration, interval: Option<Duration>, callback: Callback) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.pending.borrow_mut().push(Pending {
+            id,
+            due: self.now.get() + delay,
+            interval,
+            callback,
+        });
+        id
+    }
+
+    fn cancel(&self, id: u64) {
+        self.pending.borrow_mut().retain(|pending| pending.id != id);
+    }
+
+    /// Time passed since the clock was created
+    pub fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    /// Timers that haven't fired yet
+    #[cfg(test)]
+    pub fn pending(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    /// Let `by` pass, firing the timers that become due on the way in the order of their due
+    /// time, those due at the same time in the order they were created
+    pub fn advance(&self, by: Duration) {
+        let target = self.now.get() + by;
+        loop {
+            let next = {
+                let mut pending = self.pending.borrow_mut();
+                let next = pending
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, timer)| timer.due <= target)
+                    .min_by_key(|(_, timer)| (timer.due, timer.id))
+                    .map(|(i, _)| i);
+                next.map(|i| pending.remove(i))
+            };
+            let Some(mut timer) = next else {
+                break;
+            };
+
+            self.now.set(timer.due);
+            // Not borrowed, the callback may create or cancel timers
+            let again = (timer.callback)();
+            if let (glib::Continue(true), Some(interval)) = (again, timer.interval) {
+                timer.due += interval;
+                self.pending.borrow_mut().push(timer);
+            }
+        }
+        self.now.set(target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock() {
+        let clock = use_virtual();
+        let fired = Rc::new(RefCell::new(Vec::new()));
+
+        let log = |name: &'static str| {
+            let fired = fired.clone();
+            move || fired.borrow_mut().push(name)
+        };
+        timeout_once(Duration::from_millis(200), log("late"));
+        timeout_once(Duration::from_millis(100), log("first"));
+        timeout_once(Duration::from_millis(100), log("second"));
+        let cancelled = timeout_once(Duration::from_millis(50), log("cancelled"));
+        cancelled.cancel();
+
+        let ticks = fired.clone();
+        let mut count = 0;
+        timeout(Duration::from_millis(80), move || {
+            count += 1;
+            ticks.borrow_mut().push("tick");
+            glib::Continue(count < 2)
+        });
+
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(*fired.borrow(), ["tick", "first", "second"]);
+        assert_eq!(clock.now(), Duration::from_millis(100));
+
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(*fired.borrow(), ["tick", "first", "second", "tick", "late"]);
+        assert_eq!(clock.pending(), 0);
+        assert!(is_virtual());
+    }
+
+    #[test]
+    fn test_nested_timers() {
+        let clock = use_virtual();
+        let fired = Rc::new(Cell::new(0));
+
+        let outer = fired.clone();
+        timeout_once(Duration::from_millis(10), move || {
+            let inner = outer.clone();
+            timeout_once(Duration::from_millis(10), move || {
+                inner.set(inner.get() + 1)
+            });
+        });
+
+        clock.advance(Duration::from_millis(15));
+        assert_eq!(fired.get(), 0);
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(fired.get(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    io::Write,
+    panic::PanicHookInfo,
+    path::PathBuf,
+    sync::{mpsc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use gtk::glib;
+
+use crate::nvim::{GtkToNvimEvent, NvimBridge};
+
+/// RPC messages kept for the crash report
+const RPC_LOG_LEN: usize = 64;
+/// How long a crashing GUI waits for nvim to write buffers and swap files
+const RESCUE_TIMEOUT: Duration = Duration::from_secs(2);
+
+static RPC_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Remember an RPC message for a potential crash report
+pub fn log_rpc(entry: String) {
+    let Ok(mut log) = RPC_LOG.lock() else { return; };
+
+    if log.len() == RPC_LOG_LEN {
+        log.pop_front();
+    }
+    log.push_back(entry);
+}
+
+/// On a panic of the GTK thread: let nvim write all buffers and preserve swap files,
+/// detach the UI and write a crash report next to the other vimdicator caches
+pub fn install_hook(nvim_tx: NvimBridge) {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        if !glib::MainContext::default().is_owner() {
+            return;
+        }
+
+        let (done_tx, done_rx) = mpsc::sync_channel(1);
+        if nvim_tx.send(GtkToNvimEvent::Rescue(done_tx)).is_ok()
+            && done_rx.recv_timeout(RESCUE_TIMEOUT).is_err()
+        {
+            eprintln!("nvim did not confirm saving buffers in time");
+        }
+
+        match write_report(info) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(err) => eprintln!("Could not write crash report: {err}"),
+        }
+    }));
+}
+
+fn write_report(info: &PanicHookInfo) -> std::io::Result<PathBuf> {
+    let dir = glib::user_cache_dir().join("vimdicator");
+    std::fs::create_dir_all(&dir)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = dir.join(format!("crash-{timestamp}.log"));
+
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(file, "vimdicator {}", crate::config::VERSION)?;
+    writeln!(file, "{info}\n")?;
+    writeln!(file, "Backtrace:\n{}\n", Backtrace::force_capture())?;
+
+    writeln!(file, "Recent RPC messages:")?;
+    if let Ok(log) = RPC_LOG.lock() {
+        for entry in log.iter() {
+            writeln!(file, "{entry}")?;
+        }
+    }
+
+    Ok(path)
+}
@@ -0,0 +1,162 @@
+use gtk::{gio, glib, prelude::*};
+use log::error;
+
+use crate::{application::VimdicatorApplication, options::Options};
+
+pub const INTERFACE_NAME: &str = "io.github.polymeilex.vimdicator.Editor";
+
+/// Object path GApplication exports the primary instance of `app_id` at, its well-known
+/// name is `app_id` itself
+fn object_path(app_id: &str) -> String {
+    format!("/{}", app_id.replace('.', "/").replace('-', "_"))
+}
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="io.github.polymeilex.vimdicator.Editor">
+    <method name="OpenFile">
+      <arg type="s" name="path" direction="in"/>
+      <arg type="i" name="line" direction="in"/>
+    </method>
+    <method name="FocusWindow"/>
+    <method name="NewWindow"/>
+    <method name="SendKeys">
+      <arg type="s" name="keys" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// Export the editor interface next to the org.freedesktop.Application one,
+/// so file managers and other apps can open files in the running instance
+pub fn register(app: &VimdicatorApplication) {
+    let (Some(connection), Some(object_path)) = (app.dbus_connection(), app.dbus_object_path())
+    else {
+        return;
+    };
+
+    let interface = gio::DBusNodeInfo::for_xml(INTERFACE_XML)
+        .ok()
+        .and_then(|node| node.lookup_interface(INTERFACE_NAME))
+        .expect("Invalid D-Bus interface XML");
+
+    let app = glib::SendWeakRef::from(app.downgrade());
+
+    let res = connection.register_object(
+        &object_path,
+        &interface,
+        move |_, _, _, _, method, params, invocation| {
+            let Some(app) = app.upgrade() else {
+                return;
+            };
+
+            match method {
+                "OpenFile" => {
+                    let Some((path, line)) = params.get::<(String, i32)>() else {
+                        invocation.return_dbus_error(
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            "Expected (si)",
+                        );
+                        return;
+                    };
+
+                    app.open_file(&path, u64::try_from(line).ok());
+                    app.activate();
+                }
+                "FocusWindow" => app.activate(),
+                "NewWindow" => app.new_instance(),
+                "SendKeys" => {
+                    let Some((keys,)) = params.get::<(String,)>() else {
+                        invocation.return_dbus_error(
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            "Expected (s)",
+                        );
+                        return;
+                    };
+
+                    // Answer once nvim took the keys, so failures reach the caller
+                    let nvim = app.nvim().clone();
+                    glib::MainContext::default().spawn_local(async move {
+                        match nvim.input(&keys).await {
+                            Ok(_) => invocation.return_value(None),
+                            Err(err) => invocation.return_dbus_error(
+                                "org.freedesktop.DBus.Error.Failed",
+                                &err.to_string(),
+                            ),
+                        }
+                    });
+                    return;
+                }
+                _ => {
+                    invocation.return_dbus_error(
+                        "org.freedesktop.DBus.Error.UnknownMethod",
+                        &format!("Unknown method {method}"),
+                    );
+                    return;
+                }
+            }
+
+            invocation.return_value(None);
+        },
+        |_, _, _, _, _| unreachable!("Interface has no properties"),
+        |_, _, _, _, _, _| unreachable!("Interface has no properties"),
+    );
+
+    if let Err(err) = res {
+        error!("Failed to export D-Bus interface: {err}");
+    }
+}
+
+/// Handle `--remote`/`--remote-send` by calling into the running instance instead of starting one
+pub fn remote(app_id: &str, options: &Options, files: &[String]) -> glib::ExitCode {
+    let connection = match gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE) {
+        Ok(connection) => connection,
+        Err(err) => {
+            eprintln!("Could not connect to the session bus: {err}");
+            return glib::ExitCode::FAILURE;
+        }
+    };
+
+    let object_path = object_path(app_id);
+    let call = |method: &str, params: Option<glib::Variant>| {
+        connection
+            .call_sync(
+                Some(app_id),
+                &object_path,
+                INTERFACE_NAME,
+                method,
+                params.as_ref(),
+                None,
+                gio::DBusCallFlags::NO_AUTO_START,
+                -1,
+                gio::Cancellable::NONE,
+            )
+            .map(|_| ())
+    };
+
+    let mut result = Ok(());
+
+    if options.remote {
+        // The running instance has its own working directory
+        for file in files {
+            let path = gio::File::for_commandline_arg(file)
+                .path()
+                .map_or_else(|| file.clone(), |path| path.to_string_lossy().into_owned());
+
+            result = result.and_then(|_| call("OpenFile", Some((path, -1i32).to_variant())));
+        }
+        result = result.and_then(|_| call("FocusWindow", None));
+    }
+
+    if let Some(keys) = &options.remote_send {
+        result = result.and_then(|_| call("SendKeys", Some((keys.as_str(),).to_variant())));
+    }
+
+    match result {
+        Ok(()) => glib::ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Remote call failed, is vimdicator running? {err}");
+            glib::ExitCode::FAILURE
+        }
+    }
+}
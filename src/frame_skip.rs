@@ -0,0 +1,113 @@
+//! Skipping flushes while nvim floods the screen, e.g. with `:!cat` of a huge file. Each
+//! flush carries the whole grid, so none is lost as long as a later one is shown.
+
+use std::time::{Duration, Instant};
+
+/// A frame at 60 Hz, flushes are counted per frame
+pub const FRAME: Duration = Duration::from_micros(16_667);
+
+#[derive(Debug)]
+pub struct FrameSkip {
+    /// More flushes than this within a frame start a burst
+    max_flushes: u32,
+    /// During a burst only every `every`th flush is shown
+    every: u32,
+    frame_start: Option<Instant>,
+    /// Flushes since `frame_start`, or since the last `tick` during a burst
+    flushes: u32,
+    burst: Option<Burst>,
+}
+
+#[derive(Debug, Default)]
+struct Burst {
+    since_shown: u32,
+    skipped: u64,
+}
+
+impl FrameSkip {
+    pub fn new(max_flushes: u32, every: u32) -> Self {
+        Self {
+            max_flushes,
+            every: every.max(1),
+            frame_start: None,
+            flushes: 0,
+            burst: None,
+        }
+    }
+
+    pub fn is_bursting(&self) -> bool {
+        self.burst.is_some()
+    }
+
+    /// Whether the flush arriving at `now` is to be shown
+    pub fn flush(&mut self, now: Instant) -> bool {
+        self.flushes += 1;
+
+        let Some(burst) = self.burst.as_mut() else {
+            match self.frame_start {
+                Some(start) if now.duration_since(start) < FRAME => {}
+                _ => {
+                    self.frame_start = Some(now);
+                    self.flushes = 1;
+                }
+            }
+            // The flush starting a burst is still shown
+            if self.flushes > self.max_flushes {
+                self.burst = Some(Burst::default());
+            }
+            return true;
+        };
+
+        burst.since_shown += 1;
+        if burst.since_shown >= self.every {
+            burst.since_shown = 0;
+            true
+        } else {
+            burst.skipped += 1;
+            false
+        }
+    }
+
+    /// A frame passed during a burst. Ends it when the frame had no more flushes than a
+    /// burst starts with, with the number of flushes skipped (0 without a burst).
+    pub fn tick(&mut self) -> Option<u64> {
+        let flushes = std::mem::take(&mut self.flushes);
+        if self.burst.is_some() && flushes > self.max_flushes {
+            return None;
+        }
+        self.frame_start = None;
+        Some(self.burst.take().map_or(0, |burst| burst.skipped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst() {
+        let start = Instant::now();
+        let mut skip = FrameSkip::new(2, 3);
+
+        // Flushes spread over frames are all shown
+        assert!(skip.flush(start));
+        assert!(skip.flush(start + FRAME));
+        assert!(skip.flush(start + FRAME * 2));
+        assert!(!skip.is_bursting());
+
+        // The third flush of a frame starts the burst
+        let now = start + FRAME * 3;
+        assert!(skip.flush(now));
+        assert!(skip.flush(now));
+        assert!(skip.flush(now));
+        assert!(skip.is_bursting());
+
+        let shown: Vec<_> = (0..6).map(|_| skip.flush(now)).collect();
+        assert_eq!(shown, [false, false, true, false, false, true]);
+        assert_eq!(skip.tick(), None);
+
+        skip.flush(now);
+        assert_eq!(skip.tick(), Some(5));
+        assert!(!skip.is_bursting());
+    }
+}
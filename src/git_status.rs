@@ -0,0 +1,186 @@
+//! `git status` of the file browser's root, shown next to its breadcrumbs in the path bar
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use gettextrs::gettext;
+use gtk::gio;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Conflicted,
+    Modified,
+    Added,
+    Renamed,
+    Deleted,
+    Untracked,
+    Ignored,
+}
+
+impl Status {
+    /// From the two status letters of a porcelain entry, the work tree's taking precedence
+    fn parse(xy: &[u8]) -> Option<Self> {
+        let [x, y] = *xy else {
+            return None;
+        };
+        Some(match (x, y) {
+            (b'?', b'?') => Self::Untracked,
+            (b'!', b'!') => Self::Ignored,
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => Self::Conflicted,
+            (_, b'M' | b'T') | (b'M' | b'T', b' ') => Self::Modified,
+            (_, b'D') | (b'D', b' ') => Self::Deleted,
+            (b'A', b' ') | (_, b'A') => Self::Added,
+            (b'R' | b'C', b' ') | (_, b'R' | b'C') => Self::Renamed,
+            _ => return None,
+        })
+    }
+
+    /// The letter `git status --short` shows
+    pub fn letter(self) -> &'static str {
+        match self {
+            Self::Conflicted => "U",
+            Self::Modified => "M",
+            Self::Added => "A",
+            Self::Renamed => "R",
+            Self::Deleted => "D",
+            Self::Untracked => "?",
+            Self::Ignored => "!",
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Self::Conflicted => gettext("Conflicted"),
+            Self::Modified => gettext("Modified"),
+            Self::Added => gettext("Added"),
+            Self::Renamed => gettext("Renamed"),
+            Self::Deleted => gettext("Deleted"),
+            Self::Untracked => gettext("Untracked"),
+            Self::Ignored => gettext("Ignored"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub status: Status,
+    /// Absolute, directories end with a `/`
+    pub path: PathBuf,
+}
+
+/// Entries of `git status --porcelain -z` output, whose paths are relative to the top of the
+/// repository at `top`. Sorted by status, then path.
+fn parse(output: &[u8], top: &Path) -> Vec<Entry> {
+    let mut fields = output.split(|&byte| byte == 0).filter(|f| !f.is_empty());
+    let mut entries = Vec::new();
+
+    while let Some(field) = fields.next() {
+        let (Some(xy), Some(path)) = (field.get(..2), field.get(3..)) else {
+            continue;
+        };
+        // Renames and copies are followed by the path they came from
+        if xy.iter().any(|c| matches!(c, b'R' | b'C')) {
+            fields.next();
+        }
+        let Some(status) = Status::parse(xy) else {
+            continue;
+        };
+        entries.push(Entry {
+            status,
+            path: top.join(&*String::from_utf8_lossy(path)),
+        });
+    }
+
+    entries.sort_by(|a, b| (a.status, &a.path).cmp(&(b.status, &b.path)));
+    entries
+}
+
+/// Counts per status like "M 3  ? 1", ignored files left out. Empty for a clean tree.
+pub fn summary(entries: &[Entry]) -> String {
+    let mut counts: Vec<(Status, usize)> = Vec::new();
+    for entry in entries.iter().filter(|e| e.status != Status::Ignored) {
+        match counts.last_mut() {
+            Some((status, count)) if *status == entry.status => *count += 1,
+            _ => counts.push((entry.status, 1)),
+        }
+    }
+
+    counts
+        .iter()
+        .map(|(status, count)| format!("{} {count}", status.letter()))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+fn status_blocking(root: &Path) -> Option<Vec<Entry>> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .output()
+            .map_err(|err| log::warn!("Could not run git: {err}"))
+            .ok()?;
+        output.status.success().then_some(output.stdout)
+    };
+
+    // Outside a repository this fails, quietly
+    let top = git(&["rev-parse", "--show-toplevel"])?;
+    let top = PathBuf::from(String::from_utf8_lossy(&top).trim_end());
+    let output = git(&["status", "--porcelain", "-z", "--ignored", "--", "."])?;
+    Some(parse(&output, &top))
+}
+
+/// Changed, untracked and ignored files under `root`, `None` outside of a repository
+pub async fn status(root: String) -> Option<Vec<Entry>> {
+    gio::spawn_blocking(move || status_blocking(Path::new(&root)))
+        .await
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = b" M src/main.rs\0R  new.rs\0old.rs\0?? notes.txt\0!! target/\0A  added.rs\0UU both.rs\0";
+        let entries = parse(output, Path::new("/repo"));
+        let statuses: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.status, entry.path.display().to_string()))
+            .collect();
+
+        assert_eq!(
+            statuses,
+            [
+                (Status::Conflicted, "/repo/both.rs".to_string()),
+                (Status::Modified, "/repo/src/main.rs".to_string()),
+                (Status::Added, "/repo/added.rs".to_string()),
+                (Status::Renamed, "/repo/new.rs".to_string()),
+                (Status::Untracked, "/repo/notes.txt".to_string()),
+                (Status::Ignored, "/repo/target/".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let entry = |status| Entry {
+            status,
+            path: PathBuf::new(),
+        };
+        let entries = [
+            entry(Status::Modified),
+            entry(Status::Modified),
+            entry(Status::Untracked),
+            entry(Status::Ignored),
+        ];
+
+        assert_eq!(summary(&entries), "M 2  ? 1");
+        assert_eq!(summary(&[entry(Status::Ignored)]), "");
+    }
+}
@@ -1,9 +1,61 @@
-use gtk::gdk;
 use gtk::Inhibit;
+use gtk::{gdk, glib};
 
 use log::debug;
 
+/// How the Super modifier (Cmd on macOS) is forwarded to nvim
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuperKey {
+    /// `D` on macOS, ignored elsewhere
+    #[default]
+    Platform,
+    /// As `<D-…>`, understood by nvim 0.10+
+    D,
+    /// Same as Alt
+    Alt,
+    Ignore,
+}
+
+impl SuperKey {
+    /// Value of the `super-key` setting
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "d" => Self::D,
+            "alt" => Self::Alt,
+            "ignore" => Self::Ignore,
+            _ => Self::Platform,
+        }
+    }
+
+    fn resolve(self) -> Self {
+        match self {
+            Self::Platform if cfg!(target_os = "macos") => Self::D,
+            Self::Platform => Self::Ignore,
+            other => other,
+        }
+    }
+}
+
+/// GDK reports Command as Meta on macOS
+const CMD_IS_META: bool = cfg!(target_os = "macos");
+
+fn super_mask() -> gdk::ModifierType {
+    if CMD_IS_META {
+        gdk::ModifierType::SUPER_MASK | gdk::ModifierType::META_MASK
+    } else {
+        gdk::ModifierType::SUPER_MASK
+    }
+}
+
 pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> String {
+    keyval_to_input_string_with(in_str, in_state, SuperKey::Platform)
+}
+
+pub fn keyval_to_input_string_with(
+    in_str: &str,
+    in_state: gdk::ModifierType,
+    super_key: SuperKey,
+) -> String {
     let mut val = in_str;
     let mut state = in_state;
     let empty = in_str.is_empty();
@@ -47,9 +99,17 @@ pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> Stri
     if state.contains(gdk::ModifierType::CONTROL_MASK) {
         mod_chars.push("C");
     }
-    if state.contains(gdk::ModifierType::ALT_MASK) || state.contains(gdk::ModifierType::META_MASK) {
+    let super_key = super_key.resolve();
+    let is_super = state.intersects(super_mask());
+    if state.contains(gdk::ModifierType::ALT_MASK)
+        || (!CMD_IS_META && state.contains(gdk::ModifierType::META_MASK))
+        || (is_super && super_key == SuperKey::Alt)
+    {
         mod_chars.push("A");
     }
+    if is_super && super_key == SuperKey::D {
+        mod_chars.push("D");
+    }
 
     let sep = if empty { "" } else { "-" };
     let input = [mod_chars.as_slice(), &[val]].concat().join(sep);
@@ -102,23 +162,43 @@ fn map_keyval(v: &str) -> Option<&'static str> {
     Some(v)
 }
 
-pub fn convert_key(keyval: gdk::Key, modifiers: gdk::ModifierType) -> Option<String> {
+pub fn convert_key(
+    keyval: gdk::Key,
+    modifiers: gdk::ModifierType,
+    super_key: SuperKey,
+) -> Option<String> {
     if let Some(ref keyval_name) = keyval.name() {
         if let Some(cnvt) = map_keyval(keyval_name.as_str()) {
-            return Some(keyval_to_input_string(cnvt, modifiers));
+            return Some(keyval_to_input_string_with(cnvt, modifiers, super_key));
         }
     }
 
     keyval
         .to_unicode()
-        .map(|ch| keyval_to_input_string(&ch.to_string(), modifiers))
+        .map(|ch| keyval_to_input_string_with(&ch.to_string(), modifiers, super_key))
+}
+
+/// Whether a Super shortcut is listed in `super-key-reserved` and belongs to the GUI
+pub fn is_reserved(keyval: gdk::Key, modifiers: gdk::ModifierType, reserved: &glib::StrV) -> bool {
+    if !modifiers.intersects(super_mask()) {
+        return false;
+    }
+
+    let modifiers = modifiers & gtk::accelerator_get_default_mod_mask();
+    let keyval = keyval.to_lower();
+
+    reserved.iter().any(|accel| {
+        gtk::accelerator_parse(accel.to_str())
+            .is_some_and(|(key, mods)| key.to_lower() == keyval && mods == modifiers)
+    })
 }
 
 pub fn gtk_key_press_to_vim_input(
     keyval: gdk::Key,
     modifiers: gdk::ModifierType,
+    super_key: SuperKey,
 ) -> (Inhibit, Option<String>) {
-    if let Some(input) = convert_key(keyval, modifiers) {
+    if let Some(input) = convert_key(keyval, modifiers, super_key) {
         debug!("nvim_input -> {}", input);
 
         (Inhibit(true), Some(input))
@@ -127,10 +207,147 @@ pub fn gtk_key_press_to_vim_input(
     }
 }
 
+/// Text committed by an input method, in `nvim_input` notation
+pub fn im_commit_to_vim_input(text: &str) -> String {
+    text.replace('<', "<lt>")
+}
+
+/// Keeps an input method commit and the raw key press of the same event from both reaching nvim.
+///
+/// The IM context sees key events first: keys it claims only arrive as a commit, but some input
+/// methods commit the text and still let the key through, which would type it twice.
+#[derive(Debug, Default)]
+pub struct ImFilter {
+    /// Event time and text of the last commit
+    last_commit: Option<(u32, String)>,
+}
+
+impl ImFilter {
+    pub fn commit(&mut self, time: u32, text: &str) -> String {
+        self.last_commit = Some((time, text.to_string()));
+        im_commit_to_vim_input(text)
+    }
+
+    /// `text` is what the key would type without modifiers, `input` what would be sent for it
+    pub fn key_pressed(&mut self, time: u32, text: Option<&str>, input: String) -> Option<String> {
+        match self.last_commit.take() {
+            Some((commit_time, commit)) if commit_time == time && text == Some(&commit) => None,
+            _ => Some(input),
+        }
+    }
+}
+
+/// Turns (possibly fractional) scroll deltas into whole wheel steps,
+/// carrying the remainder over so slow touchpad swipes still scroll
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScrollAccumulator {
+    pending: f64,
+}
+
+impl ScrollAccumulator {
+    /// Returns the number of whole steps, negative for up/left
+    pub fn accumulate(&mut self, delta: f64) -> i64 {
+        // Don't let leftovers from the opposite direction eat into a reversal
+        if self.pending * delta < 0.0 {
+            self.pending = 0.0;
+        }
+
+        self.pending += delta;
+        let steps = self.pending.trunc();
+        self.pending -= steps;
+
+        steps as i64
+    }
+}
+
+/// Factor a pinch has to spread or narrow by for one step
+const PINCH_STEP: f64 = 1.25;
+
+/// Turns the scale of a pinch gesture, relative to where it began, into whole steps
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PinchAccumulator {
+    steps: i32,
+}
+
+impl PinchAccumulator {
+    /// Steps since the last call, negative when narrowing. `scale` is 1 where the gesture began.
+    pub fn accumulate(&mut self, scale: f64) -> i32 {
+        if scale <= 0.0 {
+            return 0;
+        }
+        let steps = (scale.ln() / PINCH_STEP.ln()).trunc() as i32;
+        let new = steps - self.steps;
+        self.steps = steps;
+        new
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_scroll_accumulator() {
+        let mut acc = ScrollAccumulator::default();
+
+        // Discrete wheel ticks
+        assert_eq!(acc.accumulate(1.0), 1);
+        assert_eq!(acc.accumulate(-3.0), -3);
+
+        // Fractional touchpad deltas add up
+        assert_eq!(acc.accumulate(0.4), 0);
+        assert_eq!(acc.accumulate(0.4), 0);
+        assert_eq!(acc.accumulate(0.4), 1);
+
+        // Reversing direction drops the leftover
+        assert_eq!(acc.accumulate(-0.9), 0);
+        assert_eq!(acc.accumulate(-0.2), -1);
+    }
+
+    #[test]
+    fn test_pinch_accumulator() {
+        let mut acc = PinchAccumulator::default();
+
+        assert_eq!(acc.accumulate(1.1), 0);
+        assert_eq!(acc.accumulate(1.3), 1);
+        // Two steps at once, 1.25³ is about 1.95
+        assert_eq!(acc.accumulate(2.0), 2);
+        // Narrowing back past where it began
+        assert_eq!(acc.accumulate(0.7), -4);
+        assert_eq!(acc.accumulate(0.0), 0);
+    }
+
+    #[test]
+    fn test_im_filter() {
+        let mut filter = ImFilter::default();
+
+        // Commit followed by the same key press of the same event
+        assert_eq!(filter.commit(1, "a"), "a");
+        assert_eq!(filter.key_pressed(1, Some("a"), "a".to_string()), None);
+
+        // Keys the IM claims never reach key_pressed, later presses go through
+        assert_eq!(filter.commit(2, "b"), "b");
+        assert_eq!(
+            filter.key_pressed(3, Some("b"), "b".to_string()),
+            Some("b".to_string())
+        );
+
+        // Unrelated keys of the committing event, e.g. a composed character
+        assert_eq!(filter.commit(4, "é"), "é");
+        assert_eq!(
+            filter.key_pressed(4, Some("e"), "e".to_string()),
+            Some("e".to_string())
+        );
+
+        // Keys without text
+        assert_eq!(
+            filter.key_pressed(5, None, "<Esc>".to_string()),
+            Some("<Esc>".to_string())
+        );
+
+        assert_eq!(filter.commit(6, "<"), "<lt>");
+    }
+
     #[test]
     fn test_keyval_to_input_string() {
         macro_rules! test {
@@ -154,10 +371,23 @@ mod tests {
             "a", SHIFT_MASK == "<S-a>";
             "a", SHIFT_MASK | CONTROL_MASK | ALT_MASK == "<S-C-A-a>";
             "6", CONTROL_MASK == "<C-^>";
-            "6", CONTROL_MASK | META_MASK == "<C-A-6>";
             "2", CONTROL_MASK == "<C-@>";
             "2", CONTROL_MASK | ALT_MASK == "<C-A-2>";
             "j", SUPER_MASK == "j";
         }
+
+        let sup = gdk::ModifierType::SUPER_MASK;
+        assert_eq!(keyval_to_input_string_with("j", sup, SuperKey::D), "<D-j>");
+        assert_eq!(
+            keyval_to_input_string_with("j", sup, SuperKey::Alt),
+            "<A-j>"
+        );
+        assert_eq!(keyval_to_input_string_with("j", sup, SuperKey::Ignore), "j");
+
+        let control_meta = gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::META_MASK;
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(keyval_to_input_string("6", control_meta), "<C-A-6>");
+        #[cfg(target_os = "macos")]
+        assert_eq!(keyval_to_input_string("6", control_meta), "<C-D-6>");
     }
 }
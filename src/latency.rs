@@ -0,0 +1,101 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Samples kept for the percentile window
+const WINDOW: usize = 200;
+
+/// Measures the time from a key press being sent to nvim to the flush redrawing the cursor
+#[derive(Debug, Default)]
+pub struct LatencyTracer {
+    enabled: bool,
+    pending: VecDeque<Instant>,
+    samples: VecDeque<Duration>,
+    /// Samples recorded since the last log line
+    unlogged: usize,
+}
+
+impl LatencyTracer {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        *self = Self {
+            enabled,
+            ..Self::default()
+        };
+    }
+
+    pub fn input(&mut self) {
+        if self.enabled {
+            self.pending.push_back(Instant::now());
+        }
+    }
+
+    /// A flush that touched the cursor cell, completes every pending input
+    pub fn flushed(&mut self) {
+        let Some(oldest) = self.pending.front().copied() else { return; };
+        self.pending.clear();
+
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(oldest.elapsed());
+
+        self.unlogged += 1;
+        if self.unlogged == WINDOW {
+            self.unlogged = 0;
+            if let Some((p50, p95)) = self.percentiles() {
+                log::debug!("input latency p50 {p50:?} p95 {p95:?}");
+            }
+        }
+    }
+
+    /// p50 and p95 of the recent samples
+    pub fn percentiles(&self) -> Option<(Duration, Duration)> {
+        let mut samples: Vec<_> = self.samples.iter().copied().collect();
+        samples.sort_unstable();
+
+        Some((percentile(&samples, 50)?, percentile(&samples, 95)?))
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], p: usize) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile(&samples, 50), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&samples, 95), Some(Duration::from_millis(95)));
+        assert_eq!(
+            percentile(&samples[..1], 95),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(percentile(&[], 50), None);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut tracer = LatencyTracer::default();
+        tracer.input();
+        tracer.flushed();
+        assert_eq!(tracer.percentiles(), None);
+
+        tracer.set_enabled(true);
+        tracer.input();
+        tracer.input();
+        tracer.flushed();
+        assert_eq!(tracer.samples.len(), 1);
+    }
+}
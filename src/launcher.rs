@@ -0,0 +1,82 @@
+//! vimdicator's entry in docks and taskbars. GTK 4 can only name the window icon, a variant
+//! with a dot for unsaved changes is installed next to the regular one. Progress and counts
+//! go out as the `com.canonical.Unity.LauncherEntry` signal, which Ubuntu's dock, Dash to Dock,
+//! Plank and KDE's task manager show on the entry of the `.desktop` file. Elsewhere nobody
+//! listens and nothing is shown.
+
+use gtk::{gio, glib, prelude::*};
+
+/// Icon of the window, the name of the `.desktop` file too
+pub const ICON: &str = "io.github.polymeilex.vimdicator";
+
+/// [`ICON`] with a dot for a buffer with unsaved changes
+pub const MODIFIED_ICON: &str = "io.github.polymeilex.vimdicator-modified";
+
+const INTERFACE_NAME: &str = "com.canonical.Unity.LauncherEntry";
+const OBJECT_PATH: &str = "/io/github/polymeilex/vimdicator/launcher";
+
+pub fn icon_name(modified: bool) -> &'static str {
+    if modified {
+        MODIFIED_ICON
+    } else {
+        ICON
+    }
+}
+
+/// Average of the jobs' fractions, jobs without one don't count. `None` without any.
+pub fn combine(fractions: impl IntoIterator<Item = Option<f64>>) -> Option<f64> {
+    let (sum, known) = fractions
+        .into_iter()
+        .flatten()
+        .fold((0.0, 0), |(sum, known), fraction| {
+            (sum + fraction.clamp(0.0, 1.0), known + 1)
+        });
+    (known > 0).then(|| sum / f64::from(known))
+}
+
+/// What the entry shows, the default hides both
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LauncherState {
+    /// Bar from 0 to 1 over the icon
+    pub progress: Option<f64>,
+    /// Badge with a number
+    pub count: Option<usize>,
+}
+
+/// Show `state` on the entry of `app`, which must be registered on the session bus
+pub fn update(app: &gio::Application, state: LauncherState) {
+    let Some(connection) = app.dbus_connection() else {
+        return;
+    };
+
+    let properties = glib::VariantDict::new(None);
+    properties.insert("progress-visible", state.progress.is_some());
+    properties.insert("progress", state.progress.unwrap_or_default());
+    properties.insert("count-visible", state.count.is_some());
+    properties.insert("count", state.count.unwrap_or_default() as i64);
+
+    let uri = format!("application://{ICON}.desktop");
+    let parameters = glib::Variant::tuple_from_iter([uri.to_variant(), properties.end()]);
+    if let Err(err) = connection.emit_signal(
+        None,
+        OBJECT_PATH,
+        INTERFACE_NAME,
+        "Update",
+        Some(&parameters),
+    ) {
+        log::warn!("Could not update the launcher entry: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combine() {
+        assert_eq!(combine([]), None);
+        assert_eq!(combine([None, None]), None);
+        assert_eq!(combine([Some(0.5), None, Some(1.0)]), Some(0.75));
+        assert_eq!(combine([Some(2.0)]), Some(1.0));
+    }
+}
@@ -0,0 +1,41 @@
+//! Numbers, sizes and times written the way the user's locale writes them. Rust's own
+//! formatting knows no locales, text shown in the GUI goes through these instead.
+
+use gtk::glib::{self, translate::FromGlibPtrFull};
+
+/// `n` with the digits grouped as in `1,234,567` or `1.234.567`
+pub fn number(n: u64) -> String {
+    // printf's `'` flag groups digits like LC_NUMERIC says, which GTK set from the environment
+    unsafe {
+        let formatted = glib::ffi::g_strdup_printf(c"%'llu".as_ptr(), n as std::ffi::c_ulonglong);
+        glib::GString::from_glib_full(formatted).into()
+    }
+}
+
+/// `n` of `usize` counts, see [`number`]
+pub fn count(n: usize) -> String {
+    number(n as u64)
+}
+
+/// `bytes` in kB, MB... with the locale's decimal mark
+pub fn size(bytes: u64) -> String {
+    glib::format_size(bytes).into()
+}
+
+/// Date and time at `unix` seconds since the epoch in the locale's format and local time
+pub fn date_time(unix: i64) -> Option<String> {
+    let time = glib::DateTime::from_unix_local(unix).ok()?;
+    time.format("%c").ok().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number() {
+        // Tests run in the C locale, which doesn't group
+        assert_eq!(number(1_234_567), "1234567");
+        assert_eq!(count(0), "0");
+    }
+}
@@ -21,18 +21,43 @@
 #![allow(clippy::single_match)]
 
 mod application;
+mod autosave;
+mod clock;
 mod config;
+mod crash;
+mod dbus;
+mod frame_skip;
+mod git_status;
 mod input;
+mod latency;
+mod launcher;
+mod locale;
+mod markdown;
+mod notify;
 mod nvim;
+mod options;
+mod paths;
+mod portal;
+mod profiling;
+mod project;
+mod recent;
+mod search;
+mod session;
+mod settings;
+mod theme;
+mod title;
+mod trash;
 mod widgets;
 
-use nvim::{ExtLineGridMap, ExtPopupMenu, ExtTabline, NvimEvent, RedrawEvent};
+use nvim::{
+    protocol::{self, Coverage},
+    NvimEvent,
+};
 
 use application::VimdicatorApplication;
-use config::{GETTEXT_PACKAGE, LOCALEDIR, PKGDATADIR};
+use config::GETTEXT_PACKAGE;
 use gettextrs::{bind_textdomain_codeset, bindtextdomain, textdomain};
 use gtk::{gdk, gio, glib, prelude::*};
-use std::collections::HashMap;
 
 fn main() -> glib::ExitCode {
     glib_logger::init(&glib_logger::SIMPLE);
@@ -40,87 +65,163 @@ fn main() -> glib::ExitCode {
 
     let (gtk_tx, gtk_rx) = glib::MainContext::channel::<NvimEvent>(glib::Priority::default());
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<nvim::GtkToNvimEvent>();
+    let nvim = nvim::NvimBridge::new(tx);
+
+    let (options, args) = match options::Options::parse(std::env::args()) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{err}");
+            return glib::ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(path) = &options.trace {
+        if let Err(err) = profiling::init(path) {
+            eprintln!("Could not write the trace to {path}: {err}");
+            return glib::ExitCode::FAILURE;
+        }
+    }
+
+    protocol::set_strict(options.strict_ui);
+
+    // Has to happen before GTK is initialized and before any other thread is spawned
+    if let Some(renderer) = &options.renderer {
+        std::env::set_var("GSK_RENDERER", renderer);
+    }
+
+    let app_id = application::app_id(&options);
+
+    if options.is_remote() {
+        let files: Vec<_> = args
+            .iter()
+            .skip(1)
+            .filter(|arg| !arg.starts_with('-'))
+            .cloned()
+            .collect();
+        return dbus::remote(&app_id, &options, &files);
+    }
+
+    crash::install_hook(nvim.clone());
+
+    let rpc_timeout =
+        std::time::Duration::from_millis(settings::settings().uint("rpc-timeout").into());
 
     let rt = tokio::runtime::Runtime::new().unwrap();
-    rt.spawn(nvim::run(rx, gtk_tx));
+    rt.spawn(nvim::run(rx, gtk_tx, options.clone(), rpc_timeout));
 
     // Set up gettext translations
-    bindtextdomain(GETTEXT_PACKAGE, LOCALEDIR).expect("Unable to bind the text domain");
+    bindtextdomain(GETTEXT_PACKAGE, paths::localedir()).expect("Unable to bind the text domain");
     bind_textdomain_codeset(GETTEXT_PACKAGE, "UTF-8")
         .expect("Unable to set the text domain encoding");
     textdomain(GETTEXT_PACKAGE).expect("Unable to switch to the text domain");
 
-    // Load resources
-    let resources = gio::Resource::load(PKGDATADIR.to_owned() + "/vimdicator.gresource")
-        .expect("Could not load resources");
+    let Some(resources) = load_resources() else {
+        return glib::ExitCode::FAILURE;
+    };
     gio::resources_register(&resources);
 
-    let app = VimdicatorApplication::new(
-        "io.github.polymeilex.vimdicator",
-        &gio::ApplicationFlags::empty(),
-        tx,
-    );
+    let flags = if options.new_instance {
+        gio::ApplicationFlags::NON_UNIQUE
+    } else {
+        gio::ApplicationFlags::empty()
+    };
+
+    if std::env::var_os(clock::VIRTUAL_ENV).is_some() {
+        clock::use_virtual();
+    }
+
+    // X11 has no app-id, GTK names the window class after the program instead
+    if app_id != application::DEFAULT_APP_ID {
+        glib::set_prgname(Some(app_id.as_str()));
+    }
+
+    let app = VimdicatorApplication::new(&app_id, &flags, nvim);
+    app.set_window_options(options.window);
+    if let Some(addr) = &options.server {
+        app.set_server(addr);
+    }
+
+    let marker = match session::Marker::create() {
+        Ok((marker, unclean)) => {
+            app.set_offer_restore(unclean);
+            Some(marker)
+        }
+        Err(err) => {
+            log::warn!("Could not mark the session as running: {err}");
+            None
+        }
+    };
 
     gtk_rx.attach(None, {
         let app = app.clone();
-        let mut grid_map = ExtLineGridMap::new();
-        let mut popup_menu = ExtPopupMenu::new();
-        let mut tabline = ExtTabline::new();
-        let mut flush_state = FlushState::default();
-        let mut style = HashMap::new();
-
-        let mut default_colors = nvim::Colors {
-            foreground: Some(nvim::Color {
-                r: 1.0,
-                g: 1.0,
-                b: 1.0,
-            }),
-            background: Some(nvim::Color {
-                r: 0.0,
-                g: 0.0,
-                b: 0.0,
-            }),
-            special: Some(nvim::Color {
-                r: 1.0,
-                g: 0.0,
-                b: 0.0,
-            }),
-            // ..Default::default()
-        };
-
         move |event| {
             if let Some(window) = app.active_window() {
                 let window: widgets::VimdicatorWindow = window.downcast().unwrap();
 
                 match event {
-                    NvimEvent::Redraw(events) => {
-                        let flushed = handle_redraw_event(
-                            &mut default_colors,
-                            &mut style,
-                            &mut flush_state,
-                            &mut grid_map,
-                            &mut popup_menu,
-                            &mut tabline,
-                            &events,
-                        );
+                    NvimEvent::Flush(flush) => {
+                        let _span = profiling::span("apply flush");
+                        let flush = *flush;
+                        let grid_widget = window.ext_line_grid();
 
-                        if flushed {
-                            let grid_widget = window.ext_line_grid();
+                        if let Some(guifont) = &flush.guifont {
+                            grid_widget.set_guifont(guifont);
+                        }
 
-                            if let Some(grid) = grid_map.get_default() {
-                                let mut grid = grid.clone();
-                                grid.style = style.clone();
-                                grid.default_colors = default_colors.clone();
-                                grid_widget.set_grid(grid);
+                        if flush.highlights_changed {
+                            if let Some(id) = flush.cursor_line_highlight {
+                                grid_widget.set_cursor_line_highlight(id);
                             }
 
-                            if flush_state.tabline_changed {
-                                window.ext_tabline().update_tabs(&tabline);
-                            }
+                            let (bar, thumb) = flush.scrollbar_colors;
+                            window.set_scrollbar_colors(bar, thumb);
+                            window.ext_popup_menu().set_group_blend(flush.popup_blend);
+                        }
 
-                            if let Some(popup) = popup_menu.get() {
+                        grid_widget.push_scrollback(
+                            flush.scrolled_off,
+                            settings::settings().uint("scrollback-lines") as usize,
+                        );
+
+                        if let Some(grid) = flush.grid {
+                            window.show_grid(grid);
+                        }
+
+                        if let Some(viewport) = flush.viewport {
+                            window.set_viewport(viewport);
+                        }
+
+                        for option in flush.options {
+                            apply_option(&window, option);
+                        }
+
+                        if let Some(title) = flush.title {
+                            window.set_nvim_title(title);
+                        }
+
+                        if let Some(icon) = flush.icon {
+                            window.set_nvim_icon(icon);
+                        }
+
+                        if let Some(mode) = flush.mode {
+                            let mode = nvim::Mode::from_name(&mode);
+                            window.dismiss_signature_help();
+                            window.set_mode(mode);
+                        }
+
+                        if flush.cursor_changed {
+                            window.cursor_flushed();
+                        }
+
+                        if let Some(tabline) = &flush.tabline {
+                            window.ext_tabline().update_tabs(tabline);
+                        }
+
+                        // Only the last popup state of a flush is applied, and only if it changed
+                        match (flush.popup_changed, flush.popup) {
+                            (true, Some(popup)) => {
                                 let ext_popup_menu = window.ext_popup_menu();
-                                ext_popup_menu.set_items(popup.items.clone());
+                                ext_popup_menu.set_items(popup.items);
                                 ext_popup_menu.select(popup.selected);
 
                                 let cell_metrics = grid_widget.cell_metrics();
@@ -136,13 +237,126 @@ fn main() -> glib::ExitCode {
 
                                 ext_popup_menu.popup();
                                 window.focus();
-                            } else {
-                                window.ext_popup_menu().popdown();
                             }
-
-                            flush_state = FlushState::default();
+                            (true, None) => window.ext_popup_menu().popdown(),
+                            (false, Some(popup)) if flush.popup_selection_changed => {
+                                window.ext_popup_menu().select(popup.selected);
+                            }
+                            _ => {}
                         }
                     }
+                    NvimEvent::Connecting { addr, attempt } => {
+                        window.set_connecting(&addr, attempt);
+                    }
+                    NvimEvent::Connected => window.set_connected(),
+                    NvimEvent::Unresponsive(unresponsive) => {
+                        window.set_unresponsive(unresponsive);
+                    }
+                    NvimEvent::ConnectFailed { addr, error } => {
+                        window.set_connect_failed(&addr, &error);
+                    }
+                    NvimEvent::Subscription(nvim::Subscription::Buffer(buffer)) => {
+                        window.set_buffer(buffer);
+                    }
+                    NvimEvent::Subscription(nvim::Subscription::FileOpened(path)) => {
+                        recent::add(&path);
+                    }
+                    NvimEvent::Subscription(nvim::Subscription::Oldfiles(oldfiles)) => {
+                        window.set_oldfiles(oldfiles);
+                    }
+                    NvimEvent::Subscription(nvim::Subscription::Cursor {
+                        line,
+                        column,
+                        line_count,
+                    }) => {
+                        window.status_footer().set_cursor(line, column, line_count);
+                    }
+                    NvimEvent::Subscription(nvim::Subscription::Background(background)) => {
+                        window.set_nvim_background(&background);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Notify { message, level }) => {
+                        log::info!("nvim notify ({level}): {message}");
+                        window.notify(&message, level);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Hover {
+                        grid,
+                        row,
+                        col,
+                        markdown,
+                    }) => {
+                        window.show_hover(grid, (col, row), &markdown);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::SignatureHelp { label, active }) => {
+                        window.show_signature_help(&label, active);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::GutterChanged) => {
+                        window.refresh_gutter();
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::CacheStats) => {
+                        window.report_cache_stats();
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Cursor(args)) => {
+                        window.apply_cursor_command(&args);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Quickfix { list, show }) => {
+                        window.set_quickfix(list, show);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Registers { registers, show }) => {
+                        window.set_registers(registers, show);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Images(images)) => {
+                        window.ext_line_grid().set_images(images);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::FilePreview { path, area }) => {
+                        window.show_file_preview(&path, area);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::FileBrowser(root)) => {
+                        window.set_file_browser_root(&root);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::EditPath) => {
+                        window.edit_file_browser_path();
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::GitChanged) => {
+                        window.refresh_git_status();
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::HexView(path)) => {
+                        window.show_hex_view(&path);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Presentation) => {
+                        window.toggle_presentation();
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::AdvanceClock(ms)) => {
+                        clock::advance(std::time::Duration::from_millis(ms));
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Trash { permanent }) => {
+                        if let Some(app) = window.application() {
+                            app.activate_action(if permanent { "delete" } else { "trash" }, None);
+                        }
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::ProjectConfig {
+                        path,
+                        contents,
+                        reload,
+                    }) => {
+                        window.set_project_config(path, &contents, reload);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Progress {
+                        action,
+                        id,
+                        percent,
+                        title,
+                    }) => {
+                        window.set_progress(action, id, percent, &title);
+                    }
+                    NvimEvent::Request(request, responder) => {
+                        window.handle_request(request, responder);
+                    }
+                    NvimEvent::Gui(nvim::GuiEvent::Unknown(name, args)) => {
+                        protocol::record(&format!("Gui {name}"), Coverage::Unknown, &args);
+                    }
+                    NvimEvent::Subscription(nvim::Subscription::Unknown(name, args)) => {
+                        protocol::record(&format!("subscription {name}"), Coverage::Unknown, &args);
+                    }
                     _ => {}
                 }
             }
@@ -151,116 +365,72 @@ fn main() -> glib::ExitCode {
         }
     });
 
-    app.run()
-}
-
-#[derive(Debug, Default)]
-struct FlushState {
-    popup_changed: bool,
-    tabline_changed: bool,
-}
-
-fn handle_redraw_event(
-    default_colors: &mut nvim::Colors,
-    style_map: &mut HashMap<u64, nvim::Style>,
-    flush_state: &mut FlushState,
-    grids: &mut ExtLineGridMap,
-    popup_menu: &mut ExtPopupMenu,
-    tabline: &mut ExtTabline,
-    events: &[RedrawEvent],
-) -> bool {
-    let mut flushed = false;
-
-    for event in events {
-        match event {
-            RedrawEvent::GridResize {
-                grid,
-                width,
-                height,
-            } => {
-                grids.grid_resize(grid, *width as usize, *height as usize);
-            }
-
-            RedrawEvent::GridClear { grid } => {
-                grids.grid_clear(grid);
-            }
-
-            RedrawEvent::GridDestroy { grid } => {
-                grids.grid_destroy(grid);
-            }
-
-            RedrawEvent::GridScroll {
-                grid,
-                top,
-                bottom,
-                left,
-                right,
-                rows,
-                columns,
-            } => {
-                grids.grid_scroll(grid, *top, *bottom, *left, *right, *rows, *columns);
-            }
-
-            RedrawEvent::GridLine {
-                grid,
-                row,
-                column_start,
-                cells,
-            } => {
-                grids.grid_line(grid, *row as usize, *column_start as usize, cells);
-            }
-
-            RedrawEvent::GridCursorGoto { grid, row, column } => {
-                grids.grid_cursor_goto(grid, *row as usize, *column as usize);
-            }
-
-            RedrawEvent::Flush => {
-                flushed = true;
-            }
-
-            RedrawEvent::PopupmenuShow {
-                items,
-                selected,
-                row,
-                col,
-                grid,
-            } => {
-                popup_menu.show(
-                    items.clone(),
-                    selected.map(|s| s as usize),
-                    *row as usize,
-                    *col as usize,
-                    *grid,
-                );
-                flush_state.popup_changed = true;
-            }
+    let code = app.run_with_args(&args);
+    profiling::flush();
+    if let Some(marker) = marker {
+        marker.remove();
+    }
 
-            RedrawEvent::TablineUpdate { current_tab, tabs } => {
-                tabline.update(current_tab.clone(), tabs.clone());
-                flush_state.tabline_changed = true;
-            }
+    for (what, count) in nvim::event::parse_warnings() {
+        log::warn!("Dropped {count} malformed {what} during the session");
+    }
 
-            RedrawEvent::PopupmenuSelect { selected } => {
-                popup_menu.select(selected.map(|s| s as usize));
-            }
+    code
+}
 
-            RedrawEvent::PopupmenuHide => {
-                popup_menu.hide();
-            }
+/// The UI files and stylesheet: a bundle named by `VIMDICATOR_RESOURCES`, the one built into
+/// the binary with the `embed-resources` feature, or the first found of the installed one and
+/// one next to the executable
+fn load_resources() -> Option<gio::Resource> {
+    let named = std::env::var_os(paths::RESOURCES_ENV).is_some();
+
+    #[cfg(feature = "embed-resources")]
+    if !named {
+        let bytes = include_bytes!(concat!(env!("OUT_DIR"), "/vimdicator.gresource"));
+        return Some(
+            gio::Resource::from_data(&glib::Bytes::from_static(bytes))
+                .expect("Invalid embedded resources"),
+        );
+    }
 
-            RedrawEvent::DefaultColorsSet { colors } => {
-                *default_colors = colors.clone();
-            }
+    let mut errors = Vec::new();
+    for path in paths::resources_candidates() {
+        match gio::Resource::load(&path) {
+            Ok(resources) => return Some(resources),
+            Err(err) => errors.push(format!("{}: {err}", path.display())),
+        }
+        // A bundle that was asked for isn't silently replaced by another one
+        if named {
+            break;
+        }
+    }
 
-            RedrawEvent::HighlightAttributesDefine { id, style } => {
-                *style_map.entry(*id).or_default() = style.clone();
-            }
+    eprintln!("Could not load the resources, tried:");
+    for error in errors {
+        eprintln!("  {error}");
+    }
+    None
+}
 
-            event => {
-                dbg!(event);
-            }
+/// Options the GUI reacts to, the others are recorded for the protocol diagnostics
+fn apply_option(window: &widgets::VimdicatorWindow, option: nvim::GuiOption) {
+    match option {
+        nvim::GuiOption::ShowTabLine(show_tabline) => {
+            window.ext_tabline().set_show_tabline(show_tabline);
+        }
+        nvim::GuiOption::Mousefocus(mousefocus) => {
+            window.set_mousefocus(mousefocus);
+        }
+        nvim::GuiOption::Pumblend(blend) => {
+            window.ext_popup_menu().set_blend(blend);
+        }
+        nvim::GuiOption::Unknown(name, value) => {
+            protocol::record(&format!("option_set {name}"), Coverage::Unknown, &value);
+        }
+        option => {
+            let name = format!("{option:?}");
+            let name = name.split('(').next().unwrap_or_default();
+            protocol::record(&format!("option_set {name}"), Coverage::Ignored, &option);
         }
     }
-
-    flushed
 }
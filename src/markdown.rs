@@ -0,0 +1,147 @@
+//! Minimal markdown to pango markup conversion for LSP documentation.
+//!
+//! Handles what language servers commonly send: headings, lists, fenced code blocks,
+//! inline code, bold and italic. Everything else is shown as escaped text.
+
+use gtk::glib;
+
+/// Inline delimiters and the pango tags they map to, longest first
+const EMPHASIS: &[(&str, &str)] = &[("**", "b"), ("__", "b"), ("*", "i"), ("_", "i")];
+
+pub fn to_pango(markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut code_block: Option<Vec<String>> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") {
+            match code_block.take() {
+                Some(code) => out.push(format!("<tt>{}</tt>", escape(&code.join("\n")))),
+                None => code_block = Some(Vec::new()),
+            }
+            continue;
+        }
+
+        if let Some(code) = code_block.as_mut() {
+            code.push(line.to_string());
+            continue;
+        }
+
+        out.push(block(trimmed));
+    }
+
+    // Unterminated fence, still show the code
+    if let Some(code) = code_block {
+        out.push(format!("<tt>{}</tt>", escape(&code.join("\n"))));
+    }
+
+    out.join("\n").trim().to_string()
+}
+
+fn block(line: &str) -> String {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+        let size = if level <= 2 { "large" } else { "medium" };
+        return format!(
+            "<span size=\"{size}\" weight=\"bold\">{}</span>",
+            inline(line[level..].trim())
+        );
+    }
+
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))
+    {
+        return format!("• {}", inline(item));
+    }
+
+    if matches!(line, "---" | "***" | "___") {
+        return "──────".to_string();
+    }
+
+    inline(line)
+}
+
+fn escape(text: &str) -> String {
+    glib::markup_escape_text(text).to_string()
+}
+
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    'outer: while !rest.is_empty() {
+        // No intraword underscore emphasis, identifiers like snake_case are common
+        let after_word = text[..text.len() - rest.len()]
+            .chars()
+            .last()
+            .is_some_and(char::is_alphanumeric);
+
+        if let Some(after) = rest.strip_prefix('`') {
+            if let Some(end) = after.find('`') {
+                out.push_str(&format!("<tt>{}</tt>", escape(&after[..end])));
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+
+        for (delimiter, tag) in EMPHASIS {
+            if after_word && delimiter.starts_with('_') {
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix(delimiter) {
+                // Needs a closing delimiter and content, "a * b" stays as is
+                if let Some(end) = after.find(delimiter).filter(|end| *end > 0) {
+                    if !after.starts_with(' ') {
+                        out.push_str(&format!("<{tag}>{}</{tag}>", inline(&after[..end])));
+                        rest = &after[end + delimiter.len()..];
+                        continue 'outer;
+                    }
+                }
+            }
+        }
+
+        let next = rest.chars().next().unwrap();
+        out.push_str(&escape(&next.to_string()));
+        rest = &rest[next.len_utf8()..];
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(to_pango("a `b<c>` d"), "a <tt>b&lt;c&gt;</tt> d");
+        assert_eq!(to_pango("**bold** and *it*"), "<b>bold</b> and <i>it</i>");
+        assert_eq!(to_pango("a * b & c"), "a * b &amp; c");
+        assert_eq!(to_pango("snake_case_name"), "snake_case_name");
+        assert_eq!(
+            to_pango("an _emphasized_ word"),
+            "an <i>emphasized</i> word"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            to_pango("# Title\n- one\n* two\n---"),
+            "<span size=\"large\" weight=\"bold\">Title</span>\n• one\n• two\n──────"
+        );
+    }
+
+    #[test]
+    fn test_code_block() {
+        let markdown = "```rust\nfn a() -> Vec<u8> {}\n    *x*\n```\ntext";
+        assert_eq!(
+            to_pango(markdown),
+            "<tt>fn a() -&gt; Vec&lt;u8&gt; {}\n    *x*</tt>\ntext"
+        );
+
+        assert_eq!(to_pango("```\nopen"), "<tt>open</tt>");
+    }
+}
@@ -0,0 +1,52 @@
+//! Routing of `vim.notify` messages to in-window toasts or desktop notifications
+
+use gtk::{gio, prelude::*};
+
+/// Where a message ends up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Toast,
+    Desktop,
+    Ignore,
+}
+
+/// Settings key controlling the route of a `vim.log.levels` level
+fn level_key(level: u64) -> &'static str {
+    match level {
+        4.. => "notify-error",
+        3 => "notify-warning",
+        _ => "notify-info",
+    }
+}
+
+/// Desktop notifications only make sense while the window is in the background,
+/// otherwise they are shown as toasts
+fn resolve(setting: &str, focused: bool) -> Route {
+    match setting {
+        "desktop" if !focused => Route::Desktop,
+        "desktop" | "toast" => Route::Toast,
+        _ => Route::Ignore,
+    }
+}
+
+pub fn route(settings: &gio::Settings, level: u64, focused: bool) -> Route {
+    resolve(&settings.string(level_key(level)), focused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        assert_eq!(level_key(0), "notify-info");
+        assert_eq!(level_key(2), "notify-info");
+        assert_eq!(level_key(3), "notify-warning");
+        assert_eq!(level_key(4), "notify-error");
+
+        assert_eq!(resolve("desktop", false), Route::Desktop);
+        assert_eq!(resolve("desktop", true), Route::Toast);
+        assert_eq!(resolve("toast", false), Route::Toast);
+        assert_eq!(resolve("none", false), Route::Ignore);
+    }
+}
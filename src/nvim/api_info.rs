@@ -12,6 +12,7 @@ pub struct NeovimApiInfo {
 
     pub ui_pum_set_height: bool,
     pub ui_pum_set_bounds: bool,
+    pub ui_set_focus: bool,
 }
 
 impl NeovimApiInfo {
@@ -85,6 +86,7 @@ impl NeovimApiInfo {
             {
                 "nvim_ui_pum_set_height" => self.ui_pum_set_height = true,
                 "nvim_ui_pum_set_bounds" => self.ui_pum_set_bounds = true,
+                "nvim_ui_set_focus" => self.ui_set_focus = true,
                 _ => (),
             }
         }
@@ -0,0 +1,232 @@
+use std::{fmt, future::Future};
+
+use gtk::glib;
+use nvim_rs::Value;
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+
+use super::{GtkToNvimEvent, Neovim};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeError {
+    /// The connection to nvim is gone
+    Disconnected,
+    /// nvim answered with an error
+    Nvim(String),
+    /// nvim answered with a value of an unexpected type
+    UnexpectedValue(Value),
+}
+
+impl fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected => write!(f, "nvim is not connected"),
+            Self::Nvim(err) => write!(f, "{err}"),
+            Self::UnexpectedValue(value) => write!(f, "unexpected value from nvim: {value}"),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {}
+
+/// Requests whose result is sent back to the caller
+#[derive(Debug)]
+pub enum Call {
+    Input(String),
+    Resize { width: u64, height: u64 },
+    ExecLua { code: String, args: Vec<Value> },
+    Command(String),
+    Eval(String),
+}
+
+pub type Reply = oneshot::Sender<Result<Value, BridgeError>>;
+
+/// Conversion of nvim's answers into Rust types
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::String(s) => s.into_str(),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Option<Self> {
+        // Vimscript has no booleans, options and functions answer 0/1
+        value.as_bool().or_else(|| value.as_i64().map(|v| v != 0))
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_i64()
+    }
+}
+
+impl FromValue for u64 {
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_u64()
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Array(values) => values.into_iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+fn convert<T: FromValue>(value: Value) -> Result<T, BridgeError> {
+    T::from_value(value.clone()).ok_or(BridgeError::UnexpectedValue(value))
+}
+
+/// Run a request on the GTK main loop without blocking it, `callback` gets the result.
+/// Failures are logged with `what` as context.
+pub fn spawn_local<T: 'static>(
+    what: &'static str,
+    request: impl Future<Output = Result<T, BridgeError>> + 'static,
+    callback: impl FnOnce(T) + 'static,
+) {
+    glib::MainContext::default().spawn_local(async move {
+        match request.await {
+            Ok(value) => callback(value),
+            Err(err) => log::warn!("{what} failed: {err}"),
+        }
+    });
+}
+
+impl Call {
+    /// Span name when profiling
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Input(_) => "rpc input",
+            Self::Resize { .. } => "rpc resize",
+            Self::ExecLua { .. } => "rpc exec_lua",
+            Self::Command(_) => "rpc command",
+            Self::Eval(_) => "rpc eval",
+        }
+    }
+
+    pub(super) async fn run(self, nvim: &Neovim) -> Result<Value, BridgeError> {
+        let _span = crate::profiling::span(self.name());
+
+        let result = match self {
+            Self::Input(keys) => nvim.input(&keys).await.map(Value::from),
+            Self::Resize { width, height } => nvim
+                .ui_try_resize(width as i64, height as i64)
+                .await
+                .map(|()| Value::Nil),
+            Self::ExecLua { code, args } => nvim.exec_lua(&code, args).await,
+            Self::Command(command) => nvim.command(&command).await.map(|()| Value::Nil),
+            Self::Eval(expr) => nvim.eval(&expr).await,
+        };
+
+        result.map_err(|err| BridgeError::Nvim(err.to_string()))
+    }
+}
+
+/// Handle to the nvim connection, cheap to clone and share between widgets.
+///
+/// `send` queues a request without waiting for it, the async methods resolve
+/// with nvim's answer.
+#[derive(Debug, Clone)]
+pub struct NvimBridge {
+    tx: UnboundedSender<GtkToNvimEvent>,
+}
+
+impl NvimBridge {
+    pub fn new(tx: UnboundedSender<GtkToNvimEvent>) -> Self {
+        Self { tx }
+    }
+
+    pub fn send(&self, event: GtkToNvimEvent) -> Result<(), BridgeError> {
+        self.tx.send(event).map_err(|_| BridgeError::Disconnected)
+    }
+
+    async fn call(&self, call: Call) -> Result<Value, BridgeError> {
+        let (reply, rx) = oneshot::channel();
+        self.send(GtkToNvimEvent::Call(call, reply))?;
+        rx.await.map_err(|_| BridgeError::Disconnected)?
+    }
+
+    /// Returns the number of bytes nvim consumed
+    pub async fn input(&self, keys: &str) -> Result<u64, BridgeError> {
+        convert(self.call(Call::Input(keys.to_string())).await?)
+    }
+
+    pub async fn resize(&self, width: u64, height: u64) -> Result<(), BridgeError> {
+        self.call(Call::Resize { width, height }).await.map(drop)
+    }
+
+    pub async fn exec_lua(&self, code: &str, args: Vec<Value>) -> Result<Value, BridgeError> {
+        self.call(Call::ExecLua {
+            code: code.to_string(),
+            args,
+        })
+        .await
+    }
+
+    /// Run Lua code and convert what it returns, e.g. `exec_lua_as::<String>("return ...", vec![])`
+    pub async fn exec_lua_as<T: FromValue>(
+        &self,
+        code: &str,
+        args: Vec<Value>,
+    ) -> Result<T, BridgeError> {
+        convert(self.exec_lua(code, args).await?)
+    }
+
+    pub async fn command(&self, command: &str) -> Result<(), BridgeError> {
+        self.call(Call::Command(command.to_string()))
+            .await
+            .map(drop)
+    }
+
+    pub async fn eval(&self, expr: &str) -> Result<Value, BridgeError> {
+        self.call(Call::Eval(expr.to_string())).await
+    }
+
+    /// Evaluate a Vimscript expression, e.g. `eval_as::<String>("&completeopt")`
+    pub async fn eval_as<T: FromValue>(&self, expr: &str) -> Result<T, BridgeError> {
+        convert(self.eval(expr).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(
+            convert::<String>(Value::from("menu,preview")),
+            Ok("menu,preview".to_string())
+        );
+        assert_eq!(convert::<bool>(Value::from(1)), Ok(true));
+        assert_eq!(convert::<bool>(Value::from(false)), Ok(false));
+        assert_eq!(
+            convert::<Vec<i64>>(Value::Array(vec![Value::from(1), Value::from(-2)])),
+            Ok(vec![1, -2])
+        );
+
+        assert_eq!(
+            convert::<Vec<i64>>(Value::Array(vec![Value::from(1), Value::from("a")])),
+            Err(BridgeError::UnexpectedValue(Value::Array(vec![
+                Value::from(1),
+                Value::from("a")
+            ])))
+        );
+        assert!(convert::<u64>(Value::from(-1)).is_err());
+    }
+}
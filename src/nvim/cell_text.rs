@@ -0,0 +1,197 @@
+use std::{fmt, ops::Deref};
+
+/// Bytes stored without a heap allocation, enough for nearly every grapheme nvim sends
+const INLINE_CAPACITY: usize = 22;
+
+/// Text of a single grid cell.
+///
+/// Grids hold one of these per cell and `grid_line` events repeat them, so the common
+/// short graphemes are stored inline and cloning them never allocates.
+#[derive(Clone, PartialEq, Eq)]
+pub enum CellText {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<str>),
+}
+
+impl CellText {
+    pub const SPACE: Self = Self::Inline {
+        len: 1,
+        bytes: {
+            let mut bytes = [0; INLINE_CAPACITY];
+            bytes[0] = b' ';
+            bytes
+        },
+    };
+
+    pub fn new(text: &str) -> Self {
+        if text.len() <= INLINE_CAPACITY {
+            let mut bytes = [0; INLINE_CAPACITY];
+            bytes[..text.len()].copy_from_slice(text.as_bytes());
+            Self::Inline {
+                len: text.len() as u8,
+                bytes,
+            }
+        } else {
+            Self::Heap(text.into())
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            // Only ever filled from a `&str` in `new`
+            Self::Inline { len, bytes } => std::str::from_utf8(&bytes[..*len as usize]).unwrap(),
+            Self::Heap(text) => text,
+        }
+    }
+}
+
+impl Deref for CellText {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for CellText {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl fmt::Debug for CellText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_text() {
+        assert_eq!(std::mem::size_of::<CellText>(), 24);
+
+        assert_eq!(&*CellText::SPACE, " ");
+        assert_eq!(CellText::SPACE, CellText::new(" "));
+
+        let text = CellText::new("é");
+        assert!(matches!(text, CellText::Inline { .. }));
+        assert_eq!(&*text, "é");
+
+        // Family emoji, a single grapheme of 25 bytes
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}";
+        let text = CellText::new(family);
+        assert!(matches!(text, CellText::Heap(_)));
+        assert_eq!(&*text, family);
+    }
+
+    #[test]
+    fn test_inline_capacity() {
+        let text = CellText::new(&"a".repeat(INLINE_CAPACITY));
+        assert!(matches!(text, CellText::Inline { len: 22, .. }));
+        assert_eq!(text.len(), INLINE_CAPACITY);
+
+        let text = CellText::new(&"a".repeat(INLINE_CAPACITY + 1));
+        assert!(matches!(text, CellText::Heap(_)));
+        assert_eq!(text.len(), INLINE_CAPACITY + 1);
+
+        // 7 three-byte characters fit, an eighth doesn't
+        let text = CellText::new(&"漢".repeat(7));
+        assert!(matches!(text, CellText::Inline { len: 21, .. }));
+        assert_eq!(&*text, "漢漢漢漢漢漢漢");
+        assert!(matches!(CellText::new(&"漢".repeat(8)), CellText::Heap(_)));
+
+        assert!(matches!(CellText::new(""), CellText::Inline { len: 0, .. }));
+        assert_eq!(&*CellText::new(""), "");
+    }
+
+    /// Counts the allocations of the current thread, so tests running in parallel don't add up.
+    /// Replacing the allocator affects the whole test binary, hence the feature.
+    #[cfg(feature = "count-allocations")]
+    mod counting {
+        use std::{
+            alloc::{GlobalAlloc, Layout, System},
+            cell::Cell,
+        };
+
+        thread_local! {
+            static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Counting;
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                // Not counted while the thread's locals are torn down
+                let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static ALLOCATOR: Counting = Counting;
+
+        pub fn allocations() -> usize {
+            ALLOCATIONS.with(Cell::get)
+        }
+    }
+
+    /// Time and allocations of `f`
+    #[cfg(feature = "count-allocations")]
+    fn measure<T>(f: impl FnOnce() -> T) -> (T, std::time::Duration, usize) {
+        let allocations = counting::allocations();
+        let start = std::time::Instant::now();
+        let value = f();
+        (
+            value,
+            start.elapsed(),
+            counting::allocations() - allocations,
+        )
+    }
+
+    /// Cells of `grid_line` events redrawing a 200x50 grid of code 100 times, like scrolling a
+    /// large file does: one text per cell, repeated cells cloned. Run with
+    /// `cargo test --release --features count-allocations bench -- --nocapture` for the timings.
+    #[cfg(feature = "count-allocations")]
+    #[test]
+    fn bench_grid_line_cells() {
+        const COLUMNS: usize = 200;
+        const ROWS: usize = 50 * 100;
+
+        let code = "    let résumé = items.iter().map(|item| item.len()).sum::<usize>(); // ✓ → 漢";
+        let graphemes: Vec<&str> = code
+            .char_indices()
+            .map(|(i, c)| &code[i..i + c.len_utf8()])
+            .cycle()
+            .take(COLUMNS)
+            .collect();
+
+        let (strings, string_time, string_allocations) = measure(|| {
+            (0..ROWS)
+                .map(|_| graphemes.iter().map(|&text| text.to_string()).collect())
+                .collect::<Vec<Vec<String>>>()
+        });
+        let (cells, cell_time, cell_allocations) = measure(|| {
+            (0..ROWS)
+                .map(|_| graphemes.iter().map(|&text| CellText::new(text)).collect())
+                .collect::<Vec<Vec<CellText>>>()
+        });
+        assert_eq!(strings.len(), cells.len());
+
+        println!("String:   {string_time:?}, {string_allocations} allocations");
+        println!("CellText: {cell_time:?}, {cell_allocations} allocations");
+
+        // Only the rows themselves allocate
+        assert!(string_allocations >= ROWS * COLUMNS);
+        assert!(cell_allocations <= ROWS + 1);
+    }
+}
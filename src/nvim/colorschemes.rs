@@ -0,0 +1,95 @@
+use nvim_rs::Value;
+
+use super::bridge::FromValue;
+
+/// Installed colorschemes, as returned by `require("vimdicator").colorschemes()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Colorschemes {
+    /// Name of the colorscheme applied before, `default` without one
+    pub current: String,
+    /// `background` before, "dark" or "light"
+    pub background: String,
+    pub schemes: Vec<Colorscheme>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colorscheme {
+    pub name: String,
+    /// `background` was "dark" after applying it
+    pub dark: bool,
+    /// Normal background as `#rrggbb`, `None` when it leaves it to the GUI default
+    pub background: Option<String>,
+    /// Foregrounds of Normal, Comment, Statement, String, Function, Type and Constant
+    pub foregrounds: Vec<Option<String>>,
+}
+
+/// Lua sends "" for unset colors
+fn color(value: Value) -> Option<Option<String>> {
+    String::from_value(value).map(|color| Some(color).filter(|color| !color.is_empty()))
+}
+
+impl FromValue for Colorscheme {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Array(fields) = value else {
+            return None;
+        };
+        let mut fields = fields.into_iter();
+
+        Some(Self {
+            name: String::from_value(fields.next()?)?,
+            dark: String::from_value(fields.next()?)? == "dark",
+            background: color(fields.next()?)?,
+            foregrounds: match fields.next()? {
+                Value::Array(colors) => colors.into_iter().map(color).collect::<Option<_>>()?,
+                _ => return None,
+            },
+        })
+    }
+}
+
+impl FromValue for Colorschemes {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Array(fields) = value else {
+            return None;
+        };
+        let mut fields = fields.into_iter();
+
+        Some(Self {
+            current: String::from_value(fields.next()?)?,
+            background: String::from_value(fields.next()?)?,
+            schemes: Vec::from_value(fields.next()?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value = Value::from(vec![
+            Value::from("habamax"),
+            Value::from("dark"),
+            Value::from(vec![Value::from(vec![
+                Value::from("morning"),
+                Value::from("light"),
+                Value::from("#e4e4e4"),
+                Value::from(vec![Value::from("#000000"), Value::from("")]),
+            ])]),
+        ]);
+
+        let colorschemes = Colorschemes::from_value(value).unwrap();
+        assert_eq!(colorschemes.current, "habamax");
+        assert_eq!(colorschemes.background, "dark");
+        assert_eq!(
+            colorschemes.schemes,
+            [Colorscheme {
+                name: "morning".to_string(),
+                dark: false,
+                background: Some("#e4e4e4".to_string()),
+                foregrounds: vec![Some("#000000".to_string()), None],
+            }]
+        );
+    }
+}
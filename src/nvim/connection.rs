@@ -0,0 +1,159 @@
+use std::{io, process::Stdio, time::Duration};
+
+use gtk::glib;
+use log::warn;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{nvim_command, NvimEvent};
+use crate::options::Options;
+
+pub type BoxedReader = Box<dyn AsyncRead + Send + Unpin>;
+pub type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Attempts before giving up on `--server` when `--connect-attempts` isn't passed
+const DEFAULT_ATTEMPTS: u32 = 10;
+/// Total time spent retrying when `--connect-timeout` isn't passed
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Start `nvim --embed` and talk to it over stdio
+pub fn spawn(options: &Options) -> io::Result<(BoxedReader, BoxedWriter)> {
+    let mut child = nvim_command()
+        .arg("--embed")
+        .args(&options.nvim_args)
+        .stderr(Stdio::inherit())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    Ok((
+        Box::new(child.stdout.take().unwrap()),
+        Box::new(child.stdin.take().unwrap()),
+    ))
+}
+
+/// Connect to a `nvim --listen` server, `addr` is either `host:port`, a socket path or on
+/// Windows a named pipe like `\\.\pipe\nvim`
+pub async fn connect(addr: &str) -> io::Result<(BoxedReader, BoxedWriter)> {
+    #[cfg(windows)]
+    if let Some(path) = named_pipe_path(addr) {
+        // Fails with ERROR_PIPE_BUSY while another client connects, the caller retries
+        let pipe = tokio::net::windows::named_pipe::ClientOptions::new().open(path)?;
+        let (reader, writer) = tokio::io::split(pipe);
+        return Ok((Box::new(reader), Box::new(writer)));
+    }
+
+    #[cfg(unix)]
+    if !is_tcp(addr) {
+        let (reader, writer) = tokio::net::UnixStream::connect(addr).await?.into_split();
+        return Ok((Box::new(reader), Box::new(writer)));
+    }
+
+    let (reader, writer) = tokio::net::TcpStream::connect(addr).await?.into_split();
+    Ok((Box::new(reader), Box::new(writer)))
+}
+
+/// Keep trying to connect while the server starts up, reporting each attempt to the GUI
+pub async fn connect_with_retry(
+    addr: &str,
+    options: &Options,
+    gtk_tx: &glib::Sender<NvimEvent>,
+) -> Option<(BoxedReader, BoxedWriter)> {
+    let attempts = options.connect_attempts.unwrap_or(DEFAULT_ATTEMPTS);
+    let timeout = options
+        .connect_timeout
+        .map_or(DEFAULT_TIMEOUT, Duration::from_secs);
+    let started = tokio::time::Instant::now();
+
+    let mut attempt = 1;
+    loop {
+        let error = match connect(addr).await {
+            Ok(io) => {
+                gtk_tx.send(NvimEvent::Connected).unwrap();
+                return Some(io);
+            }
+            Err(err) => err,
+        };
+
+        warn!("Connecting to {addr} failed (attempt {attempt}): {error}");
+
+        let delay = backoff(attempt);
+        if attempt >= attempts || started.elapsed() + delay > timeout {
+            gtk_tx
+                .send(NvimEvent::ConnectFailed {
+                    addr: addr.to_string(),
+                    error: error.to_string(),
+                })
+                .unwrap();
+            return None;
+        }
+
+        attempt += 1;
+        gtk_tx
+            .send(NvimEvent::Connecting {
+                addr: addr.to_string(),
+                attempt,
+            })
+            .unwrap();
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// `host:port` addresses, anything else is a socket path
+fn is_tcp(addr: &str) -> bool {
+    !addr.contains('/')
+        && addr
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+}
+
+/// Local named pipes, what nvim listens on by default on Windows, with `/` as `\`
+#[cfg(windows)]
+fn named_pipe_path(addr: &str) -> Option<String> {
+    let path = addr.replace('/', "\\");
+    (path.starts_with(r"\\.\pipe\") || path.starts_with(r"\\?\pipe\")).then_some(path)
+}
+
+/// Delay after the `attempt`th failure, doubling from 250ms up to 5s
+fn backoff(attempt: u32) -> Duration {
+    let ms = 250u64.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    Duration::from_millis(ms.min(5000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_tcp() {
+        assert!(is_tcp("127.0.0.1:6666"));
+        assert!(is_tcp("localhost:6666"));
+        assert!(is_tcp("[::1]:6666"));
+        assert!(!is_tcp("/tmp/nvim.sock"));
+        assert!(!is_tcp("nvim.sock"));
+        assert!(!is_tcp(r"\\.\pipe\nvim.1234.0"));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_named_pipe_path() {
+        assert_eq!(
+            named_pipe_path(r"\\.\pipe\nvim.1234.0").as_deref(),
+            Some(r"\\.\pipe\nvim.1234.0")
+        );
+        assert_eq!(
+            named_pipe_path("//./pipe/nvim").as_deref(),
+            Some(r"\\.\pipe\nvim")
+        );
+        assert_eq!(named_pipe_path("127.0.0.1:6666"), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_millis(250));
+        assert_eq!(backoff(2), Duration::from_millis(500));
+        assert_eq!(backoff(5), Duration::from_millis(4000));
+        assert_eq!(backoff(6), Duration::from_millis(5000));
+        assert_eq!(backoff(100), Duration::from_millis(5000));
+    }
+}
@@ -1,12 +1,220 @@
-use log::{debug, error};
+use std::{collections::BTreeMap, sync::Mutex};
+
+use log::{debug, error, warn};
 use nvim_rs::Value;
 
-#[derive(Debug, Clone)]
+use super::{cell_text::CellText, palette, Flush, GuiRequest, Responder, Subscription};
+
+#[derive(Debug)]
 pub enum NvimEvent {
+    /// A batch of redraw notifications, applied by the handler and not sent on to GTK
     Redraw(Vec<RedrawEvent>),
-    Gui(Vec<Value>),
-    Subscription(Vec<Value>),
+    /// What the redraw events up to a `flush` changed
+    Flush(Box<Flush>),
+    Gui(GuiEvent),
+    /// `rpcrequest(chan, "Gui", ...)`, nvim blocks until the responder answers
+    Request(GuiRequest, Responder),
+    Subscription(Subscription),
     Resized(Vec<Value>),
+    /// Waiting for the `--server` to come up, `attempt` is about to be made
+    Connecting {
+        addr: String,
+        attempt: u32,
+    },
+    Connected,
+    /// A request took longer than the `rpc-timeout` setting (true) or finally completed (false)
+    Unresponsive(bool),
+    /// Gave up connecting to the `--server`
+    ConnectFailed {
+        addr: String,
+        error: String,
+    },
+}
+
+/// Notifications sent by the `vimdicator` Lua module via `rpcnotify(chan, "Gui", ...)`
+#[derive(Clone, Debug)]
+pub enum GuiEvent {
+    Notify {
+        message: String,
+        level: u64,
+    },
+    /// LSP hover documentation for a grid cell, in markdown
+    Hover {
+        grid: u64,
+        row: u64,
+        col: u64,
+        markdown: String,
+    },
+    /// Active LSP signature, `label` is empty when there is none
+    SignatureHelp {
+        label: String,
+        /// Byte range of the active parameter in `label`
+        active: Option<(usize, usize)>,
+    },
+    /// Progress of a long-running job reported through `require("vimdicator").progress`
+    Progress {
+        action: ProgressAction,
+        id: String,
+        /// 0-100, `None` when the progress is unknown
+        percent: Option<f64>,
+        title: String,
+    },
+    /// Signs may have changed, e.g. after new diagnostics
+    GutterChanged,
+    /// `:VimdicatorCacheStats` wants a report of the render caches
+    CacheStats,
+    /// `:VimdicatorCursor` arguments, e.g. `insert=bar size=2px`
+    Cursor(String),
+    /// The quickfix list changed, `show` asks for the native panel to open
+    Quickfix {
+        list: QuickfixList,
+        show: bool,
+    },
+    /// Register contents, `show` asks for the register panel to open
+    Registers {
+        registers: Vec<Register>,
+        show: bool,
+    },
+    /// `:VimdicatorTrash` on the selected files, `:VimdicatorTrash!` deletes them for good
+    Trash {
+        permanent: bool,
+    },
+    /// `.vimdicator.toml` found from nvim's working directory, `path` is empty without one
+    ProjectConfig {
+        path: String,
+        contents: String,
+        /// `:VimdicatorReloadConfig`, invalid settings are reported again
+        reload: bool,
+    },
+    /// Images placed with `require("vimdicator.image")` that are on screen
+    Images(Vec<ImagePlacement>),
+    /// Image or PDF under the cursor of the file browser, `path` is empty when there is none
+    FilePreview {
+        path: String,
+        /// Grid cells of the file browser window as (row, col, rows, columns)
+        area: (usize, usize, usize, usize),
+    },
+    /// Root of the file browser, empty while it is closed
+    FileBrowser(String),
+    /// Ctrl+L in the file browser, to type the path of a directory to show
+    EditPath,
+    /// A file was written while the file browser is open, its git status may have changed
+    GitChanged,
+    /// `:VimdicatorHexView`, the file to show in the hex viewer
+    HexView(String),
+    /// Milliseconds to step the virtual clock of UI tests, see `clock`
+    AdvanceClock(u64),
+    /// `:VimdicatorPresent`, toggles presentation mode
+    Presentation,
+    Unknown(String, Vec<Value>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressAction {
+    Start,
+    Update,
+    Stop,
+}
+
+impl GuiEvent {
+    fn parse(args: Vec<Value>) -> Option<Self> {
+        let mut args = args.into_iter();
+
+        let name = into_string(args.next()?)?;
+
+        let this = match name.as_str() {
+            "Notify" => Self::Notify {
+                message: into_string(args.next()?)?,
+                level: args.next().and_then(|v| v.as_u64()).unwrap_or(2),
+            },
+            "Hover" => Self::Hover {
+                grid: args.next()?.as_u64()?,
+                row: args.next()?.as_u64()?,
+                col: args.next()?.as_u64()?,
+                markdown: into_string(args.next()?)?,
+            },
+            "SignatureHelp" => Self::SignatureHelp {
+                label: into_string(args.next()?)?,
+                active: {
+                    let start = args.next()?.as_i64()?;
+                    let end = args.next()?.as_i64()?;
+                    usize::try_from(start).ok().zip(usize::try_from(end).ok())
+                },
+            },
+            "Progress" => Self::Progress {
+                action: match into_string(args.next()?)?.as_str() {
+                    "start" => ProgressAction::Start,
+                    "update" => ProgressAction::Update,
+                    "stop" => ProgressAction::Stop,
+                    _ => return None,
+                },
+                id: into_string(args.next()?)?,
+                percent: args
+                    .next()
+                    .and_then(|v| v.as_f64().or_else(|| v.as_i64().map(|v| v as f64)))
+                    .filter(|percent| *percent >= 0.0),
+                title: args.next().and_then(into_string).unwrap_or_default(),
+            },
+            "GutterChanged" => Self::GutterChanged,
+            "CacheStats" => Self::CacheStats,
+            "Cursor" => Self::Cursor(into_string(args.next()?)?),
+            "Quickfix" => Self::Quickfix {
+                list: QuickfixList {
+                    title: into_string(args.next()?)?,
+                    current: args.next()?.as_u64()?,
+                    entries: into_array(args.next()?)?
+                        .into_iter()
+                        .filter_map(QuickfixEntry::parse)
+                        .collect(),
+                },
+                show: args.next().and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            "Registers" => Self::Registers {
+                registers: into_array(args.next()?)?
+                    .into_iter()
+                    .filter_map(Register::parse)
+                    .collect(),
+                show: args.next().and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            "Trash" => Self::Trash {
+                permanent: args.next()?.as_bool()?,
+            },
+            "ProjectConfig" => Self::ProjectConfig {
+                path: into_string(args.next()?)?,
+                contents: into_string(args.next()?)?,
+                reload: args.next().and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            "Images" => Self::Images(
+                into_array(args.next()?)?
+                    .into_iter()
+                    .filter_map(ImagePlacement::parse)
+                    .collect(),
+            ),
+            "FilePreview" => {
+                let path = into_string(args.next()?)?;
+                let area = into_array(args.next()?)?
+                    .into_iter()
+                    .map(|value| value.as_u64().map(|cells| cells as usize))
+                    .collect::<Option<Vec<_>>>()?;
+                let [row, col, rows, columns] = area[..] else {
+                    return None;
+                };
+                Self::FilePreview {
+                    path,
+                    area: (row, col, rows, columns),
+                }
+            }
+            "FileBrowser" => Self::FileBrowser(into_string(args.next()?)?),
+            "EditPath" => Self::EditPath,
+            "GitChanged" => Self::GitChanged,
+            "HexView" => Self::HexView(into_string(args.next()?)?),
+            "AdvanceClock" => Self::AdvanceClock(args.next()?.as_u64()?),
+            "Presentation" => Self::Presentation,
+            _ => Self::Unknown(name, args.collect()),
+        };
+
+        Some(this)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -76,7 +284,7 @@ impl GuiOption {
 
 #[derive(Clone, Debug)]
 pub struct GridLineCell {
-    pub text: String,
+    pub text: CellText,
     pub highlight_id: Option<u64>,
     pub repeat: Option<u64>,
 }
@@ -84,7 +292,7 @@ pub struct GridLineCell {
 impl GridLineCell {
     pub fn empty() -> Self {
         Self {
-            text: " ".to_string(),
+            text: CellText::SPACE,
             highlight_id: None,
             repeat: None,
         }
@@ -93,13 +301,121 @@ impl GridLineCell {
     fn parse(fields: Vec<Value>) -> Option<Self> {
         let mut fields = fields.into_iter();
         Some(Self {
-            text: into_string(fields.next()?)?,
+            text: CellText::new(fields.next()?.as_str()?),
             highlight_id: fields.next().and_then(|v| v.as_u64()),
             repeat: fields.next().and_then(|v| v.as_u64()),
         })
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuickfixList {
+    pub title: String,
+    /// 1-based index of the current entry, 0 when the list is empty
+    pub current: u64,
+    pub entries: Vec<QuickfixEntry>,
+}
+
+/// An item of `getqflist()`, as sent by `sync_quickfix()` in the runtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickfixEntry {
+    /// Relative to nvim's working directory, empty for entries without a buffer
+    pub path: String,
+    pub line: u64,
+    pub column: u64,
+    /// `E`, `W`, `I`, `N` or empty
+    pub kind: String,
+    pub text: String,
+    /// Not recognized by 'errorformat', shown as plain text
+    pub valid: bool,
+}
+
+impl QuickfixEntry {
+    /// `[path, lnum, col, type, text, valid]`
+    fn parse(value: Value) -> Option<Self> {
+        let mut fields = into_array(value)?.into_iter();
+        Some(Self {
+            path: into_string(fields.next()?)?,
+            line: fields.next()?.as_u64()?,
+            column: fields.next()?.as_u64()?,
+            kind: into_string(fields.next()?)?,
+            text: into_string(fields.next()?)?,
+            valid: fields.next()?.as_bool()?,
+        })
+    }
+}
+
+/// A non-empty register, as sent by `sync_registers()` in the runtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct Register {
+    pub name: String,
+    /// `getregtype()`: `v`, `V` or `<C-V>` followed by the block width
+    pub kind: String,
+    /// Lines joined with newlines
+    pub contents: String,
+}
+
+impl Register {
+    /// `[name, type, contents]`
+    fn parse(value: Value) -> Option<Self> {
+        let mut fields = into_array(value)?.into_iter();
+        Some(Self {
+            name: into_string(fields.next()?)?,
+            kind: into_string(fields.next()?)?,
+            contents: into_string(fields.next()?)?,
+        })
+    }
+}
+
+/// An image drawn over the grid, as sent by `sync()` of the `vimdicator.image` runtime module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePlacement {
+    pub id: u64,
+    /// Absolute path of the image file
+    pub path: String,
+    /// Grid cell of the top left corner
+    pub row: usize,
+    pub col: usize,
+    /// Size in cells, `None` keeps the aspect ratio or the size of the image
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    /// Text area of the window showing it as (row, col, rows, columns), the image is cut off
+    /// at its edges
+    pub clip: (usize, usize, usize, usize),
+}
+
+impl ImagePlacement {
+    /// `[id, path, row, col, width, height, [clip row, col, rows, columns]]`, sizes of 0 are unset
+    fn parse(value: Value) -> Option<Self> {
+        let mut fields = into_array(value)?.into_iter();
+        let cells = |value: Value| value.as_u64().map(|cells| cells as usize);
+
+        let id = fields.next()?.as_u64()?;
+        let path = into_string(fields.next()?)?;
+        let row = cells(fields.next()?)?;
+        let col = cells(fields.next()?)?;
+        let width = cells(fields.next()?)?;
+        let height = cells(fields.next()?)?;
+        let clip = into_array(fields.next()?)?
+            .into_iter()
+            .map(cells)
+            .collect::<Option<Vec<_>>>()?;
+        let [clip_row, clip_col, clip_rows, clip_columns] = clip[..] else {
+            return None;
+        };
+
+        Some(Self {
+            id,
+            path,
+            row,
+            col,
+            width: Some(width).filter(|width| *width > 0),
+            height: Some(height).filter(|height| *height > 0),
+            clip: (clip_row, clip_col, clip_rows, clip_columns),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PopupMenuItem {
     pub word: String,
@@ -126,12 +442,17 @@ pub enum RedrawEvent {
     ModeInfoSet,
     DefaultColorsSet {
         colors: Colors,
+        cterm_colors: Colors,
     },
     HighlightAttributesDefine {
         id: u64,
         style: Style,
     },
-    HighlightGroupSet,
+    /// Highlight id used for one of the builtin UI groups, e.g. `CursorLine`
+    HighlightGroupSet {
+        name: String,
+        id: u64,
+    },
 
     GridLine {
         grid: u64,
@@ -165,9 +486,17 @@ pub enum RedrawEvent {
         height: u64,
     },
 
-    WindowViewport,
+    /// Without ext_multigrid only sent for the current window
+    WindowViewport {
+        viewport: super::gutter::Viewport,
+    },
 
-    ModeChange,
+    /// Name of the mode from `mode_info_set`, e.g. "insert" or "cmdline_normal"
+    ModeChange(String),
+    /// `titlestring`, only sent while 'title' is set
+    SetTitle(String),
+    /// `iconstring`, only sent while 'icon' is set
+    SetIcon(String),
     MouseOn,
     MouseOff,
     Flush,
@@ -209,7 +538,74 @@ fn into_string(value: Value) -> Option<String> {
     }
 }
 
+/// A `{ tab = ..., name = ... }` entry of `tabline_update`
+fn into_tab(data: &Value, nvim: &super::Neovim) -> Option<(String, super::Tabpage)> {
+    let data = data.as_map()?;
+    let tab = data
+        .iter()
+        .find(|(name, _)| name.as_str() == Some("tab"))
+        .and_then(|(_, v)| super::Tabpage::new(v.clone(), nvim.clone()))?;
+
+    let name = data
+        .iter()
+        .find(|(name, _)| name.as_str() == Some("name"))
+        .and_then(|(_, v)| v.as_str())?;
+
+    Some((name.to_string(), tab))
+}
+
+/// Malformed data dropped by the parsers so far, by what was dropped
+static PARSE_WARNINGS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// Counts `parsed` being `None` as a dropped `what`, only the first one of each kind is logged
+fn or_warn<T>(what: &str, parsed: Option<T>) -> Option<T> {
+    if parsed.is_none() {
+        let mut warnings = PARSE_WARNINGS.lock().unwrap();
+        let count = warnings.entry(what.to_string()).or_default();
+        if *count == 0 {
+            warn!("Dropped malformed {what}, further ones are only counted");
+        }
+        *count += 1;
+    }
+
+    parsed
+}
+
+/// How many of each kind of malformed data were dropped, e.g. `("grid_line cell", 3)`
+pub fn parse_warnings() -> BTreeMap<String, u64> {
+    PARSE_WARNINGS.lock().unwrap().clone()
+}
+
 impl RedrawEvent {
+    /// Name of the event in the UI protocol
+    pub fn name(&self) -> &str {
+        match self {
+            Self::OptionSet(_) => "option_set",
+            Self::ModeInfoSet => "mode_info_set",
+            Self::DefaultColorsSet { .. } => "default_colors_set",
+            Self::HighlightAttributesDefine { .. } => "hl_attr_define",
+            Self::HighlightGroupSet { .. } => "hl_group_set",
+            Self::GridLine { .. } => "grid_line",
+            Self::GridClear { .. } => "grid_clear",
+            Self::GridDestroy { .. } => "grid_destroy",
+            Self::GridCursorGoto { .. } => "grid_cursor_goto",
+            Self::GridScroll { .. } => "grid_scroll",
+            Self::GridResize { .. } => "grid_resize",
+            Self::WindowViewport { .. } => "win_viewport",
+            Self::ModeChange(_) => "mode_change",
+            Self::SetTitle(_) => "set_title",
+            Self::SetIcon(_) => "set_icon",
+            Self::MouseOn => "mouse_on",
+            Self::MouseOff => "mouse_off",
+            Self::Flush => "flush",
+            Self::TablineUpdate { .. } => "tabline_update",
+            Self::PopupmenuShow { .. } => "popupmenu_show",
+            Self::PopupmenuSelect { .. } => "popupmenu_select",
+            Self::PopupmenuHide => "popupmenu_hide",
+            Self::Unknown(name, _) => name,
+        }
+    }
+
     fn parse(args: Vec<nvim_rs::Value>, nvim: super::Neovim) -> Option<Vec<Self>> {
         let mut args_iter = args.into_iter();
 
@@ -231,174 +627,200 @@ impl RedrawEvent {
         };
 
         let events = args_iter
-            .filter_map(into_array)
             .filter_map(|event| {
-                let event = match name {
-                    "option_set" => RedrawEvent::OptionSet(GuiOption::parse(event)?),
-                    "mode_info_set" => RedrawEvent::ModeInfoSet,
-                    "default_colors_set" => {
-                        let mut event = event.into_iter();
-
-                        let foreground = event.next()?.as_u64()?;
-                        let background = event.next()?.as_u64()?;
-                        let special = event.next()?.as_u64()?;
-                        let _term_foreground = event.next();
-                        let _term_background = event.next();
-
-                        RedrawEvent::DefaultColorsSet {
-                            colors: Colors {
-                                foreground: Some(Color::unpack_color(foreground)),
-                                background: Some(Color::unpack_color(background)),
-                                special: Some(Color::unpack_color(special)),
-                            },
-                        }
-                    }
-                    "hl_attr_define" => {
-                        let mut event = event.into_iter();
-
-                        let id = event.next()?.as_u64()?;
-                        let attributes = event.next()?;
-                        let _terminal_attributes = event.next()?;
-                        let _info = event.next()?;
-
-                        let style = into_style(attributes)?;
-
-                        RedrawEvent::HighlightAttributesDefine { id, style }
-                    }
-                    "hl_group_set" => RedrawEvent::HighlightGroupSet,
-
-                    "grid_line" => {
-                        let mut event = event.into_iter();
-
-                        let grid = event.next()?.as_u64()?;
-                        let row = event.next()?.as_u64()?;
-                        let column_start = event.next()?.as_u64()?;
-
-                        let cells = into_array(event.next()?)?;
-
-                        RedrawEvent::GridLine {
-                            grid,
-                            row,
-                            column_start,
-                            cells: cells
-                                .into_iter()
-                                .filter_map(into_array)
-                                .filter_map(GridLineCell::parse)
-                                .collect(),
-                        }
-                    }
-                    "grid_clear" => RedrawEvent::GridClear {
-                        grid: event.first()?.as_u64()?,
-                    },
-                    "grid_destroy" => RedrawEvent::GridDestroy {
-                        grid: event.first()?.as_u64()?,
-                    },
-                    "grid_cursor_goto" => {
-                        let mut event = event.into_iter();
-                        RedrawEvent::GridCursorGoto {
-                            grid: event.next()?.as_u64()?,
-                            row: event.next()?.as_u64()?,
-                            column: event.next()?.as_u64()?,
-                        }
-                    }
-                    "grid_scroll" => {
-                        let mut event = event.into_iter();
-
-                        RedrawEvent::GridScroll {
-                            grid: event.next()?.as_u64()?,
-                            top: event.next()?.as_u64()?,
-                            bottom: event.next()?.as_u64()?,
-                            left: event.next()?.as_u64()?,
-                            right: event.next()?.as_u64()?,
-                            rows: event.next()?.as_i64()?,
-                            columns: event.next()?.as_i64()?,
-                        }
-                    }
-                    "grid_resize" => {
-                        let mut event = event.into_iter();
-                        RedrawEvent::GridResize {
-                            grid: event.next()?.as_u64()?,
-                            width: event.next()?.as_u64()?,
-                            height: event.next()?.as_u64()?,
-                        }
-                    }
-
-                    "win_viewport" => RedrawEvent::WindowViewport,
-                    "mode_change" => RedrawEvent::ModeChange,
-                    "mouse_on" => RedrawEvent::MouseOn,
-                    "mouse_off" => RedrawEvent::MouseOff,
-                    "flush" => RedrawEvent::Flush,
-
-                    "tabline_update" => {
-                        let mut event = event.into_iter();
-
-                        let current_tab = event.next()?;
-                        let current_tab = super::Tabpage::new(current_tab, nvim.clone());
-
-                        let tabs = event.next()?;
-                        let tabs = tabs.as_array()?;
-                        let tabs: Vec<_> = tabs
-                            .iter()
-                            .filter_map(|data| data.as_map())
-                            .filter_map(|data| {
-                                let tab = data
-                                    .iter()
-                                    .find(|(name, _)| name.as_str() == Some("tab"))
-                                    .map(|(_, v)| super::Tabpage::new(v.clone(), nvim.clone()))?;
-
-                                let name = data
-                                    .iter()
-                                    .find(|(name, _)| name.as_str() == Some("name"))
-                                    .and_then(|(_, v)| v.as_str())?;
-
-                                Some((name.to_string(), tab))
-                            })
-                            .collect();
-
-                        // let curbuf = event.next()?;
-                        // let buffers = event.next()?;
-
-                        RedrawEvent::TablineUpdate { current_tab, tabs }
-                    }
-
-                    "popupmenu_show" => {
-                        let mut event = event.into_iter();
-
-                        let items = into_array(event.next()?)?
-                            .into_iter()
-                            .filter_map(into_array)
-                            .map(|array| {
-                                array
-                                    .into_iter()
-                                    .flat_map(into_string)
-                                    .collect::<Vec<String>>()
-                            })
-                            .filter_map(PopupMenuItem::new)
-                            .collect();
-
-                        RedrawEvent::PopupmenuShow {
-                            items,
-                            selected: u64::try_from(event.next()?.as_i64()?).ok(),
-                            row: event.next()?.as_u64()?,
-                            col: event.next()?.as_u64()?,
-                            grid: event.next()?.as_u64()?,
-                        }
-                    }
-                    "popupmenu_select" => {
-                        let selected = u64::try_from(event.first()?.as_i64()?).ok();
-                        RedrawEvent::PopupmenuSelect { selected }
-                    }
-                    "popupmenu_hide" => RedrawEvent::PopupmenuHide,
-
-                    name => RedrawEvent::Unknown(name.to_string(), event),
-                };
-
-                Some(event)
+                let event = into_array(event).and_then(|event| Self::parse_one(name, event, &nvim));
+                or_warn(name, event)
             })
             .collect::<Vec<_>>();
 
         Some(events)
     }
+
+    fn parse_one(name: &str, event: Vec<Value>, nvim: &super::Neovim) -> Option<Self> {
+        let event = match name {
+            "option_set" => RedrawEvent::OptionSet(GuiOption::parse(event)?),
+            "mode_info_set" => RedrawEvent::ModeInfoSet,
+            "default_colors_set" => {
+                let mut event = event.into_iter();
+
+                let foreground = event.next()?.as_u64()?;
+                let background = event.next()?.as_u64()?;
+                let special = event.next()?.as_u64()?;
+                let term_foreground = event.next().and_then(|v| v.as_i64());
+                let term_background = event.next().and_then(|v| v.as_i64());
+
+                RedrawEvent::DefaultColorsSet {
+                    colors: Colors {
+                        foreground: Some(Color::unpack_color(foreground)),
+                        background: Some(Color::unpack_color(background)),
+                        special: Some(Color::unpack_color(special)),
+                    },
+                    cterm_colors: Colors {
+                        foreground: term_foreground.and_then(palette::xterm_256),
+                        background: term_background.and_then(palette::xterm_256),
+                        special: None,
+                    },
+                }
+            }
+            "hl_attr_define" => {
+                let mut event = event.into_iter();
+
+                let id = event.next()?.as_u64()?;
+                let attributes = event.next()?;
+                let terminal_attributes = event.next()?;
+                let _info = event.next()?;
+
+                let mut style = into_style(attributes)?;
+                style.cterm_colors = into_cterm_colors(terminal_attributes);
+
+                RedrawEvent::HighlightAttributesDefine { id, style }
+            }
+            "hl_group_set" => {
+                let mut event = event.into_iter();
+
+                RedrawEvent::HighlightGroupSet {
+                    name: into_string(event.next()?)?,
+                    id: event.next()?.as_u64()?,
+                }
+            }
+
+            "grid_line" => {
+                let mut event = event.into_iter();
+
+                let grid = event.next()?.as_u64()?;
+                let row = event.next()?.as_u64()?;
+                let column_start = event.next()?.as_u64()?;
+
+                let cells = into_array(event.next()?)?;
+
+                RedrawEvent::GridLine {
+                    grid,
+                    row,
+                    column_start,
+                    cells: cells
+                        .into_iter()
+                        .filter_map(|cell| {
+                            let cell = into_array(cell).and_then(GridLineCell::parse);
+                            or_warn("grid_line cell", cell)
+                        })
+                        .collect(),
+                }
+            }
+            "grid_clear" => RedrawEvent::GridClear {
+                grid: event.first()?.as_u64()?,
+            },
+            "grid_destroy" => RedrawEvent::GridDestroy {
+                grid: event.first()?.as_u64()?,
+            },
+            "grid_cursor_goto" => {
+                let mut event = event.into_iter();
+                RedrawEvent::GridCursorGoto {
+                    grid: event.next()?.as_u64()?,
+                    row: event.next()?.as_u64()?,
+                    column: event.next()?.as_u64()?,
+                }
+            }
+            "grid_scroll" => {
+                let mut event = event.into_iter();
+
+                RedrawEvent::GridScroll {
+                    grid: event.next()?.as_u64()?,
+                    top: event.next()?.as_u64()?,
+                    bottom: event.next()?.as_u64()?,
+                    left: event.next()?.as_u64()?,
+                    right: event.next()?.as_u64()?,
+                    rows: event.next()?.as_i64()?,
+                    columns: event.next()?.as_i64()?,
+                }
+            }
+            "grid_resize" => {
+                let mut event = event.into_iter();
+                RedrawEvent::GridResize {
+                    grid: event.next()?.as_u64()?,
+                    width: event.next()?.as_u64()?,
+                    height: event.next()?.as_u64()?,
+                }
+            }
+
+            "win_viewport" => {
+                let mut event = event.into_iter();
+                // The window's own grid handle, which isn't drawn to without ext_multigrid
+                let _grid = event.next()?;
+                let _win = event.next()?;
+
+                // nvim sends 0-based lines, botline is exclusive
+                let topline = event.next()?.as_u64()?.checked_add(1)?;
+                let botline = event.next()?.as_u64()?;
+                let curline = event.next()?.as_u64()?.checked_add(1)?;
+                let _curcol = event.next()?;
+
+                RedrawEvent::WindowViewport {
+                    viewport: super::gutter::Viewport {
+                        topline,
+                        botline,
+                        curline,
+                        line_count: event.next()?.as_u64()?,
+                    },
+                }
+            }
+            "mode_change" => RedrawEvent::ModeChange(into_string(event.into_iter().next()?)?),
+            "set_title" => RedrawEvent::SetTitle(into_string(event.into_iter().next()?)?),
+            "set_icon" => RedrawEvent::SetIcon(into_string(event.into_iter().next()?)?),
+            "mouse_on" => RedrawEvent::MouseOn,
+            "mouse_off" => RedrawEvent::MouseOff,
+            "flush" => RedrawEvent::Flush,
+
+            "tabline_update" => {
+                let mut event = event.into_iter();
+
+                let current_tab = event.next()?;
+                let current_tab = super::Tabpage::new(current_tab, nvim.clone())?;
+
+                let tabs = event.next()?;
+                let tabs = tabs.as_array()?;
+                let tabs: Vec<_> = tabs
+                    .iter()
+                    .filter_map(|data| or_warn("tabline_update tab", into_tab(data, nvim)))
+                    .collect();
+
+                // let curbuf = event.next()?;
+                // let buffers = event.next()?;
+
+                RedrawEvent::TablineUpdate { current_tab, tabs }
+            }
+
+            "popupmenu_show" => {
+                let mut event = event.into_iter();
+
+                let items = into_array(event.next()?)?
+                    .into_iter()
+                    .filter_map(|item| {
+                        let item = into_array(item).and_then(|array| {
+                            PopupMenuItem::new(array.into_iter().flat_map(into_string).collect())
+                        });
+                        or_warn("popupmenu_show item", item)
+                    })
+                    .collect();
+
+                RedrawEvent::PopupmenuShow {
+                    items,
+                    selected: u64::try_from(event.next()?.as_i64()?).ok(),
+                    row: event.next()?.as_u64()?,
+                    col: event.next()?.as_u64()?,
+                    grid: event.next()?.as_u64()?,
+                }
+            }
+            "popupmenu_select" => {
+                let selected = u64::try_from(event.first()?.as_i64()?).ok();
+                RedrawEvent::PopupmenuSelect { selected }
+            }
+            "popupmenu_hide" => RedrawEvent::PopupmenuHide,
+
+            name => RedrawEvent::Unknown(name.to_string(), event),
+        };
+
+        Some(event)
+    }
 }
 
 impl NvimEvent {
@@ -413,8 +835,10 @@ impl NvimEvent {
 
                 NvimEvent::Redraw(args.collect())
             }
-            "Gui" => NvimEvent::Gui(args),
-            "subscription" => NvimEvent::Subscription(args),
+            "Gui" => NvimEvent::Gui(or_warn("Gui notification", GuiEvent::parse(args))?),
+            "subscription" => {
+                NvimEvent::Subscription(or_warn("subscription", Subscription::parse(args))?)
+            }
             "resized" => NvimEvent::Resized(args),
             _ => {
                 error!("Notification {}({:?})", name, args);
@@ -426,59 +850,75 @@ impl NvimEvent {
     }
 }
 
+fn into_cterm_colors(cterm_map: Value) -> Colors {
+    let mut colors = Colors::default();
+
+    for (name, value) in into_map(cterm_map).unwrap_or_default() {
+        let index = value.as_i64().and_then(palette::xterm_256);
+
+        match name.as_str() {
+            Some("foreground") => colors.foreground = index,
+            Some("background") => colors.background = index,
+            Some("special") => colors.special = index,
+            _ => (),
+        }
+    }
+
+    colors
+}
+
 fn into_style(style_map: Value) -> Option<Style> {
     let attributes = into_map(style_map)?;
 
     let mut style = Style::default();
 
-    for attribute in attributes {
-        if let (Value::String(name), value) = attribute {
-            match (name.as_str().unwrap(), value) {
-                ("foreground", Value::Integer(packed_color)) => {
-                    style.colors.foreground =
-                        Some(Color::unpack_color(packed_color.as_u64().unwrap()))
-                }
-                ("background", Value::Integer(packed_color)) => {
-                    style.colors.background =
-                        Some(Color::unpack_color(packed_color.as_u64().unwrap()))
-                }
-                ("special", Value::Integer(packed_color)) => {
-                    style.colors.special = Some(Color::unpack_color(packed_color.as_u64().unwrap()))
-                }
-                ("reverse", Value::Boolean(reverse)) => style.reverse = reverse,
-                ("italic", Value::Boolean(italic)) => style.italic = italic,
-                ("bold", Value::Boolean(bold)) => style.bold = bold,
-                ("strikethrough", Value::Boolean(strikethrough)) => {
-                    style.strikethrough = strikethrough
-                }
-                ("blend", Value::Integer(blend)) => style.blend = blend.as_u64().unwrap() as u8,
+    for (name, value) in attributes {
+        let Some(name) = or_warn("highlight attribute name", name.as_str()) else {
+            continue;
+        };
 
-                ("underline", Value::Boolean(true)) => {
-                    style.underline = Some(UnderlineStyle::Underline)
-                }
-                ("undercurl", Value::Boolean(true)) => {
-                    style.underline = Some(UnderlineStyle::UnderCurl)
-                }
-                ("underdotted" | "underdot", Value::Boolean(true)) => {
-                    style.underline = Some(UnderlineStyle::UnderDot)
-                }
-                ("underdashed" | "underdash", Value::Boolean(true)) => {
-                    style.underline = Some(UnderlineStyle::UnderDash)
-                }
-                ("underdouble" | "underlineline", Value::Boolean(true)) => {
-                    style.underline = Some(UnderlineStyle::UnderDouble)
-                }
+        match (name, value) {
+            ("foreground", value) => style.colors.foreground = into_color(value),
+            ("background", value) => style.colors.background = into_color(value),
+            ("special", value) => style.colors.special = into_color(value),
+            ("reverse", Value::Boolean(reverse)) => style.reverse = reverse,
+            ("standout", Value::Boolean(standout)) => style.standout = standout,
+            ("italic", Value::Boolean(italic)) => style.italic = italic,
+            ("bold", Value::Boolean(bold)) => style.bold = bold,
+            ("strikethrough", Value::Boolean(strikethrough)) => style.strikethrough = strikethrough,
+            ("blend", value) => {
+                let blend = or_warn("highlight blend", value.as_u64()).unwrap_or(0);
+                style.blend = blend.min(100) as u8;
+            }
 
-                _ => debug!("Ignored style attribute: {}", name),
+            ("underline", Value::Boolean(true)) => {
+                style.underline = Some(UnderlineStyle::Underline)
             }
-        } else {
-            debug!("Invalid attribute format");
+            ("undercurl", Value::Boolean(true)) => {
+                style.underline = Some(UnderlineStyle::UnderCurl)
+            }
+            ("underdotted" | "underdot", Value::Boolean(true)) => {
+                style.underline = Some(UnderlineStyle::UnderDot)
+            }
+            ("underdashed" | "underdash", Value::Boolean(true)) => {
+                style.underline = Some(UnderlineStyle::UnderDash)
+            }
+            ("underdouble" | "underlineline", Value::Boolean(true)) => {
+                style.underline = Some(UnderlineStyle::UnderDouble)
+            }
+
+            _ => debug!("Ignored style attribute: {}", name),
         }
     }
 
     Some(style)
 }
 
+/// A packed `0xRRGGBB` highlight color
+fn into_color(value: Value) -> Option<Color> {
+    or_warn("highlight color", value.as_u64()).map(Color::unpack_color)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: f32,
@@ -519,42 +959,494 @@ pub enum UnderlineStyle {
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Style {
     pub colors: Colors,
+    /// Colors from the `cterm` attributes, resolved through the 256-color palette
+    pub cterm_colors: Colors,
     pub reverse: bool,
+    pub standout: bool,
     pub italic: bool,
     pub bold: bool,
     pub strikethrough: bool,
+    /// Only drawn for the external popup menu, without ext_multigrid nvim blends floats into
+    /// the grid's colors itself
     pub blend: u8,
     pub underline: Option<UnderlineStyle>,
 }
 
 impl Style {
+    /// Standout is rendered as reverse video, like most terminals do
+    pub fn is_reversed(&self) -> bool {
+        self.reverse || self.standout
+    }
+
+    fn color_or_default(color: Option<Color>, default: Option<Color>) -> Color {
+        color.or(default).unwrap_or(Color {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+        })
+    }
+
     pub fn foreground(&self, default_colors: &Colors) -> Color {
-        if self.reverse {
-            self.colors
-                .background
-                .unwrap_or_else(|| default_colors.background.unwrap())
+        if self.is_reversed() {
+            Self::color_or_default(self.colors.background, default_colors.background)
         } else {
-            self.colors
-                .foreground
-                .unwrap_or_else(|| default_colors.foreground.unwrap())
+            Self::color_or_default(self.colors.foreground, default_colors.foreground)
         }
     }
 
     pub fn background(&self, default_colors: &Colors) -> Color {
-        if self.reverse {
-            self.colors
-                .foreground
-                .unwrap_or_else(|| default_colors.foreground.unwrap())
+        if self.is_reversed() {
+            Self::color_or_default(self.colors.foreground, default_colors.foreground)
         } else {
-            self.colors
-                .background
-                .unwrap_or_else(|| default_colors.background.unwrap())
+            Self::color_or_default(self.colors.background, default_colors.background)
         }
     }
 
+    /// (background, text) colors of a block cursor over a cell with this style
+    pub fn cursor_colors(&self, default_colors: &Colors) -> (Color, Color) {
+        (
+            self.foreground(default_colors),
+            self.background(default_colors),
+        )
+    }
+
     pub fn special(&self, default_colors: &Colors) -> Color {
         self.colors
             .special
             .unwrap_or_else(|| self.foreground(default_colors))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+    };
+    const GREEN: Color = Color {
+        r: 0.0,
+        g: 1.0,
+        b: 0.0,
+    };
+    const WHITE: Color = Color {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+    };
+    const BLACK: Color = Color {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+    };
+
+    fn default_colors() -> Colors {
+        Colors {
+            foreground: Some(WHITE),
+            background: Some(BLACK),
+            special: None,
+        }
+    }
+
+    #[test]
+    fn test_plain_style() {
+        let defaults = default_colors();
+
+        let style = Style::default();
+        assert_eq!(style.foreground(&defaults), WHITE);
+        assert_eq!(style.background(&defaults), BLACK);
+
+        let style = Style {
+            colors: Colors {
+                foreground: Some(RED),
+                background: Some(GREEN),
+                special: None,
+            },
+            ..Default::default()
+        };
+        assert_eq!(style.foreground(&defaults), RED);
+        assert_eq!(style.background(&defaults), GREEN);
+    }
+
+    #[test]
+    fn test_reverse_without_colors_swaps_defaults() {
+        let defaults = default_colors();
+
+        let style = Style {
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(style.foreground(&defaults), BLACK);
+        assert_eq!(style.background(&defaults), WHITE);
+    }
+
+    #[test]
+    fn test_reverse_with_partial_colors() {
+        let defaults = default_colors();
+
+        let style = Style {
+            reverse: true,
+            colors: Colors {
+                foreground: Some(RED),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(style.foreground(&defaults), BLACK);
+        assert_eq!(style.background(&defaults), RED);
+    }
+
+    #[test]
+    fn test_standout_is_reverse() {
+        let defaults = default_colors();
+
+        let standout = Style {
+            standout: true,
+            ..Default::default()
+        };
+        let reverse = Style {
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            standout.foreground(&defaults),
+            reverse.foreground(&defaults)
+        );
+        assert_eq!(
+            standout.background(&defaults),
+            reverse.background(&defaults)
+        );
+
+        // Both attributes don't cancel each other out
+        let both = Style {
+            reverse: true,
+            standout: true,
+            ..Default::default()
+        };
+        assert_eq!(both.foreground(&defaults), BLACK);
+    }
+
+    #[test]
+    fn test_cursor_colors() {
+        let defaults = default_colors();
+
+        // Cursor over a plain cell is drawn in reverse video
+        assert_eq!(Style::default().cursor_colors(&defaults), (WHITE, BLACK));
+
+        // Cursor over a reversed cell restores the original colors
+        let style = Style {
+            reverse: true,
+            colors: Colors {
+                foreground: Some(RED),
+                background: Some(GREEN),
+                special: None,
+            },
+            ..Default::default()
+        };
+        assert_eq!(style.cursor_colors(&defaults), (GREEN, RED));
+    }
+
+    #[test]
+    fn test_missing_default_colors() {
+        let style = Style {
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(style.foreground(&Colors::default()), BLACK);
+    }
+
+    #[derive(Clone)]
+    struct NoHandler;
+
+    #[async_trait::async_trait]
+    impl nvim_rs::Handler for NoHandler {
+        type Writer = super::super::NeovimWriter;
+    }
+
+    /// A client that is never talked to, parsed tabpages only hold on to it
+    fn nvim() -> super::super::Neovim {
+        use super::super::connection::{BoxedReader, BoxedWriter};
+        use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+        let reader: BoxedReader = Box::new(tokio::io::empty());
+        let writer: BoxedWriter = Box::new(tokio::io::sink());
+        let (nvim, _io) = nvim_rs::Neovim::new(reader.compat(), writer.compat_write(), NoHandler);
+        nvim
+    }
+
+    /// A msgpack string that is not valid UTF-8
+    fn invalid_utf8() -> Value {
+        rmpv::decode::read_value(&mut &[0xa2, 0xff, 0xfe][..]).unwrap()
+    }
+
+    /// Event, option, attribute and notification names, so random values get past the
+    /// name checks and into the field parsing
+    const NAMES: [&str; 30] = [
+        "option_set",
+        "default_colors_set",
+        "hl_attr_define",
+        "grid_line",
+        "grid_clear",
+        "grid_destroy",
+        "grid_cursor_goto",
+        "grid_scroll",
+        "grid_resize",
+        "win_viewport",
+        "mode_change",
+        "set_title",
+        "flush",
+        "tabline_update",
+        "popupmenu_show",
+        "popupmenu_select",
+        "guifont",
+        "linespace",
+        "foreground",
+        "background",
+        "blend",
+        "bold",
+        "undercurl",
+        "tab",
+        "name",
+        "Notify",
+        "Hover",
+        "SignatureHelp",
+        "Progress",
+        "redraw",
+    ];
+
+    /// xorshift64, deterministic so failures can be reproduced
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn name(&mut self) -> Value {
+            Value::from(NAMES[self.below(NAMES.len())])
+        }
+
+        fn value(&mut self, depth: u32) -> Value {
+            let kinds = if depth == 0 { 8 } else { 10 };
+            match self.below(kinds) {
+                0 => Value::Nil,
+                1 => Value::from(self.below(2) == 0),
+                2 => Value::from(self.below(300)),
+                3 => Value::from(self.next()),
+                4 => Value::from(self.next() as i64),
+                5 => self.name(),
+                6 => invalid_utf8(),
+                7 => Value::Ext(self.below(3) as i8, vec![self.below(256) as u8]),
+                8 => Value::Array((0..self.below(8)).map(|_| self.value(depth - 1)).collect()),
+                _ => Value::Map(
+                    (0..self.below(4))
+                        .map(|_| (self.name(), self.value(depth - 1)))
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    /// Stands in for a fuzzer, nothing coming from nvim may panic the parsers
+    #[test]
+    fn test_parse_arbitrary_values() {
+        let nvim = nvim();
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..20_000 {
+            let mut batch = vec![rng.name()];
+            batch.extend((0..rng.below(3)).map(|_| rng.value(3)));
+            let _ = RedrawEvent::parse(batch.clone(), nvim.clone());
+            let _ = NvimEvent::parse("redraw".into(), vec![Value::Array(batch)], nvim.clone());
+
+            let _ = into_style(rng.value(2));
+            let _ = into_cterm_colors(rng.value(2));
+
+            let mut args = vec![rng.name()];
+            args.extend((0..rng.below(5)).map(|_| rng.value(2)));
+            let _ = GuiEvent::parse(args.clone());
+            let _ = GuiOption::parse(args);
+
+            let _ = NvimEvent::parse("Gui".into(), vec![rng.value(2)], nvim.clone());
+            let _ = NvimEvent::parse("subscription".into(), vec![rng.value(2)], nvim.clone());
+        }
+    }
+
+    #[test]
+    fn test_malformed_style() {
+        let style = Value::Map(vec![
+            (invalid_utf8(), Value::from(true)),
+            (Value::from("foreground"), Value::from(-1)),
+            (Value::from("background"), Value::from(0x00ff00)),
+            (Value::from("blend"), Value::from(-30)),
+            (Value::from("bold"), Value::from(true)),
+        ]);
+
+        let style = into_style(style).unwrap();
+        assert_eq!(style.colors.foreground, None);
+        assert_eq!(style.colors.background, Some(GREEN));
+        assert_eq!(style.blend, 0);
+        assert!(style.bold);
+
+        let warnings = parse_warnings();
+        assert!(warnings["highlight attribute name"] >= 1);
+        assert!(warnings["highlight color"] >= 1);
+        assert!(warnings["highlight blend"] >= 1);
+    }
+
+    #[test]
+    fn test_malformed_events_are_counted() {
+        let batch = vec![
+            Value::from("grid_cursor_goto"),
+            Value::Array(vec![Value::from(1), Value::from(2), Value::from(3)]),
+            Value::Array(vec![Value::from(1), Value::from("row")]),
+        ];
+
+        let events = RedrawEvent::parse(batch, nvim()).unwrap();
+        assert!(matches!(
+            events[..],
+            [RedrawEvent::GridCursorGoto {
+                grid: 1,
+                row: 2,
+                column: 3
+            }]
+        ));
+        assert!(parse_warnings()["grid_cursor_goto"] >= 1);
+    }
+
+    #[test]
+    fn test_highlight_group_set() {
+        let batch = vec![
+            Value::from("hl_group_set"),
+            Value::Array(vec![Value::from("CursorLine"), Value::from(42)]),
+        ];
+
+        let events = RedrawEvent::parse(batch, nvim()).unwrap();
+        assert!(matches!(
+            &events[..],
+            [RedrawEvent::HighlightGroupSet { name, id: 42 }] if name == "CursorLine"
+        ));
+    }
+
+    #[test]
+    fn test_quickfix() {
+        let entry = |path: &str, line: u64| {
+            Value::Array(vec![
+                Value::from(path),
+                Value::from(line),
+                Value::from(5),
+                Value::from("E"),
+                Value::from("expected `;`"),
+                Value::from(true),
+            ])
+        };
+        let args = vec![
+            Value::from("Quickfix"),
+            Value::from(":make"),
+            Value::from(2),
+            Value::Array(vec![
+                entry("src/main.rs", 10),
+                Value::from("bogus"),
+                entry("", 0),
+            ]),
+        ];
+
+        let Some(GuiEvent::Quickfix { list, show }) = GuiEvent::parse(args) else {
+            panic!("not a quickfix event");
+        };
+        assert!(!show);
+        assert_eq!(list.title, ":make");
+        assert_eq!(list.current, 2);
+        // Malformed entries are skipped
+        assert_eq!(list.entries.len(), 2);
+        assert_eq!(
+            list.entries[0],
+            QuickfixEntry {
+                path: "src/main.rs".to_string(),
+                line: 10,
+                column: 5,
+                kind: "E".to_string(),
+                text: "expected `;`".to_string(),
+                valid: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_registers() {
+        let args = vec![
+            Value::from("Registers"),
+            Value::Array(vec![
+                Value::Array(vec![
+                    Value::from("a"),
+                    Value::from("V"),
+                    Value::from("x\ny"),
+                ]),
+                Value::Array(vec![Value::from("b")]),
+            ]),
+            Value::from(true),
+        ];
+
+        let Some(GuiEvent::Registers { registers, show }) = GuiEvent::parse(args) else {
+            panic!("not a registers event");
+        };
+        assert!(show);
+        assert_eq!(
+            registers,
+            [Register {
+                name: "a".to_string(),
+                kind: "V".to_string(),
+                contents: "x\ny".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_images() {
+        let image = |path: &str, height: u64| {
+            Value::Array(vec![
+                Value::from(3),
+                Value::from(path),
+                Value::from(4),
+                Value::from(10),
+                Value::from(0),
+                Value::from(height),
+                Value::Array(vec![
+                    Value::from(1),
+                    Value::from(6),
+                    Value::from(20),
+                    Value::from(74),
+                ]),
+            ])
+        };
+        let args = vec![
+            Value::from("Images"),
+            Value::Array(vec![image("/tmp/plot.png", 8), Value::from("bogus")]),
+        ];
+
+        let Some(GuiEvent::Images(images)) = GuiEvent::parse(args) else {
+            panic!("not an images event");
+        };
+        assert_eq!(
+            images,
+            [ImagePlacement {
+                id: 3,
+                path: "/tmp/plot.png".to_string(),
+                row: 4,
+                col: 10,
+                width: None,
+                height: Some(8),
+                clip: (1, 6, 20, 74),
+            }]
+        );
+    }
+}
@@ -1,9 +1,63 @@
-use std::collections::{hash_map, HashMap};
-
-use log::error;
+use std::{
+    collections::{hash_map, HashMap},
+    fmt,
+    sync::Arc,
+};
 
 use super::event::GridLineCell;
 
+/// An event that doesn't fit the grid as known, e.g. a `grid_line` racing a `grid_resize`.
+/// What fits was applied, the grid should be redrawn by nvim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridError {
+    UnknownGrid(u64),
+    Row {
+        grid: u64,
+        row: usize,
+        rows: usize,
+    },
+    /// `column` is the last one written to or moved to
+    Column {
+        grid: u64,
+        row: usize,
+        column: usize,
+        columns: usize,
+    },
+    /// A scroll region past the grid, or moving more rows than it has
+    Scroll {
+        grid: u64,
+        rows: usize,
+        columns: usize,
+    },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownGrid(grid) => write!(f, "grid {grid} not found"),
+            Self::Row { grid, row, rows } => {
+                write!(f, "row {row} outside of grid {grid} with {rows} rows")
+            }
+            Self::Column {
+                grid,
+                row,
+                column,
+                columns,
+            } => write!(
+                f,
+                "column {column} in row {row} outside of grid {grid} with {columns} columns"
+            ),
+            Self::Scroll {
+                grid,
+                rows,
+                columns,
+            } => write!(f, "scroll outside of grid {grid} of {columns}x{rows}"),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
 #[derive(Debug, Default)]
 pub struct ExtLineGridMap {
     map: HashMap<u64, ExtLineGrid>,
@@ -23,6 +77,8 @@ impl ExtLineGridMap {
     }
 
     pub fn grid_resize(&mut self, grid: &u64, columns: usize, rows: usize) {
+        let _span = crate::profiling::span("grid resize");
+
         match self.map.entry(*grid) {
             hash_map::Entry::Occupied(mut grid) => {
                 grid.get_mut().resize(columns, rows);
@@ -33,13 +89,13 @@ impl ExtLineGridMap {
         }
     }
 
-    pub fn grid_clear(&mut self, grid: &u64) {
-        if let Some(grid) = self.map.get_mut(grid) {
-            grid.clear();
-        } else {
-            error!("Grid '{grid}' not found");
-            debug_assert!(false, "Grid '{grid}' not found");
-        }
+    fn get_mut(&mut self, grid: &u64) -> Result<&mut ExtLineGrid, GridError> {
+        self.map.get_mut(grid).ok_or(GridError::UnknownGrid(*grid))
+    }
+
+    pub fn grid_clear(&mut self, grid: &u64) -> Result<(), GridError> {
+        self.get_mut(grid)?.clear();
+        Ok(())
     }
 
     pub fn grid_destroy(&mut self, grid: &u64) {
@@ -56,13 +112,19 @@ impl ExtLineGridMap {
         right: u64,
         rows: i64,
         columns: i64,
-    ) {
-        if let Some(grid) = self.map.get_mut(grid) {
-            grid.scroll(top, bottom, left, right, rows, columns);
-        } else {
-            error!("Grid '{grid}' not found");
-            debug_assert!(false, "Grid '{grid}' not found");
-        }
+    ) -> Result<(), GridError> {
+        let _span = crate::profiling::span("grid scroll");
+
+        self.get_mut(grid)?
+            .scroll(top, bottom, left, right, rows, columns)
+    }
+
+    /// Lines that scrolled off the top of `grid` since the last call, oldest first
+    pub fn take_scrolled_off(&mut self, grid: &u64) -> Vec<Line> {
+        self.map
+            .get_mut(grid)
+            .map(|grid| std::mem::take(&mut grid.scrolled_off))
+            .unwrap_or_default()
     }
 
     pub fn grid_line(
@@ -71,22 +133,19 @@ impl ExtLineGridMap {
         row: usize,
         column_start: usize,
         cells: &[GridLineCell],
-    ) {
-        if let Some(grid) = self.map.get_mut(grid) {
-            grid.update_line(row, column_start, cells);
-        } else {
-            error!("Grid '{grid}' not found");
-            debug_assert!(false, "Grid '{grid}' not found");
-        }
+    ) -> Result<(), GridError> {
+        let _span = crate::profiling::span("grid line");
+
+        self.get_mut(grid)?.update_line(row, column_start, cells)
     }
 
-    pub fn grid_cursor_goto(&mut self, grid: &u64, row: usize, column: usize) {
-        if let Some(grid) = self.map.get_mut(grid) {
-            grid.update_cursor(row, column);
-        } else {
-            error!("Grid '{grid}' not found");
-            debug_assert!(false, "Grid '{grid}' not found");
-        }
+    pub fn grid_cursor_goto(
+        &mut self,
+        grid: &u64,
+        row: usize,
+        column: usize,
+    ) -> Result<(), GridError> {
+        self.get_mut(grid)?.update_cursor(row, column)
     }
 }
 
@@ -98,25 +157,69 @@ pub struct ExtLineGrid {
 
     cursor_position: CursorPosition,
     buffer: Vec<Line>,
-    pub style: HashMap<u64, super::Style>,
+    /// Lines pushed off the top by full screen scrolls, kept for the GUI scrollback
+    scrolled_off: Vec<Line>,
+    /// Shared with the redraw loop, only copied when a highlight is (re)defined while in use
+    pub style: Arc<HashMap<u64, super::Style>>,
     pub default_colors: super::Colors,
 }
 
+/// Lines are shared between the redraw loop and the grid last handed to the widget,
+/// so a flush only copies the lines that changed since the previous one
 #[derive(Debug, Clone)]
 pub struct Line {
-    columns: Vec<GridLineCell>,
+    columns: Arc<Vec<GridLineCell>>,
 }
 
 impl Line {
     fn new(len: usize) -> Self {
         Self {
-            columns: vec![GridLineCell::empty(); len],
+            columns: Arc::new(vec![GridLineCell::empty(); len]),
         }
     }
 
     pub fn columns(&self) -> &[GridLineCell] {
         &self.columns
     }
+
+    /// Same for clones of the line until either is changed, as long as this one lives
+    pub fn id(&self) -> usize {
+        Arc::as_ptr(&self.columns) as usize
+    }
+
+    /// Text of the line without trailing blanks
+    pub fn text(&self) -> String {
+        let text: String = self.columns.iter().map(|cell| &*cell.text).collect();
+        text.trim_end().to_string()
+    }
+
+    fn columns_mut(&mut self) -> &mut Vec<GridLineCell> {
+        Arc::make_mut(&mut self.columns)
+    }
+
+    #[cfg(test)]
+    pub fn from_text(text: &str) -> Self {
+        Self {
+            columns: Arc::new(
+                text.chars()
+                    .map(|c| GridLineCell {
+                        text: super::cell_text::CellText::new(&c.to_string()),
+                        highlight_id: None,
+                        repeat: None,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Number of cells the grapheme at `column` covers, nvim follows double width
+/// graphemes (CJK, emoji) with an empty cell
+pub fn cell_width(cells: &[GridLineCell], column: usize) -> usize {
+    match cells.get(column + 1) {
+        Some(next) if next.text.is_empty() && !cells[column].text.is_empty() => 2,
+        _ => 1,
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -133,6 +236,7 @@ impl ExtLineGrid {
             rows,
             cursor_position: CursorPosition { column: 0, row: 0 },
             buffer: vec![Line::new(columns); rows],
+            scrolled_off: Vec::new(),
             style: Default::default(),
             default_colors: Default::default(),
         }
@@ -158,30 +262,93 @@ impl ExtLineGrid {
         &self.buffer
     }
 
+    /// The grid as it looked `offset` lines back in `history`, without a cursor
+    pub fn with_history(&self, history: &[Line], offset: usize) -> ExtLineGrid {
+        let offset = offset.min(history.len());
+        let columns = self.columns;
+
+        let mut grid = self.clone();
+        grid.scrolled_off.clear();
+        grid.buffer = history[history.len() - offset..]
+            .iter()
+            .chain(&self.buffer)
+            .take(self.rows)
+            .map(|line| {
+                let mut line = line.clone();
+                if line.columns.len() != columns {
+                    line.columns_mut().resize(columns, GridLineCell::empty());
+                }
+                line
+            })
+            .collect();
+        grid
+    }
+
     fn clear(&mut self) {
-        self.buffer
-            .iter_mut()
-            .for_each(|line| line.columns.fill(GridLineCell::empty()));
+        self.buffer.fill(Line::new(self.columns));
     }
 
-    fn scroll(&mut self, top: u64, bottom: u64, left: u64, right: u64, rows: i64, _columns: i64) {
-        let top = top as usize;
-        let bottom = bottom as usize;
-        let left = left as usize;
-        let right = right as usize;
+    fn scroll(
+        &mut self,
+        top: u64,
+        bottom: u64,
+        left: u64,
+        right: u64,
+        rows: i64,
+        _columns: i64,
+    ) -> Result<(), GridError> {
+        let (top, bottom, left, right) =
+            (top as usize, bottom as usize, left as usize, right as usize);
+        let fits = top <= bottom
+            && bottom <= self.rows
+            && left <= right
+            && right <= self.columns
+            && rows.unsigned_abs() <= (bottom - top) as u64;
+        let result = if fits {
+            Ok(())
+        } else {
+            Err(GridError::Scroll {
+                grid: self.id,
+                rows: self.rows,
+                columns: self.columns,
+            })
+        };
+
+        // A region past the grid is cut to it, scrolling by more than its height clears it
+        let bottom = bottom.min(self.rows);
+        let top = top.min(bottom);
+        let right = right.min(self.columns);
+        let left = left.min(right);
+        let height = (bottom - top) as i64;
+        let rows = rows.clamp(-height, height);
+
+        // Full width regions move whole lines without touching their cells
+        let full_width = left == 0 && right == self.columns;
 
         match rows.cmp(&0) {
             std::cmp::Ordering::Greater => {
                 let rows = rows as usize;
 
+                // The top window scrolling pushes text into history, above the statusline and
+                // cmdline rows it leaves out. Side by side splits and lower windows don't.
+                if full_width && top == 0 {
+                    self.scrolled_off
+                        .extend_from_slice(&self.buffer[..rows.min(bottom)]);
+                }
+
                 for n in top..bottom - rows {
+                    if full_width {
+                        self.buffer.swap(n, n + rows);
+                        continue;
+                    }
+
                     let (to, from) = self.buffer.split_at_mut(n + rows);
 
                     let from = &mut from[0];
                     let to = &mut to[n];
 
-                    let from = &mut from.columns[left..right];
-                    let to = &mut to.columns[left..right];
+                    let from = &mut from.columns_mut()[left..right];
+                    let to = &mut to.columns_mut()[left..right];
 
                     to.swap_with_slice(from);
                 }
@@ -190,19 +357,26 @@ impl ExtLineGrid {
                 let rows = -rows as usize;
 
                 for n in ((top + rows)..bottom).rev() {
+                    if full_width {
+                        self.buffer.swap(n - rows, n);
+                        continue;
+                    }
+
                     let (from, to) = self.buffer.split_at_mut(n);
 
                     let from = &mut from[n - rows];
                     let to = &mut to[0];
 
-                    let from = &mut from.columns[left..right];
-                    let to = &mut to.columns[left..right];
+                    let from = &mut from.columns_mut()[left..right];
+                    let to = &mut to.columns_mut()[left..right];
 
                     from.swap_with_slice(to);
                 }
             }
             std::cmp::Ordering::Equal => {}
         }
+
+        result
     }
 
     fn resize(&mut self, columns: usize, rows: usize) {
@@ -211,7 +385,8 @@ impl ExtLineGrid {
             (true, false) => {
                 self.columns = columns;
                 self.buffer.iter_mut().for_each(|line| {
-                    line.columns.resize(self.columns, GridLineCell::empty());
+                    line.columns_mut()
+                        .resize(self.columns, GridLineCell::empty());
                 });
             }
             // Rows changed
@@ -226,7 +401,7 @@ impl ExtLineGrid {
 
                 self.buffer.resize(rows, Line::new(columns));
                 self.buffer.iter_mut().for_each(|line| {
-                    line.columns.resize(columns, GridLineCell::empty());
+                    line.columns_mut().resize(columns, GridLineCell::empty());
                 });
 
                 self.columns = columns;
@@ -236,26 +411,259 @@ impl ExtLineGrid {
         }
     }
 
-    fn update_line(&mut self, row: usize, column_start: usize, cells: &[GridLineCell]) {
-        let line = &mut self.buffer[row];
+    /// Cells past the end of the line are dropped
+    fn update_line(
+        &mut self,
+        row: usize,
+        column_start: usize,
+        cells: &[GridLineCell],
+    ) -> Result<(), GridError> {
+        let Some(line) = self.buffer.get_mut(row) else {
+            return Err(GridError::Row {
+                grid: self.id,
+                row,
+                rows: self.rows,
+            });
+        };
+        let line = line.columns_mut();
 
         let mut column = column_start;
+        // Cells without a highlight id reuse the one of the previous cell in the same event
+        let mut highlight_id = None;
 
-        for cell in cells {
+        'cells: for cell in cells {
             let repeat = cell.repeat.unwrap_or(1);
+            highlight_id = cell.highlight_id.or(highlight_id);
 
             for _ in 0..repeat {
+                let Some(target) = line.get_mut(column) else {
+                    break 'cells;
+                };
+
                 let mut cell = cell.clone();
                 cell.repeat = None;
+                cell.highlight_id = highlight_id;
 
-                line.columns[column] = cell;
+                *target = cell;
                 column += 1;
             }
         }
+
+        let end = cells.iter().fold(column_start, |end, cell| {
+            end.saturating_add(cell.repeat.unwrap_or(1) as usize)
+        });
+        if end > self.columns {
+            return Err(GridError::Column {
+                grid: self.id,
+                row,
+                column: end - 1,
+                columns: self.columns,
+            });
+        }
+        Ok(())
+    }
+
+    /// The cursor is kept inside the grid
+    fn update_cursor(&mut self, row: usize, column: usize) -> Result<(), GridError> {
+        self.cursor_position.row = row.min(self.rows.saturating_sub(1));
+        self.cursor_position.column = column.min(self.columns.saturating_sub(1));
+
+        if row >= self.rows {
+            Err(GridError::Row {
+                grid: self.id,
+                row,
+                rows: self.rows,
+            })
+        } else if column >= self.columns {
+            Err(GridError::Column {
+                grid: self.id,
+                row,
+                column,
+                columns: self.columns,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nvim::cell_text::CellText;
+
+    fn cell(text: &str) -> GridLineCell {
+        GridLineCell {
+            text: CellText::new(text),
+            highlight_id: None,
+            repeat: None,
+        }
+    }
+
+    fn text(grid: &ExtLineGrid) -> Vec<String> {
+        grid.buffer()
+            .iter()
+            .map(|line| line.columns().iter().map(|cell| &*cell.text).collect())
+            .collect()
+    }
+
+    fn grid(lines: &[&str]) -> ExtLineGrid {
+        let mut grid = ExtLineGrid::new(1, 2, lines.len());
+        for (row, line) in lines.iter().enumerate() {
+            let cells: Vec<_> = line.chars().map(|c| cell(&c.to_string())).collect();
+            grid.update_line(row, 0, &cells).unwrap();
+        }
+        grid
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut full = grid(&["aa", "bb", "cc"]);
+        full.scroll(0, 3, 0, 2, 1, 0).unwrap();
+        assert_eq!(text(&full)[..2], ["bb", "cc"]);
+
+        let mut partial = grid(&["aa", "bb", "cc"]);
+        partial.scroll(0, 3, 1, 2, 1, 0).unwrap();
+        assert_eq!(text(&partial)[..2], ["ab", "bc"]);
+
+        let mut down = grid(&["aa", "bb", "cc"]);
+        down.scroll(0, 3, 0, 2, -1, 0).unwrap();
+        assert_eq!(text(&down)[1..], ["aa", "bb"]);
+    }
+
+    #[test]
+    fn test_scrolled_off() {
+        let mut full = grid(&["aa", "bb", "cc"]);
+        full.scroll(0, 3, 0, 2, 1, 0).unwrap();
+        assert_eq!(full.scrolled_off.len(), 1);
+        assert_eq!(&*full.scrolled_off[0].columns()[0].text, "a");
+
+        // Above the statusline
+        let mut window = grid(&["aa", "bb", "cc"]);
+        window.scroll(0, 2, 0, 2, 1, 0).unwrap();
+        assert_eq!(window.scrolled_off.len(), 1);
+
+        // A lower window, or a split not spanning the width
+        let mut lower = grid(&["aa", "bb", "cc"]);
+        lower.scroll(1, 3, 0, 2, 1, 0).unwrap();
+        assert!(lower.scrolled_off.is_empty());
+        let mut split = grid(&["aa", "bb", "cc"]);
+        split.scroll(0, 2, 0, 1, 1, 0).unwrap();
+        assert!(split.scrolled_off.is_empty());
+    }
+
+    #[test]
+    fn test_with_history() {
+        let current = grid(&["cc", "dd"]);
+        let history = [Line::from_text("a"), Line::from_text("bbb")];
+
+        assert_eq!(text(&current.with_history(&history, 1)), ["bb", "cc"]);
+        assert_eq!(text(&current.with_history(&history, 5)), ["a ", "bb"]);
+        assert_eq!(text(&current.with_history(&history, 0)), ["cc", "dd"]);
+    }
+
+    #[test]
+    fn test_grapheme_clusters() {
+        // How nvim sends double width graphemes: the cluster, then an empty cell
+        let fixtures = [
+            // ZWJ family
+            "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}",
+            // Thumbs up with a skin tone modifier
+            "\u{1F44D}\u{1F3FD}",
+            // Flag from two regional indicators
+            "\u{1F1F3}\u{1F1F1}",
+            // Rainbow flag, emoji presentation selector and ZWJ
+            "\u{1F3F3}\u{FE0F}\u{200D}\u{1F308}",
+            // CJK
+            "\u{6F22}",
+        ];
+
+        for fixture in fixtures {
+            let mut grid = ExtLineGrid::new(1, 4, 1);
+            grid.update_line(0, 0, &[cell("a"), cell(fixture), cell(""), cell("b")])
+                .unwrap();
+
+            let cells = grid.buffer()[0].columns();
+            assert_eq!(&*cells[1].text, fixture);
+            assert_eq!(text(&grid), [format!("a{fixture}b")]);
+
+            assert_eq!(cell_width(cells, 0), 1);
+            assert_eq!(cell_width(cells, 1), 2);
+            assert_eq!(cell_width(cells, 3), 1);
+        }
+    }
+
+    #[test]
+    fn test_line_text() {
+        let mut grid = ExtLineGrid::new(1, 6, 1);
+        grid.update_line(
+            0,
+            0,
+            &[cell("a"), cell("界"), cell(""), cell("b"), cell(" ")],
+        )
+        .unwrap();
+
+        assert_eq!(grid.buffer()[0].text(), "a界b");
+        assert_eq!(Line::from_text("   ").text(), "");
+    }
+
+    #[test]
+    fn test_clone_is_isolated() {
+        let mut grid = grid(&["aa", "bb"]);
+        let flushed = grid.clone();
+
+        grid.update_line(0, 0, &[cell("x")]).unwrap();
+
+        assert_eq!(text(&grid), ["xa", "bb"]);
+        assert_eq!(text(&flushed), ["aa", "bb"]);
+        assert!(Arc::ptr_eq(
+            &grid.buffer()[1].columns,
+            &flushed.buffer()[1].columns
+        ));
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let mut grid = grid(&["aa", "bb"]);
+
+        // What fits is written
+        assert_eq!(
+            grid.update_line(0, 1, &[cell("x"), cell("y")]),
+            Err(GridError::Column {
+                grid: 1,
+                row: 0,
+                column: 2,
+                columns: 2,
+            })
+        );
+        assert_eq!(text(&grid), ["ax", "bb"]);
+        assert!(grid.update_line(2, 0, &[cell("x")]).is_err());
+
+        assert!(grid.update_cursor(5, 1).is_err());
+        assert_eq!(grid.cursor_position().row, 1);
+
+        // The region is cut to the grid, all of it scrolls off
+        assert!(grid.scroll(0, 3, 0, 2, 4, 0).is_err());
+        assert_eq!(grid.scrolled_off.len(), 2);
     }
 
-    fn update_cursor(&mut self, row: usize, column: usize) {
-        self.cursor_position.row = row;
-        self.cursor_position.column = column;
+    #[test]
+    fn test_racy_events() {
+        let wide = |text: &str| -> Vec<_> { text.chars().map(|c| cell(&c.to_string())).collect() };
+        let mut grids = ExtLineGridMap::new();
+
+        // A line for the old size arriving after a resize made the grid smaller
+        grids.grid_resize(&2, 4, 3);
+        grids.grid_resize(&2, 2, 2);
+        assert!(grids.grid_line(&2, 2, 0, &wide("abcd")).is_err());
+        assert!(grids.grid_line(&2, 1, 0, &wide("abcd")).is_err());
+        assert!(grids.grid_scroll(&2, 0, 3, 0, 4, 1, 0).is_err());
+        assert!(grids.grid_cursor_goto(&2, 2, 3).is_err());
+        assert_eq!(text(grids.get(&2).unwrap()), ["ab", "  "]);
+
+        // Events for a grid destroyed meanwhile
+        grids.grid_destroy(&2);
+        assert_eq!(grids.grid_clear(&2), Err(GridError::UnknownGrid(2)));
+        assert!(grids.grid_line(&2, 0, 0, &wide("a")).is_err());
     }
 }
@@ -1,6 +1,6 @@
 use super::event::PopupMenuItem;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExtPopupMenuState {
     pub items: Vec<PopupMenuItem>,
     pub selected: Option<usize>,
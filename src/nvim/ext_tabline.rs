@@ -1,4 +1,4 @@
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct ExtTabline {
     current_tab: Option<super::Tabpage>,
     tabs: Vec<(String, super::Tabpage)>,
@@ -0,0 +1,147 @@
+use nvim_rs::Value;
+
+use super::bridge::FromValue;
+
+/// Lines of the buffer visible in a window from `win_viewport`, 1-based
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Viewport {
+    pub topline: u64,
+    pub botline: u64,
+    pub curline: u64,
+    pub line_count: u64,
+}
+
+impl Viewport {
+    /// The same lines are on screen, only the cursor line may differ
+    pub fn same_lines(&self, other: &Self) -> bool {
+        (self.topline, self.botline, self.line_count)
+            == (other.topline, other.botline, other.line_count)
+    }
+
+    /// How far through the buffer the last line on screen is, from 0 to 1
+    pub fn progress(&self) -> f64 {
+        if self.line_count == 0 {
+            return 0.0;
+        }
+        (self.botline as f64 / self.line_count as f64).min(1.0)
+    }
+}
+
+/// Line numbers and signs of the current window, one entry per screen row,
+/// as returned by `require("vimdicator").gutter()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GutterLines {
+    /// Grid row of the first line
+    pub first_row: usize,
+    pub rows: Vec<GutterRow>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GutterRow {
+    /// 1-based buffer line, `None` for the continuation of a wrapped line
+    pub line: Option<u64>,
+    /// Text of the highest priority sign, empty without signs
+    pub sign: String,
+    /// Foreground of the sign highlight as `#rrggbb`
+    pub sign_color: Option<String>,
+}
+
+impl FromValue for GutterRow {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Array(fields) = value else {
+            return None;
+        };
+        let mut fields = fields.into_iter();
+
+        Some(Self {
+            line: fields.next()?.as_u64().filter(|line| *line > 0),
+            sign: String::from_value(fields.next()?)?,
+            sign_color: Some(String::from_value(fields.next()?)?).filter(|color| !color.is_empty()),
+        })
+    }
+}
+
+impl FromValue for GutterLines {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Array(fields) = value else {
+            return None;
+        };
+        let mut fields = fields.into_iter();
+
+        Some(Self {
+            first_row: fields.next()?.as_u64()? as usize,
+            rows: Vec::from_value(fields.next()?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_lines() {
+        let viewport = Viewport {
+            topline: 1,
+            botline: 40,
+            curline: 3,
+            line_count: 100,
+        };
+
+        assert!(viewport.same_lines(&Viewport {
+            curline: 20,
+            ..viewport
+        }));
+        assert!(!viewport.same_lines(&Viewport {
+            topline: 2,
+            ..viewport
+        }));
+    }
+
+    #[test]
+    fn test_progress() {
+        let viewport = Viewport {
+            topline: 1,
+            botline: 25,
+            curline: 3,
+            line_count: 100,
+        };
+        assert_eq!(viewport.progress(), 0.25);
+        assert_eq!(Viewport::default().progress(), 0.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        let value = Value::from(vec![
+            Value::from(2),
+            Value::from(vec![
+                Value::from(vec![
+                    Value::from(10),
+                    Value::from("E"),
+                    Value::from("#ff0000"),
+                ]),
+                Value::from(vec![Value::from(0), Value::from(""), Value::from("")]),
+            ]),
+        ]);
+
+        assert_eq!(
+            GutterLines::from_value(value),
+            Some(GutterLines {
+                first_row: 2,
+                rows: vec![
+                    GutterRow {
+                        line: Some(10),
+                        sign: "E".to_string(),
+                        sign_color: Some("#ff0000".to_string()),
+                    },
+                    GutterRow {
+                        line: None,
+                        sign: String::new(),
+                        sign_color: None,
+                    },
+                ],
+            })
+        );
+        assert_eq!(GutterLines::from_value(Value::from("")), None);
+    }
+}
@@ -4,12 +4,15 @@ use nvim_rs::{Neovim, Value};
 
 use async_trait::async_trait;
 
-use super::event::NvimEvent;
+use super::{event::NvimEvent, redraw::RedrawState, ColorMode, GuiRequest, NeovimWriter};
 use gtk::glib;
+use tokio::sync::oneshot;
 
 #[derive(Debug)]
 struct InnerData {
     gtk_tx: glib::Sender<NvimEvent>,
+    /// Notifications are handled one after another, in the order nvim sent them
+    redraw: RedrawState,
 }
 
 #[derive(Debug, Clone)]
@@ -18,9 +21,12 @@ pub struct NvimHadler {
 }
 
 impl NvimHadler {
-    pub fn new(gtk_tx: glib::Sender<NvimEvent>) -> Self {
+    pub fn new(gtk_tx: glib::Sender<NvimEvent>, color_mode: ColorMode) -> Self {
         Self {
-            data: Arc::new(Mutex::new(InnerData { gtk_tx })),
+            data: Arc::new(Mutex::new(InnerData {
+                gtk_tx,
+                redraw: RedrawState::new(color_mode),
+            })),
         }
     }
 }
@@ -30,18 +36,76 @@ impl nvim_rs::Handler for NvimHadler {
     type Writer = super::NeovimWriter;
 
     async fn handle_notify(&self, name: String, args: Vec<Value>, nvim: Neovim<Self::Writer>) {
-        let event = NvimEvent::parse(name, args, nvim).unwrap();
-        self.data.lock().unwrap().gtk_tx.send(event).unwrap();
+        crate::crash::log_rpc(format!("<- {name}"));
+
+        let event = {
+            let _span = crate::profiling::span("parse notification");
+            NvimEvent::parse(name, args, nvim.clone())
+        };
+        // Already counted or logged by the parser
+        let Some(event) = event else {
+            return;
+        };
+
+        let mut data = self.data.lock().unwrap();
+        let event = match event {
+            NvimEvent::Redraw(events) => {
+                let _span = crate::profiling::span("redraw batch");
+                let flush = data.redraw.apply(events);
+                if data.redraw.take_refresh() {
+                    // Requests are answered by the loop running this, they can't be awaited here
+                    tokio::spawn(refresh(nvim, data.redraw.default_size()));
+                }
+                let Some(flush) = flush else {
+                    return;
+                };
+                NvimEvent::Flush(Box::new(flush))
+            }
+            event => event,
+        };
+        data.gtk_tx.send(event).unwrap();
     }
 
     async fn handle_request(
         &self,
         name: String,
-        _args: Vec<Value>,
+        args: Vec<Value>,
         _: Neovim<Self::Writer>,
     ) -> Result<Value, Value> {
-        dbg!(name);
+        crate::crash::log_rpc(format!("<- request {name}"));
+
+        if name != "Gui" {
+            return Err(Value::from(format!("Unknown request {name}")));
+        }
+        let Some(request) = GuiRequest::parse(args) else {
+            return Err(Value::from("Invalid arguments for a Gui request"));
+        };
+
+        let (tx, rx) = oneshot::channel();
+        // The lock must not be held across the await
+        let sent = self
+            .data
+            .lock()
+            .unwrap()
+            .gtk_tx
+            .send(NvimEvent::Request(request, tx));
+        if sent.is_err() {
+            return Err(Value::from("The GUI is gone"));
+        }
+        rx.await
+            .unwrap_or_else(|_| Err(Value::from("The GUI dropped the request")))
+    }
+}
 
-        Ok(nvim_rs::Value::Nil)
+/// Have nvim send the grids again after events that didn't fit them. Resizing to nvim's own
+/// size sends the grid sizes again, `redraw!` their content.
+async fn refresh(nvim: Neovim<NeovimWriter>, size: Option<(usize, usize)>) {
+    if let Some((columns, rows)) = size {
+        if let Err(err) = nvim.ui_try_resize(columns as i64, rows as i64).await {
+            log::warn!("Resizing for a refresh failed: {err}");
+        }
+    }
+    if let Err(err) = nvim.command("redraw!").await {
+        log::warn!("Refreshing the grids failed: {err}");
     }
 }
@@ -0,0 +1,216 @@
+/// Severity of a `:checkhealth` line, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthLevel {
+    /// Plain report lines without a status
+    Info,
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthItem {
+    pub level: HealthLevel,
+    pub text: String,
+    /// The indented lines under a warning or error, without the `ADVICE:` header
+    pub advice: Vec<String>,
+}
+
+/// Lines under a `Heading ~` of a section
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthGroup {
+    /// Empty for lines before the first heading
+    pub title: String,
+    pub items: Vec<HealthItem>,
+}
+
+/// One health check, e.g. `vim.lsp` or `provider.clipboard`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthSection {
+    pub name: String,
+    pub groups: Vec<HealthGroup>,
+    /// The lines as `:checkhealth` printed them, for copying
+    pub report: String,
+}
+
+impl HealthGroup {
+    /// Most severe level of its items
+    pub fn level(&self) -> HealthLevel {
+        self.items
+            .iter()
+            .map(|item| item.level)
+            .max()
+            .unwrap_or(HealthLevel::Info)
+    }
+}
+
+impl HealthSection {
+    /// Most severe level of all items
+    pub fn level(&self) -> HealthLevel {
+        self.groups
+            .iter()
+            .map(HealthGroup::level)
+            .max()
+            .unwrap_or(HealthLevel::Info)
+    }
+}
+
+/// Status prefixes, nvim 0.10 puts an emoji in front of them
+fn parse_level(text: &str) -> (HealthLevel, &str) {
+    let text = text
+        .trim_start_matches(['✅', '⚠', '\u{fe0f}', '❌'])
+        .trim_start();
+
+    for (prefix, level) in [
+        ("OK", HealthLevel::Ok),
+        ("WARNING", HealthLevel::Warning),
+        ("ERROR", HealthLevel::Error),
+    ] {
+        if let Some(rest) = text.strip_prefix(prefix) {
+            if rest.is_empty() || rest.starts_with([' ', ':']) {
+                return (level, rest.trim_start_matches(':').trim());
+            }
+        }
+    }
+    (HealthLevel::Info, text)
+}
+
+/// Split the buffer `:checkhealth` writes into its sections
+pub fn parse(lines: &[String]) -> Vec<HealthSection> {
+    let mut sections: Vec<HealthSection> = Vec::new();
+    let mut expect_name = false;
+
+    for line in lines {
+        if line.starts_with("====") {
+            expect_name = true;
+            continue;
+        }
+        if expect_name {
+            if line.trim().is_empty() {
+                continue;
+            }
+            expect_name = false;
+            // `vim.lsp: require("vim.lsp.health").check()`
+            let name = line.split_once(':').map_or(line.as_str(), |(name, _)| name);
+            sections.push(HealthSection {
+                name: name.trim().to_string(),
+                groups: Vec::new(),
+                report: String::new(),
+            });
+            continue;
+        }
+        // Nothing but a title before the first section
+        let Some(section) = sections.last_mut() else {
+            continue;
+        };
+
+        if !section.report.is_empty() || !line.trim().is_empty() {
+            section.report.push_str(line);
+            section.report.push('\n');
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(title) = trimmed.strip_suffix(" ~") {
+            section.groups.push(HealthGroup {
+                title: title.trim().to_string(),
+                items: Vec::new(),
+            });
+            continue;
+        }
+        if section.groups.is_empty() {
+            section.groups.push(HealthGroup {
+                title: String::new(),
+                items: Vec::new(),
+            });
+        }
+        let items = &mut section.groups.last_mut().unwrap().items;
+
+        let indented = line.starts_with([' ', '\t']);
+        match (indented, items.last_mut()) {
+            (true, Some(item)) => {
+                let advice = trimmed.trim_start_matches("- ");
+                if advice != "ADVICE:" {
+                    item.advice.push(advice.to_string());
+                }
+            }
+            _ => {
+                let (level, text) = parse_level(trimmed.trim_start_matches("- "));
+                items.push(HealthItem {
+                    level,
+                    text: text.to_string(),
+                    advice: Vec::new(),
+                });
+            }
+        }
+    }
+
+    for section in &mut sections {
+        section.report = section.report.trim_end().to_string();
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let lines: Vec<String> = r#"
+==============================================================================
+nvim: require("nvim.health").check()
+
+Configuration ~
+- OK no issues found
+
+Performance ~
+- WARNING Build type: Debug
+  - ADVICE:
+    - Install a release build
+==============================================================================
+provider.clipboard:                    require("provider.clipboard.health").check()
+
+- ❌ ERROR No clipboard tool found.
+- Run :help clipboard
+"#
+        .lines()
+        .map(str::to_string)
+        .collect();
+
+        let sections = parse(&lines);
+        assert_eq!(sections.len(), 2);
+
+        assert_eq!(sections[0].name, "nvim");
+        assert_eq!(sections[0].level(), HealthLevel::Warning);
+        assert_eq!(sections[0].groups[0].title, "Configuration");
+        assert_eq!(sections[0].groups[0].items[0].level, HealthLevel::Ok);
+        assert_eq!(sections[0].groups[0].items[0].text, "no issues found");
+        assert_eq!(
+            sections[0].groups[1].items[0],
+            HealthItem {
+                level: HealthLevel::Warning,
+                text: "Build type: Debug".to_string(),
+                advice: vec!["Install a release build".to_string()],
+            }
+        );
+        assert!(sections[0].report.starts_with("Configuration ~\n"));
+        assert!(sections[0].report.ends_with("release build"));
+
+        assert_eq!(sections[1].name, "provider.clipboard");
+        assert_eq!(sections[1].level(), HealthLevel::Error);
+        let items = &sections[1].groups[0].items;
+        assert_eq!(items[0].text, "No clipboard tool found.");
+        assert_eq!(items[1].level, HealthLevel::Info);
+        assert_eq!(items[1].text, "Run :help clipboard");
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("⚠️ WARNING foo"), (HealthLevel::Warning, "foo"));
+        assert_eq!(parse_level("ERROR: foo"), (HealthLevel::Error, "foo"));
+        assert_eq!(parse_level("OKAY"), (HealthLevel::Info, "OKAY"));
+    }
+}
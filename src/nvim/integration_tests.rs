@@ -0,0 +1,349 @@
+//! Drives a real `nvim --embed --headless` through the redraw parsing and grid state,
+//! run with `cargo test --features nvim-tests` (needs `nvim` in `PATH` or `$NVIM_BIN`)
+
+use std::{collections::HashMap, process::Stdio, time::Duration};
+
+use async_trait::async_trait;
+use nvim_rs::{UiAttachOptions, Value};
+use tokio::{
+    process::Child,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use super::{
+    connection::{BoxedReader, BoxedWriter},
+    nvim_command,
+    redraw::RedrawState,
+    ColorMode, ExtLineGridMap, ExtPopupMenu, Neovim, NeovimWriter, NvimEvent, RedrawEvent, Style,
+    Viewport,
+};
+
+/// How long a condition may take to show up in the grid before the test fails
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Forwards parsed notifications to the test, instead of the GTK main loop
+#[derive(Clone)]
+struct Collector {
+    tx: UnboundedSender<NvimEvent>,
+}
+
+#[async_trait]
+impl nvim_rs::Handler for Collector {
+    type Writer = NeovimWriter;
+
+    async fn handle_notify(&self, name: String, args: Vec<Value>, nvim: Neovim) {
+        let event = NvimEvent::parse(name.clone(), args, nvim);
+        let event = event.unwrap_or_else(|| panic!("Could not parse {name:?}"));
+        // The test may be done and gone already
+        let _ = self.tx.send(event);
+    }
+}
+
+/// The grid state the GUI would have, kept up to date with the redraw events
+struct Harness {
+    nvim: Neovim,
+    events: UnboundedReceiver<NvimEvent>,
+    grids: ExtLineGridMap,
+    popup_menu: ExtPopupMenu,
+    styles: HashMap<u64, Style>,
+    /// What the GUI applies the events with, for what only its flushes carry
+    redraw: RedrawState,
+    viewport: Option<Viewport>,
+    _child: Child,
+}
+
+impl Harness {
+    async fn new(columns: i64, rows: i64) -> Self {
+        let mut child = nvim_command()
+            .args(["--embed", "--headless", "--clean", "-n"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("Could not start nvim");
+
+        let reader: BoxedReader = Box::new(child.stdout.take().unwrap());
+        let writer: BoxedWriter = Box::new(child.stdin.take().unwrap());
+
+        let (tx, events) = mpsc::unbounded_channel();
+        let (nvim, io_future) =
+            Neovim::new(reader.compat(), writer.compat_write(), Collector { tx });
+        tokio::spawn(io_future);
+
+        nvim.ui_attach(
+            columns,
+            rows,
+            UiAttachOptions::new()
+                .set_rgb(true)
+                .set_popupmenu_external(true)
+                .set_linegrid_external(true),
+        )
+        .await
+        .unwrap();
+
+        let mut this = Self {
+            nvim,
+            events,
+            grids: ExtLineGridMap::new(),
+            popup_menu: ExtPopupMenu::new(),
+            styles: HashMap::new(),
+            redraw: RedrawState::new(ColorMode::Rgb),
+            viewport: None,
+            _child: child,
+        };
+        this.wait_for("the first screen", |this| this.grids.get(&1).is_some())
+            .await;
+
+        this
+    }
+
+    fn apply(&mut self, events: &[RedrawEvent]) {
+        for event in events {
+            match event {
+                RedrawEvent::GridResize {
+                    grid,
+                    width,
+                    height,
+                } => self
+                    .grids
+                    .grid_resize(grid, *width as usize, *height as usize),
+                RedrawEvent::GridClear { grid } => self.grids.grid_clear(grid).unwrap(),
+                RedrawEvent::GridDestroy { grid } => self.grids.grid_destroy(grid),
+                RedrawEvent::GridScroll {
+                    grid,
+                    top,
+                    bottom,
+                    left,
+                    right,
+                    rows,
+                    columns,
+                } => self
+                    .grids
+                    .grid_scroll(grid, *top, *bottom, *left, *right, *rows, *columns)
+                    .unwrap(),
+                RedrawEvent::GridLine {
+                    grid,
+                    row,
+                    column_start,
+                    cells,
+                } => self
+                    .grids
+                    .grid_line(grid, *row as usize, *column_start as usize, cells)
+                    .unwrap(),
+                RedrawEvent::GridCursorGoto { grid, row, column } => self
+                    .grids
+                    .grid_cursor_goto(grid, *row as usize, *column as usize)
+                    .unwrap(),
+                RedrawEvent::HighlightAttributesDefine { id, style } => {
+                    self.styles.insert(*id, style.clone());
+                }
+                RedrawEvent::PopupmenuShow {
+                    items,
+                    selected,
+                    row,
+                    col,
+                    grid,
+                } => self.popup_menu.show(
+                    items.clone(),
+                    selected.map(|s| s as usize),
+                    *row as usize,
+                    *col as usize,
+                    *grid,
+                ),
+                RedrawEvent::PopupmenuSelect { selected } => {
+                    self.popup_menu.select(selected.map(|s| s as usize))
+                }
+                RedrawEvent::PopupmenuHide => self.popup_menu.hide(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Applies redraw batches until `done` holds, only checking the flushed
+    /// (consistent) states like the GUI only draws those
+    async fn wait_for(&mut self, what: &str, done: impl Fn(&Self) -> bool) {
+        let deadline = tokio::time::Instant::now() + TIMEOUT;
+
+        while !done(self) {
+            loop {
+                let event = tokio::time::timeout_at(deadline, self.events.recv())
+                    .await
+                    .unwrap_or_else(|_| panic!("Timed out waiting for {what}:\n{}", self.screen()))
+                    .expect("nvim exited");
+
+                let NvimEvent::Redraw(events) = event else {
+                    continue;
+                };
+                self.apply(&events);
+                if let Some(flush) = self.redraw.apply(events) {
+                    self.viewport = flush.viewport.or(self.viewport);
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn input(&self, keys: &str) {
+        self.nvim.input(keys).await.unwrap();
+    }
+
+    async fn command(&self, command: &str) {
+        self.nvim.command(command).await.unwrap();
+    }
+
+    /// Text of a row of the default grid, without trailing blanks
+    fn row(&self, row: usize) -> String {
+        let Some(line) = self.grids.get(&1).and_then(|grid| grid.buffer().get(row)) else {
+            return String::new();
+        };
+
+        let text: String = line.columns().iter().map(|cell| &*cell.text).collect();
+        text.trim_end().to_string()
+    }
+
+    fn screen(&self) -> String {
+        let rows = self.grids.get(&1).map_or(0, |grid| grid.rows());
+        (0..rows).map(|row| self.row(row) + "\n").collect()
+    }
+
+    fn style_at(&self, row: usize, column: usize) -> Option<&Style> {
+        let grid = self.grids.get(&1)?;
+        let cell = grid.buffer().get(row)?.columns().get(column)?;
+        self.styles.get(&cell.highlight_id?)
+    }
+}
+
+#[tokio::test]
+async fn test_typed_text() {
+    let mut ui = Harness::new(40, 10).await;
+
+    ui.input("ihello world<Esc>").await;
+    ui.wait_for("the typed text", |ui| ui.row(0) == "hello world")
+        .await;
+
+    let cursor = ui.grids.get(&1).unwrap().cursor_position();
+    assert_eq!((cursor.row, cursor.column), (0, 10));
+    assert!(ui.row(1).starts_with('~'));
+}
+
+#[tokio::test]
+async fn test_highlight_styles() {
+    let mut ui = Harness::new(40, 10).await;
+
+    ui.command("highlight VimdicatorTest gui=bold,undercurl guifg=#ff0000")
+        .await;
+    ui.command("call setline(1, 'plain marked plain')").await;
+    ui.command("call matchadd('VimdicatorTest', 'marked')")
+        .await;
+    ui.wait_for("the match highlight", |ui| {
+        ui.style_at(0, 6).is_some_and(|style| style.bold)
+    })
+    .await;
+
+    let style = ui.style_at(0, 6).unwrap();
+    assert_eq!(
+        style.colors.foreground,
+        Some(super::Color {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0
+        })
+    );
+    assert_eq!(
+        style.underline,
+        Some(super::event::UnderlineStyle::UnderCurl)
+    );
+
+    // Cells after the match go back to the default highlight
+    assert!(!ui.style_at(0, 12).is_some_and(|style| style.bold));
+    assert!(!ui.style_at(0, 0).is_some_and(|style| style.bold));
+}
+
+#[tokio::test]
+async fn test_scroll() {
+    let mut ui = Harness::new(40, 10).await;
+
+    ui.command("call setline(1, map(range(1, 100), 'string(v:val)'))")
+        .await;
+    ui.wait_for("the buffer", |ui| ui.row(0) == "1").await;
+
+    // nvim scrolls the grid and only redraws the line that came into view
+    ui.input("3<C-e>").await;
+    ui.wait_for("the scroll", |ui| ui.row(0) == "4").await;
+    for row in 0..8 {
+        assert_eq!(ui.row(row), (row + 4).to_string(), "{}", ui.screen());
+    }
+
+    ui.input("2<C-y>").await;
+    ui.wait_for("the scroll back", |ui| ui.row(0) == "2").await;
+    for row in 0..8 {
+        assert_eq!(ui.row(row), (row + 2).to_string(), "{}", ui.screen());
+    }
+}
+
+#[tokio::test]
+async fn test_popup_menu() {
+    let mut ui = Harness::new(40, 10).await;
+
+    ui.command("set completeopt=menu").await;
+    ui.command("call setline(1, ['apple', 'apricot'])").await;
+    ui.input("Goap<C-n>").await;
+    ui.wait_for("the popup menu", |ui| ui.popup_menu.get().is_some())
+        .await;
+
+    let state = ui.popup_menu.get().unwrap();
+    let words: Vec<_> = state.items.iter().map(|item| item.word.as_str()).collect();
+    assert_eq!(words, ["apple", "apricot"]);
+    assert_eq!(state.selected, Some(0));
+    // Anchored at the start of the completed word
+    assert_eq!((state.row, state.col), (2, 0));
+
+    ui.input("<C-n>").await;
+    ui.wait_for("the next item", |ui| {
+        ui.popup_menu
+            .get()
+            .is_some_and(|state| state.selected == Some(1))
+    })
+    .await;
+    ui.wait_for("the completed word", |ui| ui.row(2) == "apricot")
+        .await;
+
+    ui.input("<Esc>").await;
+    ui.wait_for("the popup menu to close", |ui| {
+        ui.popup_menu.get().is_none()
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_resize() {
+    let mut ui = Harness::new(40, 10).await;
+
+    ui.nvim.ui_try_resize(60, 20).await.unwrap();
+    ui.wait_for("the resize", |ui| ui.grids.get(&1).unwrap().columns() == 60)
+        .await;
+
+    let grid = ui.grids.get(&1).unwrap();
+    assert_eq!(grid.rows(), 20);
+    assert_eq!(grid.buffer().len(), 20);
+    assert!(grid.buffer().iter().all(|line| line.columns().len() == 60));
+}
+
+#[tokio::test]
+async fn test_viewport() {
+    let mut ui = Harness::new(40, 10).await;
+
+    ui.command("call setline(1, map(range(1, 100), 'string(v:val)'))")
+        .await;
+    ui.input("50G").await;
+    ui.wait_for("the viewport", |ui| {
+        ui.viewport.is_some_and(|viewport| viewport.curline == 50)
+    })
+    .await;
+
+    let viewport = ui.viewport.unwrap();
+    assert_eq!(viewport.line_count, 100);
+    assert!(viewport.topline <= 50 && 50 <= viewport.botline);
+    assert_eq!(ui.row(50 - viewport.topline as usize), "50");
+}
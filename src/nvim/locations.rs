@@ -0,0 +1,94 @@
+use nvim_rs::Value;
+
+use super::bridge::FromValue;
+
+/// Marks and jump list of the current window, as returned by `require("vimdicator").locations()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Locations {
+    /// Local marks of the current buffer, then the global ones
+    pub marks: Vec<Location>,
+    /// Newest first
+    pub jumps: Vec<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// Mark name, or the jump count relative to the current position (`-2`, `0`, `1`)
+    pub label: String,
+    /// Relative to nvim's working directory or home
+    pub path: String,
+    /// 1-based
+    pub line: u64,
+    /// Text of the line, empty when its buffer isn't loaded
+    pub text: String,
+}
+
+impl FromValue for Location {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Array(fields) = value else {
+            return None;
+        };
+        let mut fields = fields.into_iter();
+
+        Some(Self {
+            label: String::from_value(fields.next()?)?,
+            path: String::from_value(fields.next()?)?,
+            line: fields.next()?.as_u64()?,
+            text: String::from_value(fields.next()?)?,
+        })
+    }
+}
+
+impl FromValue for Locations {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Array(fields) = value else {
+            return None;
+        };
+        let mut fields = fields.into_iter();
+
+        Some(Self {
+            marks: Vec::from_value(fields.next()?)?,
+            jumps: Vec::from_value(fields.next()?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let location = |label: &str, line: u64| {
+            Value::from(vec![
+                Value::from(label),
+                Value::from("src/main.rs"),
+                Value::from(line),
+                Value::from("fn main() {"),
+            ])
+        };
+        let value = Value::from(vec![
+            Value::from(vec![location("a", 3)]),
+            Value::from(vec![location("0", 7), location("-1", 1)]),
+        ]);
+
+        let locations = Locations::from_value(value).unwrap();
+        assert_eq!(
+            locations.marks,
+            [Location {
+                label: "a".to_string(),
+                path: "src/main.rs".to_string(),
+                line: 3,
+                text: "fn main() {".to_string(),
+            }]
+        );
+        assert_eq!(locations.jumps.len(), 2);
+        assert_eq!(locations.jumps[1].label, "-1");
+
+        // Lua sends empty tables for missing lists, a missing list is an error
+        assert_eq!(
+            Locations::from_value(Value::from(vec![Value::Array(vec![])])),
+            None
+        );
+    }
+}
@@ -1,5 +1,6 @@
 pub mod api_info;
-use std::process::Stdio;
+pub mod bridge;
+pub use bridge::NvimBridge;
 
 pub use api_info::NeovimApiInfo;
 
@@ -7,7 +8,31 @@ pub mod handler;
 pub use handler::NvimHadler;
 
 pub mod event;
-pub use event::{Color, Colors, NvimEvent, RedrawEvent, Style};
+pub use event::{
+    Color, Colors, GuiEvent, GuiOption, ImagePlacement, NvimEvent, ProgressAction, QuickfixEntry,
+    QuickfixList, RedrawEvent, Register, Style,
+};
+
+pub mod cell_text;
+pub mod colorschemes;
+pub use colorschemes::{Colorscheme, Colorschemes};
+pub mod connection;
+pub mod gutter;
+pub use gutter::{GutterLines, Viewport};
+pub mod health;
+pub mod locations;
+pub use locations::{Location, Locations};
+pub mod overflow;
+pub use overflow::OverflowWindow;
+pub mod mode;
+pub use mode::Mode;
+pub mod palette;
+pub use palette::ColorMode;
+pub mod protocol;
+pub mod redraw;
+pub use redraw::Flush;
+pub mod request;
+pub use request::{GuiRequest, Responder};
 
 pub mod ext_line_grid;
 pub use ext_line_grid::{ExtLineGrid, ExtLineGridMap};
@@ -18,14 +43,19 @@ pub use ext_popup_menu::{ExtPopupMenu, ExtPopupMenuState};
 pub mod ext_tabline;
 pub use ext_tabline::ExtTabline;
 
+pub mod subscription;
+pub use subscription::{BufferInfo, Subscription};
+
+#[cfg(all(test, feature = "nvim-tests"))]
+mod integration_tests;
+
+use crate::options::Options;
 use gtk::glib;
-use tokio::{
-    process::{ChildStdin, Command},
-    sync::mpsc::UnboundedReceiver,
-};
+use log::error;
+use tokio::{process::Command, sync::mpsc::UnboundedReceiver};
 use tokio_util::compat::{Compat, TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
-type NeovimWriter = Compat<ChildStdin>;
+type NeovimWriter = Compat<connection::BoxedWriter>;
 type Neovim = nvim_rs::Neovim<NeovimWriter>;
 
 #[derive(Clone, PartialEq)]
@@ -37,17 +67,16 @@ pub struct Tabpage {
 impl std::cmp::Eq for Tabpage {}
 
 impl Tabpage {
-    pub fn new(code_data: nvim_rs::Value, nvim: Neovim) -> Self {
-        let inner = nvim_rs::Tabpage::new(code_data.clone(), nvim);
-
-        Self {
-            ext: if let nvim_rs::Value::Ext(a, b) = code_data {
-                (a, b)
-            } else {
-                panic!("Expected Value::Ext in Tabpage")
-            },
-            inner,
-        }
+    /// `None` unless `code_data` is the `Value::Ext` nvim sends for tabpages
+    pub fn new(code_data: nvim_rs::Value, nvim: Neovim) -> Option<Self> {
+        let nvim_rs::Value::Ext(a, b) = &code_data else {
+            return None;
+        };
+
+        Some(Self {
+            ext: (*a, b.clone()),
+            inner: nvim_rs::Tabpage::new(code_data, nvim),
+        })
     }
 }
 
@@ -88,6 +117,8 @@ pub enum NvimMouseAction {
     Release,
     Up,
     Down,
+    Left,
+    Right,
     Drag,
 }
 
@@ -98,11 +129,14 @@ impl NvimMouseAction {
             Self::Release => "release",
             Self::Up => "up",
             Self::Down => "down",
+            Self::Left => "left",
+            Self::Right => "right",
             Self::Drag => "drag",
         }
     }
 }
 
+#[derive(Debug)]
 pub enum GtkToNvimEvent {
     Input(String),
     InputMouse {
@@ -113,36 +147,109 @@ pub enum GtkToNvimEvent {
         /// (col, row)
         pos: Option<(u64, u64)>,
     },
-    Resized {
-        width: u64,
-        height: u64,
-    },
     ExecLua(String),
+    Command(String),
+    OpenFile {
+        path: String,
+        line: Option<u64>,
+    },
+    ChangeDirectory(String),
+    /// Put the paths, relative to nvim's working directory, at the cursor
+    InsertPaths(Vec<String>),
+    /// Open a `:terminal` split, or start `external` (argv) in the current directory
+    OpenTerminal {
+        external: Option<Vec<String>>,
+    },
+    Focus(bool),
+    /// Typed request, see [`NvimBridge`]
+    Call(bridge::Call, bridge::Reply),
+    /// The GUI is crashing: write all buffers and swap files, detach and acknowledge
+    Rescue(std::sync::mpsc::SyncSender<()>),
+}
+
+/// Installed runtime directory, or the one in the source tree when running uninstalled
+fn runtime_path() -> String {
+    let installed = crate::paths::pkgdatadir().join("runtime");
+
+    if installed.is_dir() {
+        installed.to_string_lossy().into_owned()
+    } else {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/runtime").to_string()
+    }
 }
 
-pub async fn run(mut rx: UnboundedReceiver<GtkToNvimEvent>, gtk_tx: glib::Sender<NvimEvent>) {
-    let (reader, writer) = {
-        let mut child = Command::new("nvim")
-            .arg("--embed")
-            .stderr(Stdio::inherit())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .unwrap();
+/// Apps started from Finder/launchd get a minimal PATH without Homebrew or MacPorts,
+/// ask the login shell first and fall back to their default prefixes
+#[cfg(target_os = "macos")]
+fn nvim_program() -> std::ffi::OsString {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+
+    let from_shell = std::process::Command::new(shell)
+        .args(["-lc", "command -v nvim"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty());
+
+    from_shell
+        .or_else(|| {
+            ["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin"]
+                .map(|dir| format!("{dir}/nvim"))
+                .into_iter()
+                .find(|path| std::path::Path::new(path).exists())
+        })
+        .unwrap_or_else(|| "nvim".to_string())
+        .into()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn nvim_program() -> std::ffi::OsString {
+    "nvim".into()
+}
+
+pub(crate) fn nvim_command() -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(nvim_program());
+
+    // Don't flash a console window for the embedded nvim
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    command
+}
 
-        (child.stdout.take().unwrap(), child.stdin.take().unwrap())
+pub async fn run(
+    mut rx: UnboundedReceiver<GtkToNvimEvent>,
+    gtk_tx: glib::Sender<NvimEvent>,
+    options: Options,
+    rpc_timeout: std::time::Duration,
+) {
+    let (reader, writer) = match &options.server {
+        Some(addr) => {
+            let Some(io) = connection::connect_with_retry(addr, &options, &gtk_tx).await else {
+                // The window stays open showing the error, the GUI's sends mustn't fail
+                while rx.recv().await.is_some() {}
+                return;
+            };
+            io
+        }
+        None => connection::spawn(&options).unwrap(),
     };
 
-    let handler = NvimHadler::new(gtk_tx);
+    let color_mode = if options.cterm_colors {
+        ColorMode::Cterm
+    } else {
+        ColorMode::Rgb
+    };
+    let handler = NvimHadler::new(gtk_tx.clone(), color_mode);
 
     let (nvim, io_future) = Neovim::new(reader.compat(), writer.compat_write(), handler);
 
-    // let stream = tokio::net::TcpStream::connect("127.0.0.1:8080")
-    //     .await
-    //     .unwrap();
-    // let (reader, writer) = stream.into_split();
-    // let (nvim, io_future) = Neovim::new(reader.compat(), writer.compat_write(), handler);
-
     let join = tokio::spawn(async move {
         // add callback on session end
         if let Err(e) = io_future.await {
@@ -152,7 +259,7 @@ pub async fn run(mut rx: UnboundedReceiver<GtkToNvimEvent>, gtk_tx: glib::Sender
         }
     });
 
-    {
+    let api_info = {
         let mut version_info: Vec<(nvim_rs::Value, nvim_rs::Value)> = vec![
             ("major".into(), env!("CARGO_PKG_VERSION_MAJOR").into()),
             ("minor".into(), env!("CARGO_PKG_VERSION_MINOR").into()),
@@ -174,7 +281,7 @@ pub async fn run(mut rx: UnboundedReceiver<GtkToNvimEvent>, gtk_tx: glib::Sender
 
         let api_info = nvim.get_api_info().await.unwrap();
         let api_info = NeovimApiInfo::new(api_info).unwrap();
-        dbg!(api_info);
+        dbg!(&api_info);
 
         nvim.ui_attach(
             200,
@@ -191,35 +298,60 @@ pub async fn run(mut rx: UnboundedReceiver<GtkToNvimEvent>, gtk_tx: glib::Sender
         )
         .await
         .unwrap();
-    }
+
+        nvim.exec_lua(
+            subscription::SUBSCRIPTIONS_LUA,
+            vec![api_info.channel.into()],
+        )
+        .await
+        .unwrap();
+
+        let runtime_path = runtime_path();
+        nvim.exec_lua(
+            r#"
+                local path, chan = ...
+                vim.opt.runtimepath:prepend(path)
+                vim.cmd.runtime({ "plugin/vimdicator.lua", bang = true })
+                require("vimdicator")._attach(chan)
+                vim.cmd.runtime({ "ginit.vim", "ginit.lua", bang = true })
+            "#,
+            vec![runtime_path.as_str().into(), api_info.channel.into()],
+        )
+        .await
+        .unwrap();
+
+        for cmd in &options.post_cmds {
+            if let Err(err) = nvim.command(cmd).await {
+                error!("Post command '{cmd}' failed: {err}");
+            }
+        }
+
+        api_info
+    };
 
     tokio::spawn(async move {
         while let Some(event) = rx.recv().await {
-            match event {
-                GtkToNvimEvent::Input(input) => {
-                    nvim.input(&input).await.unwrap();
-                }
-                GtkToNvimEvent::InputMouse {
-                    button,
-                    action,
-                    modifier,
-                    grid,
-                    pos,
-                } => {
-                    let grid = grid.map(|g| g as i64).unwrap_or(-1);
-                    let (col, row) = pos.map(|(c, r)| (c as i64, r as i64)).unwrap_or((-1, -1));
-
-                    nvim.input_mouse(button.as_str(), action.as_str(), &modifier, grid, row, col)
-                        .await
-                        .unwrap();
-                }
-                GtkToNvimEvent::Resized { width, height } => {
-                    nvim.ui_try_resize(width as i64, height as i64)
-                        .await
-                        .unwrap();
-                }
-                GtkToNvimEvent::ExecLua(code) => {
-                    nvim.exec_lua(&code, vec![]).await.unwrap();
+            // Everything queued up meanwhile, e.g. during key repeat or a paste
+            let mut batch = vec![event];
+            while let Ok(event) = rx.try_recv() {
+                batch.push(event);
+            }
+
+            for event in coalesce_input(batch) {
+                crate::crash::log_rpc(format!("-> {event:?}"));
+
+                let request = handle_event(&nvim, &api_info, event);
+                tokio::pin!(request);
+
+                // Keep waiting, but let the GUI tell the user nvim is stuck
+                if tokio::time::timeout(rpc_timeout, &mut request)
+                    .await
+                    .is_err()
+                {
+                    error!("nvim did not answer within {rpc_timeout:?}");
+                    gtk_tx.send(NvimEvent::Unresponsive(true)).unwrap();
+                    request.await;
+                    gtk_tx.send(NvimEvent::Unresponsive(false)).unwrap();
                 }
             }
         }
@@ -227,3 +359,197 @@ pub async fn run(mut rx: UnboundedReceiver<GtkToNvimEvent>, gtk_tx: glib::Sender
 
     join.await.unwrap();
 }
+
+/// Longest key sequence sent in one `nvim_input` call
+const MAX_INPUT_BATCH: usize = 4096;
+
+/// Merge consecutive `Input` events into one, keeping the order of all events.
+/// Events hold complete keys, so joining them never splits a `<...>` key notation.
+fn coalesce_input(events: Vec<GtkToNvimEvent>) -> Vec<GtkToNvimEvent> {
+    let mut coalesced = Vec::with_capacity(events.len());
+
+    for event in events {
+        match (coalesced.last_mut(), event) {
+            (Some(GtkToNvimEvent::Input(keys)), GtkToNvimEvent::Input(next))
+                if keys.len() + next.len() <= MAX_INPUT_BATCH =>
+            {
+                keys.push_str(&next);
+            }
+            (_, event) => coalesced.push(event),
+        }
+    }
+
+    coalesced
+}
+
+async fn handle_event(nvim: &Neovim, api_info: &NeovimApiInfo, event: GtkToNvimEvent) {
+    let _span = match &event {
+        // Typed calls have their own spans
+        GtkToNvimEvent::Call(..) => None,
+        GtkToNvimEvent::Input(_) => crate::profiling::span("rpc input"),
+        GtkToNvimEvent::InputMouse { .. } => crate::profiling::span("rpc input_mouse"),
+        _ => crate::profiling::span("rpc request"),
+    };
+
+    // Errors from nvim, like E37 on a modified buffer or a timed out request, are logged.
+    // Panicking here would stop all further input.
+    match event {
+        GtkToNvimEvent::Input(input) => {
+            if let Err(err) = nvim.input(&input).await {
+                error!("Input {input:?} failed: {err}");
+            }
+        }
+        GtkToNvimEvent::InputMouse {
+            button,
+            action,
+            modifier,
+            grid,
+            pos,
+        } => {
+            let grid = grid.map(|g| g as i64).unwrap_or(-1);
+            let (col, row) = pos.map(|(c, r)| (c as i64, r as i64)).unwrap_or((-1, -1));
+
+            let res = nvim
+                .input_mouse(button.as_str(), action.as_str(), &modifier, grid, row, col)
+                .await;
+            if let Err(err) = res {
+                error!("Mouse input failed: {err}");
+            }
+        }
+        GtkToNvimEvent::ExecLua(code) => {
+            if let Err(err) = nvim.exec_lua(&code, vec![]).await {
+                error!("Lua from the GUI failed: {err}");
+            }
+        }
+        GtkToNvimEvent::Command(command) => {
+            if let Err(err) = nvim.command(&command).await {
+                error!("Command '{command}' failed: {err}");
+            }
+        }
+        GtkToNvimEvent::OpenFile { path, line } => {
+            let res = nvim
+                .exec_lua(
+                    r#"
+                    local path, line = ...
+                    vim.cmd.edit(vim.fn.fnameescape(path))
+                    if line > 0 then
+                        vim.api.nvim_win_set_cursor(0, { line, 0 })
+                    end
+                "#,
+                    vec![path.clone().into(), line.unwrap_or(0).into()],
+                )
+                .await;
+            if let Err(err) = res {
+                error!("Opening {path} failed: {err}");
+            }
+        }
+        GtkToNvimEvent::ChangeDirectory(path) => {
+            let res = nvim
+                .exec_lua(
+                    "vim.cmd.cd(vim.fn.fnameescape(...))",
+                    vec![path.clone().into()],
+                )
+                .await;
+            if let Err(err) = res {
+                error!("Changing the directory to {path} failed: {err}");
+            }
+        }
+        GtkToNvimEvent::InsertPaths(paths) => {
+            let res = nvim
+                .exec_lua(
+                    r#"
+                    local relative = vim.tbl_map(function(path)
+                        return vim.fn.fnamemodify(path, ":.")
+                    end, ...)
+                    vim.api.nvim_put({ table.concat(relative, " ") }, "c", true, true)
+                "#,
+                    vec![nvim_rs::Value::Array(
+                        paths.into_iter().map(nvim_rs::Value::from).collect(),
+                    )],
+                )
+                .await;
+            if let Err(err) = res {
+                error!("Inserting the dropped paths failed: {err}");
+            }
+        }
+        GtkToNvimEvent::OpenTerminal { external } => {
+            let argv = external.map_or(nvim_rs::Value::Nil, |argv| {
+                nvim_rs::Value::Array(argv.into_iter().map(nvim_rs::Value::from).collect())
+            });
+
+            let res = nvim
+                .exec_lua("require('vimdicator').open_terminal(...)", vec![argv])
+                .await;
+            if let Err(err) = res {
+                error!("Opening a terminal failed: {err}");
+            }
+        }
+        GtkToNvimEvent::Focus(focused) if api_info.ui_set_focus => {
+            match nvim.call("nvim_ui_set_focus", vec![focused.into()]).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => error!("nvim_ui_set_focus failed: {err}"),
+                Err(err) => error!("nvim_ui_set_focus failed: {err}"),
+            }
+        }
+        GtkToNvimEvent::Focus(focused) => {
+            let event = if focused { "FocusGained" } else { "FocusLost" };
+            if let Err(err) = nvim
+                .command(&format!("doautocmd <nomodeline> {event}"))
+                .await
+            {
+                error!("{event} autocmds failed: {err}");
+            }
+        }
+        GtkToNvimEvent::Call(call, reply) => {
+            // The caller may have stopped waiting
+            let _ = reply.send(call.run(nvim).await);
+        }
+        GtkToNvimEvent::Rescue(done) => {
+            // Best effort, the GUI is already going down
+            let _ = nvim.command("silent! wall").await;
+            let _ = nvim.command("silent! preserve").await;
+            let _ = nvim.ui_detach().await;
+            let _ = done.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(events: &[GtkToNvimEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                GtkToNvimEvent::Input(keys) => keys.clone(),
+                GtkToNvimEvent::Command(command) => format!(":{command}"),
+                event => format!("{event:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_coalesce_input() {
+        let events = vec![
+            GtkToNvimEvent::Input("i".to_string()),
+            GtkToNvimEvent::Input("<C-w>".to_string()),
+            GtkToNvimEvent::Input("a".to_string()),
+            GtkToNvimEvent::Command("w".to_string()),
+            GtkToNvimEvent::Input("<Esc>".to_string()),
+            GtkToNvimEvent::Input("u".to_string()),
+        ];
+
+        assert_eq!(keys(&coalesce_input(events)), ["i<C-w>a", ":w", "<Esc>u"]);
+    }
+
+    #[test]
+    fn test_coalesce_input_limit() {
+        let events = vec![
+            GtkToNvimEvent::Input("a".repeat(MAX_INPUT_BATCH)),
+            GtkToNvimEvent::Input("b".to_string()),
+        ];
+
+        assert_eq!(coalesce_input(events).len(), 2);
+    }
+}
@@ -0,0 +1,84 @@
+/// Editing mode, grouped from the mode names of `mode_info_set`/`mode_change`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Replace,
+    Visual,
+    Select,
+    /// Operator pending
+    Operator,
+    Command,
+}
+
+impl Mode {
+    pub const ALL: [Self; 7] = [
+        Self::Normal,
+        Self::Insert,
+        Self::Replace,
+        Self::Visual,
+        Self::Select,
+        Self::Operator,
+        Self::Command,
+    ];
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            name if name.starts_with("cmdline") => Self::Command,
+            "insert" => Self::Insert,
+            "replace" => Self::Replace,
+            "visual" => Self::Visual,
+            "visual_select" => Self::Select,
+            "operator" => Self::Operator,
+            _ => Self::Normal,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Insert => "insert",
+            Self::Replace => "replace",
+            Self::Visual => "visual",
+            Self::Select => "select",
+            Self::Operator => "operator",
+            Self::Command => "command",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Replace => "REPLACE",
+            Self::Visual => "VISUAL",
+            Self::Select => "SELECT",
+            Self::Operator => "PENDING",
+            Self::Command => "COMMAND",
+        }
+    }
+
+    /// CSS class set on the window while in this mode
+    pub fn css_class(self) -> String {
+        format!("mode-{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Mode::from_name("normal"), Mode::Normal);
+        assert_eq!(Mode::from_name("insert"), Mode::Insert);
+        assert_eq!(Mode::from_name("cmdline_normal"), Mode::Command);
+        assert_eq!(Mode::from_name("cmdline_insert"), Mode::Command);
+        assert_eq!(Mode::from_name("visual_select"), Mode::Select);
+        assert_eq!(Mode::from_name("more"), Mode::Normal);
+
+        assert_eq!(Mode::Insert.css_class(), "mode-insert");
+        assert_eq!(Mode::Operator.label(), "PENDING");
+    }
+}
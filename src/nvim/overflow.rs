@@ -0,0 +1,85 @@
+use nvim_rs::Value;
+
+use super::bridge::FromValue;
+
+/// Text area of a window without 'wrap' and which of its rows continue off screen,
+/// as returned by `require("vimdicator").overflow()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverflowWindow {
+    /// Grid position of the text area, right of the number and sign columns
+    pub row: usize,
+    pub col: usize,
+    pub width: usize,
+    /// One entry per screen row from `row`
+    pub rows: Vec<RowOverflow>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowOverflow {
+    /// Text is scrolled off the left edge, see 'leftcol'
+    pub left: bool,
+    /// Text continues past the right edge
+    pub right: bool,
+}
+
+impl FromValue for RowOverflow {
+    fn from_value(value: Value) -> Option<Self> {
+        let flags = value.as_u64()?;
+        Some(Self {
+            left: flags & 1 != 0,
+            right: flags & 2 != 0,
+        })
+    }
+}
+
+impl FromValue for OverflowWindow {
+    fn from_value(value: Value) -> Option<Self> {
+        let Value::Array(fields) = value else {
+            return None;
+        };
+        let mut fields = fields.into_iter();
+
+        Some(Self {
+            row: fields.next()?.as_u64()? as usize,
+            col: fields.next()?.as_u64()? as usize,
+            width: fields.next()?.as_u64()? as usize,
+            rows: Vec::from_value(fields.next()?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value = Value::from(vec![
+            Value::from(1),
+            Value::from(4),
+            Value::from(76),
+            Value::from(vec![Value::from(0), Value::from(2), Value::from(3)]),
+        ]);
+
+        assert_eq!(
+            OverflowWindow::from_value(value),
+            Some(OverflowWindow {
+                row: 1,
+                col: 4,
+                width: 76,
+                rows: vec![
+                    RowOverflow::default(),
+                    RowOverflow {
+                        left: false,
+                        right: true,
+                    },
+                    RowOverflow {
+                        left: true,
+                        right: true,
+                    },
+                ],
+            })
+        );
+        assert_eq!(OverflowWindow::from_value(Value::from(1)), None);
+    }
+}
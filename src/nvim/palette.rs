@@ -0,0 +1,96 @@
+use super::event::{Color, Colors, Style};
+
+/// Which colors of the highlight definitions are used for rendering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Rgb,
+    /// Use `ctermfg`/`ctermbg` resolved through the xterm 256-color palette
+    Cterm,
+}
+
+impl ColorMode {
+    pub fn resolve_style(self, style: &Style) -> Style {
+        match self {
+            Self::Rgb => style.clone(),
+            Self::Cterm => Style {
+                colors: style.cterm_colors.clone(),
+                ..style.clone()
+            },
+        }
+    }
+
+    /// Cterm default colors are often unknown (-1), keep the current ones then
+    pub fn resolve_default_colors(self, current: &Colors, rgb: &Colors, cterm: &Colors) -> Colors {
+        match self {
+            Self::Rgb => rgb.clone(),
+            Self::Cterm => Colors {
+                foreground: cterm.foreground.or(current.foreground),
+                background: cterm.background.or(current.background),
+                special: cterm.special.or(current.special),
+            },
+        }
+    }
+}
+
+/// Standard 16 ANSI colors, as used by xterm
+const ANSI: [u32; 16] = [
+    0x000000, 0xcd0000, 0x00cd00, 0xcdcd00, 0x0000ee, 0xcd00cd, 0x00cdcd, 0xe5e5e5, 0x7f7f7f,
+    0xff0000, 0x00ff00, 0xffff00, 0x5c5cff, 0xff00ff, 0x00ffff, 0xffffff,
+];
+
+fn rgb(r: u32, g: u32, b: u32) -> Color {
+    Color {
+        r: r as f32 / 255.0,
+        g: g as f32 / 255.0,
+        b: b as f32 / 255.0,
+    }
+}
+
+/// Color of a 256-color palette index, `None` for out of range indices (e.g. -1 "default")
+pub fn xterm_256(index: i64) -> Option<Color> {
+    let index = u32::try_from(index).ok()?;
+
+    let color = match index {
+        0..=15 => {
+            let packed = ANSI[index as usize];
+            rgb(packed >> 16, (packed >> 8) & 0xff, packed & 0xff)
+        }
+        16..=231 => {
+            let index = index - 16;
+            let level = |n: u32| if n == 0 { 0 } else { 55 + n * 40 };
+            rgb(level(index / 36), level((index / 6) % 6), level(index % 6))
+        }
+        232..=255 => {
+            let level = 8 + (index - 232) * 10;
+            rgb(level, level, level)
+        }
+        _ => return None,
+    };
+
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xterm_256() {
+        assert_eq!(xterm_256(-1), None);
+        assert_eq!(xterm_256(256), None);
+
+        assert_eq!(xterm_256(0), Some(rgb(0, 0, 0)));
+        assert_eq!(xterm_256(9), Some(rgb(0xff, 0, 0)));
+
+        // Color cube
+        assert_eq!(xterm_256(16), Some(rgb(0, 0, 0)));
+        assert_eq!(xterm_256(196), Some(rgb(0xff, 0, 0)));
+        assert_eq!(xterm_256(231), Some(rgb(0xff, 0xff, 0xff)));
+        assert_eq!(xterm_256(67), Some(rgb(0x5f, 0x87, 0xaf)));
+
+        // Grayscale ramp
+        assert_eq!(xterm_256(232), Some(rgb(8, 8, 8)));
+        assert_eq!(xterm_256(255), Some(rgb(0xee, 0xee, 0xee)));
+    }
+}
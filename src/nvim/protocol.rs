@@ -0,0 +1,119 @@
+//! Parts of the UI protocol the GUI has no handling for, so coverage gaps show up in the
+//! protocol diagnostics window instead of only in the log
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// Longest payload kept for an event, nvim can send whole screens in one
+const MAX_PAYLOAD_CHARS: usize = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coverage {
+    /// Not even parsed, usually an event newer than vimdicator
+    Unknown,
+    /// Parsed, but nothing is done with it
+    Ignored,
+}
+
+impl Coverage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Ignored => "ignored",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncoveredEvent {
+    pub coverage: Coverage,
+    pub count: u64,
+    /// Debug representation of the first occurrence, cut at `MAX_PAYLOAD_CHARS`
+    pub first_payload: String,
+}
+
+static EVENTS: Mutex<BTreeMap<String, UncoveredEvent>> = Mutex::new(BTreeMap::new());
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// `--strict-ui`: unknown events are a bug to be fixed, not something to skip over
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Count an event the GUI doesn't handle, panics on unknown ones in strict mode
+pub fn record(name: &str, coverage: Coverage, payload: &dyn fmt::Debug) {
+    record_with(name, coverage, payload, STRICT.load(Ordering::Relaxed));
+}
+
+fn record_with(name: &str, coverage: Coverage, payload: &dyn fmt::Debug, strict: bool) {
+    let first = {
+        let mut events = EVENTS.lock().unwrap();
+        let event = events
+            .entry(name.to_string())
+            .or_insert_with(|| UncoveredEvent {
+                coverage,
+                count: 0,
+                first_payload: truncate(format!("{payload:?}")),
+            });
+        event.count += 1;
+        event.count == 1
+    };
+
+    if coverage == Coverage::Unknown && strict {
+        panic!("Unknown UI event {name} with {payload:?} (--strict-ui)");
+    }
+
+    if first {
+        log::debug!("{} UI event {name}: {payload:?}", coverage.name());
+    }
+}
+
+/// Everything recorded so far, by event name
+pub fn uncovered_events() -> BTreeMap<String, UncoveredEvent> {
+    EVENTS.lock().unwrap().clone()
+}
+
+fn truncate(mut payload: String) -> String {
+    if let Some((end, _)) = payload.char_indices().nth(MAX_PAYLOAD_CHARS) {
+        payload.truncate(end);
+        payload.push('…');
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        record("test_event", Coverage::Ignored, &[1, 2]);
+        record("test_event", Coverage::Ignored, &[3]);
+
+        let event = &uncovered_events()["test_event"];
+        assert_eq!(event.coverage, Coverage::Ignored);
+        assert_eq!(event.count, 2);
+        assert_eq!(event.first_payload, "[1, 2]");
+    }
+
+    #[test]
+    #[should_panic(expected = "--strict-ui")]
+    fn test_strict() {
+        record_with("test_unknown_event", Coverage::Unknown, &(), true);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short".to_string()), "short");
+
+        let long = truncate("é".repeat(MAX_PAYLOAD_CHARS + 10));
+        assert_eq!(long.chars().count(), MAX_PAYLOAD_CHARS + 1);
+        assert!(long.ends_with('…'));
+    }
+}
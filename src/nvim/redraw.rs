@@ -0,0 +1,412 @@
+//! Grids, highlights, the popup menu and tabs, kept up to date with the redraw events where
+//! they arrive. The GTK main loop only gets what changed at each `flush`, with the default
+//! grid as a snapshot sharing its unchanged lines with the one kept here.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use super::{
+    ext_line_grid::{GridError, Line},
+    protocol::{self, Coverage},
+    Color, ColorMode, Colors, ExtLineGrid, ExtLineGridMap, ExtPopupMenu, ExtPopupMenuState,
+    ExtTabline, GuiOption, RedrawEvent, Style, Viewport,
+};
+
+/// What changed up to a `flush`, for the GUI to show
+#[derive(Debug, Default)]
+pub struct Flush {
+    /// The default grid with the highlights it's drawn with, `None` until nvim sized it
+    pub grid: Option<ExtLineGrid>,
+    /// Lines scrolled off the top of the default grid since the previous flush
+    pub scrolled_off: Vec<Line>,
+    /// The popup menu was shown, replaced or hidden
+    pub popup_changed: bool,
+    /// Only the selected popup menu item changed
+    pub popup_selection_changed: bool,
+    /// The popup menu as of the flush, `None` while hidden
+    pub popup: Option<ExtPopupMenuState>,
+    /// The tabs, if they changed
+    pub tabline: Option<ExtTabline>,
+    /// The cursor moved or its line was redrawn, completes pending input latency samples
+    pub cursor_changed: bool,
+    pub mode: Option<String>,
+    /// Highlights or the UI groups using them changed, the GUI drawn parts follow them
+    pub highlights_changed: bool,
+    /// Highlight id of the `CursorLine` group
+    pub cursor_line_highlight: Option<u64>,
+    /// Backgrounds of `PmenuSbar` and `PmenuThumb`, `None` where the group doesn't set one
+    pub scrollbar_colors: (Option<Color>, Option<Color>),
+    /// `blend` of the `Pmenu` group, `None` where it doesn't set one
+    pub popup_blend: Option<u8>,
+    /// Last `win_viewport` of the current window
+    pub viewport: Option<Viewport>,
+    pub guifont: Option<String>,
+    pub title: Option<String>,
+    pub icon: Option<String>,
+    pub options: Vec<GuiOption>,
+}
+
+#[derive(Debug)]
+pub struct RedrawState {
+    color_mode: ColorMode,
+    default_colors: Colors,
+    /// Shared with the flushed grids, only copied when a highlight is (re)defined while in use
+    style: Arc<HashMap<u64, Style>>,
+    highlight_groups: HashMap<String, u64>,
+    grids: ExtLineGridMap,
+    popup_menu: ExtPopupMenu,
+    tabline: ExtTabline,
+    /// Changes since the last flush
+    pending: Flush,
+    /// An event didn't fit its grid, the grids may be off until nvim draws them again
+    inconsistent: bool,
+    last_refresh: Option<Instant>,
+}
+
+/// nvim is asked to draw the grids again at most this often, a client that keeps getting
+/// events it can't place shouldn't flood nvim with redraws
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+impl RedrawState {
+    pub fn new(color_mode: ColorMode) -> Self {
+        Self {
+            color_mode,
+            default_colors: Colors {
+                foreground: Some(Color {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                }),
+                background: Some(Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                }),
+                special: Some(Color {
+                    r: 1.0,
+                    g: 0.0,
+                    b: 0.0,
+                }),
+            },
+            style: Default::default(),
+            highlight_groups: HashMap::new(),
+            grids: ExtLineGridMap::new(),
+            popup_menu: ExtPopupMenu::new(),
+            tabline: ExtTabline::new(),
+            pending: Flush::default(),
+            inconsistent: false,
+            last_refresh: None,
+        }
+    }
+
+    /// Whether nvim has to be asked to draw the grids again, see [`Self::default_size`]
+    pub fn take_refresh(&mut self) -> bool {
+        self.take_refresh_at(Instant::now())
+    }
+
+    fn take_refresh_at(&mut self, now: Instant) -> bool {
+        let recent = self
+            .last_refresh
+            .is_some_and(|last| now.duration_since(last) < REFRESH_INTERVAL);
+        if !self.inconsistent || recent {
+            return false;
+        }
+        self.inconsistent = false;
+        self.last_refresh = Some(now);
+        true
+    }
+
+    /// Columns and rows of the default grid, as nvim last sized it
+    pub fn default_size(&self) -> Option<(usize, usize)> {
+        self.grids
+            .get_default()
+            .map(|grid| (grid.columns(), grid.rows()))
+    }
+
+    /// Events racing a resize can address cells the grid no longer has, what fits was applied
+    fn check(&mut self, res: Result<(), GridError>) {
+        if let Err(err) = res {
+            warn!("{err}, asking nvim to redraw");
+            self.inconsistent = true;
+        }
+    }
+
+    /// Apply a batch of redraw events, what changed if the batch ends with a `flush`
+    pub fn apply(&mut self, events: Vec<RedrawEvent>) -> Option<Flush> {
+        let mut flushed = false;
+
+        for event in events {
+            match event {
+                RedrawEvent::GridResize {
+                    grid,
+                    width,
+                    height,
+                } => {
+                    self.grids
+                        .grid_resize(&grid, width as usize, height as usize);
+                }
+
+                RedrawEvent::GridClear { grid } => {
+                    let res = self.grids.grid_clear(&grid);
+                    self.check(res);
+                }
+
+                RedrawEvent::GridDestroy { grid } => {
+                    self.grids.grid_destroy(&grid);
+                }
+
+                RedrawEvent::GridScroll {
+                    grid,
+                    top,
+                    bottom,
+                    left,
+                    right,
+                    rows,
+                    columns,
+                } => {
+                    let res = self
+                        .grids
+                        .grid_scroll(&grid, top, bottom, left, right, rows, columns);
+                    self.check(res);
+                }
+
+                RedrawEvent::GridLine {
+                    grid,
+                    row,
+                    column_start,
+                    cells,
+                } => {
+                    let res =
+                        self.grids
+                            .grid_line(&grid, row as usize, column_start as usize, &cells);
+                    self.check(res);
+
+                    if self
+                        .grids
+                        .get(&grid)
+                        .is_some_and(|grid| grid.cursor_position().row == row as usize)
+                    {
+                        self.pending.cursor_changed = true;
+                    }
+                }
+
+                RedrawEvent::GridCursorGoto { grid, row, column } => {
+                    let res = self
+                        .grids
+                        .grid_cursor_goto(&grid, row as usize, column as usize);
+                    self.check(res);
+                    self.pending.cursor_changed = true;
+                }
+
+                RedrawEvent::Flush => {
+                    flushed = true;
+                }
+
+                RedrawEvent::PopupmenuShow {
+                    items,
+                    selected,
+                    row,
+                    col,
+                    grid,
+                } => {
+                    self.popup_menu.show(
+                        items,
+                        selected.map(|s| s as usize),
+                        row as usize,
+                        col as usize,
+                        grid,
+                    );
+                    self.pending.popup_changed = true;
+                }
+
+                RedrawEvent::TablineUpdate { current_tab, tabs } => {
+                    self.tabline.update(current_tab, tabs);
+                    self.pending.tabline = Some(self.tabline.clone());
+                }
+
+                RedrawEvent::PopupmenuSelect { selected } => {
+                    self.popup_menu.select(selected.map(|s| s as usize));
+                    self.pending.popup_selection_changed = true;
+                }
+
+                RedrawEvent::PopupmenuHide => {
+                    self.popup_menu.hide();
+                    self.pending.popup_changed = true;
+                }
+
+                RedrawEvent::DefaultColorsSet {
+                    colors,
+                    cterm_colors,
+                } => {
+                    self.default_colors = self.color_mode.resolve_default_colors(
+                        &self.default_colors,
+                        &colors,
+                        &cterm_colors,
+                    );
+                    self.pending.highlights_changed = true;
+                }
+
+                RedrawEvent::HighlightAttributesDefine { id, style } => {
+                    // Copy-on-write, the table is only cloned if a flushed grid still uses it
+                    *Arc::make_mut(&mut self.style).entry(id).or_default() =
+                        self.color_mode.resolve_style(&style);
+                    self.pending.highlights_changed = true;
+                }
+
+                RedrawEvent::HighlightGroupSet { name, id } => {
+                    self.highlight_groups.insert(name, id);
+                    self.pending.highlights_changed = true;
+                }
+
+                RedrawEvent::SetTitle(title) => {
+                    self.pending.title = Some(title);
+                }
+
+                RedrawEvent::SetIcon(icon) => {
+                    self.pending.icon = Some(icon);
+                }
+
+                RedrawEvent::ModeChange(mode) => {
+                    self.pending.mode = Some(mode);
+                }
+
+                RedrawEvent::WindowViewport { viewport } => {
+                    self.pending.viewport = Some(viewport);
+                }
+
+                RedrawEvent::OptionSet(GuiOption::GuiFont(guifont)) => {
+                    if !guifont.is_empty() {
+                        self.pending.guifont = Some(guifont);
+                    }
+                }
+
+                RedrawEvent::OptionSet(option) => {
+                    self.pending.options.push(option);
+                }
+
+                RedrawEvent::Unknown(name, args) => {
+                    protocol::record(&name, Coverage::Unknown, &args);
+                }
+
+                event => {
+                    protocol::record(event.name(), Coverage::Ignored, &event);
+                }
+            }
+        }
+
+        flushed.then(|| self.flush())
+    }
+
+    fn flush(&mut self) -> Flush {
+        let _span = crate::profiling::span("flush");
+        let mut flush = std::mem::take(&mut self.pending);
+
+        flush.grid = self.grids.get_default().map(|grid| {
+            let mut grid = grid.clone();
+            grid.style = Arc::clone(&self.style);
+            grid.default_colors = self.default_colors.clone();
+            grid
+        });
+        flush.scrolled_off = self.grids.take_scrolled_off(&1);
+        flush.popup = self.popup_menu.get().cloned();
+
+        if flush.highlights_changed {
+            flush.cursor_line_highlight = self.highlight_groups.get("CursorLine").copied();
+            flush.scrollbar_colors = (
+                self.group_background("PmenuSbar"),
+                self.group_background("PmenuThumb"),
+            );
+            flush.popup_blend = self
+                .highlight_groups
+                .get("Pmenu")
+                .and_then(|id| self.style.get(id))
+                .map(|style| style.blend)
+                .filter(|&blend| blend > 0);
+        }
+
+        flush
+    }
+
+    /// Background of a builtin UI group, `None` if the group doesn't set one
+    fn group_background(&self, group: &str) -> Option<Color> {
+        let style = self.style.get(self.highlight_groups.get(group)?)?;
+        style
+            .colors
+            .background
+            .is_some()
+            .then(|| style.background(&self.default_colors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nvim::{cell_text::CellText, event::GridLineCell};
+
+    fn line(row: u64, text: &str) -> RedrawEvent {
+        RedrawEvent::GridLine {
+            grid: 1,
+            row,
+            column_start: 0,
+            cells: vec![GridLineCell {
+                text: CellText::new(text),
+                highlight_id: Some(0),
+                repeat: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_flush() {
+        let mut state = RedrawState::new(ColorMode::Rgb);
+        let resize = RedrawEvent::GridResize {
+            grid: 1,
+            width: 2,
+            height: 2,
+        };
+        assert!(state.apply(vec![resize, line(0, "a")]).is_none());
+
+        let first = state.apply(vec![RedrawEvent::Flush]).unwrap();
+        let first = first.grid.unwrap();
+        assert_eq!(first.buffer()[0].text(), "a");
+
+        let second = state.apply(vec![line(1, "b"), RedrawEvent::Flush]).unwrap();
+        let second = second.grid.unwrap();
+        assert_eq!(first.buffer()[1].text(), "");
+        assert_eq!(second.buffer()[1].text(), "b");
+        // Unchanged lines aren't copied
+        assert_eq!(first.buffer()[0].id(), second.buffer()[0].id());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut state = RedrawState::new(ColorMode::Rgb);
+        let resize = |height| RedrawEvent::GridResize {
+            grid: 1,
+            width: 2,
+            height,
+        };
+        state.apply(vec![resize(3), line(2, "a"), RedrawEvent::Flush]);
+        assert!(!state.take_refresh());
+
+        // A line sent before nvim saw the grid shrink
+        let flush = state
+            .apply(vec![resize(2), line(2, "b"), RedrawEvent::Flush])
+            .unwrap();
+        assert_eq!(flush.grid.unwrap().rows(), 2);
+        let now = Instant::now();
+        assert!(state.take_refresh_at(now));
+        assert_eq!(state.default_size(), Some((2, 2)));
+        assert!(!state.take_refresh_at(now));
+
+        // Asked again only once the interval passed
+        state.apply(vec![line(5, "c")]);
+        assert!(!state.take_refresh_at(now));
+        assert!(state.take_refresh_at(now + REFRESH_INTERVAL));
+    }
+}
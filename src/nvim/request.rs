@@ -0,0 +1,246 @@
+use nvim_rs::Value;
+use tokio::sync::oneshot;
+
+/// Where the answer to a [`GuiRequest`] goes, nvim waits for it
+pub type Responder = oneshot::Sender<Result<Value, Value>>;
+
+/// Panels a plugin can open and close
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Quickfix,
+    Registers,
+    Search,
+}
+
+/// Files of a name pattern the dialog can be limited to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileFilter {
+    pub name: String,
+    /// Globs like `*.lua`
+    pub patterns: Vec<String>,
+}
+
+/// `opts` of `require("vimdicator.ui").open_file_dialog` and `save_file_dialog`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileDialogOptions {
+    pub save: bool,
+    pub title: Option<String>,
+    /// Shown first, nvim's working directory unless given
+    pub folder: Option<String>,
+    /// Suggested name of the saved file
+    pub name: Option<String>,
+    /// Pick several files, answered with a list
+    pub multiple: bool,
+    pub filters: Vec<FileFilter>,
+}
+
+impl FileDialogOptions {
+    /// Lua's empty `{}` arrives as an empty array, unknown keys are skipped
+    fn parse(save: bool, opts: Value) -> Option<Self> {
+        let mut this = Self {
+            save,
+            ..Default::default()
+        };
+        let opts = match opts {
+            Value::Map(opts) => opts,
+            Value::Array(opts) if opts.is_empty() => Vec::new(),
+            Value::Nil => Vec::new(),
+            _ => return None,
+        };
+
+        let string = |value: &Value| value.as_str().map(str::to_string);
+        for (key, value) in &opts {
+            match key.as_str()? {
+                "title" => this.title = Some(string(value)?),
+                "folder" => this.folder = Some(string(value)?),
+                "name" => this.name = Some(string(value)?),
+                "multiple" => this.multiple = value.as_bool()?,
+                "filters" => {
+                    for filter in value.as_array()? {
+                        let mut parsed = FileFilter::default();
+                        for (key, value) in filter.as_map()? {
+                            match key.as_str()? {
+                                "name" => parsed.name = string(value)?,
+                                "patterns" => {
+                                    parsed.patterns =
+                                        value.as_array()?.iter().filter_map(string).collect()
+                                }
+                                _ => {}
+                            }
+                        }
+                        this.filters.push(parsed);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(this)
+    }
+}
+
+/// Requests sent by `require("vimdicator.api")` via `rpcrequest(chan, "Gui", ...)`, for
+/// plugins to query and drive the GUI
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuiRequest {
+    /// Size of the grid in cells and of the window in pixels
+    WindowSize,
+    /// Native file dialog, answered with the picked path, or a list of them when `multiple`.
+    /// nil when dismissed.
+    FileDialog(FileDialogOptions),
+    /// Native color dialog, answered with the chosen color as `#rrggbb`, nil when dismissed.
+    /// `eyedropper` picks from the screen instead where the desktop portal can.
+    ColorPicker {
+        initial: Option<String>,
+        eyedropper: bool,
+    },
+    /// Open or close a panel, `None` toggles it
+    Panel {
+        panel: Panel,
+        open: Option<bool>,
+    },
+    /// In-window notification, hidden after `timeout` seconds or never with 0
+    Toast {
+        message: String,
+        timeout: u32,
+    },
+    SetClipboard(String),
+    Unknown(String),
+}
+
+impl GuiRequest {
+    pub fn parse(args: Vec<Value>) -> Option<Self> {
+        let mut args = args.into_iter();
+
+        let name = args.next()?;
+        let name = name.as_str()?.to_string();
+
+        let this = match name.as_str() {
+            "WindowSize" => Self::WindowSize,
+            "FileDialog" => {
+                let save = match args.next()?.as_str()? {
+                    "open" => false,
+                    "save" => true,
+                    _ => return None,
+                };
+                Self::FileDialog(FileDialogOptions::parse(
+                    save,
+                    args.next().unwrap_or(Value::Nil),
+                )?)
+            }
+            "ColorPicker" => Self::ColorPicker {
+                initial: args
+                    .next()
+                    .and_then(|initial| initial.as_str().map(str::to_string)),
+                eyedropper: args
+                    .next()
+                    .and_then(|eyedropper| eyedropper.as_bool())
+                    .unwrap_or(false),
+            },
+            "Panel" => Self::Panel {
+                panel: match args.next()?.as_str()? {
+                    "quickfix" => Panel::Quickfix,
+                    "registers" => Panel::Registers,
+                    "search" => Panel::Search,
+                    _ => return None,
+                },
+                // nil toggles
+                open: args.next().and_then(|open| open.as_bool()),
+            },
+            "Toast" => Self::Toast {
+                message: args.next()?.as_str()?.to_string(),
+                timeout: match args.next().and_then(|timeout| timeout.as_u64()) {
+                    Some(timeout) => u32::try_from(timeout).ok()?,
+                    None => 5,
+                },
+            },
+            "SetClipboard" => Self::SetClipboard(args.next()?.as_str()?.to_string()),
+            _ => Self::Unknown(name),
+        };
+
+        Some(this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            GuiRequest::parse(vec!["Panel".into(), "search".into(), true.into()]),
+            Some(GuiRequest::Panel {
+                panel: Panel::Search,
+                open: Some(true),
+            })
+        );
+        assert_eq!(
+            GuiRequest::parse(vec!["Panel".into(), "quickfix".into(), Value::Nil]),
+            Some(GuiRequest::Panel {
+                panel: Panel::Quickfix,
+                open: None,
+            })
+        );
+        assert_eq!(
+            GuiRequest::parse(vec!["Toast".into(), "Hi".into()]),
+            Some(GuiRequest::Toast {
+                message: "Hi".to_string(),
+                timeout: 5,
+            })
+        );
+        assert_eq!(
+            GuiRequest::parse(vec!["Panel".into(), "sidebar".into()]),
+            None
+        );
+        assert_eq!(
+            GuiRequest::parse(vec!["ColorPicker".into(), Value::Nil, true.into()]),
+            Some(GuiRequest::ColorPicker {
+                initial: None,
+                eyedropper: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_file_dialog() {
+        let filter = Value::Map(vec![
+            ("name".into(), "Lua".into()),
+            ("patterns".into(), Value::Array(vec!["*.lua".into()])),
+        ]);
+        let opts = Value::Map(vec![
+            ("title".into(), "Pick".into()),
+            ("multiple".into(), true.into()),
+            ("filters".into(), Value::Array(vec![filter])),
+            ("unknown".into(), 1.into()),
+        ]);
+        assert_eq!(
+            GuiRequest::parse(vec!["FileDialog".into(), "open".into(), opts]),
+            Some(GuiRequest::FileDialog(FileDialogOptions {
+                title: Some("Pick".to_string()),
+                multiple: true,
+                filters: vec![FileFilter {
+                    name: "Lua".to_string(),
+                    patterns: vec!["*.lua".to_string()],
+                }],
+                ..Default::default()
+            }))
+        );
+
+        // `{}` from Lua
+        assert_eq!(
+            GuiRequest::parse(vec![
+                "FileDialog".into(),
+                "save".into(),
+                Value::Array(Vec::new())
+            ]),
+            Some(GuiRequest::FileDialog(FileDialogOptions {
+                save: true,
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            GuiRequest::parse(vec!["FileDialog".into(), "save".into(), 1.into()]),
+            None
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use nvim_rs::Value;
+
+/// Registers the autocmds feeding [`Subscription`] notifications, expects the channel id as argument
+pub const SUBSCRIPTIONS_LUA: &str = include_str!("subscriptions.lua");
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferInfo {
+    pub name: String,
+    pub modified: bool,
+    pub filetype: String,
+    /// 'fileencoding' and 'fileformat'
+    pub encoding: String,
+    pub fileformat: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Subscription {
+    Buffer(BufferInfo),
+    FileOpened(String),
+    Oldfiles(Vec<String>),
+    /// Cursor position in the current window, 1-based
+    Cursor {
+        line: u64,
+        column: u64,
+        line_count: u64,
+    },
+    /// Value of the `background` option
+    Background(String),
+    Unknown(String, Vec<Value>),
+}
+
+impl Subscription {
+    pub fn parse(args: Vec<Value>) -> Option<Self> {
+        let mut args = args.into_iter();
+
+        let name = args.next()?;
+        let name = name.as_str()?.to_string();
+
+        let this = match name.as_str() {
+            "buffer" => Self::Buffer(BufferInfo {
+                name: args.next()?.as_str()?.to_string(),
+                modified: args.next()?.as_bool()?,
+                filetype: args.next()?.as_str()?.to_string(),
+                encoding: args.next()?.as_str()?.to_string(),
+                fileformat: args.next()?.as_str()?.to_string(),
+            }),
+            "cursor" => Self::Cursor {
+                line: args.next()?.as_u64()?,
+                column: args.next()?.as_u64()?,
+                line_count: args.next()?.as_u64()?,
+            },
+            "file_opened" => Self::FileOpened(args.next()?.as_str()?.to_string()),
+            "oldfiles" => Self::Oldfiles(
+                args.next()?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|path| path.as_str())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            "background" => Self::Background(args.next()?.as_str()?.to_string()),
+            _ => Self::Unknown(name, args.collect()),
+        };
+
+        Some(this)
+    }
+}
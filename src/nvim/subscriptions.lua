@@ -0,0 +1,62 @@
+local chan = ...
+
+local group = vim.api.nvim_create_augroup("Vimdicator", { clear = true })
+
+local function notify_buffer()
+  vim.rpcnotify(
+    chan,
+    "subscription",
+    "buffer",
+    vim.api.nvim_buf_get_name(0),
+    vim.bo.modified,
+    vim.bo.filetype,
+    vim.bo.fileencoding,
+    vim.bo.fileformat
+  )
+end
+
+vim.api.nvim_create_autocmd({ "BufEnter", "BufModifiedSet", "BufWritePost", "FileType" }, {
+  group = group,
+  callback = notify_buffer,
+})
+
+vim.api.nvim_create_autocmd("OptionSet", {
+  group = group,
+  pattern = { "fileencoding", "fileformat" },
+  callback = notify_buffer,
+})
+
+notify_buffer()
+
+local function notify_cursor()
+  local line, col = unpack(vim.api.nvim_win_get_cursor(0))
+  vim.rpcnotify(chan, "subscription", "cursor", line, col + 1, vim.api.nvim_buf_line_count(0))
+end
+
+vim.api.nvim_create_autocmd({ "CursorMoved", "CursorMovedI", "BufEnter" }, {
+  group = group,
+  callback = notify_cursor,
+})
+
+vim.api.nvim_create_autocmd("BufReadPost", {
+  group = group,
+  callback = function(args)
+    if vim.bo[args.buf].buftype == "" then
+      vim.rpcnotify(chan, "subscription", "file_opened", vim.api.nvim_buf_get_name(args.buf))
+    end
+  end,
+})
+
+local function notify_background()
+  vim.rpcnotify(chan, "subscription", "background", vim.o.background)
+end
+
+vim.api.nvim_create_autocmd("OptionSet", {
+  group = group,
+  pattern = "background",
+  callback = notify_background,
+})
+
+notify_background()
+
+vim.rpcnotify(chan, "subscription", "oldfiles", vim.list_slice(vim.v.oldfiles, 1, 20))
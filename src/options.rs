@@ -0,0 +1,299 @@
+use gtk::gio;
+
+/// Command line options consumed by vimdicator itself, everything else is left for GTK
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Extra argv for the embedded nvim
+    pub nvim_args: Vec<String>,
+    /// Ex commands executed once the UI is attached
+    pub post_cmds: Vec<String>,
+    /// Don't hand over to an already running instance
+    pub new_instance: bool,
+    /// Render with the 256-color terminal palette instead of RGB colors
+    pub cterm_colors: bool,
+    /// GSK renderer override, exported as `GSK_RENDERER`
+    pub renderer: Option<String>,
+    /// Open the file arguments in the running instance
+    pub remote: bool,
+    /// Keys sent to the running instance
+    pub remote_send: Option<String>,
+    /// Address of a `nvim --listen` server to attach to instead of embedding nvim
+    pub server: Option<String>,
+    /// How often, and for how many seconds, connecting to `server` is retried
+    pub connect_attempts: Option<u32>,
+    pub connect_timeout: Option<u64>,
+    /// File spans are written to, see `profiling`
+    pub trace: Option<String>,
+    /// Panic on UI events the GUI doesn't know, see `nvim::protocol`
+    pub strict_ui: bool,
+    /// Application id replacing the `app-id` setting, see `application::app_id`
+    pub app_id: Option<String>,
+    pub window: WindowOptions,
+}
+
+/// Initial state of the window, for this launch only
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WindowOptions {
+    pub maximized: bool,
+    pub fullscreen: bool,
+    /// Size of the grid in (columns, rows), the window is sized to fit it
+    pub geometry: Option<(u32, u32)>,
+}
+
+/// Renderers accepted by `--renderer`
+const RENDERERS: &[&str] = &["gl", "ngl", "vulkan", "cairo"];
+
+impl Options {
+    /// Talk to a running instance instead of starting the GUI
+    pub fn is_remote(&self) -> bool {
+        self.remote || self.remote_send.is_some()
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<(Self, Vec<String>), String> {
+        let mut options = Self::default();
+        let mut rest = Vec::new();
+
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Everything after it is left for GTK as is, which stops at it too
+            if arg == "--" {
+                rest.push(arg);
+                rest.extend(args);
+                break;
+            }
+
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("Missing value for {name}"))
+            };
+            let no_value = || match inline_value {
+                Some(_) => Err(format!("{name} doesn't take a value")),
+                None => Ok(true),
+            };
+
+            match name.as_str() {
+                "--clean" => {
+                    no_value()?;
+                    options.nvim_args.push("--clean".to_string());
+                }
+                "--noplugin" => {
+                    no_value()?;
+                    options.nvim_args.push("--noplugin".to_string());
+                }
+                "--nvim-arg" => options.nvim_args.push(value()?),
+                "--cmd" => {
+                    let cmd = value()?;
+                    options.nvim_args.extend(["--cmd".to_string(), cmd]);
+                }
+                "--post-cmd" => options.post_cmds.push(value()?),
+                "--new-instance" => options.new_instance = no_value()?,
+                "--cterm-colors" => options.cterm_colors = no_value()?,
+                "--strict-ui" => options.strict_ui = no_value()?,
+                "--maximized" => options.window.maximized = no_value()?,
+                "--fullscreen" => options.window.fullscreen = no_value()?,
+                "--geometry" => options.window.geometry = Some(parse_geometry(&value()?)?),
+                "--remote" => options.remote = no_value()?,
+                "--remote-send" => options.remote_send = Some(value()?),
+                "--server" => options.server = Some(value()?),
+                "--trace" => options.trace = Some(value()?),
+                "--app-id" | "--class" => {
+                    let id = value()?;
+                    if !gio::Application::id_is_valid(&id) {
+                        return Err(format!(
+                            "Invalid application id {id}, expected e.g. org.example.Project"
+                        ));
+                    }
+                    options.app_id = Some(id);
+                }
+                "--connect-attempts" => {
+                    options.connect_attempts = Some(parse_number(&name, &value()?)?);
+                }
+                "--connect-timeout" => {
+                    options.connect_timeout = Some(parse_number(&name, &value()?)?);
+                }
+                "--renderer" => {
+                    let renderer = value()?;
+                    if !RENDERERS.contains(&renderer.as_str()) {
+                        return Err(format!(
+                            "Unknown renderer {renderer}, expected one of {}",
+                            RENDERERS.join(", ")
+                        ));
+                    }
+                    options.renderer = Some(renderer);
+                }
+                _ => rest.push(arg),
+            }
+        }
+
+        Ok((options, rest))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number for {name}: {value}"))
+}
+
+/// `COLSxROWS`, e.g. `120x40`
+fn parse_geometry(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid geometry {value}, expected COLSxROWS e.g. 120x40");
+
+    let (cols, rows) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let cols: u32 = cols.trim().parse().map_err(|_| invalid())?;
+    let rows: u32 = rows.trim().parse().map_err(|_| invalid())?;
+    if cols == 0 || rows == 0 {
+        return Err(invalid());
+    }
+
+    Ok((cols, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(Options, Vec<String>), String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let (options, rest) = parse(&[
+            "vimdicator",
+            "--clean",
+            "--cmd",
+            "set nocompatible",
+            "--post-cmd=colorscheme default",
+            "--nvim-arg",
+            "-n",
+            "--gapplication-service",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            options.nvim_args,
+            ["--clean", "--cmd", "set nocompatible", "-n"]
+        );
+        assert_eq!(options.post_cmds, ["colorscheme default"]);
+        assert_eq!(rest, ["vimdicator", "--gapplication-service"]);
+    }
+
+    #[test]
+    fn test_parse_strict_ui() {
+        let (options, rest) = parse(&["vimdicator", "--strict-ui", "a.txt"]).unwrap();
+        assert!(options.strict_ui);
+        assert_eq!(rest, ["vimdicator", "a.txt"]);
+
+        let (options, _) = parse(&["vimdicator"]).unwrap();
+        assert!(!options.strict_ui);
+    }
+
+    #[test]
+    fn test_parse_missing_value() {
+        assert!(parse(&["vimdicator", "--post-cmd"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_flag_with_value() {
+        assert!(parse(&["vimdicator", "--clean=no"]).is_err());
+        assert!(parse(&["vimdicator", "--remote=0"]).is_err());
+        assert!(parse(&["vimdicator", "--maximized=true"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_terminator() {
+        let (options, rest) = parse(&[
+            "vimdicator",
+            "--clean",
+            "--",
+            "--remote",
+            "--geometry=wide",
+            "a.txt",
+        ])
+        .unwrap();
+
+        assert_eq!(options.nvim_args, ["--clean"]);
+        assert!(!options.is_remote());
+        assert_eq!(options.window.geometry, None);
+        assert_eq!(
+            rest,
+            ["vimdicator", "--", "--remote", "--geometry=wide", "a.txt"]
+        );
+    }
+
+    #[test]
+    fn test_parse_renderer() {
+        let (options, _) = parse(&["vimdicator", "--renderer=ngl"]).unwrap();
+        assert_eq!(options.renderer.as_deref(), Some("ngl"));
+
+        assert!(parse(&["vimdicator", "--renderer", "software"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_remote() {
+        let (options, rest) =
+            parse(&["vimdicator", "--remote", "a.txt", "--remote-send=:w<CR>"]).unwrap();
+
+        assert!(options.is_remote());
+        assert_eq!(options.remote_send.as_deref(), Some(":w<CR>"));
+        assert_eq!(rest, ["vimdicator", "a.txt"]);
+    }
+
+    #[test]
+    fn test_parse_server() {
+        let (options, _) = parse(&[
+            "vimdicator",
+            "--server",
+            "127.0.0.1:6666",
+            "--connect-attempts=3",
+        ])
+        .unwrap();
+
+        assert_eq!(options.server.as_deref(), Some("127.0.0.1:6666"));
+        assert_eq!(options.connect_attempts, Some(3));
+        assert_eq!(options.connect_timeout, None);
+
+        assert!(parse(&["vimdicator", "--connect-timeout", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_app_id() {
+        let (options, rest) = parse(&["vimdicator", "--class=org.example.Work", "a.txt"]).unwrap();
+        assert_eq!(options.app_id.as_deref(), Some("org.example.Work"));
+        assert_eq!(rest, ["vimdicator", "a.txt"]);
+
+        let (options, _) = parse(&["vimdicator", "--app-id", "org.example.Home"]).unwrap();
+        assert_eq!(options.app_id.as_deref(), Some("org.example.Home"));
+
+        assert!(parse(&["vimdicator", "--app-id", "work"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_window() {
+        let (options, rest) =
+            parse(&["vimdicator", "--maximized", "--geometry", "120x40", "a.txt"]).unwrap();
+        assert_eq!(
+            options.window,
+            WindowOptions {
+                maximized: true,
+                fullscreen: false,
+                geometry: Some((120, 40)),
+            }
+        );
+        assert_eq!(rest, ["vimdicator", "a.txt"]);
+
+        assert!(parse(&["vimdicator", "--geometry=120"]).is_err());
+        assert!(parse(&["vimdicator", "--geometry=0x40"]).is_err());
+    }
+}
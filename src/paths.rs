@@ -0,0 +1,69 @@
+//! Install locations.
+//!
+//! Meson bakes the Linux prefix into `config.rs`. Windows and macOS builds are usually
+//! relocated (installer, app bundle), so there the data is looked up next to the executable.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{LOCALEDIR, PKGDATADIR};
+
+/// `share` directory relative to the executable of a relocated install
+fn relocated_share_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let bin_dir = exe.parent()?;
+
+    // vimdicator.app/Contents/MacOS/vimdicator -> vimdicator.app/Contents/Resources/share
+    let share = if cfg!(target_os = "macos") && bin_dir.ends_with("Contents/MacOS") {
+        bin_dir.parent()?.join("Resources/share")
+    } else {
+        // <prefix>/bin/vimdicator -> <prefix>/share
+        bin_dir.parent()?.join("share")
+    };
+
+    share.is_dir().then_some(share)
+}
+
+fn installed_or_relocated(configured: &str, relative: &str) -> PathBuf {
+    let configured = Path::new(configured);
+
+    if cfg!(any(windows, target_os = "macos")) || !configured.exists() {
+        if let Some(dir) = relocated_share_dir().map(|share| share.join(relative)) {
+            if dir.exists() {
+                return dir;
+            }
+        }
+    }
+
+    configured.to_path_buf()
+}
+
+/// Directory holding the gresource bundle and the nvim runtime files
+pub fn pkgdatadir() -> PathBuf {
+    installed_or_relocated(PKGDATADIR, "vimdicator")
+}
+
+/// Path of a gresource bundle to load instead of the installed one, e.g. from a portable bundle
+pub const RESOURCES_ENV: &str = "VIMDICATOR_RESOURCES";
+
+const RESOURCES_FILE: &str = "vimdicator.gresource";
+
+/// Where the gresource bundle may be, in the order to try: named by [`RESOURCES_ENV`], installed,
+/// and next to the executable, which is where meson's build tree (`_build/src`) has it
+pub fn resources_candidates() -> Vec<PathBuf> {
+    let mut candidates: Vec<_> = std::env::var_os(RESOURCES_ENV)
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    candidates.push(pkgdatadir().join(RESOURCES_FILE));
+    if let Some(bin_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push(bin_dir.join(RESOURCES_FILE));
+    }
+    candidates
+}
+
+pub fn localedir() -> PathBuf {
+    installed_or_relocated(LOCALEDIR, "locale")
+}
@@ -0,0 +1,139 @@
+//! xdg-desktop-portal calls GTK has no API for. Portal methods answer right away with a
+//! request object, the result follows as its `Response` signal once the user is done.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use gtk::{gdk, gio, glib, prelude::*};
+
+const BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+/// `Response` code of a request the user cancelled
+const RESPONSE_CANCELLED: u32 = 1;
+
+thread_local! {
+    static NEXT_TOKEN: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Call `method` of `interface` with `options` as its last argument, and `done` with the
+/// results of the request, `None` when the user cancelled
+fn request(
+    connection: &gio::DBusConnection,
+    interface: &str,
+    method: &str,
+    mut parameters: Vec<glib::Variant>,
+    options: glib::VariantDict,
+    done: impl FnOnce(Result<Option<glib::VariantDict>, glib::Error>) + 'static,
+) {
+    let Some(sender) = connection.unique_name() else {
+        done(Err(glib::Error::new(
+            gio::IOErrorEnum::NotConnected,
+            "Not on the session bus",
+        )));
+        return;
+    };
+
+    // The request's path follows from the token, subscribing before the call can't miss
+    // an answer that comes quickly
+    let token = NEXT_TOKEN.with(|next| next.replace(next.get() + 1));
+    let token = format!("vimdicator{token}");
+    let handle = format!(
+        "{OBJECT_PATH}/request/{}/{token}",
+        sender.trim_start_matches(':').replace('.', "_")
+    );
+    options.insert("handle_token", token);
+
+    let done = Rc::new(RefCell::new(Some(done)));
+    let subscription = Rc::new(Cell::new(None));
+    let id = connection.signal_subscribe(
+        Some(BUS_NAME),
+        Some(REQUEST_INTERFACE),
+        Some("Response"),
+        Some(&handle),
+        None,
+        gio::DBusSignalFlags::NONE,
+        glib::clone!(@strong done, @strong subscription => move |connection, _, _, _, _, params| {
+            if let Some(id) = subscription.take() {
+                connection.signal_unsubscribe(id);
+            }
+            let Some(done) = done.take() else {
+                return;
+            };
+            let Some((response, results)) = params.get::<(u32, glib::VariantDict)>() else {
+                done(Err(glib::Error::new(
+                    gio::IOErrorEnum::InvalidData,
+                    "Unexpected portal response",
+                )));
+                return;
+            };
+            match response {
+                0 => done(Ok(Some(results))),
+                RESPONSE_CANCELLED => done(Ok(None)),
+                _ => done(Err(glib::Error::new(
+                    gio::IOErrorEnum::Failed,
+                    "The portal request failed",
+                ))),
+            }
+        }),
+    );
+    subscription.set(Some(id));
+
+    parameters.push(options.end());
+    connection.call(
+        Some(BUS_NAME),
+        OBJECT_PATH,
+        interface,
+        method,
+        Some(&glib::Variant::tuple_from_iter(parameters)),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+        glib::clone!(@weak connection => move |res| {
+            // Without a portal no answer comes
+            if let Err(err) = res {
+                if let Some(id) = subscription.take() {
+                    connection.signal_unsubscribe(id);
+                }
+                if let Some(done) = done.take() {
+                    done(Err(err));
+                }
+            }
+        }),
+    );
+}
+
+/// Let the user pick a color anywhere on screen with the Screenshot portal's eyedropper.
+/// `None` when the user cancelled, an error without a portal that can.
+pub fn pick_color(
+    connection: &gio::DBusConnection,
+    done: impl FnOnce(Result<Option<gdk::RGBA>, glib::Error>) + 'static,
+) {
+    // No parent window, exporting a Wayland or X11 handle needs GDK backend specific API
+    request(
+        connection,
+        "org.freedesktop.portal.Screenshot",
+        "PickColor",
+        vec!["".to_variant()],
+        glib::VariantDict::new(None),
+        |res| {
+            done(res.and_then(|results| {
+                let Some(results) = results else {
+                    return Ok(None);
+                };
+                let (r, g, b) = results
+                    .lookup::<(f64, f64, f64)>("color")
+                    .ok()
+                    .flatten()
+                    .ok_or_else(|| {
+                        glib::Error::new(gio::IOErrorEnum::InvalidData, "No color picked")
+                    })?;
+                Ok(Some(gdk::RGBA::new(r as f32, g as f32, b as f32, 1.0)))
+            }))
+        },
+    );
+}
@@ -0,0 +1,117 @@
+//! Spans recorded in the Chrome trace event format, enabled with `--trace <file>`.
+//! Open the file in `chrome://tracing` or https://ui.perfetto.dev
+
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{self, BufWriter, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Instant,
+};
+
+static TRACE: OnceLock<Mutex<Trace>> = OnceLock::new();
+
+struct Trace {
+    epoch: Instant,
+    out: BufWriter<File>,
+}
+
+/// Start writing spans to `path`
+pub fn init(path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    // Viewers accept the array without its closing bracket, so a crash still leaves a usable file
+    out.write_all(b"[\n")?;
+
+    let trace = Trace {
+        epoch: Instant::now(),
+        out,
+    };
+    if TRACE.set(Mutex::new(trace)).is_err() {
+        log::warn!("Tracing was already started");
+    }
+    Ok(())
+}
+
+/// Write out buffered spans, called before exiting
+pub fn flush() {
+    if let Some(trace) = TRACE.get() {
+        let mut trace = trace.lock().unwrap();
+        if let Err(err) = trace.out.flush() {
+            log::error!("Failed to write the trace: {err}");
+        }
+    }
+}
+
+/// Time spent until the returned guard is dropped, nothing is recorded without `--trace`
+#[must_use]
+pub fn span(name: &'static str) -> Option<Span> {
+    TRACE.get()?;
+    Some(Span {
+        name,
+        start: Instant::now(),
+    })
+}
+
+pub struct Span {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(trace) = TRACE.get() else {
+            return;
+        };
+        let mut trace = trace.lock().unwrap();
+
+        let ts = self.start.duration_since(trace.epoch).as_micros();
+        let dur = self.start.elapsed().as_micros();
+        let event = complete_event(self.name, thread_id(), ts, dur);
+
+        if let Err(err) = writeln!(trace.out, "{event},") {
+            log::error!("Failed to write the trace: {err}");
+        }
+    }
+}
+
+/// Small stable ids, the GTK main thread is usually 1
+fn thread_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: Cell<u64> = const { Cell::new(0) };
+    }
+
+    ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+/// A "complete" event, timestamps in microseconds
+fn complete_event(name: &str, tid: u64, ts: u128, dur: u128) -> String {
+    let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+    format!(r#"{{"name":"{name}","ph":"X","pid":1,"tid":{tid},"ts":{ts},"dur":{dur}}}"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_event() {
+        assert_eq!(
+            complete_event("grid \"snapshot\"", 1, 1500, 250),
+            r#"{"name":"grid \"snapshot\"","ph":"X","pid":1,"tid":1,"ts":1500,"dur":250}"#
+        );
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(span("unused").is_none());
+    }
+}
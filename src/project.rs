@@ -0,0 +1,184 @@
+//! Per-project overrides of GUI settings from a `.vimdicator.toml` in (or above) nvim's
+//! working directory. Only a small subset of TOML is understood: `key = value` lines with
+//! booleans, numbers and strings, and only the keys of [`ProjectConfig`].
+
+/// Name of the file, looked up from nvim's working directory upwards
+pub const FILE_NAME: &str = ".vimdicator.toml";
+
+/// Font sizes a project may set, in points
+const FONT_SIZES: std::ops::RangeInclusive<f64> = 4.0..=96.0;
+
+/// Settings a project may override, `None` keeps the user's own
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectConfig {
+    /// Replaces the size of `guifont`, in points
+    pub font_size: Option<f64>,
+    /// Open or close the nvim-tree file browser
+    pub sidebar: Option<bool>,
+    /// Show the native gutter, see the `native-gutter` setting
+    pub native_gutter: Option<bool>,
+    /// Window title, replaces the `title-format` setting
+    pub title_format: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+/// A basic (`"..."`) or literal (`'...'`) string, and what follows it
+fn parse_string(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.char_indices();
+    let quote = chars.next()?.1;
+    let mut string = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some((string, &text[i + c.len_utf8()..])),
+            '\\' if quote == '"' => match chars.next()?.1 {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                c @ ('"' | '\\') => string.push(c),
+                _ => return None,
+            },
+            '\n' => return None,
+            c => string.push(c),
+        }
+    }
+    None
+}
+
+/// The value at the start of `text`, which may only be followed by a comment
+fn parse_value(text: &str) -> Option<Value> {
+    let (value, rest) = if text.starts_with(['"', '\'']) {
+        let (string, rest) = parse_string(text)?;
+        (Value::String(string), rest)
+    } else {
+        let end = text.find(['#', ' ', '\t']).unwrap_or(text.len());
+        let (word, rest) = text.split_at(end);
+        let value = match word {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            word => Value::Number(word.replace('_', "").parse().ok()?),
+        };
+        (value, rest)
+    };
+
+    let rest = rest.trim_start();
+    (rest.is_empty() || rest.starts_with('#')).then_some(value)
+}
+
+impl ProjectConfig {
+    /// Apply one `key = value`, `false` when the key isn't overridable or the value is invalid
+    fn set(&mut self, key: &str, value: Value) -> bool {
+        match (key, value) {
+            ("font-size", Value::Number(size)) if FONT_SIZES.contains(&size) => {
+                self.font_size = Some(size)
+            }
+            ("sidebar", Value::Bool(sidebar)) => self.sidebar = Some(sidebar),
+            ("native-gutter", Value::Bool(gutter)) => self.native_gutter = Some(gutter),
+            ("title-format", Value::String(format)) => self.title_format = Some(format),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// The overrides in `text`, and the keys (or `line N` where there is no key) that were
+/// ignored because they aren't overridable or their value is invalid
+pub fn parse(text: &str) -> (ProjectConfig, Vec<String>) {
+    let mut config = ProjectConfig::default();
+    let mut ignored = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            // Tables and anything else this subset doesn't know
+            ignored.push(format!("line {}", i + 1));
+            continue;
+        };
+        // Both `font-size` and `font_size` are fine, the settings use dashes
+        let key = key.trim().replace('_', "-");
+        let valid = !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && parse_value(value.trim()).is_some_and(|value| config.set(&key, value));
+        if !valid {
+            ignored.push(if key.is_empty() {
+                format!("line {}", i + 1)
+            } else {
+                key
+            });
+        }
+    }
+
+    (config, ignored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let (config, ignored) = parse(
+            r#"
+# Bigger font for demos
+font-size = 14.5
+sidebar = true # comment
+native_gutter = false
+title-format = "{file} – \"demo\""
+"#,
+        );
+        assert_eq!(
+            config,
+            ProjectConfig {
+                font_size: Some(14.5),
+                sidebar: Some(true),
+                native_gutter: Some(false),
+                title_format: Some("{file} – \"demo\"".to_string()),
+            }
+        );
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ignored() {
+        let (config, ignored) = parse(
+            "[window]\nfont-size = 400\nsidebar = \"yes\"\nshell = 'rm -rf'\ntitle-format = 'a' b\n= 1",
+        );
+        assert_eq!(config, ProjectConfig::default());
+        assert_eq!(
+            ignored,
+            [
+                "line 1",
+                "font-size",
+                "sidebar",
+                "shell",
+                "title-format",
+                "line 6"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("1_000"), Some(Value::Number(1000.0)));
+        assert_eq!(
+            parse_value("'C:\\path'"),
+            Some(Value::String("C:\\path".to_string()))
+        );
+        assert_eq!(
+            parse_value("\"tab\\there\""),
+            Some(Value::String("tab\there".to_string()))
+        );
+        assert_eq!(parse_value("\"open"), None);
+        assert_eq!(parse_value("\"bad \\x\""), None);
+        assert_eq!(parse_value("yes"), None);
+    }
+}
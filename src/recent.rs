@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use gtk::{gio, glib, prelude::*};
+
+/// Entries shown per section of the open button menu
+const MENU_ENTRIES: usize = 10;
+
+/// Register a file opened in nvim with the desktop wide recent files list
+pub fn add(path: &str) {
+    if path.is_empty() {
+        return;
+    }
+
+    let uri = gio::File::for_path(path).uri();
+    gtk::RecentManager::default().add_item(&uri);
+}
+
+fn display_path(path: &str) -> String {
+    shorten_home(path, &glib::home_dir())
+}
+
+/// `path` with `home` replaced by `~`, only where it is a whole parent directory
+fn shorten_home(path: &str, home: &Path) -> String {
+    if home.as_os_str().is_empty() {
+        return path.to_string();
+    }
+    match Path::new(path).strip_prefix(home) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~/{}", rest.display()),
+        Err(_) => path.to_string(),
+    }
+}
+
+fn menu_item(path: &str) -> gio::MenuItem {
+    let item = gio::MenuItem::new(Some(&display_path(path)), None);
+    item.set_action_and_target_value(Some("app.open-recent"), Some(&path.to_variant()));
+    item
+}
+
+/// Menu with the files recently opened by vimdicator followed by nvim's `v:oldfiles`
+pub fn build_menu(oldfiles: &[String]) -> gio::Menu {
+    let menu = gio::Menu::new();
+
+    let app_name = glib::application_name();
+
+    let recent = gio::Menu::new();
+    let mut items = gtk::RecentManager::default().items();
+    items.sort_by_key(|info| std::cmp::Reverse(info.modified().to_unix()));
+
+    for path in items
+        .iter()
+        .filter(|info| info.is_local() && info.exists())
+        .filter(|info| {
+            app_name
+                .as_ref()
+                .is_none_or(|name| info.has_application(name))
+        })
+        .filter_map(|info| gio::File::for_uri(&info.uri()).path())
+        .take(MENU_ENTRIES)
+    {
+        recent.append_item(&menu_item(&path.to_string_lossy()));
+    }
+    menu.append_section(Some(&gettextrs::gettext("Recent")), &recent);
+
+    let old = gio::Menu::new();
+    for path in oldfiles.iter().take(MENU_ENTRIES) {
+        old.append_item(&menu_item(path));
+    }
+    menu.append_section(Some(&gettextrs::gettext("Oldfiles")), &old);
+
+    menu
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorten_home() {
+        let home = Path::new("/home/al");
+        assert_eq!(shorten_home("/home/al/x.txt", home), "~/x.txt");
+        assert_eq!(shorten_home("/home/al", home), "~");
+        assert_eq!(shorten_home("/home/alice/x.txt", home), "/home/alice/x.txt");
+        assert_eq!(shorten_home("/tmp/x.txt", Path::new("")), "/tmp/x.txt");
+    }
+}
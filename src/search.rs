@@ -0,0 +1,424 @@
+//! Project wide text search for the search panel, through ripgrep when it is installed
+
+use std::{
+    io::{BufRead, BufReader},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use gtk::glib;
+
+/// Matches reported before the search stops, a pattern like `e` would list the whole project
+pub const MAX_MATCHES: usize = 10_000;
+
+/// Matches sent to the main loop at once
+const BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub pattern: String,
+    pub regex: bool,
+    pub whole_word: bool,
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// Relative to the search root
+    pub path: String,
+    /// 1-based
+    pub line: u64,
+    pub text: String,
+    /// Byte ranges of the matches in `text`
+    pub spans: Vec<Range<usize>>,
+}
+
+#[derive(Debug)]
+pub enum SearchEvent {
+    Matches(Vec<SearchMatch>),
+    /// ripgrep isn't installed, regexes are searched as plain text
+    Fallback,
+    Error(String),
+    /// `truncated` when `MAX_MATCHES` was reached
+    Done {
+        truncated: bool,
+    },
+}
+
+/// Running search, stopped on `cancel` or drop
+#[derive(Debug, Default)]
+pub struct Search {
+    cancelled: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl Search {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            child.kill().ok();
+        }
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Search the files under `root` on a thread, `on_event` is called on the main loop until `Done`
+pub fn start(
+    root: PathBuf,
+    options: SearchOptions,
+    on_event: impl Fn(SearchEvent) + 'static,
+) -> Search {
+    let search = Search::default();
+
+    let (tx, rx) = glib::MainContext::channel(glib::Priority::default());
+    let cancelled = search.cancelled.clone();
+    rx.attach(None, move |event| {
+        // A cancelled search has been replaced, or its results cleared
+        if cancelled.load(Ordering::Relaxed) {
+            return glib::Continue(false);
+        }
+        let done = matches!(event, SearchEvent::Done { .. });
+        on_event(event);
+        glib::Continue(!done)
+    });
+
+    let cancelled = search.cancelled.clone();
+    let child = search.child.clone();
+    std::thread::spawn(move || {
+        let mut sink = Sink {
+            tx,
+            cancelled,
+            batch: Vec::new(),
+            count: 0,
+        };
+
+        let res = match ripgrep(&root, &options) {
+            Ok(process) => {
+                let stdout = {
+                    let mut child = child.lock().unwrap();
+                    child.insert(process).stdout.take()
+                };
+                let res = stdout.map_or(Ok(()), |stdout| read_ripgrep(stdout, &mut sink));
+                if let Some(mut process) = child.lock().unwrap().take() {
+                    process.kill().ok();
+                    process.wait().ok();
+                }
+                res
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                sink.tx.send(SearchEvent::Fallback).ok();
+                search_plain(&root, Path::new(""), &options, &mut sink);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = res {
+            sink.tx.send(SearchEvent::Error(err.to_string())).ok();
+        }
+        sink.flush();
+        let truncated = sink.count >= MAX_MATCHES;
+        sink.tx.send(SearchEvent::Done { truncated }).ok();
+    });
+
+    search
+}
+
+/// Batches matches for the main loop and says when to stop
+struct Sink {
+    tx: glib::Sender<SearchEvent>,
+    cancelled: Arc<AtomicBool>,
+    batch: Vec<SearchMatch>,
+    count: usize,
+}
+
+impl Sink {
+    /// `false` once the search should stop
+    fn push(&mut self, m: SearchMatch) -> bool {
+        self.batch.push(m);
+        self.count += 1;
+        if self.batch.len() >= BATCH_SIZE {
+            self.flush();
+        }
+        !self.stopped()
+    }
+
+    fn stopped(&self) -> bool {
+        self.count >= MAX_MATCHES || self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn flush(&mut self) {
+        if !self.batch.is_empty() && !self.cancelled.load(Ordering::Relaxed) {
+            self.tx
+                .send(SearchEvent::Matches(std::mem::take(&mut self.batch)))
+                .ok();
+        }
+    }
+}
+
+/// Matches come back bold, which is how their spans in the line are found
+fn ripgrep_args(options: &SearchOptions) -> Vec<&str> {
+    let mut args = vec![
+        "--color=always",
+        "--colors=path:none",
+        "--colors=line:none",
+        "--colors=column:none",
+        "--colors=match:none",
+        "--colors=match:style:bold",
+        "--null",
+        "--line-number",
+        "--no-heading",
+        "--with-filename",
+    ];
+    if !options.regex {
+        args.push("--fixed-strings");
+    }
+    if options.whole_word {
+        args.push("--word-regexp");
+    }
+    args.push(if options.case_sensitive {
+        "--case-sensitive"
+    } else {
+        "--ignore-case"
+    });
+    args.extend(["--regexp", &options.pattern]);
+    args
+}
+
+fn ripgrep(root: &Path, options: &SearchOptions) -> std::io::Result<Child> {
+    Command::new("rg")
+        .args(ripgrep_args(options))
+        .arg("--")
+        .arg(".")
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+fn read_ripgrep(stdout: impl std::io::Read, sink: &mut Sink) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stdout);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let text = String::from_utf8_lossy(&line);
+        if let Some(m) = parse_ripgrep_line(text.trim_end_matches(['\n', '\r'])) {
+            if !sink.push(m) {
+                break;
+            }
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Text without the terminal escapes, and the byte ranges that were bold
+fn strip_escapes(colored: &str) -> (String, Vec<Range<usize>>) {
+    let mut plain = String::with_capacity(colored.len());
+    let mut spans = Vec::new();
+    let mut bold_from = None;
+
+    let mut rest = colored;
+    while let Some(start) = rest.find('\x1b') {
+        plain.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('m') else {
+            break;
+        };
+        let bold = rest
+            .get(2..end)
+            .unwrap_or("")
+            .split(';')
+            .any(|param| param == "1");
+        match (bold, bold_from) {
+            (true, None) => bold_from = Some(plain.len()),
+            (false, Some(from)) => {
+                if from < plain.len() {
+                    spans.push(from..plain.len());
+                }
+                bold_from = None;
+            }
+            _ => {}
+        }
+        rest = &rest[end + 1..];
+    }
+    plain.push_str(rest);
+    if let Some(from) = bold_from {
+        spans.push(from..plain.len());
+    }
+
+    (plain, spans)
+}
+
+/// `path\0line:text`, as printed for `ripgrep_args`
+fn parse_ripgrep_line(colored: &str) -> Option<SearchMatch> {
+    let (plain, spans) = strip_escapes(colored);
+
+    let (path, rest) = plain.split_once('\0')?;
+    let (line, text) = rest.split_once(':')?;
+    let offset = plain.len() - text.len();
+
+    Some(SearchMatch {
+        path: path.strip_prefix("./").unwrap_or(path).to_string(),
+        line: line.parse().ok()?,
+        text: text.to_string(),
+        spans: spans
+            .into_iter()
+            .filter(|span| span.start >= offset)
+            .map(|span| span.start - offset..span.end - offset)
+            .collect(),
+    })
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Fallback matching, the pattern is always plain text
+fn plain_spans(text: &str, options: &SearchOptions) -> Vec<Range<usize>> {
+    if options.pattern.is_empty() {
+        return Vec::new();
+    }
+
+    // Lowercasing can change byte lengths, only ASCII is folded to keep offsets valid
+    let (haystack, needle) = if options.case_sensitive {
+        (text.to_string(), options.pattern.clone())
+    } else {
+        (
+            text.to_ascii_lowercase(),
+            options.pattern.to_ascii_lowercase(),
+        )
+    };
+
+    haystack
+        .match_indices(&needle)
+        .map(|(start, _)| start..start + needle.len())
+        .filter(|span| {
+            !options.whole_word
+                || (!text[..span.start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(is_word_char)
+                    && !text[span.end..].chars().next().is_some_and(is_word_char))
+        })
+        .collect()
+}
+
+/// Walks `root`/`dir`, skipping hidden entries and files that aren't UTF-8 text
+fn search_plain(root: &Path, dir: &Path, options: &SearchOptions, sink: &mut Sink) {
+    let Ok(entries) = std::fs::read_dir(root.join(dir)) else {
+        return;
+    };
+
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if sink.stopped() {
+            return;
+        }
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = dir.join(entry.file_name());
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            search_plain(root, &path, options, sink);
+            continue;
+        }
+
+        let Ok(contents) = std::fs::read_to_string(root.join(&path)) else {
+            continue;
+        };
+        for (index, text) in contents.lines().enumerate() {
+            let spans = plain_spans(text, options);
+            if spans.is_empty() {
+                continue;
+            }
+
+            let m = SearchMatch {
+                path: path.to_string_lossy().into_owned(),
+                line: index as u64 + 1,
+                text: text.to_string(),
+                spans,
+            };
+            if !sink.push(m) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ripgrep_line() {
+        let line = "\x1b[0m./src/main.rs\x1b[0m\x00\x1b[0m12\x1b[0m:let \x1b[0m\x1b[1mfoo\x1b[0m = \x1b[0m\x1b[1mfoo\x1b[0m;";
+        assert_eq!(
+            parse_ripgrep_line(line),
+            Some(SearchMatch {
+                path: "src/main.rs".to_string(),
+                line: 12,
+                text: "let foo = foo;".to_string(),
+                spans: vec![4..7, 10..13],
+            })
+        );
+
+        // Colons in the text stay in the text
+        let m = parse_ripgrep_line("a.rs\x0030:x: \x1b[1my\x1b[0m").unwrap();
+        assert_eq!(m.text, "x: y");
+        assert_eq!(m.spans, vec![3..4]);
+
+        assert_eq!(parse_ripgrep_line("no separator"), None);
+    }
+
+    #[test]
+    fn test_plain_spans() {
+        let mut options = SearchOptions {
+            pattern: "foo".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            plain_spans("Foo foobar foo", &options),
+            vec![0..3, 4..7, 11..14]
+        );
+
+        options.whole_word = true;
+        assert_eq!(plain_spans("Foo foobar foo", &options), vec![0..3, 11..14]);
+
+        options.case_sensitive = true;
+        assert_eq!(plain_spans("Foo foobar foo", &options), vec![11..14]);
+
+        options.pattern.clear();
+        assert!(plain_spans("foo", &options).is_empty());
+    }
+
+    #[test]
+    fn test_ripgrep_args() {
+        let options = SearchOptions {
+            pattern: "-x".to_string(),
+            regex: true,
+            ..Default::default()
+        };
+        let args = ripgrep_args(&options);
+        assert!(!args.contains(&"--fixed-strings"));
+        assert!(args.contains(&"--ignore-case"));
+        assert!(args.ends_with(&["--regexp", "-x"]));
+    }
+}
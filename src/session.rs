@@ -0,0 +1,185 @@
+//! Snapshots of the workspace taken every few seconds, see the `session-autosave-interval`
+//! setting, so a crash or power loss doesn't lose it. A marker file exists while vimdicator
+//! runs, one left behind by a process that is gone means it did not exit cleanly and the
+//! last snapshot is offered to restore.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use gtk::glib;
+
+/// Prefix of the marker files, followed by the process id
+const MARKER_PREFIX: &str = "running-";
+
+/// Where the snapshots and markers are kept
+fn dir() -> PathBuf {
+    glib::user_data_dir().join("vimdicator")
+}
+
+fn state_path() -> PathBuf {
+    dir().join("session")
+}
+
+/// nvim's part of the snapshot, written with `:mksession`
+pub fn vim_session_path() -> PathBuf {
+    dir().join("session.vim")
+}
+
+/// The GUI's part of a snapshot
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionState {
+    /// Size of the window when it isn't maximized
+    pub width: i32,
+    pub height: i32,
+    pub maximized: bool,
+    /// Open panels
+    pub quickfix: bool,
+    pub registers: bool,
+    pub search: bool,
+    /// nvim wrote `vim_session_path`, which isn't the case when attached to a `--server`
+    pub vim_session: bool,
+    /// Seconds since the epoch
+    pub saved: u64,
+}
+
+impl SessionState {
+    fn serialize(&self) -> String {
+        format!(
+            "width={}\nheight={}\nmaximized={}\nquickfix={}\nregisters={}\nsearch={}\nvim-session={}\nsaved={}\n",
+            self.width,
+            self.height,
+            self.maximized,
+            self.quickfix,
+            self.registers,
+            self.search,
+            self.vim_session,
+            self.saved,
+        )
+    }
+
+    /// Unknown keys and malformed lines are skipped, a snapshot without a size is `None`
+    fn parse(text: &str) -> Option<Self> {
+        let mut state = Self::default();
+        for (key, value) in text.lines().filter_map(|line| line.split_once('=')) {
+            let flag = || value == "true";
+            match key {
+                "width" => state.width = value.parse().ok()?,
+                "height" => state.height = value.parse().ok()?,
+                "maximized" => state.maximized = flag(),
+                "quickfix" => state.quickfix = flag(),
+                "registers" => state.registers = flag(),
+                "search" => state.search = flag(),
+                "vim-session" => state.vim_session = flag(),
+                "saved" => state.saved = value.parse().ok()?,
+                _ => {}
+            }
+        }
+        (state.width > 0 && state.height > 0).then_some(state)
+    }
+
+    /// Replace the last snapshot, never leaving a half written one behind
+    pub fn save(&self) -> io::Result<()> {
+        std::fs::create_dir_all(dir())?;
+        let path = state_path();
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, self.serialize())?;
+        std::fs::rename(temp, path)
+    }
+
+    pub fn load() -> Option<Self> {
+        let text = std::fs::read_to_string(state_path()).ok()?;
+        Self::parse(&text)
+    }
+}
+
+/// Seconds since the epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Whether the process `pid` still runs. Only Linux has `/proc`, elsewhere markers of other
+/// processes are taken for running ones and crashes aren't detected.
+fn is_running(pid: &str) -> bool {
+    let proc = Path::new("/proc");
+    !proc.is_dir() || proc.join(pid).exists()
+}
+
+/// Exists while this process runs, see the module docs
+#[derive(Debug)]
+pub struct Marker {
+    path: PathBuf,
+}
+
+impl Marker {
+    /// Mark this process as running, along with whether an earlier one ended without removing
+    /// its marker. Markers of processes that are gone are removed.
+    pub fn create() -> io::Result<(Self, bool)> {
+        let dir = dir();
+        std::fs::create_dir_all(&dir)?;
+
+        let mut unclean = false;
+        for entry in std::fs::read_dir(&dir)?.flatten() {
+            let name = entry.file_name();
+            let Some(pid) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(MARKER_PREFIX))
+            else {
+                continue;
+            };
+            if !is_running(pid) {
+                unclean = true;
+                if let Err(err) = std::fs::remove_file(entry.path()) {
+                    log::warn!("Could not remove {}: {err}", entry.path().display());
+                }
+            }
+        }
+
+        let path = dir.join(format!("{MARKER_PREFIX}{}", std::process::id()));
+        std::fs::write(&path, "")?;
+        Ok((Self { path }, unclean))
+    }
+
+    /// Clean exit, the next start won't offer to restore
+    pub fn remove(self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            log::warn!("Could not remove {}: {err}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let state = SessionState {
+            width: 1200,
+            height: 800,
+            maximized: true,
+            quickfix: true,
+            registers: false,
+            search: true,
+            vim_session: true,
+            saved: 1_700_000_000,
+        };
+        assert_eq!(SessionState::parse(&state.serialize()), Some(state));
+
+        assert_eq!(
+            SessionState::parse("width=640\nheight=480\nunknown=1\ngarbage"),
+            Some(SessionState {
+                width: 640,
+                height: 480,
+                ..Default::default()
+            })
+        );
+        assert_eq!(SessionState::parse("width=640\nheight=big"), None);
+        assert_eq!(SessionState::parse(""), None);
+    }
+}
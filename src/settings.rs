@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use gtk::{gdk, gio, prelude::*};
+
+use crate::{nvim::Mode, widgets::ext_line_grid::cursor};
+
+pub const SCHEMA_ID: &str = "io.github.polymeilex.vimdicator";
+
+/// Keys holding text GSettings can't check, the others are ensured by their types and choices
+pub const FREE_FORM_KEYS: [&str; 4] = [
+    "mode-colors",
+    "cursor-shapes",
+    "cursor-size",
+    "cursor-color",
+];
+
+pub fn settings() -> gio::Settings {
+    #[cfg(feature = "embed-resources")]
+    if let Some(schema) = compiled_schema() {
+        return gio::Settings::new_full(&schema, gio::SettingsBackend::NONE, None);
+    }
+    gio::Settings::new(SCHEMA_ID)
+}
+
+/// The schema build.rs compiled with the `embed-resources` feature, for builds run without
+/// installing it. It's the one matching the binary, so it's preferred over an installed one.
+#[cfg(feature = "embed-resources")]
+fn compiled_schema() -> Option<gio::SettingsSchema> {
+    thread_local! {
+        static SCHEMA: Option<gio::SettingsSchema> = {
+            let dir = concat!(env!("OUT_DIR"), "/schemas");
+            let default = gio::SettingsSchemaSource::default();
+            gio::SettingsSchemaSource::from_directory(dir, default.as_ref(), false)
+                .map_err(|err| log::warn!("Could not load the settings schema in {dir}: {err}"))
+                .ok()
+                .and_then(|source| source.lookup(SCHEMA_ID, false))
+        };
+    }
+    SCHEMA.with(Clone::clone)
+}
+
+fn is_color(color: &str) -> bool {
+    gdk::RGBA::parse(color.trim()).is_ok()
+}
+
+/// Every key of `map` is a mode name and every value passes `valid`
+fn valid_mode_map(map: &HashMap<String, String>, valid: impl Fn(&str) -> bool) -> bool {
+    map.iter()
+        .all(|(mode, value)| Mode::ALL.iter().any(|m| m.name() == mode) && valid(value))
+}
+
+/// Whether the value of a free form `key` means something, invalid values are ignored
+/// where they are used
+pub fn is_valid(settings: &gio::Settings, key: &str) -> bool {
+    match key {
+        "mode-colors" => valid_mode_map(&settings.get("mode-colors"), |color| {
+            color.trim().is_empty() || is_color(color)
+        }),
+        "cursor-shapes" => valid_mode_map(&settings.get("cursor-shapes"), |shape| {
+            cursor::CursorShape::from_name(shape).is_some()
+        }),
+        "cursor-size" => cursor::CursorSize::parse(&settings.string("cursor-size")).is_some(),
+        "cursor-color" => {
+            let color = settings.string("cursor-color");
+            color.trim().is_empty() || is_color(&color)
+        }
+        _ => true,
+    }
+}
+
+/// Free form keys whose values are ignored because they are malformed
+pub fn invalid_keys(settings: &gio::Settings) -> Vec<&'static str> {
+    FREE_FORM_KEYS
+        .into_iter()
+        .filter(|key| !is_valid(settings, key))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_mode_map() {
+        let colors = HashMap::from([("insert".to_string(), "#33d17a".to_string())]);
+        assert!(valid_mode_map(&colors, is_color));
+
+        let colors = HashMap::from([("insert".to_string(), "#33d17".to_string())]);
+        assert!(!valid_mode_map(&colors, is_color));
+
+        let colors = HashMap::from([("unknown".to_string(), "red".to_string())]);
+        assert!(!valid_mode_map(&colors, is_color));
+    }
+}
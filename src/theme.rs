@@ -0,0 +1,220 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use gtk::{gdk, gio, prelude::*};
+
+use crate::nvim::{bridge, Color, Mode, NvimBridge};
+
+/// Keeps nvim's `background` option and the libadwaita color scheme in sync
+#[derive(Debug)]
+pub struct ThemeSync {
+    settings: gio::Settings,
+    nvim_tx: NvimBridge,
+    /// Last known value of nvim's `background`, used to avoid ping-ponging updates
+    background: RefCell<String>,
+}
+
+impl ThemeSync {
+    pub fn new(settings: gio::Settings, nvim_tx: NvimBridge) -> Rc<Self> {
+        let this = Rc::new(Self {
+            settings,
+            nvim_tx,
+            background: RefCell::new(String::new()),
+        });
+
+        let style_manager = adw::StyleManager::default();
+
+        let weak = Rc::downgrade(&this);
+        style_manager.connect_dark_notify(move |style_manager| {
+            if let Some(this) = weak.upgrade() {
+                this.system_changed(style_manager.is_dark());
+            }
+        });
+
+        this.system_changed(style_manager.is_dark());
+
+        this
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.boolean("theme-sync")
+    }
+
+    fn system_changed(&self, dark: bool) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let background = if dark { "dark" } else { "light" };
+        if *self.background.borrow() == background {
+            return;
+        }
+        *self.background.borrow_mut() = background.to_string();
+
+        let colorscheme = self.settings.string(if dark {
+            "dark-colorscheme"
+        } else {
+            "light-colorscheme"
+        });
+        let nvim = self.nvim_tx.clone();
+        bridge::spawn_local(
+            "Switching the background",
+            async move {
+                nvim.command(&format!("set background={background}"))
+                    .await?;
+                if !colorscheme.is_empty() {
+                    nvim.command(&format!("colorscheme {colorscheme}")).await?;
+                }
+                Ok(())
+            },
+            |()| {},
+        );
+    }
+
+    /// nvim's `background` option changed
+    pub fn set_background(&self, background: &str) {
+        *self.background.borrow_mut() = background.to_string();
+
+        if !self.is_enabled() {
+            return;
+        }
+
+        // Only express a preference, an explicit system preference still wins
+        let color_scheme = if background == "dark" {
+            adw::ColorScheme::PreferDark
+        } else {
+            adw::ColorScheme::Default
+        };
+        adw::StyleManager::default().set_color_scheme(color_scheme);
+    }
+}
+
+/// Custom mode accent colors from the `mode-colors` setting, overriding the ones in style.css
+#[derive(Debug)]
+pub struct ModeColors {
+    _settings: gio::Settings,
+}
+
+impl ModeColors {
+    pub fn new(settings: gio::Settings) -> Self {
+        let provider = gtk::CssProvider::new();
+        if let Some(display) = gdk::Display::default() {
+            gtk::style_context_add_provider_for_display(
+                &display,
+                &provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+            );
+        }
+
+        let load = move |settings: &gio::Settings| {
+            let colors: HashMap<String, String> = settings.get("mode-colors");
+            provider.load_from_data(&mode_colors_css(&colors));
+        };
+        load(&settings);
+        settings.connect_changed(Some("mode-colors"), move |settings, _| load(settings));
+
+        Self {
+            _settings: settings,
+        }
+    }
+}
+
+/// Scrollbars of `nvim-scrollbars` scrolled windows (the completion popup) in the
+/// PmenuSbar/PmenuThumb colors, GTK's grey ones clash with dark colorschemes
+#[derive(Debug)]
+pub struct ScrollbarColors {
+    provider: gtk::CssProvider,
+}
+
+impl Default for ScrollbarColors {
+    fn default() -> Self {
+        let provider = gtk::CssProvider::new();
+        if let Some(display) = gdk::Display::default() {
+            gtk::style_context_add_provider_for_display(
+                &display,
+                &provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+            );
+        }
+
+        Self { provider }
+    }
+}
+
+impl ScrollbarColors {
+    /// `None` keeps the GTK default for that part
+    pub fn set(&self, trough: Option<Color>, slider: Option<Color>) {
+        self.provider.load_from_data(&scrollbar_css(trough, slider));
+    }
+}
+
+fn scrollbar_css(trough: Option<Color>, slider: Option<Color>) -> String {
+    let rule = |part: &str, color: Option<Color>| {
+        color.map_or(String::new(), |color| {
+            format!(
+                ".nvim-scrollbars scrollbar {part} {{ background-color: {}; }}\n",
+                css_color(color)
+            )
+        })
+    };
+
+    rule("trough", trough) + &rule("slider", slider)
+}
+
+fn css_color(color: Color) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "rgb({}, {}, {})",
+        channel(color.r),
+        channel(color.g),
+        channel(color.b)
+    )
+}
+
+/// Rules tinting the header bar border per mode, unknown modes and empty colors are skipped
+fn mode_colors_css(colors: &HashMap<String, String>) -> String {
+    colors
+        .iter()
+        .filter(|(_, color)| !color.trim().is_empty())
+        .filter_map(|(name, color)| {
+            let mode = Mode::ALL.into_iter().find(|mode| mode.name() == name)?;
+            Some(format!(
+                "window.{} headerbar {{ box-shadow: inset 0 -2px {color}; }}\n",
+                mode.css_class()
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_colors_css() {
+        let colors = HashMap::from([
+            ("insert".to_string(), "#33d17a".to_string()),
+            ("visual".to_string(), String::new()),
+            ("unknown".to_string(), "red".to_string()),
+        ]);
+
+        assert_eq!(
+            mode_colors_css(&colors),
+            "window.mode-insert headerbar { box-shadow: inset 0 -2px #33d17a; }\n"
+        );
+    }
+
+    #[test]
+    fn test_scrollbar_css() {
+        let thumb = Color {
+            r: 1.0,
+            g: 0.5,
+            b: 0.0,
+        };
+
+        assert_eq!(scrollbar_css(None, None), "");
+        assert_eq!(
+            scrollbar_css(None, Some(thumb)),
+            ".nvim-scrollbars scrollbar slider { background-color: rgb(255, 128, 0); }\n"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Window titles from a format string, see the `title-format` setting and `.vimdicator.toml`
+
+use std::path::Path;
+
+use crate::nvim::{BufferInfo, Mode};
+
+/// Title until the `title-format` setting is read, the default of the setting
+pub const DEFAULT_FORMAT: &str = "{modified} {file}";
+
+/// What the placeholders of a format stand for
+pub struct Context<'a> {
+    pub buffer: &'a BufferInfo,
+    /// Address of the `--server`, empty for the embedded nvim
+    pub server: &'a str,
+    pub mode: Mode,
+}
+
+/// `format` with its placeholders replaced, unknown ones are kept as they are:
+/// `{file}` the name of the current buffer, `{dir}` its directory, `{modified}` a dot
+/// while it has unsaved changes, `{server}` the address of the `--server` and `{mode}`
+/// the nvim mode as in the status footer
+pub fn format(format: &str, context: &Context) -> String {
+    let buffer = context.buffer;
+    let path = Path::new(&buffer.name);
+    let value = |placeholder: &str| -> Option<String> {
+        Some(match placeholder {
+            "file" => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "[No Name]".to_string()),
+            "dir" => path
+                .parent()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            "modified" => if buffer.modified { "•" } else { "" }.to_string(),
+            "server" => context.server.to_string(),
+            "mode" => context.mode.label().to_string(),
+            _ => return None,
+        })
+    };
+
+    // One pass, so a file named `{dir}` stays what it is
+    let mut title = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        title.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let replaced = rest
+            .find('}')
+            .and_then(|end| Some((value(&rest[1..end])?, end)));
+        match replaced {
+            Some((value, end)) => {
+                title.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                title.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    title.push_str(rest);
+
+    title.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let buffer = BufferInfo {
+            name: "/src/project/{dir}.rs".to_string(),
+            modified: true,
+            ..Default::default()
+        };
+        let context = Context {
+            buffer: &buffer,
+            server: "localhost:6666",
+            mode: Mode::Insert,
+        };
+        assert_eq!(
+            format("{modified} {file} ({dir}) - {unknown} {", &context),
+            "• {dir}.rs (/src/project) - {unknown} {"
+        );
+        assert_eq!(
+            format("{file} [{mode}] {server}", &context),
+            "{dir}.rs [INSERT] localhost:6666"
+        );
+
+        let buffer = BufferInfo::default();
+        let context = Context {
+            buffer: &buffer,
+            server: "",
+            mode: Mode::Normal,
+        };
+        assert_eq!(format("{modified} {file} {server}", &context), "[No Name]");
+    }
+}
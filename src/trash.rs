@@ -0,0 +1,106 @@
+use gtk::{gio, glib, prelude::*};
+
+/// Move files to the trash, returns the ones that made it
+pub async fn trash(paths: Vec<String>) -> Vec<String> {
+    let mut trashed = Vec::new();
+    for path in paths {
+        match gio::File::for_path(&path)
+            .trash_future(glib::PRIORITY_DEFAULT)
+            .await
+        {
+            Ok(()) => trashed.push(path),
+            Err(err) => log::error!("Could not move {path} to the trash: {err}"),
+        }
+    }
+    trashed
+}
+
+/// Trash entry for a file that was at `path`, the newest when it was trashed several times
+fn latest_entry<'a, T>(entries: &'a [(String, i64, T)], path: &str) -> Option<&'a T> {
+    entries
+        .iter()
+        .filter(|(orig_path, _, _)| orig_path == path)
+        .max_by_key(|(_, deleted, _)| *deleted)
+        .map(|(_, _, entry)| entry)
+}
+
+fn restore_blocking(paths: &[String]) -> Result<(), glib::Error> {
+    let trash = gio::File::for_uri("trash:///");
+    let children = trash.enumerate_children(
+        "standard::name,trash::orig-path,trash::deletion-date",
+        gio::FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
+        gio::Cancellable::NONE,
+    )?;
+
+    let mut entries = Vec::new();
+    for info in children {
+        let info = info?;
+        let Some(orig_path) = info.attribute_byte_string("trash::orig-path") else {
+            continue;
+        };
+        let deleted = info.deletion_date().map_or(0, |date| date.to_unix());
+        entries.push((orig_path.to_string(), deleted, trash.child(info.name())));
+    }
+
+    for path in paths {
+        match latest_entry(&entries, path) {
+            Some(entry) => entry.move_(
+                &gio::File::for_path(path),
+                gio::FileCopyFlags::NOFOLLOW_SYMLINKS,
+                gio::Cancellable::NONE,
+                None,
+            )?,
+            None => log::warn!("{path} is no longer in the trash"),
+        }
+    }
+    Ok(())
+}
+
+/// Move files trashed by `trash` back where they were
+pub async fn restore(paths: Vec<String>) {
+    match gio::spawn_blocking(move || restore_blocking(&paths)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => log::error!("Could not restore from the trash: {err}"),
+        Err(_) => log::error!("Restoring from the trash panicked"),
+    }
+}
+
+/// Delete files for good, directories with everything in them
+pub async fn delete(paths: Vec<String>) {
+    let res = gio::spawn_blocking(move || {
+        for path in paths {
+            let path = std::path::Path::new(&path);
+            let res = if path.is_dir() && !path.is_symlink() {
+                std::fs::remove_dir_all(path)
+            } else {
+                std::fs::remove_file(path)
+            };
+            if let Err(err) = res {
+                log::error!("Could not delete {}: {err}", path.display());
+            }
+        }
+    })
+    .await;
+
+    if res.is_err() {
+        log::error!("Deleting files panicked");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_entry() {
+        let entries = [
+            ("/a".to_string(), 10, "a"),
+            ("/b".to_string(), 30, "b.2"),
+            ("/b".to_string(), 20, "b"),
+        ];
+
+        assert_eq!(latest_entry(&entries, "/a"), Some(&"a"));
+        assert_eq!(latest_entry(&entries, "/b"), Some(&"b.2"));
+        assert_eq!(latest_entry(&entries, "/c"), None);
+    }
+}
@@ -0,0 +1,324 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib;
+
+use crate::nvim::{bridge, Colorscheme, Colorschemes, GtkToNvimEvent, NvimBridge};
+use crate::settings;
+use std::cell::{Cell, OnceCell, RefCell};
+
+/// Sample code of the previews as (index into `Colorscheme::foregrounds`, text)
+const SAMPLE: &[(usize, &str)] = &[
+    (2, "fn"),
+    (0, " "),
+    (4, "main"),
+    (0, "(n: "),
+    (5, "u32"),
+    (0, ") { "),
+    (3, "\"hi\""),
+    (0, " + "),
+    (6, "42"),
+    (0, " } "),
+    (1, "// ok"),
+];
+
+/// The sample code in the colors of `scheme`
+fn preview_markup(scheme: &Colorscheme) -> String {
+    let mut markup = String::new();
+    for (group, text) in SAMPLE {
+        let text = glib::markup_escape_text(text);
+        match scheme.foregrounds.get(*group).cloned().flatten() {
+            Some(color) => markup.push_str(&format!("<span foreground=\"{color}\">{text}</span>")),
+            None => markup.push_str(&text),
+        }
+    }
+    match &scheme.background {
+        Some(color) => format!("<span background=\"{color}\"> {markup} </span>"),
+        None => format!(" {markup} "),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Filter {
+    #[default]
+    All,
+    Dark,
+    Light,
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct ColorschemeDialog {
+        pub stack: gtk::Stack,
+        pub list: gtk::ListBox,
+        pub apply: gtk::Button,
+        filter: Cell<Filter>,
+        pub colorschemes: RefCell<Colorschemes>,
+        /// A row was selected, closing without applying restores the colorscheme from before
+        pub previewed: Cell<bool>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+    }
+
+    impl ColorschemeDialog {
+        fn refresh(&self) {
+            let Some(nvim) = self.nvim_tx.get().cloned() else {
+                return;
+            };
+
+            let obj = self.obj().clone();
+            bridge::spawn_local(
+                "Listing colorschemes",
+                async move {
+                    nvim.exec_lua_as("return require('vimdicator').colorschemes()", vec![])
+                        .await
+                },
+                move |colorschemes| obj.imp().set_colorschemes(colorschemes),
+            );
+        }
+
+        fn set_colorschemes(&self, colorschemes: Colorschemes) {
+            while let Some(row) = self.list.first_child() {
+                self.list.remove(&row);
+            }
+            self.colorschemes.replace(colorschemes);
+
+            let colorschemes = self.colorschemes.borrow();
+            for scheme in &colorschemes.schemes {
+                let row = adw::ActionRow::builder()
+                    .title(glib::markup_escape_text(&scheme.name))
+                    .subtitle(if scheme.dark {
+                        gettext("Dark")
+                    } else {
+                        gettext("Light")
+                    })
+                    .build();
+                row.add_suffix(
+                    &gtk::Label::builder()
+                        .use_markup(true)
+                        .label(preview_markup(scheme))
+                        .valign(gtk::Align::Center)
+                        .css_classes(["monospace"])
+                        .build(),
+                );
+                self.list.append(&row);
+
+                if scheme.name == colorschemes.current {
+                    self.list.select_row(Some(&row));
+                }
+            }
+            self.stack.set_visible_child_name("list");
+        }
+
+        fn selected(&self) -> Option<Colorscheme> {
+            let row = self.list.selected_row()?;
+            let colorschemes = self.colorschemes.borrow();
+            colorschemes.schemes.get(row.index() as usize).cloned()
+        }
+
+        fn set_colorscheme(&self, name: &str, persist: bool, background: Option<&str>) {
+            let background = background.map_or("nil".to_string(), |bg| format!("{bg:?}"));
+            if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').set_colorscheme({name:?}, {persist}, {background})"
+                )))
+                .unwrap();
+            }
+        }
+
+        fn preview(&self) {
+            let Some(scheme) = self.selected() else {
+                return;
+            };
+            self.apply
+                .set_sensitive(scheme.name != self.colorschemes.borrow().current);
+            if self.previewed.get() || scheme.name != self.colorschemes.borrow().current {
+                self.previewed.set(true);
+                self.set_colorscheme(&scheme.name, false, None);
+            }
+        }
+
+        fn apply(&self) {
+            let Some(scheme) = self.selected() else {
+                return;
+            };
+            self.set_colorscheme(&scheme.name, true, None);
+
+            // Otherwise following the system style switches back on the next change
+            let settings = settings::settings();
+            if settings.boolean("theme-sync") {
+                let key = if scheme.dark {
+                    "dark-colorscheme"
+                } else {
+                    "light-colorscheme"
+                };
+                if let Err(err) = settings.set_string(key, &scheme.name) {
+                    log::error!("Failed to save {key}: {err}");
+                }
+            }
+
+            self.previewed.set(false);
+            self.obj().close();
+        }
+
+        fn set_filter(&self, filter: Filter) {
+            self.filter.set(filter);
+            self.list.invalidate_filter();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ColorschemeDialog {
+        const NAME: &'static str = "ColorschemeDialog";
+        type Type = super::ColorschemeDialog;
+        type ParentType = adw::Window;
+    }
+
+    impl ObjectImpl for ColorschemeDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_title(Some(&gettext("Colorschemes")));
+            obj.set_default_size(640, 560);
+            obj.set_modal(true);
+
+            let header_bar = adw::HeaderBar::new();
+
+            self.apply.set_label(&gettext("_Apply"));
+            self.apply.set_use_underline(true);
+            self.apply.set_sensitive(false);
+            self.apply.add_css_class("suggested-action");
+            self.apply
+                .set_tooltip_text(Some(&gettext("Use this colorscheme from now on")));
+            self.apply
+                .connect_clicked(glib::clone!(@weak obj => move |_| {
+                    obj.imp().apply();
+                }));
+            header_bar.pack_end(&self.apply);
+
+            let filters = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+            filters.add_css_class("linked");
+            let mut group: Option<gtk::ToggleButton> = None;
+            for (label, filter) in [
+                (gettext("All"), Filter::All),
+                (gettext("Dark"), Filter::Dark),
+                (gettext("Light"), Filter::Light),
+            ] {
+                let button = gtk::ToggleButton::builder()
+                    .label(label)
+                    .active(filter == Filter::All)
+                    .build();
+                button.set_group(group.as_ref());
+                button.connect_toggled(glib::clone!(@weak obj => move |button| {
+                    if button.is_active() {
+                        obj.imp().set_filter(filter);
+                    }
+                }));
+                filters.append(&button);
+                group.get_or_insert(button);
+            }
+            header_bar.set_title_widget(Some(&filters));
+
+            self.list.add_css_class("boxed-list");
+            self.list.set_valign(gtk::Align::Start);
+            self.list.set_selection_mode(gtk::SelectionMode::Single);
+            self.list
+                .set_filter_func(glib::clone!(@weak obj => @default-return true, move |row| {
+                    let imp = obj.imp();
+                    let colorschemes = imp.colorschemes.borrow();
+                    let Some(scheme) = colorschemes.schemes.get(row.index() as usize) else {
+                        return true;
+                    };
+                    match imp.filter.get() {
+                        Filter::All => true,
+                        Filter::Dark => scheme.dark,
+                        Filter::Light => !scheme.dark,
+                    }
+                }));
+            self.list
+                .connect_selected_rows_changed(glib::clone!(@weak obj => move |_| {
+                    obj.imp().preview();
+                }));
+
+            let clamp = adw::Clamp::builder()
+                .child(&self.list)
+                .margin_top(12)
+                .margin_bottom(12)
+                .margin_start(12)
+                .margin_end(12)
+                .build();
+            let scrolled = gtk::ScrolledWindow::builder()
+                .child(&clamp)
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .build();
+
+            let loading = adw::StatusPage::builder()
+                .title(gettext("Loading Colorschemes…"))
+                .child(&gtk::Spinner::builder().spinning(true).build())
+                .build();
+
+            self.stack.add_named(&loading, Some("loading"));
+            self.stack.add_named(&scrolled, Some("list"));
+            self.stack.set_vexpand(true);
+
+            let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+            content.append(&header_bar);
+            content.append(&self.stack);
+            obj.set_content(Some(&content));
+
+            obj.connect_show(|dialog| dialog.imp().refresh());
+            obj.connect_close_request(|dialog| {
+                let imp = dialog.imp();
+                if imp.previewed.get() {
+                    let colorschemes = imp.colorschemes.borrow();
+                    imp.set_colorscheme(
+                        &colorschemes.current,
+                        false,
+                        Some(&colorschemes.background),
+                    );
+                }
+                gtk::Inhibit(false)
+            });
+        }
+    }
+    impl WidgetImpl for ColorschemeDialog {}
+    impl WindowImpl for ColorschemeDialog {}
+    impl AdwWindowImpl for ColorschemeDialog {}
+}
+
+glib::wrapper! {
+    /// Installed colorschemes with a preview of each, selecting one applies it in nvim
+    pub struct ColorschemeDialog(ObjectSubclass<imp::ColorschemeDialog>)
+        @extends adw::Window, gtk::Window, gtk::Widget;
+}
+
+impl Default for ColorschemeDialog {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl ColorschemeDialog {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_markup() {
+        let scheme = Colorscheme {
+            name: "test".to_string(),
+            dark: true,
+            background: None,
+            foregrounds: vec![None, Some("#808080".to_string())],
+        };
+        let markup = preview_markup(&scheme);
+        assert!(markup.starts_with(" fn main(n: u32) { &quot;hi&quot;"));
+        assert!(markup.ends_with("<span foreground=\"#808080\">// ok</span> "));
+    }
+}
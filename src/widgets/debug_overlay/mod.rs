@@ -0,0 +1,148 @@
+use std::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::glib;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct DebugOverlay {
+        pub label: gtk::Label,
+        pub renderer: RefCell<String>,
+        pub latency: Cell<Option<(Duration, Duration)>>,
+        pub tick: RefCell<Option<gtk::TickCallbackId>>,
+        pub last_frame_time: Cell<i64>,
+        /// Exponential moving average of the frame interval in ms
+        pub frame_ms: Cell<f64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for DebugOverlay {
+        const NAME: &'static str = "DebugOverlay";
+        type Type = super::DebugOverlay;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for DebugOverlay {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.add_css_class("osd");
+            obj.set_can_target(false);
+
+            self.label.add_css_class("monospace");
+            self.label.set_xalign(0.0);
+            self.label.set_margin_top(6);
+            self.label.set_margin_bottom(6);
+            self.label.set_margin_start(6);
+            self.label.set_margin_end(6);
+            obj.set_child(Some(&self.label));
+        }
+
+        fn dispose(&self) {
+            if let Some(tick) = self.tick.take() {
+                tick.remove();
+            }
+        }
+    }
+    impl WidgetImpl for DebugOverlay {}
+    impl BinImpl for DebugOverlay {}
+}
+
+glib::wrapper! {
+    pub struct DebugOverlay(ObjectSubclass<imp::DebugOverlay>)
+        @extends adw::Bin, gtk::Widget;
+}
+
+impl DebugOverlay {
+    pub fn set_renderer(&self, renderer: &str) {
+        *self.imp().renderer.borrow_mut() = renderer.to_string();
+        self.update_label();
+    }
+
+    /// Input latency p50 and p95
+    pub fn set_latency(&self, latency: Option<(Duration, Duration)>) {
+        self.imp().latency.set(latency);
+        self.update_label();
+    }
+
+    /// Show the overlay and start sampling frame times, sampling keeps the frame clock busy
+    pub fn set_enabled(&self, enabled: bool) {
+        let imp = self.imp();
+
+        self.set_visible(enabled);
+
+        if let Some(tick) = imp.tick.take() {
+            tick.remove();
+        }
+
+        if enabled {
+            imp.last_frame_time.set(0);
+            imp.frame_ms.set(0.0);
+
+            let tick = self.add_tick_callback(|this, frame_clock| {
+                this.frame(frame_clock);
+                glib::Continue(true)
+            });
+            *imp.tick.borrow_mut() = Some(tick);
+        }
+    }
+
+    fn frame(&self, frame_clock: &gtk::gdk::FrameClock) {
+        let imp = self.imp();
+
+        let frame_time = frame_clock.frame_time();
+        let last = imp.last_frame_time.replace(frame_time);
+
+        if last != 0 {
+            let ms = (frame_time - last) as f64 / 1000.0;
+            let avg = imp.frame_ms.get();
+            imp.frame_ms
+                .set(if avg == 0.0 { ms } else { avg * 0.9 + ms * 0.1 });
+        }
+
+        self.update_label();
+    }
+
+    fn update_label(&self) {
+        let imp = self.imp();
+
+        let fps = self
+            .frame_clock()
+            .map(|frame_clock| frame_clock.fps())
+            .unwrap_or_default();
+
+        let mut text = format!(
+            "renderer: {}\n{:5.1} fps {:6.2} ms",
+            imp.renderer.borrow(),
+            fps,
+            imp.frame_ms.get(),
+        );
+
+        if let Some((p50, p95)) = imp.latency.get() {
+            text.push_str(&format!(
+                "\ninput p50 {:6.2} ms p95 {:6.2} ms",
+                p50.as_secs_f64() * 1000.0,
+                p95.as_secs_f64() * 1000.0,
+            ));
+        }
+
+        imp.label.set_text(&text);
+    }
+}
+
+/// Short name of a GSK renderer as accepted by `GSK_RENDERER`
+pub fn renderer_name(renderer: &gtk::gsk::Renderer) -> String {
+    match renderer.type_().name() {
+        "GskGLRenderer" => "gl".to_string(),
+        "GskNglRenderer" => "ngl".to_string(),
+        "GskVulkanRenderer" => "vulkan".to_string(),
+        "GskCairoRenderer" => "cairo".to_string(),
+        other => other.to_string(),
+    }
+}
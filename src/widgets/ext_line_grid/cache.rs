@@ -0,0 +1,204 @@
+use std::{borrow::Borrow, collections::HashMap, hash::Hash};
+
+/// Hit and eviction counts since the cache was created
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    size: usize,
+    last_used: u64,
+}
+
+/// Least recently used cache limited by an estimate of its size in bytes
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    budget: usize,
+    used: usize,
+    tick: u64,
+    stats: CacheStats,
+}
+
+/// An empty cache that keeps nothing until given a budget
+impl<K, V> Default for LruCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget: 0,
+            used: 0,
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            used: 0,
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.tick += 1;
+
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                self.stats.hits += 1;
+                entry.last_used = self.tick;
+                Some(&entry.value)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Values larger than the whole budget are not kept
+    pub fn insert(&mut self, key: K, value: V, size: usize) {
+        if size > self.budget {
+            return;
+        }
+
+        if let Some(old) = self.entries.remove(&key) {
+            self.used -= old.size;
+        }
+
+        if self.used + size > self.budget {
+            // Evict down to three quarters of the budget, so a full cache doesn't
+            // scan its entries on every insert
+            self.evict_to((self.budget / 4 * 3).min(self.budget - size));
+        }
+
+        self.used += size;
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                size,
+                last_used: self.tick,
+            },
+        );
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        if self.used > budget {
+            self.evict_to(budget);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    fn evict_to(&mut self, target: usize) {
+        let mut by_age: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+        for (_, key) in by_age {
+            if self.used <= target {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                self.used -= entry.size;
+                self.stats.evictions += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let mut cache = LruCache::new(100);
+
+        cache.insert("a".to_string(), 1, 40);
+        cache.insert("b".to_string(), 2, 40);
+        assert_eq!(cache.get("a"), Some(&1));
+
+        // "b" is the least recently used one
+        cache.insert("c".to_string(), 3, 40);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(cache.get("c"), Some(&3));
+        assert_eq!(cache.used(), 80);
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+            }
+        );
+        assert_eq!(cache.stats().hit_rate(), 0.75);
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut cache = LruCache::new(100);
+
+        cache.insert("huge", (), 101);
+        assert_eq!(cache.len(), 0);
+
+        cache.insert("a", (), 30);
+        cache.insert("b", (), 30);
+        cache.set_budget(40);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used(), 30);
+
+        cache.set_budget(0);
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::nvim::Color;
+
+const WHITE: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+};
+const BLACK: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+};
+
+/// WCAG relative luminance of an sRGB color
+fn luminance(color: Color) -> f64 {
+    let linear = |channel: f32| {
+        let channel = f64::from(channel.clamp(0.0, 1.0));
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+/// WCAG contrast ratio, from 1 (same luminance) to 21 (black on white)
+pub fn contrast_ratio(a: Color, b: Color) -> f64 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+    Color {
+        r: from.r + (to.r - from.r) * amount,
+        g: from.g + (to.g - from.g) * amount,
+        b: from.b + (to.b - from.b) * amount,
+    }
+}
+
+/// `fg`, moved just far enough towards white or black to reach `min_ratio` against `bg`
+pub fn ensure_contrast(fg: Color, bg: Color, min_ratio: f64) -> Color {
+    if contrast_ratio(fg, bg) >= min_ratio {
+        return fg;
+    }
+
+    let target = if contrast_ratio(WHITE, bg) >= contrast_ratio(BLACK, bg) {
+        WHITE
+    } else {
+        BLACK
+    };
+    if contrast_ratio(target, bg) <= min_ratio {
+        return target;
+    }
+
+    // Luminance only grows towards white (and shrinks towards black), so bisect the mix
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..16 {
+        let amount = (low + high) / 2.0;
+        if contrast_ratio(mix(fg, target, amount), bg) >= min_ratio {
+            high = amount;
+        } else {
+            low = amount;
+        }
+    }
+    mix(fg, target, high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: f32) -> Color {
+        Color {
+            r: value,
+            g: value,
+            b: value,
+        }
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        assert!((contrast_ratio(BLACK, WHITE) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio(WHITE, BLACK) - 21.0).abs() < 1e-9);
+        assert_eq!(contrast_ratio(gray(0.5), gray(0.5)), 1.0);
+    }
+
+    #[test]
+    fn test_ensure_contrast() {
+        // Already readable colors are left alone
+        assert_eq!(ensure_contrast(WHITE, BLACK, 7.0), WHITE);
+
+        // Dark gray on black is lightened just enough
+        let fg = ensure_contrast(gray(0.2), BLACK, 7.0);
+        let ratio = contrast_ratio(fg, BLACK);
+        assert!((7.0..7.1).contains(&ratio), "{ratio}");
+        assert!(fg.r > 0.2);
+
+        // Light gray on white is darkened
+        let fg = ensure_contrast(gray(0.8), WHITE, 4.5);
+        assert!(contrast_ratio(fg, WHITE) >= 4.5);
+        assert!(fg.r < 0.8);
+
+        // Unreachable ratios give the best there is
+        assert_eq!(ensure_contrast(gray(0.5), gray(0.5), 30.0), BLACK);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use crate::nvim::{Color, Mode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    /// Vertical bar on the left edge of the cell
+    Bar,
+    /// Horizontal bar along the bottom of the cell
+    Underline,
+}
+
+impl CursorShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(Self::Block),
+            "bar" => Some(Self::Bar),
+            "underline" => Some(Self::Underline),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Bar => "bar",
+            Self::Underline => "underline",
+        }
+    }
+}
+
+/// Thickness of bar and underline cursors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorSize {
+    Pixels(f64),
+    /// Of the cell width for bars and the cell height for underlines
+    Percent(f64),
+}
+
+impl Default for CursorSize {
+    fn default() -> Self {
+        Self::Percent(25.0)
+    }
+}
+
+impl CursorSize {
+    /// `2px` or `25%`
+    pub fn parse(size: &str) -> Option<Self> {
+        let size = size.trim();
+        if let Some(pixels) = size.strip_suffix("px") {
+            let pixels: f64 = pixels.trim().parse().ok()?;
+            (pixels > 0.0).then_some(Self::Pixels(pixels))
+        } else if let Some(percent) = size.strip_suffix('%') {
+            let percent: f64 = percent.trim().parse().ok()?;
+            (percent > 0.0 && percent <= 100.0).then_some(Self::Percent(percent))
+        } else {
+            None
+        }
+    }
+
+    /// Thickness in pixels for a cell dimension of `cell` pixels, never less than one pixel
+    fn pixels(self, cell: f64) -> f64 {
+        let pixels = match self {
+            Self::Pixels(pixels) => pixels,
+            Self::Percent(percent) => cell * percent / 100.0,
+        };
+        pixels.clamp(1.0, cell.max(1.0))
+    }
+}
+
+/// GUI side cursor overrides, nvim's 'guicursor' is not consulted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CursorStyle {
+    /// Modes missing here get a block cursor
+    pub shapes: HashMap<&'static str, CursorShape>,
+    pub size: CursorSize,
+    /// Fixed cursor color, the cell's colors reversed when `None`
+    pub color: Option<Color>,
+    /// Tint the cursor row in the `CursorLine` color, so nvim's 'cursorline' can be off
+    pub line: bool,
+    /// Same for the cursor column
+    pub column: bool,
+}
+
+impl CursorStyle {
+    pub fn shape(&self, mode: Mode) -> CursorShape {
+        self.shapes.get(mode.name()).copied().unwrap_or_default()
+    }
+
+    /// Part of the cell rect (x, y, width, height) covered by the cursor
+    pub fn rect(&self, mode: Mode, cell: (f64, f64, f64, f64)) -> (f64, f64, f64, f64) {
+        let (x, y, width, height) = cell;
+        match self.shape(mode) {
+            CursorShape::Block => cell,
+            CursorShape::Bar => (x, y, self.size.pixels(width), height),
+            CursorShape::Underline => {
+                let thickness = self.size.pixels(height);
+                (x, y + height - thickness, width, thickness)
+            }
+        }
+    }
+}
+
+/// One `key=value` argument of `:VimdicatorCursor`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CursorChange {
+    /// `insert=bar`, `insert=default` drops the override
+    Shape(Mode, Option<CursorShape>),
+    /// `size=2px`, the value is kept as written for the setting
+    Size(String),
+    /// `color=#ff8800`, `color=reverse` uses the cell colors
+    Color(Option<String>),
+    /// `reset`, back to block cursors in the reversed cell colors
+    Reset,
+}
+
+/// Arguments of `:VimdicatorCursor`, e.g. `insert=bar size=2px color=reverse`
+pub fn parse_command(args: &str) -> Result<Vec<CursorChange>, String> {
+    args.split_whitespace()
+        .map(|arg| {
+            if arg == "reset" {
+                return Ok(CursorChange::Reset);
+            }
+
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got {arg}"))?;
+
+            match key {
+                "size" => {
+                    CursorSize::parse(value)
+                        .ok_or_else(|| format!("Invalid cursor size {value}"))?;
+                    Ok(CursorChange::Size(value.to_string()))
+                }
+                "color" if value == "reverse" => Ok(CursorChange::Color(None)),
+                "color" => Ok(CursorChange::Color(Some(value.to_string()))),
+                mode => {
+                    let mode = Mode::ALL
+                        .into_iter()
+                        .find(|m| m.name() == mode)
+                        .ok_or_else(|| format!("Unknown mode {mode}"))?;
+                    let shape = match value {
+                        "default" => None,
+                        shape => Some(
+                            CursorShape::from_name(shape)
+                                .ok_or_else(|| format!("Unknown cursor shape {shape}"))?,
+                        ),
+                    };
+                    Ok(CursorChange::Shape(mode, shape))
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_size() {
+        assert_eq!(CursorSize::parse("2px"), Some(CursorSize::Pixels(2.0)));
+        assert_eq!(CursorSize::parse(" 25% "), Some(CursorSize::Percent(25.0)));
+        assert_eq!(CursorSize::parse("0px"), None);
+        assert_eq!(CursorSize::parse("150%"), None);
+        assert_eq!(CursorSize::parse("2"), None);
+
+        assert_eq!(CursorSize::Percent(25.0).pixels(8.0), 2.0);
+        // Always visible, never larger than the cell
+        assert_eq!(CursorSize::Percent(1.0).pixels(8.0), 1.0);
+        assert_eq!(CursorSize::Pixels(20.0).pixels(8.0), 8.0);
+    }
+
+    #[test]
+    fn test_cursor_rect() {
+        let style = CursorStyle {
+            shapes: HashMap::from([
+                ("insert", CursorShape::Bar),
+                ("replace", CursorShape::Underline),
+            ]),
+            size: CursorSize::Pixels(2.0),
+            ..Default::default()
+        };
+        let cell = (10.0, 20.0, 8.0, 16.0);
+
+        assert_eq!(style.rect(Mode::Normal, cell), cell);
+        assert_eq!(style.rect(Mode::Insert, cell), (10.0, 20.0, 2.0, 16.0));
+        assert_eq!(style.rect(Mode::Replace, cell), (10.0, 34.0, 8.0, 2.0));
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("insert=block visual=underline normal=default size=3px color=reverse"),
+            Ok(vec![
+                CursorChange::Shape(Mode::Insert, Some(CursorShape::Block)),
+                CursorChange::Shape(Mode::Visual, Some(CursorShape::Underline)),
+                CursorChange::Shape(Mode::Normal, None),
+                CursorChange::Size("3px".to_string()),
+                CursorChange::Color(None),
+            ])
+        );
+        assert_eq!(parse_command("reset"), Ok(vec![CursorChange::Reset]));
+        assert_eq!(
+            parse_command("color=#ff8800"),
+            Ok(vec![CursorChange::Color(Some("#ff8800".to_string()))])
+        );
+
+        assert!(parse_command("insert").is_err());
+        assert!(parse_command("insert=beam").is_err());
+        assert!(parse_command("terminal=bar").is_err());
+        assert!(parse_command("size=wide").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! The font used until nvim sets 'guifont'. Detected on first launch from the desktop's and
+//! fontconfig's monospace font, then kept in the `default-font` setting for the user to change.
+
+use gtk::{gio, pango, prelude::*};
+
+/// Used until the setting is applied, and when no candidate is installed
+pub const GENERIC: &str = "Monospace 11";
+
+/// Points given to a detected font without a size
+const SIZE: i32 = 11;
+
+/// Tried in order after the desktop's and fontconfig's choice
+const FALLBACK_FAMILIES: [&str; 4] = [
+    "Source Code Pro",
+    "DejaVu Sans Mono",
+    "Noto Sans Mono",
+    "Liberation Mono",
+];
+
+/// A pango font description string of the first candidate whose family the font map has
+pub fn detect(context: &pango::Context) -> String {
+    let installed: Vec<String> = context
+        .list_families()
+        .iter()
+        .map(|family| family.name().to_string())
+        .collect();
+
+    let candidates = desktop_monospace()
+        .into_iter()
+        .chain(fontconfig_monospace(context))
+        .chain(FALLBACK_FAMILIES.map(str::to_string));
+    pick(candidates, &installed).unwrap_or_else(|| GENERIC.to_string())
+}
+
+fn pick(candidates: impl IntoIterator<Item = String>, installed: &[String]) -> Option<String> {
+    candidates.into_iter().find_map(|candidate| {
+        let mut desc = pango::FontDescription::from_string(&candidate);
+        let family = desc.family()?;
+        // Aliases like `Monospace` aren't families, any of them would be accepted
+        let known = installed
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(&family));
+        if !known {
+            return None;
+        }
+        if desc.size() == 0 {
+            desc.set_size(SIZE * pango::SCALE);
+        }
+        Some(desc.to_str().to_string())
+    })
+}
+
+/// GNOME's monospace font, what its terminals use by default
+fn desktop_monospace() -> Option<String> {
+    const SCHEMA: &str = "org.gnome.desktop.interface";
+    const KEY: &str = "monospace-font-name";
+
+    let schema = gio::SettingsSchemaSource::default()?.lookup(SCHEMA, true)?;
+    if !schema.has_key(KEY) {
+        return None;
+    }
+    let settings = gio::Settings::new_full(&schema, gio::SettingsBackend::NONE, None);
+    Some(settings.string(KEY).to_string()).filter(|font| !font.is_empty())
+}
+
+/// The family fontconfig resolves the `monospace` alias to
+fn fontconfig_monospace(context: &pango::Context) -> Option<String> {
+    let font = context.load_font(&pango::FontDescription::from_string("Monospace"))?;
+    Some(font.describe().family()?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick() {
+        let installed = ["DejaVu Sans Mono".to_string(), "Iosevka".to_string()];
+        let candidates =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+
+        assert_eq!(
+            pick(candidates(&["Missing Mono 10", "iosevka 13"]), &installed).as_deref(),
+            Some("iosevka 13")
+        );
+        assert_eq!(
+            pick(candidates(&["Monospace", "DejaVu Sans Mono"]), &installed).as_deref(),
+            Some("DejaVu Sans Mono 11")
+        );
+        assert_eq!(pick(candidates(&["Missing Mono"]), &installed), None);
+    }
+}
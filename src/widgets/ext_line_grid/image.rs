@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use gtk::{gdk, graphene, prelude::*};
+
+use super::CellMetrics;
+use crate::nvim::ImagePlacement;
+
+/// Textures of the placed images by path, `None` while loading or when the file isn't an image
+pub type Textures = HashMap<String, Option<gdk::Texture>>;
+
+/// Size in pixels of an image `texture_size` pixels large, `width` and `height` are cells
+fn image_size(
+    width: Option<usize>,
+    height: Option<usize>,
+    texture_size: (f64, f64),
+    cell_metrics: &CellMetrics,
+) -> (f64, f64) {
+    let (texture_width, texture_height) = texture_size;
+    let width = width.map(|cells| cells as f64 * cell_metrics.char_width);
+    let height = height.map(|cells| cells as f64 * cell_metrics.line_height);
+
+    match (width, height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, width * texture_height / texture_width),
+        (None, Some(height)) => (height * texture_width / texture_height, height),
+        (None, None) => texture_size,
+    }
+}
+
+/// The loaded images, each cut off at the edges of the window showing it
+pub fn snapshot_images(
+    cell_metrics: &CellMetrics,
+    snapshot: &gtk::Snapshot,
+    images: &[ImagePlacement],
+    textures: &Textures,
+) {
+    for image in images {
+        let Some(Some(texture)) = textures.get(&image.path) else {
+            continue;
+        };
+
+        let (x, y) = cell_metrics.pixel_coords(image.col, image.row);
+        let (width, height) = image_size(
+            image.width,
+            image.height,
+            (texture.width() as f64, texture.height() as f64),
+            cell_metrics,
+        );
+
+        let (clip_row, clip_col, clip_rows, clip_columns) = image.clip;
+        let (clip_x, clip_y) = cell_metrics.pixel_coords(clip_col, clip_row);
+        snapshot.push_clip(&graphene::Rect::new(
+            clip_x as f32,
+            clip_y as f32,
+            (clip_columns as f64 * cell_metrics.char_width) as f32,
+            (clip_rows as f64 * cell_metrics.line_height) as f32,
+        ));
+        snapshot.append_texture(
+            texture,
+            &graphene::Rect::new(x as f32, y as f32, width as f32, height as f32),
+        );
+        snapshot.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_size() {
+        let cell_metrics = CellMetrics {
+            char_width: 10.0,
+            line_height: 20.0,
+            ..Default::default()
+        };
+        let texture = (400.0, 200.0);
+
+        assert_eq!(
+            image_size(Some(8), Some(2), texture, &cell_metrics),
+            (80.0, 40.0)
+        );
+        assert_eq!(
+            image_size(Some(10), None, texture, &cell_metrics),
+            (100.0, 50.0)
+        );
+        assert_eq!(
+            image_size(None, Some(5), texture, &cell_metrics),
+            (200.0, 100.0)
+        );
+        assert_eq!(image_size(None, None, texture, &cell_metrics), texture);
+    }
+}
@@ -1,16 +1,41 @@
 use adw::prelude::*;
 use adw::subclass::prelude::*;
+use gettextrs::gettext;
 use gtk::{
-    gdk, glib,
+    gdk, gio, glib,
     graphene::{self},
     gsk, pango,
 };
-use std::cell::{OnceCell, RefCell};
-use tokio::sync::mpsc::UnboundedSender;
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    collections::VecDeque,
+    ops::Range,
+    sync::Arc,
+};
+
+use crate::clock;
+use crate::nvim::{
+    bridge,
+    event::UnderlineStyle,
+    ext_line_grid::{cell_width, Line},
+    Color, Colors, ImagePlacement, Mode, NvimBridge, OverflowWindow,
+};
 
-use crate::nvim::{Colors, GtkToNvimEvent};
+mod cache;
+mod contrast;
+pub mod cursor;
+mod default_font;
+mod image;
+mod prepare;
+mod search;
+mod selection;
+use cache::LruCache;
+use cursor::{CursorShape, CursorStyle};
+use prepare::{Highlights, PreparedLine};
+use search::Match;
+use selection::Selection;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct CellMetrics {
     pub line_height: f64,
     pub char_width: f64,
@@ -18,6 +43,7 @@ pub struct CellMetrics {
     pub descent: f64,
     pub underline_position: f64,
     pub underline_thickness: f64,
+    pub undercurl_thickness: f64,
     pub strikethrough_position: f64,
     pub strikethrough_thickness: f64,
     pub pango_ascent: i32,
@@ -55,11 +81,40 @@ impl CellMetrics {
             char_width: f64::from(font_metrics.approximate_char_width()) / f64::from(pango::SCALE),
             underline_position: ascent - underline_position + underline_thickness / 2.0,
             underline_thickness,
+            undercurl_thickness: underline_thickness,
             strikethrough_position: ascent - strikethrough_position + strikethrough_thickness / 2.0,
             strikethrough_thickness,
         }
     }
 
+    /// Apply the user's decoration settings, then keep underlines at least one device
+    /// pixel thick and inside the row, some fonts report hairlines or positions below it
+    pub fn adjust_decorations(&mut self, overrides: &DecorationOverrides, scale_factor: f64) {
+        let min_thickness = 1.0 / scale_factor.max(1.0);
+        let max_thickness = (self.line_height / 4.0).max(min_thickness);
+
+        if overrides.underline_thickness > 0.0 {
+            self.underline_position +=
+                (overrides.underline_thickness - self.underline_thickness) / 2.0;
+            self.underline_thickness = overrides.underline_thickness;
+        }
+        self.underline_thickness = self.underline_thickness.clamp(min_thickness, max_thickness);
+        self.underline_position += overrides.underline_offset;
+
+        self.undercurl_thickness = if overrides.undercurl_thickness > 0.0 {
+            overrides.undercurl_thickness
+        } else {
+            self.underline_thickness
+        }
+        .clamp(min_thickness, max_thickness);
+
+        let half = self.underline_thickness / 2.0;
+        self.underline_position = self
+            .underline_position
+            .min(self.line_height - half)
+            .max(half);
+    }
+
     // Translate the given grid coordinates into their actual pixel coordinates
     pub fn pixel_coords(&self, col: usize, row: usize) -> (f64, f64) {
         (self.char_width * col as f64, self.line_height * row as f64)
@@ -79,15 +134,85 @@ impl CellMetrics {
     }
 }
 
+/// Budget of the glyph cache until the `render-cache-mb` setting is applied
+const DEFAULT_CACHE_BUDGET: usize = 16 * 1024 * 1024;
+
+/// Shaped cell text, the font is the same for every cell until it changes
+type GlyphCache = LruCache<String, (pango::Font, pango::GlyphString)>;
+
+/// Fade of the dimming on focus changes
+const DIM_DURATION_MS: u32 = 200;
+
+/// Rough memory use of a shaped cell: glyph infos, log clusters and bookkeeping
+fn shaped_size(text: &str, glyphs: &pango::GlyphString) -> usize {
+    const PER_GLYPH: usize = 24;
+    const OVERHEAD: usize = 96;
+
+    OVERHEAD + text.len() + glyphs.num_glyphs() as usize * PER_GLYPH
+}
+
+/// Underline settings, zero values keep what the font reports
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DecorationOverrides {
+    pub underline_thickness: f64,
+    /// Pixels the underline is moved down from the font's position, negative moves it up
+    pub underline_offset: f64,
+    pub undercurl_thickness: f64,
+}
+
 mod imp {
     use super::*;
 
     #[derive(Debug, Default)]
     pub struct ExtLineGrid {
         pub grid: RefCell<Option<crate::nvim::ExtLineGrid>>,
-        pub nvim_tx: OnceCell<UnboundedSender<GtkToNvimEvent>>,
+        pub unfocused: Cell<bool>,
+        /// Background alpha laid over the grid while unfocused, 0 leaves it alone
+        pub dim_amount: Cell<f64>,
+        /// Animated from 0 (focused) to 1 (unfocused)
+        pub dim_progress: Cell<f64>,
+        pub dim_animation: OnceCell<adw::TimedAnimation>,
+        pub nvim_tx: OnceCell<NvimBridge>,
         pub context: OnceCell<pango::Context>,
-        pub cell_metrics: OnceCell<CellMetrics>,
+        /// Font from `guifont`, before `font_size` is applied
+        pub font: RefCell<Option<pango::FontDescription>>,
+        /// nvim set `guifont`, the default font no longer applies
+        pub guifont_set: Cell<bool>,
+        /// Overrides the size of `font`, in points
+        pub font_size: Cell<Option<f64>>,
+        /// Multiplies the font size, for presentation mode
+        pub font_scale: Cell<Option<f64>>,
+        pub cell_metrics: RefCell<CellMetrics>,
+        pub decoration_overrides: Cell<DecorationOverrides>,
+        pub glyph_cache: RefCell<GlyphCache>,
+        pub cursor_style: RefCell<CursorStyle>,
+        /// Picks the cursor shape from `cursor_style`
+        pub mode: Cell<Mode>,
+        /// Cursor line as last given to screen readers
+        pub accessible_line: RefCell<String>,
+        /// High contrast mode, text is adjusted to reach this ratio against its background
+        pub min_contrast: Cell<Option<f64>>,
+        /// Highlight id of the `CursorLine` group, see `hl_group_set`
+        pub cursor_line_highlight: Cell<Option<u64>>,
+        /// Lines scrolled off the top of the screen, oldest first
+        pub scrollback: RefCell<VecDeque<Line>>,
+        /// How many lines of `scrollback` are shown above the grid, 0 when following nvim
+        pub scrollback_offset: Cell<usize>,
+        pub selection: Cell<Option<Selection>>,
+        /// Find bar text and its matches on the displayed grid
+        pub search: RefCell<String>,
+        pub search_matches: RefCell<Vec<Match>>,
+        pub search_current: Cell<Option<usize>>,
+        /// Rows of windows without 'wrap' that continue off screen, faded out at the edges
+        pub overflow: RefCell<Vec<OverflowWindow>>,
+        /// Images placed from nvim that are on screen, drawn over the text
+        pub images: RefCell<Vec<ImagePlacement>>,
+        pub textures: RefCell<image::Textures>,
+        pub worker: OnceCell<prepare::Worker>,
+        /// Counts the grids given, to tell which one the worker's lines are of
+        pub generation: Cell<u64>,
+        /// Lines of `grid` from the worker, resolved in `snapshot` while they're not ready
+        pub prepared: RefCell<Option<prepare::Prepared>>,
     }
 
     #[glib::object_subclass]
@@ -95,37 +220,165 @@ mod imp {
         const NAME: &'static str = "ExtLineGrid";
         type Type = super::ExtLineGrid;
         type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_accessible_role(gtk::AccessibleRole::TextBox);
+        }
     }
 
     impl ObjectImpl for ExtLineGrid {
         fn constructed(&self) {
             self.obj().set_widget_name("ext_line_grid");
+            // Focusable so screen readers follow it, keys are still handled by the window
+            self.obj().set_focusable(true);
+            self.obj().update_property(&[
+                gtk::accessible::Property::MultiLine(true),
+                gtk::accessible::Property::Label(&gettext("Editor")),
+            ]);
 
-            let desc = pango::FontDescription::from_string("Source Code Pro 11");
-
+            // nvim lays out the cells, also of right-to-left text (see 'rightleft'), mirroring
+            // them again in right-to-left locales would garble the text
+            self.obj().set_direction(gtk::TextDirection::Ltr);
             let context = self.obj().create_pango_context();
-            context.set_font_description(Some(&desc));
+            context.set_base_dir(pango::Direction::Ltr);
+            self.context.set(context).unwrap();
 
-            let font_metrics = context.metrics(Some(&desc), None);
+            self.glyph_cache
+                .replace(GlyphCache::new(DEFAULT_CACHE_BUDGET));
+            self.set_font(&pango::FontDescription::from_string(default_font::GENERIC));
 
-            self.cell_metrics
-                .set(CellMetrics::new(&font_metrics, 0))
-                .unwrap();
-            self.context.set(context).unwrap();
+            self.obj().connect_scale_factor_notify(|grid| {
+                grid.imp().reload_font();
+                grid.queue_draw();
+            });
+
+            let obj = self.obj();
+            let target =
+                adw::CallbackAnimationTarget::new(glib::clone!(@weak obj => move |value| {
+                    obj.imp().dim_progress.set(value);
+                    obj.queue_draw();
+                }));
+            let animation = adw::TimedAnimation::new(&*obj, 0.0, 1.0, DIM_DURATION_MS, target);
+            self.dim_animation.set(animation).unwrap();
+
+            // Usually done before the frame the grid is drawn in, later ones still save the
+            // redraws of the cursor, selection and dimming
+            let worker = prepare::Worker::spawn(glib::clone!(@weak obj => move |prepared| {
+                let imp = obj.imp();
+                if prepared.generation == imp.generation.get() {
+                    imp.prepared.replace(Some(prepared));
+                }
+            }));
+            self.worker.set(worker).ok().unwrap();
+        }
+    }
+
+    impl ExtLineGrid {
+        /// The grid as currently shown, taking the scrollback into account
+        pub fn displayed_grid(&self) -> Option<crate::nvim::ExtLineGrid> {
+            let grid = self.grid.borrow();
+            let grid = grid.as_ref()?;
+
+            let offset = self.scrollback_offset.get();
+            if offset == 0 {
+                return Some(grid.clone());
+            }
+
+            let mut scrollback = self.scrollback.borrow_mut();
+            Some(grid.with_history(scrollback.make_contiguous(), offset))
+        }
+
+        /// Have the worker resolve the lines of `grid`, after it or the highlights changed
+        pub fn prepare_lines(&self) {
+            self.generation.set(self.generation.get() + 1);
+            self.prepared.replace(None);
+
+            let grid = self.grid.borrow();
+            let (Some(grid), Some(worker)) = (grid.as_ref(), self.worker.get()) else {
+                return;
+            };
+            worker.prepare(
+                self.generation.get(),
+                grid.buffer().to_vec(),
+                self.highlights(grid),
+            );
+        }
+
+        fn highlights(&self, grid: &crate::nvim::ExtLineGrid) -> Highlights {
+            Highlights {
+                style: grid.style.clone(),
+                default_colors: grid.default_colors.clone(),
+                min_contrast: self.min_contrast.get(),
+            }
+        }
+
+        /// Find the search text again after the displayed grid changed
+        pub fn refresh_search(&self) {
+            let search = self.search.borrow();
+            let matches = match self.displayed_grid() {
+                Some(grid) if !search.is_empty() => search::find(grid.buffer(), &search),
+                _ => Vec::new(),
+            };
+
+            let current = self
+                .search_current
+                .get()
+                .filter(|_| !matches.is_empty())
+                .map(|current| current.min(matches.len() - 1));
+            self.search_current.set(current);
+            *self.search_matches.borrow_mut() = matches;
+        }
+
+        pub fn set_font(&self, desc: &pango::FontDescription) {
+            *self.font.borrow_mut() = Some(desc.clone());
+
+            let mut desc = desc.clone();
+            if self.font_size.get().is_some() || self.font_scale.get().is_some() {
+                let size = self
+                    .font_size
+                    .get()
+                    .unwrap_or_else(|| f64::from(desc.size()) / f64::from(pango::SCALE));
+                let scale = self.font_scale.get().unwrap_or(1.0);
+                desc.set_size((size * scale * f64::from(pango::SCALE)) as i32);
+            }
+            let desc = &desc;
+
+            let context = self.context.get().unwrap();
+            context.set_font_description(Some(desc));
+
+            self.glyph_cache.borrow_mut().clear();
+
+            let font_metrics = context.metrics(Some(desc), None);
+            let mut cell_metrics = CellMetrics::new(&font_metrics, 0);
+            cell_metrics.adjust_decorations(
+                &self.decoration_overrides.get(),
+                self.obj().scale_factor().into(),
+            );
+            *self.cell_metrics.borrow_mut() = cell_metrics;
+        }
+
+        /// Compute the metrics again, e.g. after the scale factor changed
+        pub fn reload_font(&self) {
+            let desc = self.font.borrow().clone();
+            if let Some(desc) = desc {
+                self.set_font(&desc);
+            }
         }
     }
 
     impl WidgetImpl for ExtLineGrid {
         fn snapshot(&self, snapshot_in: &gtk::Snapshot) {
+            let _span = crate::profiling::span("grid snapshot");
+
             let width = self.obj().width();
             let height = self.obj().height();
 
             let context = self.context.get().unwrap();
-            let cell_metrics = self.cell_metrics.get().unwrap();
+            let cell_metrics = self.cell_metrics.borrow();
 
             let grid = self.grid.borrow();
 
-            if let Some(tx) = self.nvim_tx.get() {
+            if let Some(nvim) = self.nvim_tx.get() {
                 let width = width as f64;
                 let height = height as f64;
 
@@ -135,179 +388,727 @@ mod imp {
                 let width = width.trunc() as u64;
                 let height = height.trunc() as u64;
 
-                if let Some(grid) = grid.as_ref() {
-                    if width as usize != grid.columns() || height as usize != grid.rows() {
-                        tx.send(GtkToNvimEvent::Resized { width, height }).unwrap();
-                    }
-                } else {
-                    tx.send(GtkToNvimEvent::Resized { width, height }).unwrap();
+                let resized = grid.as_ref().is_none_or(|grid| {
+                    width as usize != grid.columns() || height as usize != grid.rows()
+                });
+
+                if resized {
+                    let nvim = nvim.clone();
+                    bridge::spawn_local(
+                        "Resizing the grid",
+                        async move { nvim.resize(width, height).await },
+                        |()| {},
+                    );
                 }
             }
 
             let Some(grid) = grid.as_ref() else { return; };
 
+            let offset = self.scrollback_offset.get();
+            let scrolled_back = offset > 0;
+            let history;
+            let grid = if scrolled_back {
+                let mut scrollback = self.scrollback.borrow_mut();
+                history = grid.with_history(scrollback.make_contiguous(), offset);
+                &history
+            } else {
+                grid
+            };
+
             let default_colors = grid.default_colors.clone();
 
-            snapshot_bg(grid, cell_metrics, snapshot_in, &default_colors);
-            snapshot_fg(grid, cell_metrics, snapshot_in, &default_colors, context);
+            let visible = self.obj().visible_cells(grid);
+            let mut glyph_cache = self.glyph_cache.borrow_mut();
 
-            let pos = grid.cursor_position();
+            // The worker only has nvim's own rows
+            let prepared = self.prepared.borrow();
+            let resolved;
+            let lines = match prepared.as_ref() {
+                Some(prepared) if !scrolled_back => &prepared.lines,
+                _ => {
+                    let _span = crate::profiling::span("prepare visible lines");
+                    let highlights = self.highlights(grid);
+                    resolved = grid
+                        .buffer()
+                        .iter()
+                        .enumerate()
+                        .map(|(row, line)| {
+                            Arc::new(if visible.rows.contains(&row) {
+                                prepare::prepare_line(line.columns(), &highlights)
+                            } else {
+                                PreparedLine::default()
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    &resolved
+                }
+            };
 
-            let (x, y) = self
-                .cell_metrics
-                .get()
-                .unwrap()
-                .pixel_coords(pos.column, pos.row);
+            snapshot_bg(lines, &cell_metrics, snapshot_in, &visible);
+            if !scrolled_back {
+                snapshot_cursor_line(
+                    grid,
+                    &cell_metrics,
+                    snapshot_in,
+                    &default_colors,
+                    &self.cursor_style.borrow(),
+                    self.cursor_line_highlight.get(),
+                );
+            }
+            snapshot_fg(
+                grid,
+                lines,
+                &cell_metrics,
+                snapshot_in,
+                &default_colors,
+                context,
+                &mut glyph_cache,
+                &visible,
+            );
+            snapshot_decorations(lines, &cell_metrics, snapshot_in, &visible);
 
-            snapshot_in.append_color(
-                &gdk::RGBA::new(1.0, 1.0, 1.0, 0.1),
+            if let Some(selection) = self.selection.get() {
+                snapshot_selection(
+                    grid,
+                    &cell_metrics,
+                    snapshot_in,
+                    &default_colors,
+                    &selection,
+                );
+            }
+
+            snapshot_matches(
+                &cell_metrics,
+                snapshot_in,
+                &default_colors,
+                &self.search_matches.borrow(),
+                self.search_current.get(),
+            );
+
+            // Like the cursor, images and what continues off screen are only known for nvim's
+            // own rows
+            if !scrolled_back {
+                image::snapshot_images(
+                    &cell_metrics,
+                    snapshot_in,
+                    &self.images.borrow(),
+                    &self.textures.borrow(),
+                );
+                snapshot_overflow(
+                    &cell_metrics,
+                    snapshot_in,
+                    &default_colors,
+                    &self.overflow.borrow(),
+                );
+            }
+
+            // nvim's cursor is not part of the history being looked at
+            if !scrolled_back {
+                snapshot_cursor(
+                    grid,
+                    &cell_metrics,
+                    snapshot_in,
+                    &default_colors,
+                    context,
+                    &mut glyph_cache,
+                    &self.cursor_style.borrow(),
+                    self.mode.get(),
+                    !self.unfocused.get(),
+                );
+            }
+
+            let dim = self.dim_amount.get() * self.dim_progress.get();
+            if dim > 0.0 {
+                let bg = default_colors.background.unwrap();
+                snapshot_in.append_color(
+                    &gdk::RGBA::new(bg.r, bg.g, bg.b, dim as f32),
+                    &graphene::Rect::new(0.0, 0.0, width as f32, height as f32),
+                );
+            }
+        }
+    }
+    impl BinImpl for ExtLineGrid {}
+}
+
+/// Range of cells intersecting `start..end` pixels, for `count` cells of `cell_size` pixels
+fn visible_range(start: f64, end: f64, cell_size: f64, count: usize) -> Range<usize> {
+    if end <= start || cell_size <= 0.0 {
+        return 0..0;
+    }
+
+    let first = (start.max(0.0) / cell_size).floor() as usize;
+    let last = (end / cell_size).ceil().max(0.0) as usize;
+
+    first.min(count)..last.min(count)
+}
+
+/// Part of the grid that is actually on screen, when embedded in a scrolled container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisibleCells {
+    pub rows: Range<usize>,
+    pub columns: Range<usize>,
+}
+
+impl VisibleCells {
+    fn lines<'a>(
+        &'a self,
+        grid: &'a crate::nvim::ExtLineGrid,
+    ) -> impl Iterator<Item = (usize, &'a [crate::nvim::event::GridLineCell])> {
+        grid.buffer()
+            .iter()
+            .enumerate()
+            .skip(self.rows.start)
+            .take(self.rows.len())
+            .map(|(y, line)| {
+                let columns = line.columns();
+                let end = self.columns.end.min(columns.len());
+                let start = self.columns.start.min(end);
+                (y, &columns[start..end])
+            })
+    }
+
+    fn prepared_lines<'a>(
+        &'a self,
+        lines: &'a [Arc<PreparedLine>],
+    ) -> impl Iterator<Item = (usize, &'a PreparedLine)> {
+        lines
+            .iter()
+            .enumerate()
+            .skip(self.rows.start)
+            .take(self.rows.len())
+            .map(|(y, line)| (y, &**line))
+    }
+}
+
+/// Part of the cells `start..start + len` that is visible
+fn visible_run(visible: &VisibleCells, start: usize, len: usize) -> Option<Range<usize>> {
+    let end = (start + len).min(visible.columns.end);
+    let start = start.max(visible.columns.start);
+    (start < end).then_some(start..end)
+}
+
+fn snapshot_bg(
+    lines: &[Arc<PreparedLine>],
+    cell_metrics: &CellMetrics,
+    snapshot: &gtk::Snapshot,
+    visible: &VisibleCells,
+) {
+    for (y, line) in visible.prepared_lines(lines) {
+        let y = y as f32 * cell_metrics.line_height as f32;
+
+        for background in &line.backgrounds {
+            let Some(run) = visible_run(visible, background.start, background.len) else {
+                continue;
+            };
+            let color = background.color;
+
+            snapshot.append_color(
+                &gdk::RGBA::new(color.r, color.g, color.b, 1.0),
                 &graphene::Rect::new(
-                    x as f32,
-                    y as f32,
-                    cell_metrics.char_width as f32,
+                    run.start as f32 * cell_metrics.char_width as f32,
+                    y,
+                    cell_metrics.char_width as f32 * run.len() as f32,
                     cell_metrics.line_height as f32,
                 ),
             );
         }
     }
-    impl BinImpl for ExtLineGrid {}
 }
 
-fn snapshot_bg(
+#[allow(clippy::too_many_arguments)]
+fn snapshot_fg(
     grid: &crate::nvim::ExtLineGrid,
+    lines: &[Arc<PreparedLine>],
     cell_metrics: &CellMetrics,
     snapshot: &gtk::Snapshot,
     default_colors: &Colors,
+    context: &pango::Context,
+    glyph_cache: &mut GlyphCache,
+    visible: &VisibleCells,
 ) {
-    for (y, line) in grid.buffer().iter().enumerate() {
+    for (y, line) in visible.lines(grid) {
+        let foregrounds = &lines[y].foregrounds;
         let y = y as f32 * cell_metrics.line_height as f32;
 
-        struct RectangleInProggres {
-            x: f32,
-            len: usize,
-            highlight_id: Option<u64>,
-        }
-
-        let mut rectangle_in_proggres = None::<RectangleInProggres>;
-        let mut last_hl = None;
-
-        for (x, cell) in line.columns().iter().enumerate() {
-            let x = x as f32 * cell_metrics.char_width as f32;
-
-            let highlight_id = cell.highlight_id.or(last_hl);
-
-            if let Some(rect) = rectangle_in_proggres.as_mut() {
-                if rect.highlight_id == highlight_id {
-                    rect.len += 1;
-                    continue;
-                } else {
-                    let color = rect
-                        .highlight_id
-                        .and_then(|id| grid.style.get(&id))
-                        .map(|style| style.background(default_colors))
-                        .unwrap_or(default_colors.background.unwrap());
-
-                    snapshot.append_color(
-                        &gdk::RGBA::new(color.r, color.g, color.b, 1.0),
-                        &graphene::Rect::new(
-                            rect.x,
-                            y,
-                            cell_metrics.char_width as f32 * rect.len as f32,
-                            cell_metrics.line_height as f32,
-                        ),
-                    );
-                }
-            }
+        for (column, cell) in line.iter().enumerate() {
+            let column = visible.columns.start + column;
+            let x = column as f32 * cell_metrics.char_width as f32;
 
-            rectangle_in_proggres = Some(RectangleInProggres {
-                x,
-                len: 1,
-                highlight_id: cell.highlight_id,
-            });
+            let color = foregrounds
+                .get(column)
+                .copied()
+                .unwrap_or(default_colors.foreground.unwrap());
+
+            let width = cell_width(line, column - visible.columns.start) as f32
+                * cell_metrics.char_width as f32;
+            let bounds = graphene::Rect::new(x, y, width, cell_metrics.line_height as f32);
+            snapshot_text(
+                snapshot,
+                context,
+                glyph_cache,
+                cell_metrics,
+                &cell.text,
+                color,
+                &bounds,
+            );
+        }
+    }
+}
+
+/// Shapes the grapheme of a cell, or reuses it from the cache
+fn shape_text(
+    context: &pango::Context,
+    glyph_cache: &mut GlyphCache,
+    text: &str,
+) -> Option<(pango::Font, pango::GlyphString)> {
+    if let Some(shaped) = glyph_cache.get(text) {
+        return Some(shaped.clone());
+    }
+
+    let items = pango::itemize(
+        context,
+        text,
+        0,
+        text.len() as i32,
+        &pango::AttrList::new(),
+        None,
+    );
+
+    // The whole cluster is shaped as one item, so ZWJ sequences, skin tone modifiers
+    // and flags become a single glyph instead of several drawn on top of each other
+    let item = items.first()?;
+    let analysis = item.analysis();
+    let font = analysis.font();
+
+    let mut glyphs = pango::GlyphString::new();
+    pango::shape(text, analysis, &mut glyphs);
+
+    let size = shaped_size(text, &glyphs);
+    glyph_cache.insert(text.to_string(), (font.clone(), glyphs.clone()), size);
+
+    Some((font, glyphs))
+}
+
+/// Draws the grapheme of a cell inside `bounds`, which spans both cells of double width ones
+fn snapshot_text(
+    snapshot: &gtk::Snapshot,
+    context: &pango::Context,
+    glyph_cache: &mut GlyphCache,
+    cell_metrics: &CellMetrics,
+    text: &str,
+    color: Color,
+    bounds: &graphene::Rect,
+) {
+    let color = gdk::RGBA::new(color.r, color.g, color.b, 1.0);
+
+    let Some((font, glyphs)) = shape_text(context, glyph_cache, text) else { return; };
+
+    let glyph_width = f64::from(glyphs.width()) / f64::from(pango::SCALE);
+    let (offset, scale) = fit_glyphs(glyph_width, f64::from(bounds.width()));
+
+    let Some(render_node) = gsk::TextNode::new(
+        &font,
+        &glyphs,
+        &color,
+        &graphene::Point::new(0.0, cell_metrics.ascent as f32),
+    ) else {
+        return;
+    };
+
+    // Scaled glyphs stay vertically centered in the row
+    let dy = bounds.height() * (1.0 - scale as f32) / 2.0;
+
+    snapshot.save();
+    snapshot.translate(&graphene::Point::new(
+        bounds.x() + offset as f32,
+        bounds.y() + dy,
+    ));
+    if scale != 1.0 {
+        snapshot.scale(scale as f32, scale as f32);
+    }
+    snapshot.append_node(&render_node);
+    snapshot.restore();
+}
 
-            if cell.highlight_id.is_some() {
-                last_hl = cell.highlight_id;
+/// Horizontal offset and scale fitting glyphs `glyph_width` wide into a cell box,
+/// narrower glyphs are centered and wider ones (e.g. color emoji) shrunk
+fn fit_glyphs(glyph_width: f64, box_width: f64) -> (f64, f64) {
+    // Rounding of the cell width makes most glyphs a fraction of a pixel off
+    const TOLERANCE: f64 = 1.0;
+
+    if glyph_width <= 0.0 || (glyph_width - box_width).abs() <= TOLERANCE {
+        (0.0, 1.0)
+    } else if glyph_width > box_width {
+        (0.0, box_width / glyph_width)
+    } else {
+        ((box_width - glyph_width) / 2.0, 1.0)
+    }
+}
+
+/// Translucent foreground colored rectangles over the selected cells
+fn snapshot_selection(
+    grid: &crate::nvim::ExtLineGrid,
+    cell_metrics: &CellMetrics,
+    snapshot: &gtk::Snapshot,
+    default_colors: &Colors,
+    selection: &Selection,
+) {
+    let color = default_colors.foreground.unwrap();
+    let color = gdk::RGBA::new(color.r, color.g, color.b, 0.3);
+
+    for row in selection.rows().take_while(|row| *row < grid.rows()) {
+        let columns = selection.columns(row, grid.columns());
+        if columns.is_empty() {
+            continue;
+        }
+
+        let (x, y) = cell_metrics.pixel_coords(columns.start, row);
+        snapshot.append_color(
+            &color,
+            &graphene::Rect::new(
+                x as f32,
+                y as f32,
+                (cell_metrics.char_width * columns.len() as f64) as f32,
+                cell_metrics.line_height as f32,
+            ),
+        );
+    }
+}
+
+/// Find bar matches, the current one outlined
+fn snapshot_matches(
+    cell_metrics: &CellMetrics,
+    snapshot: &gtk::Snapshot,
+    default_colors: &Colors,
+    matches: &[Match],
+    current: Option<usize>,
+) {
+    let color = default_colors.foreground.unwrap();
+    let fill = gdk::RGBA::new(color.r, color.g, color.b, 0.2);
+    let border = gdk::RGBA::new(color.r, color.g, color.b, 0.8);
+
+    for (i, m) in matches.iter().enumerate() {
+        let (x, y) = cell_metrics.pixel_coords(m.columns.start, m.row);
+        let rect = graphene::Rect::new(
+            x as f32,
+            y as f32,
+            (cell_metrics.char_width * m.columns.len() as f64) as f32,
+            cell_metrics.line_height as f32,
+        );
+
+        snapshot.append_color(&fill, &rect);
+        if current == Some(i) {
+            snapshot.append_border(
+                &gsk::RoundedRect::from_rect(rect, 0.0),
+                &[1.0; 4],
+                &[border; 4],
+            );
+        }
+    }
+}
+
+/// Translucent tint across the cursor row and column, drawn under the text
+/// Gradients towards the background over the edges of rows that continue off screen
+fn snapshot_overflow(
+    cell_metrics: &CellMetrics,
+    snapshot: &gtk::Snapshot,
+    default_colors: &Colors,
+    windows: &[OverflowWindow],
+) {
+    let bg = default_colors.background.unwrap();
+    let stops = [
+        gsk::ColorStop::new(0.0, gdk::RGBA::new(bg.r, bg.g, bg.b, 0.9)),
+        gsk::ColorStop::new(1.0, gdk::RGBA::new(bg.r, bg.g, bg.b, 0.0)),
+    ];
+
+    let height = cell_metrics.line_height as f32;
+    // From mostly covered at `edge` to clear at `clear`
+    let fade = |edge: f64, clear: f64, y: f32| {
+        snapshot.append_linear_gradient(
+            &graphene::Rect::new(
+                edge.min(clear) as f32,
+                y,
+                (edge - clear).abs() as f32,
+                height,
+            ),
+            &graphene::Point::new(edge as f32, y),
+            &graphene::Point::new(clear as f32, y),
+            &stops,
+        );
+    };
+
+    for window in windows {
+        let fade_width = 2.0_f64.min(window.width as f64 / 4.0) * cell_metrics.char_width;
+        let (left, top) = cell_metrics.pixel_coords(window.col, window.row);
+        let right = left + window.width as f64 * cell_metrics.char_width;
+
+        for (i, row) in window.rows.iter().enumerate() {
+            let y = (top + i as f64 * cell_metrics.line_height) as f32;
+            if row.left {
+                fade(left, left + fade_width, y);
+            }
+            if row.right {
+                fade(right, right - fade_width, y);
             }
         }
+    }
+}
+
+fn snapshot_cursor_line(
+    grid: &crate::nvim::ExtLineGrid,
+    cell_metrics: &CellMetrics,
+    snapshot: &gtk::Snapshot,
+    default_colors: &Colors,
+    cursor_style: &CursorStyle,
+    highlight_id: Option<u64>,
+) {
+    if !cursor_style.line && !cursor_style.column {
+        return;
+    }
 
-        if let Some(rect) = rectangle_in_proggres {
-            let color = rect
-                .highlight_id
-                .or(last_hl)
-                .and_then(|id| grid.style.get(&id))
-                .map(|style| style.background(default_colors))
-                .unwrap_or(default_colors.background.unwrap());
+    // The default CursorLine of some colorschemes only underlines, fall back to a faint foreground
+    let color = highlight_id
+        .and_then(|id| grid.style.get(&id))
+        .filter(|style| style.colors.background.is_some())
+        .map(|style| {
+            let color = style.background(default_colors);
+            gdk::RGBA::new(color.r, color.g, color.b, 0.5)
+        })
+        .unwrap_or_else(|| {
+            let color = default_colors.foreground.unwrap();
+            gdk::RGBA::new(color.r, color.g, color.b, 0.08)
+        });
 
+    let pos = grid.cursor_position();
+    let (x, y) = cell_metrics.pixel_coords(pos.column, pos.row);
+
+    if cursor_style.line {
+        snapshot.append_color(
+            &color,
+            &graphene::Rect::new(
+                0.0,
+                y as f32,
+                (cell_metrics.char_width * grid.columns() as f64) as f32,
+                cell_metrics.line_height as f32,
+            ),
+        );
+    }
+
+    if cursor_style.column {
+        // With the line tinted too, the cursor cell is left out so it isn't tinted twice
+        let spans = if cursor_style.line {
+            [0..pos.row, pos.row + 1..grid.rows()]
+        } else {
+            [0..grid.rows(), 0..0]
+        };
+        for rows in spans.into_iter().filter(|rows| !rows.is_empty()) {
             snapshot.append_color(
-                &gdk::RGBA::new(color.r, color.g, color.b, 1.0),
+                &color,
                 &graphene::Rect::new(
-                    rect.x,
-                    y,
-                    cell_metrics.char_width as f32 * rect.len as f32,
-                    cell_metrics.line_height as f32,
+                    x as f32,
+                    (rows.start as f64 * cell_metrics.line_height) as f32,
+                    cell_metrics.char_width as f32,
+                    (rows.len() as f64 * cell_metrics.line_height) as f32,
                 ),
             );
         }
     }
 }
 
-fn snapshot_fg(
+/// Cursor drawn in reverse video of the cell under it, or in the color of `cursor_style`
+#[allow(clippy::too_many_arguments)]
+fn snapshot_cursor(
     grid: &crate::nvim::ExtLineGrid,
     cell_metrics: &CellMetrics,
     snapshot: &gtk::Snapshot,
     default_colors: &Colors,
     context: &pango::Context,
+    glyph_cache: &mut GlyphCache,
+    cursor_style: &CursorStyle,
+    mode: Mode,
+    focused: bool,
 ) {
-    let mut last_hl = None;
-    for (y, line) in grid.buffer().iter().enumerate() {
-        let y = y as f32 * cell_metrics.line_height as f32;
+    let pos = grid.cursor_position();
+    let Some(cell) = grid
+        .buffer()
+        .get(pos.row)
+        .and_then(|line| line.columns().get(pos.column))
+    else {
+        return;
+    };
 
-        for (x, cell) in line.columns().iter().enumerate() {
-            let x = x as f32 * cell_metrics.char_width as f32;
+    let style = cell
+        .highlight_id
+        .and_then(|id| grid.style.get(&id))
+        .cloned()
+        .unwrap_or_default();
+    let (cursor_bg, cursor_fg) = match cursor_style.color {
+        Some(color) => (color, style.background(default_colors)),
+        None => style.cursor_colors(default_colors),
+    };
 
-            let line = &cell.text;
+    let cells = grid
+        .buffer()
+        .get(pos.row)
+        .map_or(1, |line| cell_width(line.columns(), pos.column));
 
-            let s = &line;
-            let items =
-                pango::itemize(context, s, 0, s.len() as i32, &pango::AttrList::new(), None);
-            let mut glyphs = pango::GlyphString::new();
+    let (x, y) = cell_metrics.pixel_coords(pos.column, pos.row);
+    let cell_rect = (
+        x,
+        y,
+        cell_metrics.char_width * cells as f64,
+        cell_metrics.line_height,
+    );
+    let (x, y, width, height) = cursor_style.rect(mode, cell_rect);
+    let rect = graphene::Rect::new(x as f32, y as f32, width as f32, height as f32);
 
-            for item in items {
-                let analysis = item.analysis();
-                let font = analysis.font();
-                let offset = item.offset() as usize;
-                let length = item.length() as usize;
+    if cursor_style.shape(mode) != CursorShape::Block {
+        // Bars leave the text alone, they only fade out without focus
+        let alpha = if focused { 1.0 } else { 0.5 };
+        snapshot.append_color(
+            &gdk::RGBA::new(cursor_bg.r, cursor_bg.g, cursor_bg.b, alpha),
+            &rect,
+        );
+    } else if focused {
+        snapshot.append_color(
+            &gdk::RGBA::new(cursor_bg.r, cursor_bg.g, cursor_bg.b, 1.0),
+            &rect,
+        );
+        snapshot_text(
+            snapshot,
+            context,
+            glyph_cache,
+            cell_metrics,
+            &cell.text,
+            cursor_fg,
+            &rect,
+        );
+    } else {
+        // Hollow cursor, like terminals do when they lose focus
+        let color = gdk::RGBA::new(cursor_bg.r, cursor_bg.g, cursor_bg.b, 0.5);
+        snapshot.append_border(
+            &gsk::RoundedRect::from_rect(rect, 0.0),
+            &[1.0; 4],
+            &[color; 4],
+        );
+    }
+}
 
-                if let Some(line_str) = s.get(offset..offset + length) {
-                    pango::shape(line_str, analysis, &mut glyphs);
-                }
+/// Underlines are drawn per run of cells sharing a highlight, so dashes and
+/// curls stay continuous across cell boundaries
+fn snapshot_decorations(
+    lines: &[Arc<PreparedLine>],
+    cell_metrics: &CellMetrics,
+    snapshot: &gtk::Snapshot,
+    visible: &VisibleCells,
+) {
+    for (y, line) in visible.prepared_lines(lines) {
+        let y = y as f64 * cell_metrics.line_height;
 
-                let ascent = cell_metrics.ascent;
+        for underline in &line.underlines {
+            let Some(run) = visible_run(visible, underline.start, underline.len) else {
+                continue;
+            };
+            let color = underline.color;
+            let color = gdk::RGBA::new(color.r, color.g, color.b, 1.0);
 
-                let color = {
-                    let color = cell
-                        .highlight_id
-                        .or(last_hl)
-                        .and_then(|id| grid.style.get(&id))
-                        .map(|style| style.foreground(default_colors))
-                        .unwrap_or(default_colors.foreground.unwrap());
-                    gdk::RGBA::new(color.r, color.g, color.b, 1.0)
-                };
+            let x = run.start as f64 * cell_metrics.char_width;
+            let width = run.len() as f64 * cell_metrics.char_width;
 
-                if cell.highlight_id.is_some() {
-                    last_hl = cell.highlight_id;
-                }
+            snapshot_underline(snapshot, cell_metrics, underline.style, &color, x, y, width);
+        }
+    }
+}
+
+fn snapshot_underline(
+    snapshot: &gtk::Snapshot,
+    cell_metrics: &CellMetrics,
+    underline: UnderlineStyle,
+    color: &gdk::RGBA,
+    x: f64,
+    y: f64,
+    width: f64,
+) {
+    let thickness = if underline == UnderlineStyle::UnderCurl {
+        cell_metrics.undercurl_thickness
+    } else {
+        cell_metrics.underline_thickness
+    };
+    // Center of the underline, relative to the top of the row
+    let center = y + cell_metrics.underline_position;
+    let top = center - thickness / 2.0;
+
+    let line = |top: f64, x: f64, width: f64| {
+        snapshot.append_color(
+            color,
+            &graphene::Rect::new(x as f32, top as f32, width as f32, thickness as f32),
+        );
+    };
+
+    match underline {
+        UnderlineStyle::Underline => line(top, x, width),
+        UnderlineStyle::UnderDouble => {
+            line(top - thickness, x, width);
+            line(top + thickness, x, width);
+        }
+        UnderlineStyle::UnderDash => {
+            let dash = thickness * 4.0;
+            let step = dash + thickness * 2.0;
+
+            let mut dx = 0.0;
+            while dx < width {
+                line(top, x + dx, dash.min(width - dx));
+                dx += step;
+            }
+        }
+        UnderlineStyle::UnderDot => {
+            let step = thickness * 2.0;
 
-                let render_node = gsk::TextNode::new(
-                    &font,
-                    &glyphs,
-                    &color,
-                    &graphene::Point::new(x, y + ascent as f32),
+            let mut dx = 0.0;
+            while dx < width {
+                line(top, x + dx, thickness.min(width - dx));
+                dx += step;
+            }
+        }
+        UnderlineStyle::UnderCurl => {
+            let amplitude = thickness * 1.5;
+            let half_wave = amplitude * 2.0;
+
+            let bounds = graphene::Rect::new(
+                x as f32,
+                (center - amplitude - thickness) as f32,
+                width as f32,
+                (amplitude + thickness) as f32 * 2.0,
+            );
+            let cr = snapshot.append_cairo(&bounds);
+
+            cr.set_source_rgba(
+                color.red().into(),
+                color.green().into(),
+                color.blue().into(),
+                color.alpha().into(),
+            );
+            cr.set_line_width(thickness);
+            cr.move_to(x, center);
+
+            let mut dx = 0.0;
+            let mut direction = -1.0;
+            while dx < width {
+                let peak = center + amplitude * direction;
+                cr.curve_to(
+                    x + dx + half_wave / 2.0,
+                    peak,
+                    x + dx + half_wave / 2.0,
+                    peak,
+                    x + dx + half_wave,
+                    center,
                 );
+                dx += half_wave;
+                direction = -direction;
+            }
 
-                if let Some(render_node) = render_node {
-                    snapshot.append_node(&render_node);
-                }
+            if let Err(err) = cr.stroke() {
+                log::error!("Failed to draw undercurl: {err}");
             }
         }
     }
@@ -315,7 +1116,8 @@ fn snapshot_fg(
 
 glib::wrapper! {
     pub struct ExtLineGrid(ObjectSubclass<imp::ExtLineGrid>)
-        @extends gtk::Widget;
+        @extends gtk::Widget,
+        @implements gtk::Accessible;
 }
 
 impl ExtLineGrid {
@@ -324,20 +1126,537 @@ impl ExtLineGrid {
     //     this
     // }
 
-    pub fn set_nvim_tx(&self, tx: UnboundedSender<GtkToNvimEvent>) {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
         self.imp().nvim_tx.set(tx).unwrap();
     }
 
     pub fn set_grid(&self, grid: crate::nvim::ExtLineGrid) {
         *self.imp().grid.borrow_mut() = Some(grid);
+        self.imp().prepare_lines();
+        self.imp().refresh_search();
+        self.update_accessible_line();
         self.queue_draw();
     }
 
+    /// Screen readers get the cursor line as description, announced when it changes
+    fn update_accessible_line(&self) {
+        let imp = self.imp();
+        let line = imp.grid.borrow().as_ref().and_then(|grid| {
+            let row = grid.cursor_position().row;
+            grid.buffer().get(row).map(Line::text)
+        });
+        let line = line.unwrap_or_default();
+
+        if *imp.accessible_line.borrow() != line {
+            self.update_property(&[gtk::accessible::Property::Description(&line)]);
+            imp.accessible_line.replace(line);
+        }
+    }
+
+    /// Keep lines that scrolled off the screen, dropping the oldest beyond `limit`
+    pub fn push_scrollback(&self, lines: Vec<Line>, limit: usize) {
+        let imp = self.imp();
+        let mut scrollback = imp.scrollback.borrow_mut();
+
+        // Stay on the same history lines while new output arrives
+        if imp.scrollback_offset.get() > 0 {
+            imp.scrollback_offset
+                .set(imp.scrollback_offset.get() + lines.len());
+        }
+
+        scrollback.extend(lines);
+        while scrollback.len() > limit {
+            scrollback.pop_front();
+        }
+
+        let offset = imp.scrollback_offset.get().min(scrollback.len());
+        imp.scrollback_offset.set(offset);
+    }
+
+    /// Move the view `delta` lines back in history, negative values move towards nvim's screen
+    pub fn scroll_history(&self, delta: isize) {
+        let imp = self.imp();
+        let len = imp.scrollback.borrow().len();
+
+        let offset = imp
+            .scrollback_offset
+            .get()
+            .saturating_add_signed(delta)
+            .min(len);
+        if offset != imp.scrollback_offset.get() {
+            imp.scrollback_offset.set(offset);
+            imp.selection.set(None);
+            imp.refresh_search();
+            self.queue_draw();
+        }
+    }
+
+    /// Go back to showing nvim's screen
+    pub fn reset_scrollback(&self) {
+        if self.is_scrolled_back() {
+            self.imp().scrollback_offset.set(0);
+            self.imp().refresh_search();
+            self.queue_draw();
+        }
+    }
+
+    pub fn is_scrolled_back(&self) -> bool {
+        self.imp().scrollback_offset.get() > 0
+    }
+
+    /// Start a GUI side selection at the given widget coordinates
+    pub fn start_selection(&self, x: f64, y: f64, block: bool) {
+        let (col, row) = self.cell_metrics().cell_cords(x, y);
+        self.imp()
+            .selection
+            .set(Some(Selection::new((col as usize, row as usize), block)));
+        self.queue_draw();
+    }
+
+    pub fn update_selection(&self, x: f64, y: f64) {
+        let Some(mut selection) = self.imp().selection.get() else { return; };
+
+        let (col, row) = self.cell_metrics().cell_cords(x.max(0.0), y.max(0.0));
+        selection.head = (col as usize, row as usize);
+        self.imp().selection.set(Some(selection));
+        self.queue_draw();
+    }
+
+    pub fn clear_selection(&self) {
+        if self.imp().selection.take().is_some() {
+            self.queue_draw();
+        }
+    }
+
+    /// Text of the GUI side selection, as currently displayed
+    pub fn selection_text(&self) -> Option<String> {
+        let selection = self.imp().selection.get()?;
+        let grid = self.imp().displayed_grid()?;
+        Some(selection.text(grid.buffer()))
+    }
+
+    /// Highlight `text` on screen, an empty text ends the search
+    pub fn set_search(&self, text: &str) {
+        let imp = self.imp();
+        *imp.search.borrow_mut() = text.to_string();
+        imp.search_current.set(Some(0));
+        imp.refresh_search();
+        self.queue_draw();
+    }
+
+    /// Select the next match, or the previous one when `backward`
+    pub fn select_match(&self, backward: bool) {
+        let imp = self.imp();
+        let len = imp.search_matches.borrow().len();
+        if len == 0 {
+            return;
+        }
+
+        let current = match imp.search_current.get() {
+            Some(current) if backward => (current + len - 1) % len,
+            Some(current) => (current + 1) % len,
+            None => 0,
+        };
+        imp.search_current.set(Some(current));
+        self.queue_draw();
+    }
+
+    /// nvim pattern of the current search
+    pub fn search_pattern(&self) -> Option<String> {
+        let search = self.imp().search.borrow();
+        (!search.is_empty()).then(|| search::search_pattern(&search))
+    }
+
+    /// Row and column of the selected match, unless it's in the scrollback
+    pub fn selected_match(&self) -> Option<(usize, usize)> {
+        let imp = self.imp();
+        if imp.scrollback_offset.get() > 0 {
+            return None;
+        }
+
+        let current = imp.search_current.get()?;
+        let matches = imp.search_matches.borrow();
+        let selected = matches.get(current)?;
+        Some((selected.row, selected.columns.start))
+    }
+
+    /// 1-based position of the selected match and the number of matches
+    pub fn match_position(&self) -> (Option<usize>, usize) {
+        let imp = self.imp();
+        let current = imp.search_current.get().map(|current| current + 1);
+        (current, imp.search_matches.borrow().len())
+    }
+
+    pub fn set_focused(&self, focused: bool) {
+        let imp = self.imp();
+        imp.unfocused.set(!focused);
+        self.queue_draw();
+
+        let animation = imp.dim_animation.get().unwrap();
+        animation.set_value_from(imp.dim_progress.get());
+        animation.set_value_to(if focused { 0.0 } else { 1.0 });
+        animation.play();
+        if clock::is_virtual() {
+            animation.skip();
+        }
+    }
+
+    /// Minimum text contrast ratio, `None` draws the colors as nvim sends them
+    /// Rows to fade out at the edges, see `snapshot_overflow`
+    pub fn set_overflow(&self, windows: Vec<OverflowWindow>) {
+        let imp = self.imp();
+        if *imp.overflow.borrow() != windows {
+            imp.overflow.replace(windows);
+            self.queue_draw();
+        }
+    }
+
+    /// Images to draw over the grid, their files are loaded the first time they show up
+    pub fn set_images(&self, images: Vec<ImagePlacement>) {
+        let imp = self.imp();
+        let mut textures = imp.textures.borrow_mut();
+        textures.retain(|path, _| images.iter().any(|image| image.path == *path));
+
+        for image in &images {
+            if textures.contains_key(&image.path) {
+                continue;
+            }
+            textures.insert(image.path.clone(), None);
+
+            let grid = self.downgrade();
+            let path = image.path.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let texture = gio::File::for_path(&path)
+                    .load_bytes_future()
+                    .await
+                    .and_then(|(bytes, _)| gdk::Texture::from_bytes(&bytes));
+                let texture = match texture {
+                    Ok(texture) => texture,
+                    Err(err) => {
+                        log::warn!("Could not load the image {path}: {err}");
+                        return;
+                    }
+                };
+
+                let Some(grid) = grid.upgrade() else { return; };
+                // Unless it was removed in the meantime
+                let placed = match grid.imp().textures.borrow_mut().get_mut(&path) {
+                    Some(slot) => slot.replace(texture).is_none(),
+                    None => false,
+                };
+                if placed {
+                    grid.queue_draw();
+                }
+            });
+        }
+
+        imp.images.replace(images);
+        self.queue_draw();
+    }
+
+    pub fn set_min_contrast(&self, min_contrast: Option<f64>) {
+        self.imp().min_contrast.set(min_contrast);
+        self.imp().prepare_lines();
+        self.queue_draw();
+    }
+
+    /// How far an unfocused grid fades towards the background, 0 to 1
+    pub fn set_dim_amount(&self, amount: f64) {
+        self.imp().dim_amount.set(amount.clamp(0.0, 1.0));
+        self.queue_draw();
+    }
+
+    /// Cursor cell in widget coordinates
+    pub fn cursor_rect(&self) -> Option<gtk::gdk::Rectangle> {
+        let grid = self.imp().grid.borrow();
+        let pos = grid.as_ref()?.cursor_position();
+
+        let cell_metrics = self.cell_metrics();
+        let (x, y) = cell_metrics.pixel_coords(pos.column, pos.row);
+
+        Some(gtk::gdk::Rectangle::new(
+            x as i32,
+            y as i32,
+            cell_metrics.char_width.ceil() as i32,
+            cell_metrics.line_height.ceil() as i32,
+        ))
+    }
+
     pub fn grid_id(&self) -> Option<u64> {
         self.imp().grid.borrow().as_ref().map(|g| g.id())
     }
 
-    pub fn cell_metrics(&self) -> &CellMetrics {
-        self.imp().cell_metrics.get().unwrap()
+    /// Columns and rows of the grid
+    pub fn grid_size(&self) -> Option<(usize, usize)> {
+        let grid = self.imp().grid.borrow();
+        grid.as_ref().map(|g| (g.columns(), g.rows()))
+    }
+
+    pub fn font_description(&self) -> Option<pango::FontDescription> {
+        self.imp().context.get()?.font_description()
+    }
+
+    pub fn default_colors(&self) -> Option<Colors> {
+        let grid = self.imp().grid.borrow();
+        Some(grid.as_ref()?.default_colors.clone())
+    }
+
+    /// Font size in points replacing the one of `guifont`, `None` goes back to it
+    pub fn set_font_size(&self, size: Option<f64>) {
+        self.imp().font_size.set(size);
+        self.imp().reload_font();
+        self.queue_draw();
+    }
+
+    /// Factor the font size is multiplied with, `None` for the size as it is
+    pub fn set_font_scale(&self, scale: Option<f64>) {
+        self.imp().font_scale.set(scale);
+        self.imp().reload_font();
+        self.queue_draw();
+    }
+
+    pub fn set_decoration_overrides(&self, overrides: DecorationOverrides) {
+        self.imp().decoration_overrides.set(overrides);
+        self.imp().reload_font();
+        self.queue_draw();
+    }
+
+    /// Memory budget of the render caches, in bytes
+    pub fn set_cache_budget(&self, bytes: usize) {
+        self.imp().glyph_cache.borrow_mut().set_budget(bytes);
+    }
+
+    /// Human readable usage and hit rate of the render caches
+    pub fn cache_stats(&self) -> String {
+        const MB: f64 = 1024.0 * 1024.0;
+
+        let cache = self.imp().glyph_cache.borrow();
+        let stats = cache.stats();
+        format!(
+            "Glyph cache: {} entries, {:.1} of {:.1} MB, {:.1}% hits ({} hits, {} misses), {} evictions",
+            cache.len(),
+            cache.used() as f64 / MB,
+            cache.budget() as f64 / MB,
+            stats.hit_rate() * 100.0,
+            stats.hits,
+            stats.misses,
+            stats.evictions,
+        )
+    }
+
+    pub fn set_cursor_style(&self, style: CursorStyle) {
+        self.imp().cursor_style.replace(style);
+        self.queue_draw();
+    }
+
+    pub fn set_cursor_line_highlight(&self, id: u64) {
+        if self.imp().cursor_line_highlight.replace(Some(id)) != Some(id) {
+            self.queue_draw();
+        }
+    }
+
+    pub fn set_mode(&self, mode: Mode) {
+        if self.imp().mode.replace(mode) != mode {
+            // The accessible name changes with the mode, so screen readers announce it
+            let label =
+                gettext("Editor, {mode} mode").replace("{mode}", &mode.label().to_lowercase());
+            self.update_property(&[gtk::accessible::Property::Label(&label)]);
+            self.queue_draw();
+        }
+    }
+
+    pub fn cell_metrics(&self) -> std::cell::Ref<'_, CellMetrics> {
+        self.imp().cell_metrics.borrow()
+    }
+
+    /// Apply a `guifont` value, e.g. `Source Code Pro:h11`
+    pub fn set_guifont(&self, guifont: &str) {
+        let Some(desc) = guifont_to_pango(guifont) else {
+            log::warn!("Unsupported guifont: {guifont:?}");
+            return;
+        };
+
+        self.imp().guifont_set.set(true);
+        self.imp()
+            .set_font(&pango::FontDescription::from_string(&desc));
+        self.queue_draw();
+    }
+
+    /// Monospace font of the desktop or fontconfig, as a pango font description
+    pub fn detect_default_font(&self) -> String {
+        default_font::detect(self.imp().context.get().unwrap())
+    }
+
+    /// Font used until nvim sets `guifont`, a pango font description
+    pub fn set_default_font(&self, font: &str) {
+        if self.imp().guifont_set.get() || font.trim().is_empty() {
+            return;
+        }
+        self.imp()
+            .set_font(&pango::FontDescription::from_string(font));
+        self.queue_draw();
+    }
+
+    /// Cells inside the visible rect of the nearest scrolled container, or the whole grid
+    fn visible_cells(&self, grid: &crate::nvim::ExtLineGrid) -> VisibleCells {
+        let cell_metrics = self.cell_metrics();
+
+        let (mut x0, mut y0) = (0.0_f64, 0.0_f64);
+        let (mut x1, mut y1) = (self.width() as f64, self.height() as f64);
+
+        if let Some(scrolled) = self.ancestor(gtk::ScrolledWindow::static_type()) {
+            if let Some(bounds) = self.compute_bounds(&scrolled) {
+                let (bx, by) = (bounds.x() as f64, bounds.y() as f64);
+
+                x0 = x0.max(-bx);
+                y0 = y0.max(-by);
+                x1 = x1.min(scrolled.width() as f64 - bx);
+                y1 = y1.min(scrolled.height() as f64 - by);
+            }
+        }
+
+        VisibleCells {
+            rows: visible_range(y0, y1, cell_metrics.line_height, grid.rows()),
+            columns: visible_range(x0, x1, cell_metrics.char_width, grid.columns()),
+        }
+    }
+}
+
+/// Converts the first font of a `guifont` value into a pango font description string
+fn guifont_to_pango(guifont: &str) -> Option<String> {
+    let font = guifont.split(',').next()?.trim();
+
+    let mut parts = font.split(':');
+    let family = parts.next()?.replace('_', " ");
+
+    if family.is_empty() || family == "*" {
+        return None;
+    }
+
+    let mut desc = family;
+    let mut size = None;
+
+    for option in parts {
+        match option {
+            "b" => desc.push_str(" Bold"),
+            "i" => desc.push_str(" Italic"),
+            _ => {
+                if let Some(value) = option.strip_prefix('h') {
+                    size = value.parse::<f64>().ok();
+                }
+            }
+        }
+    }
+
+    if let Some(size) = size {
+        desc.push_str(&format!(" {size}"));
+    }
+
+    Some(desc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guifont_to_pango() {
+        assert_eq!(
+            guifont_to_pango("Source Code Pro:h11").as_deref(),
+            Some("Source Code Pro 11")
+        );
+        assert_eq!(
+            guifont_to_pango("Fira_Code:h12.5:b,monospace:h10").as_deref(),
+            Some("Fira Code Bold 12.5")
+        );
+        assert_eq!(guifont_to_pango("Iosevka").as_deref(), Some("Iosevka"));
+        assert_eq!(guifont_to_pango(""), None);
+        assert_eq!(guifont_to_pango("*"), None);
+        // Unknown options are skipped, multibyte ones too
+        assert_eq!(
+            guifont_to_pango("Mono:h11:é:ü1").as_deref(),
+            Some("Mono 11")
+        );
+    }
+
+    fn metrics(underline_position: f64, underline_thickness: f64) -> CellMetrics {
+        CellMetrics {
+            line_height: 20.0,
+            underline_position,
+            underline_thickness,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_adjust_decorations() {
+        let defaults = DecorationOverrides::default();
+
+        // Hairlines become one device pixel
+        let mut m = metrics(16.0, 0.0);
+        m.adjust_decorations(&defaults, 2.0);
+        assert_eq!(m.underline_thickness, 0.5);
+        assert_eq!(m.undercurl_thickness, 0.5);
+
+        // Underlines below the row are moved back inside
+        let mut m = metrics(25.0, 2.0);
+        m.adjust_decorations(&defaults, 1.0);
+        assert_eq!(m.underline_position, 19.0);
+
+        let mut m = metrics(16.0, 1.0);
+        m.adjust_decorations(
+            &DecorationOverrides {
+                underline_thickness: 3.0,
+                underline_offset: -2.0,
+                undercurl_thickness: 2.0,
+            },
+            1.0,
+        );
+        assert_eq!(m.underline_thickness, 3.0);
+        assert_eq!(m.underline_position, 15.0);
+        assert_eq!(m.undercurl_thickness, 2.0);
+    }
+
+    #[test]
+    fn test_fit_glyphs() {
+        // Regular glyphs are left alone
+        assert_eq!(fit_glyphs(10.2, 10.0), (0.0, 1.0));
+        assert_eq!(fit_glyphs(0.0, 10.0), (0.0, 1.0));
+        // Narrow fallback glyphs are centered
+        assert_eq!(fit_glyphs(6.0, 10.0), (2.0, 1.0));
+        // Emoji wider than their double width box are shrunk
+        assert_eq!(fit_glyphs(25.0, 20.0), (0.0, 0.8));
+    }
+
+    #[test]
+    fn test_visible_range() {
+        // Fully visible
+        assert_eq!(visible_range(0.0, 100.0, 10.0, 10), 0..10);
+        // Larger than the grid
+        assert_eq!(visible_range(0.0, 500.0, 10.0, 10), 0..10);
+        // Partially visible cells on both edges are included
+        assert_eq!(visible_range(15.0, 45.0, 10.0, 10), 1..5);
+        // Exact cell boundaries
+        assert_eq!(visible_range(20.0, 40.0, 10.0, 10), 2..4);
+        // Scrolled past the end
+        assert_eq!(visible_range(200.0, 300.0, 10.0, 10), 10..10);
+        // Nothing visible
+        assert_eq!(visible_range(50.0, 50.0, 10.0, 10), 0..0);
+        assert_eq!(visible_range(50.0, 10.0, 10.0, 10), 0..0);
+    }
+
+    #[test]
+    fn test_visible_run() {
+        let visible = VisibleCells {
+            rows: 0..10,
+            columns: 5..20,
+        };
+        assert_eq!(visible_run(&visible, 8, 4), Some(8..12));
+        // Cut at both edges, the end stays where the run ends
+        assert_eq!(visible_run(&visible, 2, 6), Some(5..8));
+        assert_eq!(visible_run(&visible, 18, 6), Some(18..20));
+        // Entirely off screen
+        assert_eq!(visible_run(&visible, 0, 5), None);
+        assert_eq!(visible_run(&visible, 20, 3), None);
     }
 }
@@ -0,0 +1,308 @@
+//! Highlights of the grid's lines resolved into the colors and runs `snapshot` draws. Unlike
+//! shaping, which needs the widget's pango context, this is plain data and done on a thread
+//! after each flush. Lines unchanged since the previous flush are not resolved again.
+
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc},
+};
+
+use gtk::glib;
+
+use super::contrast;
+use crate::nvim::{
+    event::{GridLineCell, UnderlineStyle},
+    ext_line_grid::Line,
+    Color, Colors, Style,
+};
+
+/// Cells `start..start + len` sharing a background
+#[derive(Debug, Clone, PartialEq)]
+pub struct Background {
+    pub start: usize,
+    pub len: usize,
+    pub color: Color,
+}
+
+/// Cells `start..start + len` sharing an underline, drawn in one piece so dashes and curls stay
+/// continuous
+#[derive(Debug, Clone, PartialEq)]
+pub struct Underline {
+    pub start: usize,
+    pub len: usize,
+    pub style: UnderlineStyle,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreparedLine {
+    pub backgrounds: Vec<Background>,
+    /// Text color of each cell
+    pub foregrounds: Vec<Color>,
+    pub underlines: Vec<Underline>,
+}
+
+/// What the lines are resolved with besides their cells
+#[derive(Debug, Clone)]
+pub struct Highlights {
+    pub style: Arc<HashMap<u64, Style>>,
+    pub default_colors: Colors,
+    /// High contrast mode, see [`contrast::ensure_contrast`]
+    pub min_contrast: Option<f64>,
+}
+
+impl Highlights {
+    fn same(&self, other: &Self) -> bool {
+        // The style map is shared, never changed while the cache holds it
+        Arc::ptr_eq(&self.style, &other.style)
+            && self.default_colors == other.default_colors
+            && self.min_contrast == other.min_contrast
+    }
+}
+
+/// Cells without a highlight continue the one before them
+pub fn prepare_line(cells: &[GridLineCell], highlights: &Highlights) -> PreparedLine {
+    let default_colors = &highlights.default_colors;
+    let mut prepared = PreparedLine {
+        foregrounds: Vec::with_capacity(cells.len()),
+        ..Default::default()
+    };
+
+    let mut last_hl = None;
+    let mut start = 0;
+    while start < cells.len() {
+        let highlight_id = cells[start].highlight_id.or(last_hl);
+        let len = cells[start..]
+            .iter()
+            .take_while(|cell| cell.highlight_id.is_none() || cell.highlight_id == highlight_id)
+            .count();
+        last_hl = highlight_id;
+
+        let style = highlight_id.and_then(|id| highlights.style.get(&id));
+        // Opaque, without ext_multigrid nvim already blended floats into the grid's colors
+        let background = style
+            .map(|style| style.background(default_colors))
+            .unwrap_or(default_colors.background.unwrap());
+        prepared.backgrounds.push(Background {
+            start,
+            len,
+            color: background,
+        });
+
+        let mut foreground = style
+            .map(|style| style.foreground(default_colors))
+            .unwrap_or(default_colors.foreground.unwrap());
+        if let Some(min_contrast) = highlights.min_contrast {
+            foreground = contrast::ensure_contrast(foreground, background, min_contrast);
+        }
+        prepared.foregrounds.resize(start + len, foreground);
+
+        if let Some((style, underline)) = style.and_then(|s| Some((s, s.underline?))) {
+            prepared.underlines.push(Underline {
+                start,
+                len,
+                style: underline,
+                color: style.special(default_colors),
+            });
+        }
+
+        start += len;
+    }
+
+    prepared
+}
+
+/// Lines of the previous flush and what they were resolved to
+#[derive(Default)]
+struct Cache {
+    highlights: Option<Highlights>,
+    /// The lines are kept so their ids aren't reused
+    lines: HashMap<usize, (Line, Arc<PreparedLine>)>,
+}
+
+impl Cache {
+    fn prepare(&mut self, lines: Vec<Line>, highlights: Highlights) -> Vec<Arc<PreparedLine>> {
+        if !self
+            .highlights
+            .as_ref()
+            .is_some_and(|cached| cached.same(&highlights))
+        {
+            self.lines.clear();
+        }
+
+        let mut cached = std::mem::take(&mut self.lines);
+        let prepared = lines
+            .into_iter()
+            .map(|line| {
+                let prepared = match cached.remove(&line.id()) {
+                    Some((_, prepared)) => prepared,
+                    None => Arc::new(prepare_line(line.columns(), &highlights)),
+                };
+                self.lines.insert(line.id(), (line, prepared.clone()));
+                prepared
+            })
+            .collect();
+
+        self.highlights = Some(highlights);
+        prepared
+    }
+}
+
+/// Lines of one flush, resolved
+#[derive(Debug)]
+pub struct Prepared {
+    pub generation: u64,
+    pub lines: Vec<Arc<PreparedLine>>,
+}
+
+#[derive(Debug)]
+struct Job {
+    generation: u64,
+    lines: Vec<Line>,
+    highlights: Highlights,
+}
+
+/// Thread resolving the lines of each flush, stopped when dropped
+#[derive(Debug)]
+pub struct Worker {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl Worker {
+    /// `done` is called on the main loop with the lines of each job, jobs sent faster than
+    /// they're done are skipped except for the last one
+    pub fn spawn(done: impl Fn(Prepared) + 'static) -> Self {
+        let (tx, rx) = glib::MainContext::channel(glib::Priority::default());
+        rx.attach(None, move |prepared| {
+            done(prepared);
+            glib::Continue(true)
+        });
+
+        let (jobs, pending) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("grid prepare".to_string())
+            .spawn(move || {
+                let mut cache = Cache::default();
+                while let Ok(mut job) = pending.recv() {
+                    while let Ok(newer) = pending.try_recv() {
+                        job = newer;
+                    }
+
+                    let _span = crate::profiling::span("prepare lines");
+                    let lines = cache.prepare(job.lines, job.highlights);
+                    let prepared = Prepared {
+                        generation: job.generation,
+                        lines,
+                    };
+                    if tx.send(prepared).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to start the grid thread");
+
+        Self { jobs }
+    }
+
+    pub fn prepare(&self, generation: u64, lines: Vec<Line>, highlights: Highlights) {
+        let job = Job {
+            generation,
+            lines,
+            highlights,
+        };
+        // Only fails once the thread panicked, `snapshot` resolves the lines itself then
+        self.jobs.send(job).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+    };
+
+    fn highlights() -> Highlights {
+        let mut style = HashMap::new();
+        style.insert(
+            1,
+            Style {
+                colors: Colors {
+                    background: Some(RED),
+                    ..Default::default()
+                },
+                underline: Some(UnderlineStyle::UnderCurl),
+                ..Default::default()
+            },
+        );
+        Highlights {
+            style: Arc::new(style),
+            default_colors: Colors {
+                foreground: Some(Color {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                }),
+                background: Some(Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                }),
+                special: Some(RED),
+            },
+            min_contrast: None,
+        }
+    }
+
+    fn cells(highlight_ids: &[Option<u64>]) -> Vec<GridLineCell> {
+        highlight_ids
+            .iter()
+            .map(|&highlight_id| GridLineCell {
+                highlight_id,
+                ..GridLineCell::empty()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prepare_line() {
+        let highlights = highlights();
+        let prepared = prepare_line(&cells(&[Some(0), Some(1), None, Some(0)]), &highlights);
+
+        let starts: Vec<_> = prepared
+            .backgrounds
+            .iter()
+            .map(|bg| (bg.start, bg.len))
+            .collect();
+        assert_eq!(starts, [(0, 1), (1, 2), (3, 1)]);
+        assert_eq!(prepared.backgrounds[1].color, RED);
+        assert_eq!(prepared.foregrounds.len(), 4);
+        assert_eq!(
+            prepared.underlines,
+            [Underline {
+                start: 1,
+                len: 2,
+                style: UnderlineStyle::UnderCurl,
+                color: RED,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut cache = Cache::default();
+        let lines = vec![Line::from_text("ab"), Line::from_text("cd")];
+
+        let first = cache.prepare(lines.clone(), highlights());
+        let highlights_again = cache.highlights.clone().unwrap();
+        let second = cache.prepare(lines.clone(), highlights_again);
+        assert!(Arc::ptr_eq(&first[0], &second[0]));
+
+        // A new style map resolves the lines again
+        let third = cache.prepare(lines, highlights());
+        assert!(!Arc::ptr_eq(&first[0], &third[0]));
+    }
+}
@@ -0,0 +1,112 @@
+use std::ops::Range;
+
+use crate::nvim::ext_line_grid::Line;
+
+/// Cells of one line matching the find bar text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub row: usize,
+    pub columns: Range<usize>,
+}
+
+/// Matches of `needle` in `lines`, case insensitive unless `needle` has uppercase letters
+pub fn find(lines: &[Line], needle: &str) -> Vec<Match> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let ignore_case = !has_uppercase(needle);
+    let needle = if ignore_case {
+        needle.to_lowercase()
+    } else {
+        needle.to_string()
+    };
+
+    let mut matches = Vec::new();
+
+    for (row, line) in lines.iter().enumerate() {
+        let cells = line.columns();
+
+        // Byte offset in `haystack` where each cell starts
+        let mut offsets = Vec::with_capacity(cells.len());
+        let mut haystack = String::new();
+        for cell in cells {
+            offsets.push(haystack.len());
+            if ignore_case {
+                haystack.push_str(&cell.text.to_lowercase());
+            } else {
+                haystack.push_str(&cell.text);
+            }
+        }
+
+        let cell_at = |byte: usize| offsets.partition_point(|&offset| offset <= byte) - 1;
+
+        for (start, text) in haystack.match_indices(&needle) {
+            let first = cell_at(start);
+            let mut end = cell_at(start + text.len() - 1) + 1;
+            // Include the empty cell following a double width character
+            while end < cells.len() && cells[end].text.is_empty() {
+                end += 1;
+            }
+
+            matches.push(Match {
+                row,
+                columns: first..end,
+            });
+        }
+    }
+
+    matches
+}
+
+/// nvim pattern matching `needle` literally, with the same case sensitivity as `find`
+pub fn search_pattern(needle: &str) -> String {
+    let case = if has_uppercase(needle) { "\\C" } else { "\\c" };
+    let pattern = needle.replace('\\', "\\\\");
+
+    format!("\\V{case}{pattern}")
+}
+
+fn has_uppercase(text: &str) -> bool {
+    text.chars().any(char::is_uppercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<Line> {
+        text.iter().map(|line| Line::from_text(line)).collect()
+    }
+
+    #[test]
+    fn test_find() {
+        let lines = lines(&["error: foo", "no match", "Error Error"]);
+
+        assert_eq!(
+            find(&lines, "error"),
+            [
+                Match {
+                    row: 0,
+                    columns: 0..5
+                },
+                Match {
+                    row: 2,
+                    columns: 0..5
+                },
+                Match {
+                    row: 2,
+                    columns: 6..11
+                },
+            ]
+        );
+        assert_eq!(find(&lines, "Error").len(), 2);
+        assert!(find(&lines, "").is_empty());
+    }
+
+    #[test]
+    fn test_search_pattern() {
+        assert_eq!(search_pattern("a/b"), "\\V\\ca/b");
+        assert_eq!(search_pattern("<Tab>\\"), "\\V\\C<Tab>\\\\");
+    }
+}
@@ -0,0 +1,95 @@
+use std::ops::{Range, RangeInclusive};
+
+use crate::nvim::ext_line_grid::Line;
+
+/// Selection made in the GUI, in screen cells, without involving nvim's visual mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    /// (col, row) where the drag started and where it is now
+    pub anchor: (usize, usize),
+    pub head: (usize, usize),
+    /// Rectangular instead of whole lines
+    pub block: bool,
+}
+
+impl Selection {
+    pub fn new(pos: (usize, usize), block: bool) -> Self {
+        Self {
+            anchor: pos,
+            head: pos,
+            block,
+        }
+    }
+
+    pub fn rows(&self) -> RangeInclusive<usize> {
+        self.anchor.1.min(self.head.1)..=self.anchor.1.max(self.head.1)
+    }
+
+    /// Selected cells of `row` in a grid `width` cells wide
+    pub fn columns(&self, row: usize, width: usize) -> Range<usize> {
+        if !self.rows().contains(&row) {
+            return 0..0;
+        }
+
+        if self.block {
+            let start = self.anchor.0.min(self.head.0).min(width);
+            let end = (self.anchor.0.max(self.head.0) + 1).min(width);
+            start..end
+        } else {
+            0..width
+        }
+    }
+
+    /// Selected text with trailing whitespace of each line removed
+    pub fn text(&self, lines: &[Line]) -> String {
+        self.rows()
+            .filter_map(|row| lines.get(row))
+            .enumerate()
+            .map(|(i, line)| {
+                let row = self.rows().start() + i;
+                let cells = line.columns();
+                let text: String = cells[self.columns(row, cells.len())]
+                    .iter()
+                    .map(|cell| &*cell.text)
+                    .collect();
+                text.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<Line> {
+        text.iter().map(|line| Line::from_text(line)).collect()
+    }
+
+    #[test]
+    fn test_linewise() {
+        let lines = lines(&["abc ", "def ", "ghi "]);
+        let selection = Selection {
+            anchor: (2, 1),
+            head: (0, 0),
+            block: false,
+        };
+
+        assert_eq!(selection.columns(2, 4), 0..0);
+        assert_eq!(selection.text(&lines), "abc\ndef");
+    }
+
+    #[test]
+    fn test_block() {
+        let lines = lines(&["abcd", "efgh", "ijkl"]);
+        let selection = Selection {
+            anchor: (1, 0),
+            head: (2, 2),
+            block: true,
+        };
+
+        assert_eq!(selection.columns(1, 4), 1..3);
+        assert_eq!(selection.text(&lines), "bc\nfg\njk");
+    }
+}
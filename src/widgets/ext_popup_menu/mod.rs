@@ -1,13 +1,19 @@
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use gtk::glib;
+use gtk::{gdk, glib};
 
 mod model;
 mod row;
 use row::PopupMenuListRow;
 
-use crate::nvim::event::PopupMenuItem;
-use std::cell::{Cell, OnceCell};
+use crate::{
+    markdown,
+    nvim::{event::PopupMenuItem, GtkToNvimEvent, NvimBridge},
+};
+use std::{
+    cell::{Cell, OnceCell},
+    rc::Rc,
+};
 
 mod imp {
     use super::*;
@@ -17,36 +23,107 @@ mod imp {
         resource = "/io/github/polymeilex/vimdicator/widgets/ext_popup_menu/ext_popup_menu.ui"
     )]
     pub struct ExtPopupMenu {
+        /// Selection as nvim knows it
         pub selected: Cell<Option<usize>>,
+        /// Row under the pointer, shown as selected but only sent to nvim on click
+        pub hovered: Cell<Option<usize>>,
+        pub nvim_tx: OnceCell<NvimBridge>,
         pub selection_model: OnceCell<gtk::SingleSelection>,
         pub items_model: OnceCell<model::ExtPopupMenuModel>,
+        /// 'pumblend'
+        pub pumblend: Cell<u64>,
+        /// `blend` of the `Pmenu` group, which wins over 'pumblend'
+        pub group_blend: Cell<Option<u8>>,
 
         #[template_child]
         list_view: TemplateChild<gtk::ListView>,
+        #[template_child]
+        info_pane: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        info_label: TemplateChild<gtk::Label>,
     }
 
     impl ExtPopupMenu {
         pub fn set_items(&self, items: Vec<PopupMenuItem>) {
+            self.hovered.set(None);
             self.items_model.get().unwrap().set_items(items);
         }
 
         pub fn select(&self, selected: Option<usize>) {
             self.selected.set(selected);
+            // nvim moved the selection (e.g. from the keyboard), that wins over the pointer
+            self.hovered.set(None);
+            self.show_selection(selected);
 
-            let selection_model = self.selection_model.get().unwrap();
-            if let Some(selected) = self.selected.get() {
-                selection_model.select_item(selected as u32, true);
-
-                let selected = selected as u32;
-
-                let len = selection_model.n_items();
-                let scrol_to = selected.min(len);
+            if let Some(selected) = selected {
+                let len = self.selection_model.get().unwrap().n_items();
+                let scrol_to = (selected as u32).min(len);
 
                 self.list_view
                     .activate_action("list.scroll-to-item", Some(&scrol_to.to_variant()))
                     .unwrap();
+            }
+        }
+
+        /// Preselect the row under the pointer, `None` goes back to nvim's selection
+        fn hover(&self, hovered: Option<usize>) {
+            if self.hovered.replace(hovered) != hovered {
+                self.show_selection(hovered.or(self.selected.get()));
+            }
+        }
+
+        /// A row was clicked, move nvim's selection there
+        fn choose(&self, index: usize) {
+            let len = self.selection_model.get().unwrap().n_items() as usize;
+            let input = selection_input(self.selected.get(), index, len);
+            if let (Some(input), Some(tx)) = (input, self.nvim_tx.get()) {
+                tx.send(GtkToNvimEvent::Input(input)).unwrap();
+            }
+        }
+
+        fn show_selection(&self, index: Option<usize>) {
+            let selection_model = self.selection_model.get().unwrap();
+            match index {
+                Some(index) => {
+                    selection_model.select_item(index as u32, true);
+                }
+                None => {
+                    selection_model.unselect_all();
+                }
+            }
+
+            self.update_info(index);
+        }
+
+        /// Rows that fit in the list, at least one
+        pub fn page_size(&self) -> usize {
+            let row_height = self.list_view.first_child().map_or(0, |row| row.height());
+            if row_height > 0 {
+                (self.list_view.height() / row_height).max(1) as usize
             } else {
-                selection_model.unselect_all();
+                1
+            }
+        }
+
+        /// Show the documentation of the selected item, LSP servers send it as markdown
+        fn update_info(&self, selected: Option<usize>) {
+            let info = selected
+                .and_then(|selected| self.selection_model.get().unwrap().item(selected as u32))
+                .map(|obj| {
+                    obj.downcast::<glib::BoxedAnyObject>()
+                        .unwrap()
+                        .borrow::<PopupMenuItem>()
+                        .info
+                        .clone()
+                })
+                .filter(|info| !info.trim().is_empty());
+
+            match info {
+                Some(info) => {
+                    self.info_label.set_markup(&markdown::to_pango(&info));
+                    self.info_pane.set_visible(true);
+                }
+                None => self.info_pane.set_visible(false),
             }
         }
     }
@@ -85,15 +162,54 @@ mod imp {
 
             let item_factory = gtk::SignalListItemFactory::new();
 
+            let popup = self.obj().downgrade();
             item_factory.connect_setup(move |_, list_item| {
-                list_item.set_child(Some(&PopupMenuListRow::new()));
+                let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
+                let row = PopupMenuListRow::new();
+
+                // Positions change as rows are recycled, so they are looked up on each event
+                let position = {
+                    let (popup, list_item) = (popup.clone(), list_item.downgrade());
+                    Rc::new(move || {
+                        let (popup, list_item) = (popup.upgrade()?, list_item.upgrade()?);
+                        let len = popup.imp().selection_model.get().unwrap().n_items();
+                        let position = list_item.position();
+                        (position < len).then_some((popup, position as usize))
+                    })
+                };
+
+                let motion = gtk::EventControllerMotion::new();
+                motion.connect_enter(glib::clone!(@strong position => move |_, _, _| {
+                    if let Some((popup, position)) = position() {
+                        popup.imp().hover(Some(position));
+                    }
+                }));
+                row.add_controller(motion);
+
+                let click = gtk::GestureClick::builder()
+                    .button(gdk::BUTTON_PRIMARY)
+                    .build();
+                click.connect_released(move |_, _, _, _| {
+                    if let Some((popup, position)) = position() {
+                        popup.imp().choose(position);
+                    }
+                });
+                row.add_controller(click);
+
+                list_item.set_child(Some(&row));
             });
 
+            let motion = gtk::EventControllerMotion::new();
+            motion.connect_leave(glib::clone!(@weak self as imp => move |_| imp.hover(None)));
+            self.list_view.add_controller(motion);
+
             item_factory.connect_teardown(|_, list_item| {
+                let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
                 list_item.set_child(Option::<&gtk::Widget>::None);
             });
 
             item_factory.connect_bind(|_, list_item| {
+                let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
                 let row: PopupMenuListRow = list_item.child().unwrap().downcast().unwrap();
                 row.set_row(
                     list_item
@@ -109,6 +225,7 @@ mod imp {
             });
 
             item_factory.connect_unbind(|_, list_item| {
+                let list_item = list_item.downcast_ref::<gtk::ListItem>().unwrap();
                 let row: PopupMenuListRow = list_item.child().unwrap().downcast().unwrap();
                 row.set_row(Option::<&PopupMenuItem>::None);
             });
@@ -120,6 +237,62 @@ mod imp {
     impl PopoverImpl for ExtPopupMenu {}
 }
 
+/// Keys moving through the popup by more than one item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigation {
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+impl Navigation {
+    pub fn from_key(key: gtk::gdk::Key) -> Option<Self> {
+        use gtk::gdk::Key;
+
+        match key {
+            Key::Page_Up | Key::KP_Page_Up => Some(Self::PageUp),
+            Key::Page_Down | Key::KP_Page_Down => Some(Self::PageDown),
+            Key::Home | Key::KP_Home => Some(Self::Home),
+            Key::End | Key::KP_End => Some(Self::End),
+            _ => None,
+        }
+    }
+}
+
+/// nvim only knows single steps, so a jump is sent as that many `<C-n>` or `<C-p>`.
+/// Without a selection `<C-n>` goes to the first item and `<C-p>` to the last one.
+fn navigation_input(
+    navigation: Navigation,
+    selected: Option<usize>,
+    len: usize,
+    page: usize,
+) -> Option<String> {
+    let last = len.checked_sub(1)?;
+    let target = match (navigation, selected) {
+        (Navigation::Home, _) => 0,
+        (Navigation::End, _) => last,
+        (Navigation::PageDown, Some(selected)) => (selected + page).min(last),
+        (Navigation::PageDown, None) => page.min(len) - 1,
+        (Navigation::PageUp, Some(selected)) => selected.saturating_sub(page),
+        (Navigation::PageUp, None) => len.saturating_sub(page),
+    };
+
+    selection_input(selected, target, len)
+}
+
+/// Input moving nvim's selection from `selected` to `target` in a popup of `len` items
+fn selection_input(selected: Option<usize>, target: usize, len: usize) -> Option<String> {
+    let (key, count) = match selected {
+        Some(selected) if target >= selected => ("<C-n>", target - selected),
+        Some(selected) => ("<C-p>", selected - target),
+        None if target < len - target => ("<C-n>", target + 1),
+        None => ("<C-p>", len - target),
+    };
+
+    (count > 0).then(|| key.repeat(count))
+}
+
 glib::wrapper! {
     pub struct ExtPopupMenu(ObjectSubclass<imp::ExtPopupMenu>)
         @extends gtk::Widget, gtk::Popover;
@@ -133,4 +306,90 @@ impl ExtPopupMenu {
     pub fn select(&self, selected: Option<usize>) {
         self.imp().select(selected);
     }
+
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    /// `<C-n>`/`<C-p>` input moving nvim's selection for a navigation key, `None` if
+    /// there is nothing to move
+    pub fn navigation_input(&self, navigation: Navigation) -> Option<String> {
+        let imp = self.imp();
+        let len = imp.selection_model.get().unwrap().n_items() as usize;
+        navigation_input(navigation, imp.selected.get(), len, imp.page_size())
+    }
+
+    /// 'pumblend', 0 is opaque and 100 fully transparent
+    pub fn set_blend(&self, blend: u64) {
+        self.imp().pumblend.set(blend);
+        self.update_opacity();
+    }
+
+    /// `blend` of the `Pmenu` group. nvim doesn't draw the external menu, so neither reaches
+    /// it blended in.
+    pub fn set_group_blend(&self, blend: Option<u8>) {
+        self.imp().group_blend.set(blend);
+        self.update_opacity();
+    }
+
+    fn update_opacity(&self) {
+        let imp = self.imp();
+        self.set_opacity(opacity(imp.pumblend.get(), imp.group_blend.get()));
+    }
+}
+
+/// Opacity of the menu, the group's blend is used over 'pumblend' like nvim does for its own
+fn opacity(pumblend: u64, group_blend: Option<u8>) -> f64 {
+    let blend = group_blend.map_or(pumblend, u64::from);
+    1.0 - blend.min(100) as f64 / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opacity() {
+        assert_eq!(opacity(0, None), 1.0);
+        assert_eq!(opacity(30, None), 0.7);
+        assert_eq!(opacity(30, Some(50)), 0.5);
+        assert_eq!(opacity(250, None), 0.0);
+    }
+
+    #[test]
+    fn test_navigation_input() {
+        let input = |navigation, selected| navigation_input(navigation, selected, 10, 4);
+
+        assert_eq!(
+            input(Navigation::PageDown, Some(2)),
+            Some("<C-n>".repeat(4))
+        );
+        assert_eq!(
+            input(Navigation::PageDown, Some(8)),
+            Some("<C-n>".to_string())
+        );
+        assert_eq!(input(Navigation::PageDown, Some(9)), None);
+        assert_eq!(input(Navigation::PageUp, Some(5)), Some("<C-p>".repeat(4)));
+        assert_eq!(input(Navigation::PageUp, Some(0)), None);
+        assert_eq!(input(Navigation::Home, Some(3)), Some("<C-p>".repeat(3)));
+        assert_eq!(input(Navigation::End, Some(3)), Some("<C-n>".repeat(6)));
+
+        // Without a selection the first and last items are one key away
+        assert_eq!(input(Navigation::PageDown, None), Some("<C-n>".repeat(4)));
+        assert_eq!(input(Navigation::PageUp, None), Some("<C-p>".repeat(4)));
+        assert_eq!(input(Navigation::Home, None), Some("<C-n>".to_string()));
+        assert_eq!(input(Navigation::End, None), Some("<C-p>".to_string()));
+
+        assert_eq!(navigation_input(Navigation::End, None, 0, 4), None);
+    }
+
+    #[test]
+    fn test_selection_input() {
+        assert_eq!(
+            selection_input(Some(1), 3, 5),
+            Some("<C-n><C-n>".to_string())
+        );
+        assert_eq!(selection_input(Some(3), 3, 5), None);
+        assert_eq!(selection_input(None, 4, 5), Some("<C-p>".to_string()));
+    }
 }
@@ -1,7 +1,19 @@
 use crate::nvim::event::PopupMenuItem;
 
-use gtk::{glib, pango};
+use gettextrs::gettext;
+use gtk::{gdk, gio, glib, pango};
 use gtk::{prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+
+/// Part of an item a context menu action copies
+type ItemPart = fn(&PopupMenuItem) -> &str;
+
+/// Context menu actions, copying a part of the item without accepting it
+const COPY_ACTIONS: [(&str, ItemPart); 3] = [
+    ("copy-word", |item| &item.word),
+    ("copy-menu", |item| &item.menu),
+    ("copy-info", |item| &item.info),
+];
 
 glib::wrapper! {
     pub struct PopupMenuListRow(ObjectSubclass<imp::PopupMenuListRow>)
@@ -33,6 +45,46 @@ mod imp {
         word_label: glib::WeakRef<gtk::Label>,
         kind_label: glib::WeakRef<gtk::Label>,
         menu_label: glib::WeakRef<gtk::Label>,
+        item: RefCell<Option<PopupMenuItem>>,
+        actions: gio::SimpleActionGroup,
+        context_menu: glib::WeakRef<gtk::PopoverMenu>,
+    }
+
+    impl PopupMenuListRow {
+        fn init_context_menu(&self) {
+            let obj = self.obj();
+
+            for (name, part) in COPY_ACTIONS {
+                let action = gio::SimpleAction::new(name, None);
+                action.connect_activate(glib::clone!(@weak obj => move |_, _| {
+                    let text = obj.imp().item.borrow().as_ref().map(|item| part(item).to_string());
+                    if let Some(text) = text {
+                        obj.clipboard().set_text(&text);
+                    }
+                }));
+                self.actions.add_action(&action);
+            }
+            obj.insert_action_group("row", Some(&self.actions));
+
+            let menu = gio::Menu::new();
+            menu.append(Some(&gettext("Copy Word")), Some("row.copy-word"));
+            menu.append(Some(&gettext("Copy Signature")), Some("row.copy-menu"));
+            menu.append(Some(&gettext("Copy Documentation")), Some("row.copy-info"));
+
+            let context_menu = gtk::PopoverMenu::from_model(Some(&menu));
+            context_menu.set_has_arrow(false);
+            context_menu.set_parent(&*obj);
+            self.context_menu.set(Some(&context_menu));
+
+            let click = gtk::GestureClick::builder()
+                .button(gdk::BUTTON_SECONDARY)
+                .build();
+            click.connect_pressed(glib::clone!(@weak context_menu => move |_, _, x, y| {
+                context_menu.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                context_menu.popup();
+            }));
+            obj.add_controller(click);
+        }
     }
 
     #[glib::object_subclass]
@@ -74,6 +126,14 @@ mod imp {
                 .build();
             self.menu_label.set(Some(&menu_label));
             obj.append(&menu_label);
+
+            self.init_context_menu();
+        }
+
+        fn dispose(&self) {
+            if let Some(context_menu) = self.context_menu.upgrade() {
+                context_menu.unparent();
+            }
         }
 
         fn properties() -> &'static [glib::ParamSpec] {
@@ -106,6 +166,15 @@ mod imp {
                         let menu_label = self.menu_label.upgrade().unwrap();
                         menu_label.set_visible(false);
                         menu_label.set_label(&row.menu);
+
+                        for (name, part) in COPY_ACTIONS {
+                            let action = self.actions.lookup_action(name).unwrap();
+                            let action = action.downcast::<gio::SimpleAction>().unwrap();
+                            action.set_enabled(!part(&row).trim().is_empty());
+                        }
+                        self.item.replace(Some(row.clone()));
+                    } else {
+                        self.item.replace(None);
                     }
                 }
                 _ => unreachable!(),
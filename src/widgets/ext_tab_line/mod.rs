@@ -1,8 +1,11 @@
 use adw::subclass::prelude::*;
-use gtk::glib;
+use gtk::{glib, prelude::*};
 
 use crate::nvim;
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
 
 mod imp {
     use super::*;
@@ -10,9 +13,13 @@ mod imp {
     #[derive(Debug, Default, gtk::CompositeTemplate)]
     #[template(resource = "/io/github/polymeilex/vimdicator/widgets/ext_tab_line/ext_tab_line.ui")]
     pub struct ExtTabLine {
+        #[template_child]
+        pub tab_bar: TemplateChild<adw::TabBar>,
         #[template_child]
         pub tab_view: TemplateChild<adw::TabView>,
         pub ext_tabline: RefCell<Option<nvim::ExtTabline>>,
+        /// Pages are kept per tabpage so reordering tabs doesn't recreate them
+        pub pages: RefCell<HashMap<nvim::Tabpage, adw::TabPage>>,
     }
 
     #[glib::object_subclass]
@@ -40,84 +47,49 @@ glib::wrapper! {
         @extends gtk::Widget;
 }
 
-struct HashItem {
-    tabpage: nvim::Tabpage,
-    page: Option<adw::TabPage>,
-    id: usize,
-}
-
-impl std::cmp::Eq for HashItem {}
-impl std::cmp::PartialEq for HashItem {
-    fn eq(&self, other: &Self) -> bool {
-        self.tabpage == other.tabpage
-    }
-}
-impl std::hash::Hash for HashItem {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.tabpage.hash(state);
+impl ExtTabLine {
+    /// Mirror nvim's 'showtabline': 0 never, 1 with at least two tabs, 2 always
+    pub fn set_show_tabline(&self, show_tabline: u64) {
+        let tab_bar = self.imp().tab_bar.get();
+        tab_bar.set_visible(show_tabline != 0);
+        tab_bar.set_autohide(show_tabline == 1);
     }
-}
 
-impl ExtTabLine {
     pub fn update_tabs(&self, tabline: &crate::nvim::ExtTabline) {
-        let tab_view = self.imp().tab_view.get();
-
-        let mut old_set = std::collections::HashSet::new();
-        let mut new_set = std::collections::HashSet::new();
-
-        for (id, (_, tabpage)) in self
-            .imp()
-            .ext_tabline
-            .borrow()
-            .as_ref()
-            .map(|last| last.tabs())
-            .into_iter()
-            .flatten()
-            .enumerate()
-        {
-            let page = tab_view.nth_page(id as i32);
-
-            old_set.insert(HashItem {
-                tabpage: tabpage.clone(),
-                page: Some(page),
-                id,
-            });
-        }
+        let imp = self.imp();
 
-        for (id, (_, tabpage)) in tabline.tabs().iter().enumerate() {
-            new_set.insert(HashItem {
-                tabpage: tabpage.clone(),
-                page: None,
-                id,
-            });
+        if imp.ext_tabline.borrow().as_ref() == Some(tabline) {
+            return;
         }
 
-        for item in old_set.difference(&new_set) {
-            if let Some(page) = item.page.as_ref() {
+        let tab_view = imp.tab_view.get();
+        let mut pages = imp.pages.borrow_mut();
+
+        let tabs: HashSet<_> = tabline.tabs().iter().map(|(_, tab)| tab).collect();
+        pages.retain(|tab, page| {
+            let keep = tabs.contains(tab);
+            if !keep {
                 tab_view.close_page(page);
             }
-        }
+            keep
+        });
 
-        let pages: Vec<_> = (0..tab_view.n_pages())
-            .map(|id| tab_view.nth_page(id))
-            .collect();
+        for (position, (name, tab)) in tabline.tabs().iter().enumerate() {
+            let page = pages
+                .entry(tab.clone())
+                .or_insert_with(|| tab_view.append(&gtk::Label::new(None)));
 
-        for item in new_set.difference(&old_set) {
-            let page = item.id.checked_sub(1).and_then(|id| pages.get(id));
-            tab_view.add_page(&gtk::Label::new(None), page);
-        }
-
-        for (id, (name, tab)) in tabline.tabs().iter().enumerate() {
-            let page = tab_view.nth_page(id as i32);
+            if tab_view.page_position(page) != position as i32 {
+                tab_view.reorder_page(page, position as i32);
+            }
 
             page.set_title(name);
-            page.is_pinned();
 
             if Some(tab) == tabline.current_tab() {
-                tab_view.set_selected_page(&page);
+                tab_view.set_selected_page(page);
             }
         }
 
-        *self.imp().ext_tabline.borrow_mut() = Some(tabline.clone());
+        *imp.ext_tabline.borrow_mut() = Some(tabline.clone());
     }
 }
@@ -0,0 +1,221 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{gdk, gdk_pixbuf, gio, glib};
+
+use crate::locale;
+use crate::nvim::{GtkToNvimEvent, NvimBridge};
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::HashMap,
+};
+
+/// Size of the thumbnails in pixels
+const THUMBNAIL_SIZE: i32 = 160;
+/// Thumbnails kept in memory, the cache starts over beyond this
+const MAX_CACHED: usize = 64;
+
+/// Thumbnail of `file`: the one of the desktop's thumbnail cache, which knows PDFs, or the
+/// image itself scaled down. `None` for files that are neither.
+async fn thumbnail(file: &gio::File, content_type: &str) -> Option<gdk::Texture> {
+    let info = file
+        .query_info_future(
+            gio::FILE_ATTRIBUTE_THUMBNAIL_PATH,
+            gio::FileQueryInfoFlags::NONE,
+            glib::PRIORITY_DEFAULT,
+        )
+        .await
+        .ok()?;
+    let cached = info
+        .attribute_byte_string(gio::FILE_ATTRIBUTE_THUMBNAIL_PATH)
+        .map(|path| gio::File::for_path(path.as_str()));
+
+    let source = match cached {
+        Some(cached) => cached,
+        None if gio::content_type_is_a(content_type, "image/*") => file.clone(),
+        None => return None,
+    };
+    let stream = source.read_future(glib::PRIORITY_DEFAULT).await.ok()?;
+    let pixbuf = gdk_pixbuf::Pixbuf::from_stream_at_scale_future(
+        &stream,
+        THUMBNAIL_SIZE,
+        THUMBNAIL_SIZE,
+        true,
+    )
+    .await
+    .map_err(|err| log::warn!("Could not load a thumbnail of {}: {err}", file.uri()))
+    .ok()?;
+
+    Some(gdk::Texture::for_pixbuf(&pixbuf))
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct FilePreview {
+        pub image: gtk::Image,
+        pub name: gtk::Label,
+        pub details: gtk::Label,
+        /// Path of the file being previewed
+        pub path: RefCell<String>,
+        /// Thumbnails by path and modification time
+        pub thumbnails: RefCell<HashMap<(String, u64), gdk::Texture>>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+    }
+
+    impl FilePreview {
+        fn open(&self) {
+            let path = self.path.borrow();
+            if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').open_previewed({:?})",
+                    *path
+                )))
+                .unwrap();
+            }
+        }
+
+        pub async fn load(&self, path: String) {
+            let file = gio::File::for_path(&path);
+            let attributes = "standard::content-type,standard::size,time::modified";
+            let info = match file
+                .query_info_future(
+                    attributes,
+                    gio::FileQueryInfoFlags::NONE,
+                    glib::PRIORITY_DEFAULT,
+                )
+                .await
+            {
+                Ok(info) => info,
+                Err(err) => {
+                    log::warn!("Could not preview {path}: {err}");
+                    return;
+                }
+            };
+            // Another file was selected in the meantime
+            if *self.path.borrow() != path {
+                return;
+            }
+
+            let content_type = info.content_type().unwrap_or_default();
+            self.details.set_text(&format!(
+                "{} · {}",
+                gio::content_type_get_description(&content_type),
+                locale::size(info.size() as u64)
+            ));
+            self.image
+                .set_from_gicon(&gio::content_type_get_icon(&content_type));
+
+            let key = (path.clone(), info.attribute_uint64("time::modified"));
+            let cached = self.thumbnails.borrow().get(&key).cloned();
+            let texture = match cached {
+                Some(texture) => texture,
+                None => {
+                    let Some(texture) = thumbnail(&file, &content_type).await else {
+                        return;
+                    };
+                    let mut thumbnails = self.thumbnails.borrow_mut();
+                    if thumbnails.len() >= MAX_CACHED {
+                        thumbnails.clear();
+                    }
+                    thumbnails.insert(key, texture.clone());
+                    texture
+                }
+            };
+            if *self.path.borrow() == path {
+                self.image.set_from_paintable(Some(&texture));
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for FilePreview {
+        const NAME: &'static str = "FilePreview";
+        type Type = super::FilePreview;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for FilePreview {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_orientation(gtk::Orientation::Vertical);
+            obj.set_spacing(6);
+            obj.add_css_class("card");
+            obj.set_margin_start(6);
+            obj.set_margin_end(6);
+            obj.set_margin_bottom(6);
+
+            self.image.set_pixel_size(THUMBNAIL_SIZE);
+            self.image.set_margin_top(12);
+            obj.append(&self.image);
+
+            self.name.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+            self.name.add_css_class("heading");
+            self.details.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            self.details.add_css_class("dim-label");
+            self.details.add_css_class("caption");
+            for label in [&self.name, &self.details] {
+                label.set_margin_start(12);
+                label.set_margin_end(12);
+                obj.append(label);
+            }
+
+            let open = gtk::Button::builder()
+                .label(gettext("_Open Anyway"))
+                .use_underline(true)
+                .tooltip_text(gettext("Open the file in the editor"))
+                .halign(gtk::Align::Center)
+                .margin_top(6)
+                .margin_bottom(12)
+                .build();
+            open.connect_clicked(glib::clone!(@weak obj => move |_| obj.imp().open()));
+            obj.append(&open);
+        }
+    }
+    impl WidgetImpl for FilePreview {}
+    impl BoxImpl for FilePreview {}
+}
+
+glib::wrapper! {
+    /// Thumbnail of the image or PDF selected in the file browser, which is shown here instead
+    /// of being opened in nvim
+    pub struct FilePreview(ObjectSubclass<imp::FilePreview>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Orientable;
+}
+
+impl Default for FilePreview {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl FilePreview {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    /// Preview `path`, an empty path hides the preview
+    pub fn set_path(&self, path: &str) {
+        let imp = self.imp();
+        self.set_visible(!path.is_empty());
+        if path.is_empty() || *imp.path.borrow() == path {
+            return;
+        }
+
+        imp.path.replace(path.to_string());
+        let name = std::path::Path::new(path).file_name().map_or_else(
+            || path.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        imp.name.set_text(&name);
+        imp.details.set_text("");
+        imp.image.set_icon_name(Some("image-loading-symbolic"));
+
+        let obj = self.clone();
+        let path = path.to_string();
+        glib::MainContext::default().spawn_local(async move { obj.imp().load(path).await });
+    }
+}
@@ -0,0 +1,167 @@
+use std::cell::{Cell, RefCell};
+
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::{gdk, glib, graphene, pango};
+
+use crate::nvim::{GutterLines, Viewport};
+use crate::widgets::ExtLineGrid;
+
+/// Cells reserved for the sign in front of the line numbers
+const SIGN_CELLS: usize = 2;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct Gutter {
+        pub grid: glib::WeakRef<ExtLineGrid>,
+        pub lines: RefCell<GutterLines>,
+        pub viewport: Cell<Viewport>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Gutter {
+        const NAME: &'static str = "Gutter";
+        type Type = super::Gutter;
+        type ParentType = gtk::Widget;
+    }
+
+    impl ObjectImpl for Gutter {
+        fn constructed(&self) {
+            self.parent_constructed();
+            // Line numbers and signs are laid out like the grid next to them
+            self.obj().set_direction(gtk::TextDirection::Ltr);
+        }
+    }
+
+    impl WidgetImpl for Gutter {
+        fn measure(&self, orientation: gtk::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+            let Some(grid) = self.grid.upgrade() else {
+                return (0, 0, -1, -1);
+            };
+
+            match orientation {
+                gtk::Orientation::Horizontal => {
+                    let cells = self.obj().width_cells();
+                    let width = (grid.cell_metrics().char_width * cells as f64).ceil() as i32;
+                    (width, width, -1, -1)
+                }
+                _ => (0, 0, -1, -1),
+            }
+        }
+
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            let Some(grid) = self.grid.upgrade() else {
+                return;
+            };
+            let Some(colors) = grid.default_colors() else {
+                return;
+            };
+            let (Some(fg), Some(bg)) = (colors.foreground, colors.background) else {
+                return;
+            };
+
+            let obj = self.obj();
+            let cell_metrics = grid.cell_metrics();
+            let font = grid.font_description();
+            let width = obj.width() as f32;
+
+            snapshot.append_color(
+                &gdk::RGBA::new(bg.r, bg.g, bg.b, 1.0),
+                &graphene::Rect::new(0.0, 0.0, width, obj.height() as f32),
+            );
+
+            let lines = self.lines.borrow();
+            let curline = self.viewport.get().curline;
+            let number_cells = obj.width_cells() - SIGN_CELLS - 1;
+
+            for (i, row) in lines.rows.iter().enumerate() {
+                let y = (lines.first_row + i) as f64 * cell_metrics.line_height;
+
+                let draw = |text: &str, x: f64, color: gdk::RGBA, bold: bool| {
+                    let layout = obj.create_pango_layout(Some(text));
+                    if let Some(font) = &font {
+                        let mut font = font.clone();
+                        if bold {
+                            font.set_weight(pango::Weight::Bold);
+                        }
+                        layout.set_font_description(Some(&font));
+                    }
+
+                    snapshot.save();
+                    snapshot.translate(&graphene::Point::new(x as f32, y as f32));
+                    snapshot.append_layout(&layout, &color);
+                    snapshot.restore();
+                };
+
+                if !row.sign.is_empty() {
+                    let color = row
+                        .sign_color
+                        .as_deref()
+                        .and_then(|color| gdk::RGBA::parse(color).ok())
+                        .unwrap_or(gdk::RGBA::new(fg.r, fg.g, fg.b, 1.0));
+                    draw(&row.sign, 0.0, color, false);
+                }
+
+                if let Some(line) = row.line {
+                    let current = line == curline;
+                    let alpha = if current { 1.0 } else { 0.5 };
+                    let text = format!("{line:>number_cells$}");
+                    let x = SIGN_CELLS as f64 * cell_metrics.char_width;
+                    draw(&text, x, gdk::RGBA::new(fg.r, fg.g, fg.b, alpha), current);
+                }
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Line numbers and signs of the current window drawn by the GUI,
+    /// meant to be used with 'nonumber' and 'signcolumn=no'
+    pub struct Gutter(ObjectSubclass<imp::Gutter>)
+        @extends gtk::Widget;
+}
+
+impl Gutter {
+    /// Follow the font, cell size and colors of `grid`
+    pub fn set_grid_widget(&self, grid: &ExtLineGrid) {
+        self.imp().grid.set(Some(grid));
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.imp().viewport.get()
+    }
+
+    pub fn set_viewport(&self, viewport: Viewport) {
+        let previous = self.imp().viewport.replace(viewport);
+        if previous.line_count.to_string().len() != viewport.line_count.to_string().len() {
+            self.queue_resize();
+        }
+        self.queue_draw();
+    }
+
+    pub fn set_lines(&self, lines: GutterLines) {
+        *self.imp().lines.borrow_mut() = lines;
+        self.queue_draw();
+    }
+
+    /// Buffer line at widget coordinates and whether the sign was hit
+    pub fn line_at(&self, x: f64, y: f64) -> Option<(u64, bool)> {
+        let grid = self.imp().grid.upgrade()?;
+        let (col, row) = grid.cell_metrics().cell_cords(x, y);
+
+        let lines = self.imp().lines.borrow();
+        let gutter_row = lines
+            .rows
+            .get((row as usize).checked_sub(lines.first_row)?)?;
+        let on_sign = (col as usize) < SIGN_CELLS && !gutter_row.sign.is_empty();
+
+        Some((gutter_row.line?, on_sign))
+    }
+
+    /// Signs, line numbers wide enough for the whole buffer like 'numberwidth', and a space
+    fn width_cells(&self) -> usize {
+        let digits = self.imp().viewport.get().line_count.to_string().len();
+        SIGN_CELLS + digits.max(3) + 1
+    }
+}
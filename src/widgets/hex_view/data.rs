@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Bytes per row of the hex view
+pub const ROW_BYTES: u64 = 16;
+/// Bytes read from the file at once
+const CHUNK_SIZE: u64 = 64 * 1024;
+/// Chunks kept in memory, the cache starts over beyond this
+const MAX_CHUNKS: usize = 32;
+/// Bytes looked at to tell binary files from text, like git does
+const SNIFF_SIZE: usize = 8000;
+
+/// A file read in chunks as rows are shown, so large files open instantly
+#[derive(Debug)]
+pub struct HexFile {
+    file: File,
+    pub size: u64,
+    chunks: HashMap<u64, Vec<u8>>,
+}
+
+impl HexFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            file,
+            size,
+            chunks: HashMap::new(),
+        })
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.size.div_ceil(ROW_BYTES)
+    }
+
+    fn chunk(&mut self, index: u64) -> io::Result<&[u8]> {
+        if !self.chunks.contains_key(&index) {
+            if self.chunks.len() >= MAX_CHUNKS {
+                self.chunks.clear();
+            }
+            let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize);
+            self.file.seek(SeekFrom::Start(index * CHUNK_SIZE))?;
+            (&self.file).take(CHUNK_SIZE).read_to_end(&mut chunk)?;
+            self.chunks.insert(index, chunk);
+        }
+        Ok(&self.chunks[&index])
+    }
+
+    /// Up to `len` bytes from `offset` on, fewer at the end of the file
+    pub fn bytes(&mut self, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let end = (offset + len).min(self.size);
+        let mut bytes = Vec::with_capacity(end.saturating_sub(offset) as usize);
+        let mut pos = offset;
+        while pos < end {
+            let chunk = self.chunk(pos / CHUNK_SIZE)?;
+            let start = (pos % CHUNK_SIZE) as usize;
+            let take = ((end - pos) as usize).min(chunk.len().saturating_sub(start));
+            if take == 0 {
+                // The file shrank since it was opened
+                break;
+            }
+            bytes.extend_from_slice(&chunk[start..start + take]);
+            pos += take as u64;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Whether the file starting with `head` is binary: it has a NUL byte early on
+pub fn is_binary(head: &[u8]) -> bool {
+    head.iter().take(SNIFF_SIZE).any(|byte| *byte == 0)
+}
+
+/// Whether the file at `path` is binary, see [`is_binary`]
+pub fn is_binary_file(path: &Path) -> bool {
+    let mut head = Vec::with_capacity(SNIFF_SIZE);
+    File::open(path)
+        .and_then(|file| file.take(SNIFF_SIZE as u64).read_to_end(&mut head))
+        .is_ok_and(|_| is_binary(&head))
+}
+
+/// `bytes` as lowercase hex pairs separated by spaces
+pub fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Offset, hex and printable ASCII columns of the row at `offset`, like `xxd -g1`
+pub fn format_row(offset: u64, bytes: &[u8]) -> String {
+    let (first, second) = bytes.split_at(bytes.len().min(ROW_BYTES as usize / 2));
+    let hex = format!("{:<23}  {:<23}", hex(first), hex(second));
+    let ascii: String = bytes
+        .iter()
+        .map(|byte| match byte {
+            0x20..=0x7e => *byte as char,
+            _ => '.',
+        })
+        .collect();
+    format!("{offset:08x}  {hex}  {ascii}")
+}
+
+/// An offset typed into "Go to Offset": hex with `0x` or decimal
+pub fn parse_offset(text: &str) -> Option<u64> {
+    let text = text.trim().replace('_', "");
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_row() {
+        assert_eq!(
+            format_row(0x10, b"Hello, world!\0\x01\xff"),
+            "00000010  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 00 01 ff  Hello, world!..."
+        );
+        assert_eq!(
+            format_row(0x20, b"ab"),
+            "00000020  61 62                                             ab"
+        );
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("0x1f"), Some(31));
+        assert_eq!(parse_offset(" 1_024 "), Some(1024));
+        assert_eq!(parse_offset("0xzz"), None);
+        assert_eq!(parse_offset("-1"), None);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\0"));
+        assert!(!is_binary("fn main() {}\n".as_bytes()));
+    }
+}
@@ -0,0 +1,267 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib;
+
+pub mod data;
+mod model;
+use data::{HexFile, ROW_BYTES};
+use model::HexRowsModel;
+
+use crate::locale;
+use crate::nvim::{GtkToNvimEvent, NvimBridge};
+use std::{
+    cell::{OnceCell, RefCell},
+    path::Path,
+};
+
+/// Bytes "Copy as Hex" copies at most, a dump of a whole large file isn't worth a clipboard
+const MAX_COPY: u64 = 1024 * 1024;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct HexView {
+        pub title: adw::WindowTitle,
+        pub offset: gtk::Entry,
+        pub list: gtk::ListView,
+        pub model: HexRowsModel,
+        pub toast_overlay: adw::ToastOverlay,
+        pub path: RefCell<String>,
+        pub file: RefCell<Option<HexFile>>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+    }
+
+    impl HexView {
+        fn selection(&self) -> Option<gtk::MultiSelection> {
+            self.list.model().and_downcast::<gtk::MultiSelection>()
+        }
+
+        fn row_text(&self, row: u32) -> String {
+            let offset = u64::from(row) * ROW_BYTES;
+            let mut file = self.file.borrow_mut();
+            let Some(file) = file.as_mut() else {
+                return String::new();
+            };
+            match file.bytes(offset, ROW_BYTES) {
+                Ok(bytes) => data::format_row(offset, &bytes),
+                Err(err) => {
+                    log::warn!("Could not read {} at {offset}: {err}", self.path.borrow());
+                    format!("{offset:08x}  {}", gettext("Unreadable"))
+                }
+            }
+        }
+
+        fn goto_offset(&self) {
+            let text = self.offset.text();
+            let size = self.file.borrow().as_ref().map_or(0, |file| file.size);
+            let Some(offset) = data::parse_offset(&text).filter(|offset| *offset < size) else {
+                self.offset.add_css_class("error");
+                return;
+            };
+            self.offset.remove_css_class("error");
+
+            let row = (offset / ROW_BYTES) as u32;
+            if let Some(selection) = self.selection() {
+                selection.select_item(row, true);
+            }
+            // `ListView::scroll_to` needs GTK 4.12
+            self.list
+                .activate_action("list.scroll-to-item", Some(&row.to_variant()))
+                .unwrap();
+            self.list.grab_focus();
+        }
+
+        fn copy_hex(&self) {
+            let Some(selection) = self.selection().map(|selection| selection.selection()) else {
+                return;
+            };
+            if selection.is_empty() {
+                return;
+            }
+
+            // The rows in between count as selected, copying one gapless range of bytes
+            let start = u64::from(selection.minimum()) * ROW_BYTES;
+            let end = (u64::from(selection.maximum()) + 1) * ROW_BYTES;
+            let len = (end - start).min(MAX_COPY);
+            let bytes = match self.file.borrow_mut().as_mut() {
+                Some(file) => file.bytes(start, len),
+                None => return,
+            };
+            let toast = match bytes {
+                Ok(bytes) => {
+                    self.obj().clipboard().set_text(&data::hex(&bytes));
+                    if end - start > MAX_COPY {
+                        gettext("Copied the first {size} of the selection")
+                            .replace("{size}", &locale::size(MAX_COPY))
+                    } else {
+                        gettext("Copied {size}")
+                            .replace("{size}", &locale::size(bytes.len() as u64))
+                    }
+                }
+                Err(err) => {
+                    gettext("Could not read the file: {err}").replace("{err}", &err.to_string())
+                }
+            };
+            self.toast_overlay.add_toast(adw::Toast::new(&toast));
+        }
+
+        fn open_in_nvim(&self) {
+            if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').open_hex({:?})",
+                    *self.path.borrow()
+                )))
+                .unwrap();
+            }
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HexView {
+        const NAME: &'static str = "HexView";
+        type Type = super::HexView;
+        type ParentType = adw::Window;
+    }
+
+    impl ObjectImpl for HexView {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_default_size(720, 640);
+
+            let header_bar = adw::HeaderBar::new();
+            header_bar.set_title_widget(Some(&self.title));
+
+            self.offset
+                .set_placeholder_text(Some(&gettext("Go to Offset")));
+            self.offset.set_tooltip_text(Some(&gettext(
+                "Offset to go to, hexadecimal with 0x or decimal",
+            )));
+            self.offset.set_width_chars(12);
+            self.offset
+                .connect_activate(glib::clone!(@weak obj => move |_| obj.imp().goto_offset()));
+            header_bar.pack_start(&self.offset);
+
+            let copy = gtk::Button::from_icon_name("edit-copy-symbolic");
+            copy.set_tooltip_text(Some(&gettext("Copy as Hex")));
+            copy.connect_clicked(glib::clone!(@weak obj => move |_| obj.imp().copy_hex()));
+            header_bar.pack_start(&copy);
+
+            let xxd = gtk::Button::builder()
+                .label(gettext("Open in nvim with _xxd"))
+                .use_underline(true)
+                .tooltip_text(gettext(
+                    "Edit a hex dump of the file, converted back when writing",
+                ))
+                .build();
+            xxd.connect_clicked(glib::clone!(@weak obj => move |_| obj.imp().open_in_nvim()));
+            header_bar.pack_end(&xxd);
+
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(|_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                item.set_child(Some(
+                    &gtk::Label::builder()
+                        .xalign(0.0)
+                        .margin_start(12)
+                        .margin_end(12)
+                        .css_classes(["monospace"])
+                        .build(),
+                ));
+            });
+            factory.connect_bind(glib::clone!(@weak obj => move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let (Some(label), Some(row)) = (
+                    item.child().and_downcast::<gtk::Label>(),
+                    item.item().and_downcast::<glib::BoxedAnyObject>(),
+                ) else {
+                    return;
+                };
+                label.set_text(&obj.imp().row_text(*row.borrow::<u32>()));
+            }));
+
+            self.list
+                .set_model(Some(&gtk::MultiSelection::new(Some(self.model.clone()))));
+            self.list.set_factory(Some(&factory));
+            // Offsets, bytes and ASCII read left to right in every locale
+            self.list.set_direction(gtk::TextDirection::Ltr);
+
+            let shortcuts = gtk::ShortcutController::new();
+            shortcuts.add_shortcut(gtk::Shortcut::new(
+                gtk::ShortcutTrigger::parse_string("<Control>c"),
+                Some(gtk::CallbackAction::new(glib::clone!(
+                    @weak obj => @default-return false, move |_, _| {
+                        obj.imp().copy_hex();
+                        true
+                    }
+                ))),
+            ));
+            self.list.add_controller(shortcuts);
+
+            let scrolled = gtk::ScrolledWindow::builder()
+                .child(&self.list)
+                .hscrollbar_policy(gtk::PolicyType::Automatic)
+                .vexpand(true)
+                .build();
+            self.toast_overlay.set_child(Some(&scrolled));
+
+            let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+            content.append(&header_bar);
+            content.append(&self.toast_overlay);
+            obj.set_content(Some(&content));
+
+            let escape = gtk::ShortcutController::new();
+            escape.add_shortcut(gtk::Shortcut::new(
+                gtk::ShortcutTrigger::parse_string("Escape"),
+                Some(gtk::NamedAction::new("window.close")),
+            ));
+            obj.add_controller(escape);
+        }
+    }
+    impl WidgetImpl for HexView {}
+    impl WindowImpl for HexView {}
+    impl AdwWindowImpl for HexView {}
+}
+
+glib::wrapper! {
+    /// Offset, hex and ASCII columns of a binary file, which nvim would show as garbage. Rows are
+    /// read from the file as they scroll into view.
+    pub struct HexView(ObjectSubclass<imp::HexView>)
+        @extends adw::Window, gtk::Window, gtk::Widget;
+}
+
+impl Default for HexView {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl HexView {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    /// Show the file at `path`
+    pub fn set_path(&self, path: &str) -> std::io::Result<()> {
+        let imp = self.imp();
+        let file = HexFile::open(Path::new(path))?;
+
+        let name = Path::new(path).file_name().map_or_else(
+            || path.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        imp.title.set_title(&name);
+        imp.title.set_subtitle(&locale::size(file.size));
+        self.set_title(Some(&name));
+
+        // Files beyond 64 GiB are cut off, the model can't count more rows
+        let rows = u32::try_from(file.rows()).unwrap_or(u32::MAX);
+        imp.path.replace(path.to_string());
+        imp.file.replace(Some(file));
+        imp.model.set_rows(rows);
+        Ok(())
+    }
+}
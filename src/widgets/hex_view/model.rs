@@ -0,0 +1,55 @@
+use gio::{prelude::*, subclass::prelude::*};
+use gtk::{gio, glib};
+
+use std::cell::Cell;
+
+glib::wrapper! {
+    /// Row numbers of a hex dump, the rows are only read from the file once they are shown
+    pub struct HexRowsModel(ObjectSubclass<imp::HexRowsModel>)
+        @implements gio::ListModel;
+}
+
+impl Default for HexRowsModel {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl HexRowsModel {
+    pub fn set_rows(&self, rows: u32) {
+        let old_rows = self.imp().rows.replace(rows);
+        self.items_changed(0, old_rows, rows);
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct HexRowsModel {
+        pub rows: Cell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HexRowsModel {
+        const NAME: &'static str = "HexRowsModel";
+        type Type = super::HexRowsModel;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for HexRowsModel {}
+
+    impl ListModelImpl for HexRowsModel {
+        fn item(&self, position: u32) -> Option<glib::Object> {
+            (position < self.rows.get()).then(|| glib::BoxedAnyObject::new(position).upcast())
+        }
+
+        fn n_items(&self) -> u32 {
+            self.rows.get()
+        }
+
+        fn item_type(&self) -> glib::Type {
+            glib::BoxedAnyObject::static_type()
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib;
+
+use crate::nvim::{bridge, GtkToNvimEvent, Location, Locations, NvimBridge};
+use std::cell::{OnceCell, RefCell};
+
+/// Where activating a row goes, arguments of `goto_location()` in the runtime
+enum Target {
+    Mark(String),
+    Jump(i64),
+}
+
+fn location_row(location: &Location) -> gtk::ListBoxRow {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+
+    let label = gtk::Label::builder()
+        .label(&location.label)
+        .width_chars(3)
+        .xalign(1.0)
+        .css_classes(["monospace", "heading"])
+        .build();
+    row.append(&label);
+
+    let details = gtk::Box::new(gtk::Orientation::Vertical, 3);
+    if !location.text.is_empty() {
+        details.append(
+            &gtk::Label::builder()
+                .label(&location.text)
+                .xalign(0.0)
+                .max_width_chars(48)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .css_classes(["monospace"])
+                .build(),
+        );
+    }
+    details.append(
+        &gtk::Label::builder()
+            .label(format!("{}:{}", location.path, location.line))
+            .xalign(0.0)
+            .max_width_chars(48)
+            .ellipsize(gtk::pango::EllipsizeMode::Start)
+            .css_classes(["dim-label", "caption"])
+            .build(),
+    );
+    row.append(&details);
+
+    gtk::ListBoxRow::builder().child(&row).build()
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct LocationsPopover {
+        pub marks: gtk::ListBox,
+        pub jumps: gtk::ListBox,
+        /// What the rows show, by position
+        pub locations: RefCell<Locations>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+    }
+
+    impl LocationsPopover {
+        fn refresh(&self) {
+            let Some(nvim) = self.nvim_tx.get().cloned() else {
+                return;
+            };
+
+            let obj = self.obj().clone();
+            bridge::spawn_local(
+                "Listing marks and jumps",
+                async move {
+                    nvim.exec_lua_as("return require('vimdicator').locations()", vec![])
+                        .await
+                },
+                move |locations| obj.imp().set_locations(locations),
+            );
+        }
+
+        fn set_locations(&self, locations: Locations) {
+            for (list, locations) in [
+                (&self.marks, &locations.marks),
+                (&self.jumps, &locations.jumps),
+            ] {
+                while let Some(row) = list.first_child() {
+                    list.remove(&row);
+                }
+                for location in locations {
+                    list.append(&location_row(location));
+                }
+            }
+            self.locations.replace(locations);
+        }
+
+        fn go(&self, target: Target) {
+            let code = match target {
+                Target::Mark(mark) => format!("require('vimdicator').goto_location({mark:?})"),
+                Target::Jump(count) => format!("require('vimdicator').goto_location(nil, {count})"),
+            };
+            if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::ExecLua(code)).unwrap();
+            }
+            self.obj().popdown();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LocationsPopover {
+        const NAME: &'static str = "LocationsPopover";
+        type Type = super::LocationsPopover;
+        type ParentType = gtk::Popover;
+    }
+
+    impl ObjectImpl for LocationsPopover {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            let container = gtk::Box::new(gtk::Orientation::Vertical, 6);
+
+            for (title, list, placeholder) in [
+                (gettext("Marks"), &self.marks, gettext("No marks")),
+                (gettext("Jumps"), &self.jumps, gettext("No jumps")),
+            ] {
+                container.append(
+                    &gtk::Label::builder()
+                        .label(title)
+                        .xalign(0.0)
+                        .css_classes(["heading"])
+                        .build(),
+                );
+
+                list.add_css_class("boxed-list");
+                list.set_selection_mode(gtk::SelectionMode::None);
+                list.set_placeholder(Some(
+                    &gtk::Label::builder()
+                        .label(placeholder)
+                        .margin_top(12)
+                        .margin_bottom(12)
+                        .css_classes(["dim-label"])
+                        .build(),
+                ));
+                container.append(list);
+            }
+
+            self.marks
+                .connect_row_activated(glib::clone!(@weak obj => move |_, row| {
+                    let locations = obj.imp().locations.borrow();
+                    let mark = locations.marks.get(row.index() as usize).map(|m| m.label.clone());
+                    drop(locations);
+                    if let Some(mark) = mark {
+                        obj.imp().go(Target::Mark(mark));
+                    }
+                }));
+            self.jumps
+                .connect_row_activated(glib::clone!(@weak obj => move |_, row| {
+                    let locations = obj.imp().locations.borrow();
+                    let jump = locations.jumps.get(row.index() as usize);
+                    let count = jump.and_then(|jump| jump.label.parse().ok());
+                    drop(locations);
+                    if let Some(count) = count {
+                        obj.imp().go(Target::Jump(count));
+                    }
+                }));
+
+            let scrolled = gtk::ScrolledWindow::builder()
+                .child(&container)
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .propagate_natural_height(true)
+                .max_content_height(480)
+                .build();
+            obj.set_child(Some(&scrolled));
+
+            obj.connect_show(|popover| popover.imp().refresh());
+        }
+    }
+    impl WidgetImpl for LocationsPopover {}
+    impl PopoverImpl for LocationsPopover {}
+}
+
+glib::wrapper! {
+    /// Marks and the jump list of the current window, activating a row jumps there
+    pub struct LocationsPopover(ObjectSubclass<imp::LocationsPopover>)
+        @extends gtk::Popover, gtk::Widget;
+}
+
+impl Default for LocationsPopover {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl LocationsPopover {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+}
@@ -1,3 +1,9 @@
+pub mod colorscheme_dialog;
+pub use colorscheme_dialog::ColorschemeDialog;
+
+pub mod debug_overlay;
+pub use debug_overlay::DebugOverlay;
+
 pub mod ext_line_grid;
 pub use ext_line_grid::ExtLineGrid;
 
@@ -7,5 +13,32 @@ pub use ext_popup_menu::ExtPopupMenu;
 pub mod ext_tab_line;
 pub use ext_tab_line::ExtTabLine;
 
+pub mod file_preview;
+pub use file_preview::FilePreview;
+
+pub mod gutter;
+pub use gutter::Gutter;
+
+pub mod hex_view;
+pub use hex_view::HexView;
+
+pub mod locations_popover;
+pub use locations_popover::LocationsPopover;
+
+pub mod path_bar;
+pub use path_bar::PathBar;
+
+pub mod quickfix_panel;
+pub use quickfix_panel::QuickfixPanel;
+
+pub mod registers_panel;
+pub use registers_panel::RegistersPanel;
+
+pub mod search_panel;
+pub use search_panel::SearchPanel;
+
+pub mod status_footer;
+pub use status_footer::StatusFooter;
+
 pub mod window;
 pub use window::VimdicatorWindow;
@@ -0,0 +1,417 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{gdk, glib};
+
+use crate::{
+    clock,
+    git_status::{self, Entry, Status},
+    nvim::{GtkToNvimEvent, NvimBridge},
+};
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+/// How often the git status is read again while the path bar is shown, besides on writes
+const GIT_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Label and directory of each breadcrumb of `root`, starting at `~` inside `home`
+fn segments(root: &Path, home: &Path) -> Vec<(String, PathBuf)> {
+    let (mut path, rest, mut segments) = match root.strip_prefix(home) {
+        Ok(rest) if !home.as_os_str().is_empty() => (
+            home.to_path_buf(),
+            rest,
+            vec![("~".to_string(), home.to_path_buf())],
+        ),
+        _ => (PathBuf::new(), root, Vec::new()),
+    };
+
+    for component in rest.components() {
+        path.push(component);
+        let label = match component {
+            Component::Normal(name) => name.to_string_lossy().into_owned(),
+            _ => path.display().to_string(),
+        };
+        segments.push((label, path.clone()));
+    }
+    segments
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct PathBar {
+        pub stack: gtk::Stack,
+        pub scroller: gtk::ScrolledWindow,
+        pub crumbs: gtk::Box,
+        pub entry: gtk::Entry,
+        /// Narrows the file browser to matching entries through nvim-tree's live filter
+        pub filter: gtk::SearchEntry,
+        /// Root of the file browser the breadcrumbs show
+        pub root: RefCell<String>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+        /// Counts of the git status, its popover lists the files
+        pub git_button: gtk::MenuButton,
+        pub git_list: gtk::ListBox,
+        /// Counts the git status reads, only the latest one is shown
+        pub git_generation: Cell<u64>,
+    }
+
+    impl PathBar {
+        pub fn change_root(&self, path: &str) {
+            if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').change_root({path:?})"
+                )))
+                .unwrap();
+            }
+        }
+
+        fn git_row(&self, root: &Path, entry: &Entry) -> gtk::ListBoxRow {
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+            row.append(
+                &gtk::Label::builder()
+                    .label(entry.status.letter())
+                    .tooltip_text(entry.status.description())
+                    .width_chars(1)
+                    .css_classes(["monospace", "heading"])
+                    .build(),
+            );
+            let path = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+            row.append(
+                &gtk::Label::builder()
+                    .label(path.display().to_string())
+                    .xalign(0.0)
+                    .max_width_chars(48)
+                    .ellipsize(gtk::pango::EllipsizeMode::Start)
+                    .build(),
+            );
+            if entry.status == Status::Ignored {
+                row.add_css_class("dim-label");
+            }
+
+            // Directories and deleted files have nothing to open
+            let is_dir = entry.path.to_string_lossy().ends_with('/');
+            gtk::ListBoxRow::builder()
+                .child(&row)
+                .activatable(!is_dir && entry.status != Status::Deleted)
+                .build()
+        }
+
+        pub fn show_git_status(&self, entries: Option<Vec<Entry>>) {
+            while let Some(child) = self.git_list.first_child() {
+                self.git_list.remove(&child);
+            }
+            let entries = entries.unwrap_or_default();
+            let summary = git_status::summary(&entries);
+            self.git_button.set_visible(!summary.is_empty());
+            self.git_button.set_label(&summary);
+
+            let root = PathBuf::from(&*self.root.borrow());
+            for entry in &entries {
+                let row = self.git_row(&root, entry);
+                let path = entry.path.display().to_string();
+                row.connect_activate(glib::clone!(@weak self as imp => move |_| {
+                    imp.git_button.popdown();
+                    if let Some(tx) = imp.nvim_tx.get() {
+                        tx.send(GtkToNvimEvent::OpenFile {
+                            path: path.clone(),
+                            line: None,
+                        })
+                        .unwrap();
+                    }
+                }));
+                self.git_list.append(&row);
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PathBar {
+        const NAME: &'static str = "PathBar";
+        type Type = super::PathBar;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for PathBar {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_orientation(gtk::Orientation::Horizontal);
+            obj.set_spacing(6);
+            obj.add_css_class("toolbar");
+
+            self.crumbs.set_spacing(3);
+            self.scroller
+                .set_policy(gtk::PolicyType::External, gtk::PolicyType::Never);
+            self.scroller.set_child(Some(&self.crumbs));
+
+            self.entry
+                .set_placeholder_text(Some(&gettext("Folder path")));
+            self.entry
+                .connect_activate(glib::clone!(@weak obj => move |entry| {
+                    obj.imp().change_root(&entry.text());
+                    obj.stop_editing();
+                }));
+            let escape = gtk::EventControllerKey::new();
+            escape.connect_key_pressed(
+                glib::clone!(@weak obj => @default-return gtk::Inhibit(false), move |_, key, _, _| {
+                    if key != gdk::Key::Escape {
+                        return gtk::Inhibit(false);
+                    }
+                    obj.stop_editing();
+                    gtk::Inhibit(true)
+                }),
+            );
+            self.entry.add_controller(escape);
+
+            self.stack.set_hexpand(true);
+            self.stack
+                .set_transition_type(gtk::StackTransitionType::Crossfade);
+            self.stack.add_named(&self.scroller, Some("crumbs"));
+            self.stack.add_named(&self.entry, Some("entry"));
+            obj.append(&self.stack);
+
+            self.filter
+                .set_placeholder_text(Some(&gettext("Filter Files")));
+            self.filter.set_width_chars(16);
+            self.filter
+                .connect_search_changed(glib::clone!(@weak obj => move |filter| {
+                    if let Some(tx) = obj.imp().nvim_tx.get() {
+                        tx.send(GtkToNvimEvent::ExecLua(format!(
+                            "require('vimdicator').filter_file_browser({:?})",
+                            filter.text()
+                        )))
+                        .unwrap();
+                    }
+                }));
+            obj.append(&self.filter);
+
+            let edit = gtk::Button::builder()
+                .icon_name("document-edit-symbolic")
+                .tooltip_text(gettext("Type a Path (Ctrl+L)"))
+                .css_classes(["flat"])
+                .build();
+            edit.connect_clicked(glib::clone!(@weak obj => move |_| obj.edit()));
+            obj.append(&edit);
+
+            self.git_list.set_selection_mode(gtk::SelectionMode::None);
+            self.git_list.add_css_class("navigation-sidebar");
+            self.git_list
+                .connect_row_activated(|_, row| row.emit_activate());
+            let scroller = gtk::ScrolledWindow::builder()
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .propagate_natural_height(true)
+                .max_content_height(360)
+                .child(&self.git_list)
+                .build();
+            self.git_button
+                .set_popover(Some(&gtk::Popover::builder().child(&scroller).build()));
+            self.git_button
+                .set_tooltip_text(Some(&gettext("Git Status")));
+            self.git_button.add_css_class("flat");
+            self.git_button.set_visible(false);
+            obj.append(&self.git_button);
+
+            let weak = obj.downgrade();
+            clock::timeout(GIT_REFRESH_INTERVAL, move || {
+                let Some(obj) = weak.upgrade() else {
+                    return glib::Continue(false);
+                };
+                if obj.is_mapped() {
+                    obj.refresh_git_status();
+                }
+                glib::Continue(true)
+            });
+        }
+    }
+    impl WidgetImpl for PathBar {}
+    impl BoxImpl for PathBar {}
+}
+
+glib::wrapper! {
+    /// Breadcrumbs of the root of the nvim-tree file browser, clicking one re-roots the tree.
+    /// Ctrl+L in the browser switches to an entry for typing or pasting a path instead. Next to
+    /// them are an entry filtering the browser's files and the git status of the root, summed
+    /// up with a popover listing the files.
+    pub struct PathBar(ObjectSubclass<imp::PathBar>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Orientable;
+}
+
+impl Default for PathBar {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl PathBar {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    /// Show the breadcrumbs of `root`, the directory at the top of the file browser
+    pub fn set_root(&self, root: &str) {
+        let imp = self.imp();
+        if *imp.root.borrow() == root {
+            return;
+        }
+        imp.root.replace(root.to_string());
+
+        while let Some(child) = imp.crumbs.first_child() {
+            imp.crumbs.remove(&child);
+        }
+
+        let segments = segments(Path::new(root), &glib::home_dir());
+        let last = segments.len().saturating_sub(1);
+        for (i, (label, path)) in segments.into_iter().enumerate() {
+            if i > 0 {
+                imp.crumbs.append(
+                    &gtk::Label::builder()
+                        .label("›")
+                        .css_classes(["dim-label"])
+                        .build(),
+                );
+            }
+            if i == last {
+                imp.crumbs.append(
+                    &gtk::Label::builder()
+                        .label(&label)
+                        .margin_start(6)
+                        .margin_end(6)
+                        .css_classes(["heading"])
+                        .build(),
+                );
+                continue;
+            }
+
+            let button = gtk::Button::builder()
+                .label(&label)
+                .tooltip_text(path.display().to_string())
+                .css_classes(["flat"])
+                .build();
+            let path = path.display().to_string();
+            button.connect_clicked(glib::clone!(@weak self as obj => move |_| {
+                obj.imp().change_root(&path);
+            }));
+            imp.crumbs.append(&button);
+        }
+
+        // nvim-tree starts over unfiltered in another root
+        imp.filter.set_text("");
+        self.refresh_git_status();
+
+        // Deep roots keep their last directories in view
+        let adjustment = imp.scroller.hadjustment();
+        glib::idle_add_local_once(move || adjustment.set_value(adjustment.upper()));
+    }
+
+    /// Call `f` when the filter entry was activated or dismissed, to go on in the file browser
+    pub fn connect_filter_done<F: Fn(&Self) + Clone + 'static>(&self, f: F) {
+        let filter = &self.imp().filter;
+        let obj = self.downgrade();
+        let done = f.clone();
+        filter.connect_activate(move |_| {
+            if let Some(obj) = obj.upgrade() {
+                done(&obj);
+            }
+        });
+        let obj = self.downgrade();
+        filter.connect_stop_search(move |filter| {
+            filter.set_text("");
+            if let Some(obj) = obj.upgrade() {
+                f(&obj);
+            }
+        });
+    }
+
+    /// Read the git status of the root again, e.g. after a file was written
+    pub fn refresh_git_status(&self) {
+        let imp = self.imp();
+        let root = imp.root.borrow().clone();
+        if root.is_empty() {
+            return;
+        }
+
+        let generation = imp.git_generation.get() + 1;
+        imp.git_generation.set(generation);
+        let obj = self.downgrade();
+        glib::MainContext::default().spawn_local(async move {
+            let entries = git_status::status(root).await;
+            let Some(obj) = obj.upgrade() else { return; };
+            // A newer read or another root took over meanwhile
+            if obj.imp().git_generation.get() == generation {
+                obj.imp().show_git_status(entries);
+            }
+        });
+    }
+
+    /// Switch to the entry with the current root, to type or paste a path
+    pub fn edit(&self) {
+        let imp = self.imp();
+        let root = imp.root.borrow();
+        imp.entry
+            .set_text(&format!("{}/", root.trim_end_matches('/')));
+        imp.stack.set_visible_child_name("entry");
+        imp.entry.grab_focus();
+        imp.entry.set_position(-1);
+    }
+
+    /// Back to the breadcrumbs
+    pub fn stop_editing(&self) {
+        self.imp().stack.set_visible_child_name("crumbs");
+    }
+
+    /// Call `f` when the entry was activated or dismissed
+    pub fn connect_editing_stopped<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        let obj = self.downgrade();
+        self.imp()
+            .stack
+            .connect_visible_child_name_notify(move |stack| {
+                let Some(obj) = obj.upgrade() else { return; };
+                if stack.visible_child_name().as_deref() == Some("crumbs") {
+                    f(&obj);
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(root: &str, home: &str) -> Vec<(String, String)> {
+        segments(Path::new(root), Path::new(home))
+            .into_iter()
+            .map(|(label, path)| (label, path.display().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_segments() {
+        let owned = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(label, path)| (label.to_string(), path.to_string()))
+                .collect()
+        };
+
+        assert_eq!(
+            labels("/home/me/src/app", "/home/me"),
+            owned(&[
+                ("~", "/home/me"),
+                ("src", "/home/me/src"),
+                ("app", "/home/me/src/app")
+            ])
+        );
+        assert_eq!(labels("/home/me", "/home/me"), owned(&[("~", "/home/me")]));
+        assert_eq!(
+            labels("/home/meta", "/home/me"),
+            owned(&[("/", "/"), ("home", "/home"), ("meta", "/home/meta")])
+        );
+        assert_eq!(labels("/usr", ""), owned(&[("/", "/"), ("usr", "/usr")]));
+    }
+}
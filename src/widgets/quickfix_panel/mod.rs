@@ -0,0 +1,225 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
+use gtk::{gio, glib};
+
+use crate::locale;
+use crate::nvim::{GtkToNvimEvent, NvimBridge, QuickfixEntry, QuickfixList};
+use std::cell::OnceCell;
+
+fn icon_name(kind: &str) -> Option<&'static str> {
+    match kind {
+        "E" | "e" => Some("dialog-error-symbolic"),
+        "W" | "w" => Some("dialog-warning-symbolic"),
+        "I" | "i" | "N" | "n" => Some("dialog-information-symbolic"),
+        _ => None,
+    }
+}
+
+fn markup(entry: &QuickfixEntry) -> String {
+    let text = glib::markup_escape_text(entry.text.trim());
+    if !entry.valid || entry.path.is_empty() {
+        return text.to_string();
+    }
+
+    let location = match (entry.line, entry.column) {
+        (0, _) => entry.path.clone(),
+        (line, 0) => format!("{}:{line}", entry.path),
+        (line, column) => format!("{}:{line}:{column}", entry.path),
+    };
+    format!(
+        "<span alpha=\"55%\">{}</span>  {text}",
+        glib::markup_escape_text(&location)
+    )
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct QuickfixPanel {
+        pub title: gtk::Label,
+        pub count: gtk::Label,
+        pub selection: OnceCell<gtk::SingleSelection>,
+        pub list_view: OnceCell<gtk::ListView>,
+        pub store: OnceCell<gio::ListStore>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+    }
+
+    impl QuickfixPanel {
+        /// Runs an Ex command moving in the quickfix list, nvim answers with the updated list
+        pub fn command(&self, command: &str) {
+            if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').quickfix_command({command:?})"
+                )))
+                .unwrap();
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for QuickfixPanel {
+        const NAME: &'static str = "QuickfixPanel";
+        type Type = super::QuickfixPanel;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for QuickfixPanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+            let toolbar = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            toolbar.add_css_class("toolbar");
+
+            self.title.add_css_class("heading");
+            self.title.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            self.title.set_xalign(0.0);
+            toolbar.append(&self.title);
+
+            self.count.add_css_class("dim-label");
+            self.count.add_css_class("numeric");
+            self.count.set_hexpand(true);
+            self.count.set_xalign(0.0);
+            toolbar.append(&self.count);
+
+            let navigation = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+            navigation.add_css_class("linked");
+            for (icon, tooltip, command) in [
+                ("go-up-symbolic", gettext("Previous Entry"), "cprevious"),
+                ("go-down-symbolic", gettext("Next Entry"), "cnext"),
+            ] {
+                let button = gtk::Button::from_icon_name(icon);
+                button.set_tooltip_text(Some(&tooltip));
+                button.connect_clicked(glib::clone!(@weak obj => move |_| {
+                    obj.imp().command(command);
+                }));
+                navigation.append(&button);
+            }
+            toolbar.append(&navigation);
+
+            let close = gtk::Button::from_icon_name("window-close-symbolic");
+            close.set_tooltip_text(Some(&gettext("Close")));
+            close.set_action_name(Some("win.quickfix-panel"));
+            close.add_css_class("flat");
+            toolbar.append(&close);
+            container.append(&toolbar);
+
+            let store = gio::ListStore::new(glib::BoxedAnyObject::static_type());
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(|_, item| {
+                let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+                row.append(&gtk::Image::new());
+                row.append(
+                    &gtk::Label::builder()
+                        .xalign(0.0)
+                        .ellipsize(gtk::pango::EllipsizeMode::End)
+                        .build(),
+                );
+                item.downcast_ref::<gtk::ListItem>()
+                    .unwrap()
+                    .set_child(Some(&row));
+            });
+            factory.connect_bind(|_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let (Some(row), Some(entry)) = (
+                    item.child(),
+                    item.item().and_downcast::<glib::BoxedAnyObject>(),
+                ) else {
+                    return;
+                };
+                let entry = entry.borrow::<QuickfixEntry>();
+
+                if let Some(icon) = row.first_child().and_downcast::<gtk::Image>() {
+                    icon.set_icon_name(icon_name(&entry.kind));
+                    icon.set_visible(icon_name(&entry.kind).is_some());
+                }
+                if let Some(label) = row.last_child().and_downcast::<gtk::Label>() {
+                    label.set_markup(&markup(&entry));
+                }
+            });
+
+            let selection = gtk::SingleSelection::new(Some(store.clone()));
+            selection.set_autoselect(false);
+            selection.set_can_unselect(true);
+
+            let list_view = gtk::ListView::new(Some(selection.clone()), Some(factory));
+            list_view.set_single_click_activate(true);
+            list_view.add_css_class("navigation-sidebar");
+            list_view.connect_activate(glib::clone!(@weak obj => move |_, position| {
+                obj.imp().command(&format!("cc {}", position + 1));
+            }));
+
+            let scrolled = gtk::ScrolledWindow::builder()
+                .child(&list_view)
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .vexpand(true)
+                .build();
+            container.append(&scrolled);
+
+            self.store.set(store).unwrap();
+            self.selection.set(selection).unwrap();
+            self.list_view.set(list_view).unwrap();
+            obj.set_child(Some(&container));
+        }
+    }
+    impl WidgetImpl for QuickfixPanel {}
+    impl BinImpl for QuickfixPanel {}
+}
+
+glib::wrapper! {
+    /// nvim's quickfix list, clicking an entry jumps there with `:cc`
+    pub struct QuickfixPanel(ObjectSubclass<imp::QuickfixPanel>)
+        @extends adw::Bin, gtk::Widget;
+}
+
+impl Default for QuickfixPanel {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl QuickfixPanel {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    pub fn set_list(&self, list: QuickfixList) {
+        let imp = self.imp();
+
+        let title = if list.title.is_empty() {
+            gettext("Quickfix List")
+        } else {
+            list.title
+        };
+        imp.title.set_text(&title);
+        let count = list.entries.len();
+        imp.count.set_text(
+            &ngettext("{count} entry", "{count} entries", count as u32)
+                .replace("{count}", &locale::count(count)),
+        );
+
+        let entries: Vec<_> = list
+            .entries
+            .into_iter()
+            .map(glib::BoxedAnyObject::new)
+            .collect();
+        let store = imp.store.get().unwrap();
+        store.splice(0, store.n_items(), &entries);
+
+        let selection = imp.selection.get().unwrap();
+        match list.current.checked_sub(1) {
+            Some(index) if (index as usize) < count => {
+                selection.set_selected(index as u32);
+                imp.list_view
+                    .get()
+                    .unwrap()
+                    .activate_action("list.scroll-to-item", Some(&(index as u32).to_variant()))
+                    .unwrap();
+            }
+            _ => selection.set_selected(gtk::INVALID_LIST_POSITION),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{gio, glib};
+
+use crate::nvim::{GtkToNvimEvent, NvimBridge, Register};
+use std::cell::OnceCell;
+
+/// Lines of a register shown in its row
+const PREVIEW_LINES: usize = 3;
+
+/// Control characters the way nvim shows them (`^[` for escape), recorded macros are full of them
+fn printable(text: &str) -> String {
+    let mut printable = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' | '\t' => printable.push(c),
+            '\x00'..='\x1f' => {
+                printable.push('^');
+                printable.push((c as u8 + b'@') as char);
+            }
+            '\x7f' => printable.push_str("^?"),
+            c => printable.push(c),
+        }
+    }
+    printable
+}
+
+fn kind_label(kind: &str) -> String {
+    match kind {
+        "v" => gettext("Characterwise"),
+        "V" => gettext("Linewise"),
+        _ => gettext("Blockwise"),
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct RegistersPanel {
+        pub store: OnceCell<gio::ListStore>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RegistersPanel {
+        const NAME: &'static str = "RegistersPanel";
+        type Type = super::RegistersPanel;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for RegistersPanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+            let toolbar = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            toolbar.add_css_class("toolbar");
+            let title = gtk::Label::builder()
+                .label(gettext("Registers"))
+                .hexpand(true)
+                .xalign(0.0)
+                .css_classes(["heading"])
+                .build();
+            toolbar.append(&title);
+
+            let close = gtk::Button::from_icon_name("window-close-symbolic");
+            close.set_tooltip_text(Some(&gettext("Close")));
+            close.set_action_name(Some("win.registers-panel"));
+            close.add_css_class("flat");
+            toolbar.append(&close);
+            container.append(&toolbar);
+
+            let store = gio::ListStore::new(glib::BoxedAnyObject::static_type());
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(glib::clone!(@weak obj => move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                item.set_activatable(false);
+
+                let row = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+                let name = gtk::Label::builder()
+                    .width_chars(2)
+                    .valign(gtk::Align::Start)
+                    .css_classes(["monospace", "heading"])
+                    .build();
+                row.append(&name);
+
+                let details = gtk::Box::new(gtk::Orientation::Vertical, 3);
+                details.set_hexpand(true);
+                let kind = gtk::Label::builder()
+                    .xalign(0.0)
+                    .css_classes(["dim-label", "caption"])
+                    .build();
+                details.append(&kind);
+                let preview = gtk::Label::builder()
+                    .xalign(0.0)
+                    .ellipsize(gtk::pango::EllipsizeMode::End)
+                    .css_classes(["monospace"])
+                    .build();
+                details.append(&preview);
+                row.append(&details);
+
+                let register = glib::clone!(@weak item => @default-return None, move || {
+                    let register = item.item().and_downcast::<glib::BoxedAnyObject>()?;
+                    let register = register.borrow::<Register>().clone();
+                    Some(register)
+                });
+
+                let copy = gtk::Button::from_icon_name("edit-copy-symbolic");
+                copy.set_tooltip_text(Some(&gettext("Copy")));
+                copy.set_valign(gtk::Align::Center);
+                copy.add_css_class("flat");
+                let register_copy = register.clone();
+                copy.connect_clicked(move |button| {
+                    if let Some(register) = register_copy() {
+                        button.clipboard().set_text(&register.contents);
+                    }
+                });
+                row.append(&copy);
+
+                let clear = gtk::Button::from_icon_name("edit-clear-symbolic");
+                clear.set_tooltip_text(Some(&gettext("Clear")));
+                clear.set_valign(gtk::Align::Center);
+                clear.add_css_class("flat");
+                clear.connect_clicked(glib::clone!(@weak obj => move |_| {
+                    if let (Some(register), Some(tx)) = (register(), obj.imp().nvim_tx.get()) {
+                        tx.send(GtkToNvimEvent::ExecLua(format!(
+                            "require('vimdicator').clear_register({:?})",
+                            register.name
+                        )))
+                        .unwrap();
+                    }
+                }));
+                row.append(&clear);
+
+                item.set_child(Some(&row));
+            }));
+            factory.connect_bind(|_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let (Some(row), Some(register)) = (
+                    item.child(),
+                    item.item().and_downcast::<glib::BoxedAnyObject>(),
+                ) else {
+                    return;
+                };
+                let register = register.borrow::<Register>();
+
+                let Some(name) = row.first_child().and_downcast::<gtk::Label>() else {
+                    return;
+                };
+                name.set_text(&register.name);
+
+                let Some(details) = name.next_sibling() else {
+                    return;
+                };
+                if let Some(kind) = details.first_child().and_downcast::<gtk::Label>() {
+                    kind.set_text(&kind_label(&register.kind));
+                }
+                if let Some(preview) = details.last_child().and_downcast::<gtk::Label>() {
+                    let lines: Vec<_> = register.contents.lines().collect();
+                    let mut text = lines
+                        .iter()
+                        .take(PREVIEW_LINES)
+                        .copied()
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if lines.len() > PREVIEW_LINES {
+                        text.push_str("\n…");
+                    }
+                    preview.set_text(&printable(&text));
+                    preview.set_tooltip_text(Some(&printable(&register.contents)));
+                }
+            });
+
+            let list_view = gtk::ListView::new(
+                Some(gtk::NoSelection::new(Some(store.clone()))),
+                Some(factory),
+            );
+            list_view.add_css_class("navigation-sidebar");
+
+            let scrolled = gtk::ScrolledWindow::builder()
+                .child(&list_view)
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .vexpand(true)
+                .build();
+            container.append(&scrolled);
+
+            self.store.set(store).unwrap();
+            obj.set_child(Some(&container));
+        }
+    }
+    impl WidgetImpl for RegistersPanel {}
+    impl BinImpl for RegistersPanel {}
+}
+
+glib::wrapper! {
+    /// Contents of nvim's registers, instead of `:registers`
+    pub struct RegistersPanel(ObjectSubclass<imp::RegistersPanel>)
+        @extends adw::Bin, gtk::Widget;
+}
+
+impl Default for RegistersPanel {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl RegistersPanel {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    pub fn set_registers(&self, registers: Vec<Register>) {
+        let registers: Vec<_> = registers
+            .into_iter()
+            .map(glib::BoxedAnyObject::new)
+            .collect();
+        let store = self.imp().store.get().unwrap();
+        store.splice(0, store.n_items(), &registers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_printable() {
+        assert_eq!(printable("iabc\x1b:w\r"), "iabc^[:w^M");
+        assert_eq!(printable("a\tb\nc\x7f"), "a\tb\nc^?");
+        assert_eq!(printable("\x00"), "^@");
+    }
+}
@@ -0,0 +1,457 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
+use gtk::{gio, glib};
+
+use crate::{
+    locale,
+    nvim::{GtkToNvimEvent, NvimBridge},
+    search::{self, SearchEvent, SearchMatch, SearchOptions},
+    widgets,
+};
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    path::PathBuf,
+};
+
+/// Items of the result list, matches are grouped under a row for their file
+#[derive(Debug, Clone)]
+pub enum SearchRow {
+    File { path: String, matches: usize },
+    Match(SearchMatch),
+}
+
+impl SearchRow {
+    fn markup(&self) -> String {
+        match self {
+            Self::File { path, matches } => format!(
+                "<b>{}</b>  <span alpha=\"55%\">{matches}</span>",
+                glib::markup_escape_text(path)
+            ),
+            Self::Match(m) => {
+                // Indentation only pushes the match out of view
+                let trimmed = m.text.trim_start();
+                let offset = m.text.len() - trimmed.len();
+
+                let mut markup = format!("<span alpha=\"55%\">{}</span>  ", m.line);
+                let mut end = offset;
+                for span in &m.spans {
+                    let (Some(before), Some(matched)) = (
+                        m.text.get(end..span.start.max(end)),
+                        m.text.get(span.start.max(end)..span.end),
+                    ) else {
+                        continue;
+                    };
+                    markup.push_str(&glib::markup_escape_text(before));
+                    markup.push_str(&format!(
+                        "<span weight=\"bold\" background=\"#f5c211\" bgalpha=\"40%\">{}</span>",
+                        glib::markup_escape_text(matched)
+                    ));
+                    end = span.end;
+                }
+                markup.push_str(&glib::markup_escape_text(m.text.get(end..).unwrap_or("")));
+                markup
+            }
+        }
+    }
+}
+
+/// Position of a file row in the store, its path and match count
+type LastFile = (u32, String, usize);
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct SearchPanel {
+        pub entry: gtk::SearchEntry,
+        pub regex: gtk::ToggleButton,
+        pub whole_word: gtk::ToggleButton,
+        pub case_sensitive: gtk::ToggleButton,
+        pub stop: gtk::Button,
+        pub status: gtk::Label,
+        pub store: OnceCell<gio::ListStore>,
+        pub nvim_tx: OnceCell<NvimBridge>,
+
+        pub search: RefCell<Option<search::Search>>,
+        /// Counts the starts and cancels, a start waiting for nvim only goes on while it's
+        /// the latest
+        pub generation: Cell<u64>,
+        /// A start is waiting for nvim's working directory
+        pub starting: Cell<bool>,
+        /// nvim's working directory when the search started, match paths are relative to it
+        pub root: RefCell<PathBuf>,
+        /// Row of the file the last match was in, the next batch may continue it
+        pub last_file: RefCell<Option<LastFile>>,
+        pub matches: Cell<usize>,
+        pub files: Cell<usize>,
+        /// ripgrep is missing, regexes were searched as text
+        pub fallback: Cell<bool>,
+    }
+
+    impl SearchPanel {
+        pub fn start(&self) {
+            self.cancel();
+            self.store.get().unwrap().remove_all();
+            self.last_file.take();
+            self.matches.set(0);
+            self.files.set(0);
+            self.fallback.set(false);
+            self.status.set_text("");
+
+            let options = SearchOptions {
+                pattern: self.entry.text().to_string(),
+                regex: self.regex.is_active(),
+                whole_word: self.whole_word.is_active(),
+                case_sensitive: self.case_sensitive.is_active(),
+            };
+            let Some(tx) = self.nvim_tx.get().cloned() else {
+                return;
+            };
+            if options.pattern.is_empty() {
+                return;
+            }
+
+            self.stop.set_visible(true);
+            self.status.set_text(&gettext("Searching…"));
+            self.starting.set(true);
+
+            let generation = self.generation.get();
+            let obj = self.obj().downgrade();
+            glib::MainContext::default().spawn_local(async move {
+                let root = match tx.eval_as::<String>("getcwd()").await {
+                    Ok(root) => PathBuf::from(root),
+                    Err(err) => {
+                        log::warn!("Could not get the working directory: {err}");
+                        return;
+                    }
+                };
+                let Some(obj) = obj.upgrade() else {
+                    return;
+                };
+
+                let imp = obj.imp();
+                if imp.generation.get() != generation {
+                    return;
+                }
+                imp.starting.set(false);
+                imp.root.replace(root.clone());
+                let weak = obj.downgrade();
+                let search = search::start(root, options, move |event| {
+                    if let Some(obj) = weak.upgrade() {
+                        obj.imp().on_event(event);
+                    }
+                });
+                imp.search.replace(Some(search));
+                imp.update_launcher();
+            });
+        }
+
+        /// `false` when there was no search to stop
+        pub fn cancel(&self) -> bool {
+            self.generation.set(self.generation.get() + 1);
+            let starting = self.starting.take();
+            let search = self.search.take();
+            if search.is_none() && !starting {
+                return false;
+            }
+            if let Some(search) = search {
+                search.cancel();
+            }
+            self.stop.set_visible(false);
+            self.status.set_text(&self.summary(gettext("Stopped")));
+            self.update_launcher();
+            true
+        }
+
+        /// The window shows the running search on its dock or taskbar entry
+        fn update_launcher(&self) {
+            let window = self
+                .obj()
+                .ancestor(widgets::VimdicatorWindow::static_type())
+                .and_downcast::<widgets::VimdicatorWindow>();
+            if let Some(window) = window {
+                window.update_launcher();
+            }
+        }
+
+        fn on_event(&self, event: SearchEvent) {
+            match event {
+                SearchEvent::Matches(matches) => self.append(matches),
+                SearchEvent::Fallback => self.fallback.set(true),
+                SearchEvent::Error(err) => log::error!("Search failed: {err}"),
+                SearchEvent::Done { truncated } => {
+                    self.search.take();
+                    self.stop.set_visible(false);
+
+                    let status = if truncated {
+                        gettext("Stopped at {max}")
+                            .replace("{max}", &search::MAX_MATCHES.to_string())
+                    } else {
+                        String::new()
+                    };
+                    self.status.set_text(&self.summary(status));
+                }
+            }
+            self.update_launcher();
+        }
+
+        fn append(&self, matches: Vec<SearchMatch>) {
+            let store = self.store.get().unwrap();
+            let first_new = store.n_items();
+            let mut rows = Vec::with_capacity(matches.len());
+
+            // A file's matches can span batches, its row is then already in the store
+            let set_count = |file: LastFile, rows: &mut Vec<SearchRow>| {
+                let (position, path, matches) = file;
+                let row = SearchRow::File { path, matches };
+                match position.checked_sub(first_new) {
+                    Some(index) => rows[index as usize] = row,
+                    None => store.splice(position, 1, &[glib::BoxedAnyObject::new(row)]),
+                }
+            };
+
+            let mut last = self.last_file.take();
+            for m in matches {
+                if last.as_ref().is_none_or(|(_, path, _)| *path != m.path) {
+                    if let Some(file) = last.take() {
+                        set_count(file, &mut rows);
+                    }
+                    let position = first_new + rows.len() as u32;
+                    last = Some((position, m.path.clone(), 0));
+                    rows.push(SearchRow::File {
+                        path: m.path.clone(),
+                        matches: 0,
+                    });
+                    self.files.set(self.files.get() + 1);
+                }
+                if let Some((_, _, count)) = &mut last {
+                    *count += 1;
+                }
+                rows.push(SearchRow::Match(m));
+                self.matches.set(self.matches.get() + 1);
+            }
+            if let Some(file) = last.clone() {
+                set_count(file, &mut rows);
+            }
+            self.last_file.replace(last);
+
+            let rows: Vec<_> = rows.into_iter().map(glib::BoxedAnyObject::new).collect();
+            store.extend_from_slice(&rows);
+            self.status.set_text(&self.summary(gettext("Searching…")));
+        }
+
+        /// "12 matches in 3 files", followed by `status`
+        fn summary(&self, status: String) -> String {
+            let (matches, files) = (self.matches.get(), self.files.get());
+            let mut summary = ngettext("{count} match", "{count} matches", matches as u32)
+                .replace("{count}", &locale::count(matches));
+            summary.push(' ');
+            summary.push_str(
+                &ngettext("in {count} file", "in {count} files", files as u32)
+                    .replace("{count}", &locale::count(files)),
+            );
+            if self.fallback.get() {
+                summary.push_str(" · ");
+                summary.push_str(&gettext("ripgrep not found, searched as plain text"));
+            }
+            if !status.is_empty() {
+                summary.push_str(" · ");
+                summary.push_str(&status);
+            }
+            summary
+        }
+
+        fn open(&self, position: u32) {
+            let Some(row) = self
+                .store
+                .get()
+                .unwrap()
+                .item(position)
+                .and_downcast::<glib::BoxedAnyObject>()
+            else {
+                return;
+            };
+            let SearchRow::Match(m) = &*row.borrow::<SearchRow>() else {
+                return;
+            };
+
+            let path = self.root.borrow().join(&m.path);
+            let path = path.to_string_lossy().into_owned();
+            // The window asks before opening large files
+            let window = self
+                .obj()
+                .ancestor(widgets::VimdicatorWindow::static_type())
+                .and_downcast::<widgets::VimdicatorWindow>();
+            if let Some(window) = window {
+                window.open_file(path, Some(m.line));
+            } else if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::OpenFile {
+                    path,
+                    line: Some(m.line),
+                })
+                .unwrap();
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SearchPanel {
+        const NAME: &'static str = "SearchPanel";
+        type Type = super::SearchPanel;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for SearchPanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            let container = gtk::Box::new(gtk::Orientation::Vertical, 6);
+            container.set_margin_top(6);
+            container.set_margin_start(6);
+            container.set_margin_end(6);
+
+            let search_row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            self.entry
+                .set_placeholder_text(Some(&gettext("Search in Project")));
+            self.entry.set_hexpand(true);
+            search_row.append(&self.entry);
+
+            let toggles = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+            toggles.add_css_class("linked");
+            for (toggle, label, tooltip) in [
+                (&self.regex, ".*", gettext("Regular Expression")),
+                (&self.whole_word, "ab", gettext("Whole Word")),
+                (&self.case_sensitive, "Aa", gettext("Match Case")),
+            ] {
+                toggle.set_label(label);
+                toggle.set_tooltip_text(Some(&tooltip));
+                toggle.add_css_class("monospace");
+                toggle.connect_toggled(glib::clone!(@weak obj => move |_| {
+                    obj.imp().start();
+                }));
+                toggles.append(toggle);
+            }
+            search_row.append(&toggles);
+
+            self.stop.set_icon_name("process-stop-symbolic");
+            self.stop.set_tooltip_text(Some(&gettext("Stop Search")));
+            self.stop.set_visible(false);
+            self.stop
+                .connect_clicked(glib::clone!(@weak obj => move |_| {
+                    obj.imp().cancel();
+                }));
+            search_row.append(&self.stop);
+            container.append(&search_row);
+
+            self.entry
+                .connect_activate(glib::clone!(@weak obj => move |_| {
+                    obj.imp().start();
+                }));
+
+            self.status.set_xalign(0.0);
+            self.status.set_wrap(true);
+            self.status.add_css_class("dim-label");
+            self.status.add_css_class("caption");
+            container.append(&self.status);
+
+            let store = gio::ListStore::new(glib::BoxedAnyObject::static_type());
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(|_, item| {
+                let label = gtk::Label::builder()
+                    .xalign(0.0)
+                    .ellipsize(gtk::pango::EllipsizeMode::End)
+                    .build();
+                item.downcast_ref::<gtk::ListItem>()
+                    .unwrap()
+                    .set_child(Some(&label));
+            });
+            factory.connect_bind(|_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let (Some(label), Some(row)) = (
+                    item.child().and_downcast::<gtk::Label>(),
+                    item.item().and_downcast::<glib::BoxedAnyObject>(),
+                ) else {
+                    return;
+                };
+                let row = row.borrow::<SearchRow>();
+                label.set_markup(&row.markup());
+                // Matches are indented under their file
+                match &*row {
+                    SearchRow::File { .. } => {
+                        label.remove_css_class("monospace");
+                        label.set_margin_start(0);
+                    }
+                    SearchRow::Match(_) => {
+                        label.add_css_class("monospace");
+                        label.set_margin_start(12);
+                    }
+                }
+            });
+
+            let list_view = gtk::ListView::new(
+                Some(gtk::SingleSelection::new(Some(store.clone()))),
+                Some(factory),
+            );
+            list_view.set_single_click_activate(true);
+            list_view.add_css_class("navigation-sidebar");
+            list_view.connect_activate(glib::clone!(@weak obj => move |_, position| {
+                obj.imp().open(position);
+            }));
+
+            let scrolled = gtk::ScrolledWindow::builder()
+                .child(&list_view)
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .vexpand(true)
+                .build();
+            container.append(&scrolled);
+            self.store.set(store).unwrap();
+
+            obj.set_child(Some(&container));
+        }
+
+        fn dispose(&self) {
+            if let Some(search) = self.search.take() {
+                search.cancel();
+            }
+        }
+    }
+    impl WidgetImpl for SearchPanel {}
+    impl BinImpl for SearchPanel {}
+}
+
+glib::wrapper! {
+    /// Project wide search, results open in nvim at their line
+    pub struct SearchPanel(ObjectSubclass<imp::SearchPanel>)
+        @extends adw::Bin, gtk::Widget;
+}
+
+impl Default for SearchPanel {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl SearchPanel {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    /// Focus the query, selected so typing replaces it
+    pub fn focus_entry(&self) {
+        let entry = &self.imp().entry;
+        entry.grab_focus();
+        entry.select_region(0, -1);
+    }
+
+    /// Stop the running search, `false` when there was none
+    pub fn cancel(&self) -> bool {
+        self.imp().cancel()
+    }
+
+    /// Matches found so far by the running search
+    pub fn running_matches(&self) -> Option<usize> {
+        let imp = self.imp();
+        imp.search.borrow().is_some().then(|| imp.matches.get())
+    }
+}
@@ -0,0 +1,178 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{gio, glib};
+use std::cell::OnceCell;
+
+use crate::locale;
+use crate::nvim::{BufferInfo, GtkToNvimEvent, Mode, NvimBridge};
+
+/// Encodings offered to reread the file with, as ('fileencoding', label)
+const ENCODINGS: &[(&str, &str)] = &[
+    ("utf-8", "UTF-8"),
+    ("latin1", "Latin-1"),
+    ("cp1252", "Windows-1252"),
+    ("utf-16le", "UTF-16"),
+];
+
+/// Line endings offered to convert to, as ('fileformat', label)
+const FILEFORMATS: &[(&str, &str)] = &[("unix", "LF"), ("dos", "CRLF"), ("mac", "CR")];
+
+fn conversion_menu() -> gio::Menu {
+    let encodings = gio::Menu::new();
+    for (encoding, label) in ENCODINGS {
+        encodings.append(
+            Some(&gettext("Reopen as {encoding}").replace("{encoding}", label)),
+            Some(&format!("footer.reopen-encoding::{encoding}")),
+        );
+    }
+
+    let fileformats = gio::Menu::new();
+    for (format, label) in FILEFORMATS {
+        fileformats.append(
+            Some(&gettext("Convert to {format}").replace("{format}", label)),
+            Some(&format!("footer.set-fileformat::{format}")),
+        );
+    }
+
+    let menu = gio::Menu::new();
+    menu.append_section(None, &encodings);
+    menu.append_section(None, &fileformats);
+    menu
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct StatusFooter {
+        pub mode: gtk::Label,
+        pub position: gtk::Label,
+        pub filetype: gtk::Label,
+        pub encoding: gtk::Label,
+        pub encoding_button: gtk::MenuButton,
+        pub nvim_tx: OnceCell<NvimBridge>,
+    }
+
+    impl StatusFooter {
+        fn exec_lua(&self, code: String) {
+            if let Some(tx) = self.nvim_tx.get() {
+                tx.send(GtkToNvimEvent::ExecLua(code)).unwrap();
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for StatusFooter {
+        const NAME: &'static str = "StatusFooter";
+        type Type = super::StatusFooter;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for StatusFooter {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.add_css_class("toolbar");
+
+            let container = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+
+            self.mode.add_css_class("heading");
+            self.mode.set_width_chars(8);
+            self.mode.set_xalign(0.0);
+            container.append(&self.mode);
+
+            let spacer = gtk::Box::builder().hexpand(true).build();
+            container.append(&spacer);
+
+            for label in [&self.filetype, &self.encoding, &self.position] {
+                label.add_css_class("dim-label");
+                label.add_css_class("caption");
+            }
+            self.position.add_css_class("numeric");
+
+            self.encoding_button.set_child(Some(&self.encoding));
+            self.encoding_button.add_css_class("flat");
+            self.encoding_button.set_valign(gtk::Align::Center);
+            self.encoding_button.set_direction(gtk::ArrowType::Up);
+            self.encoding_button
+                .set_tooltip_text(Some(&gettext("Encoding and Line Endings")));
+            self.encoding_button
+                .set_menu_model(Some(&conversion_menu()));
+
+            container.append(&self.filetype);
+            container.append(&self.encoding_button);
+            container.append(&self.position);
+
+            let reopen_encoding = gio::ActionEntry::builder("reopen-encoding")
+                .parameter_type(Some(glib::VariantTy::STRING))
+                .activate(glib::clone!(@weak obj => move |_, _, param| {
+                    if let Some(encoding) = param.and_then(|param| param.get::<String>()) {
+                        obj.imp().exec_lua(format!(
+                            "require('vimdicator').reopen_with_encoding({encoding:?})"
+                        ));
+                    }
+                }))
+                .build();
+            let set_fileformat = gio::ActionEntry::builder("set-fileformat")
+                .parameter_type(Some(glib::VariantTy::STRING))
+                .activate(glib::clone!(@weak obj => move |_, _, param| {
+                    if let Some(format) = param.and_then(|param| param.get::<String>()) {
+                        obj.imp()
+                            .exec_lua(format!("require('vimdicator').set_fileformat({format:?})"));
+                    }
+                }))
+                .build();
+            let actions = gio::SimpleActionGroup::new();
+            actions.add_action_entries([reopen_encoding, set_fileformat]);
+            obj.insert_action_group("footer", Some(&actions));
+
+            obj.set_child(Some(&container));
+        }
+    }
+    impl WidgetImpl for StatusFooter {}
+    impl BinImpl for StatusFooter {}
+}
+
+glib::wrapper! {
+    /// Native replacement for the statusline, meant to be used with 'laststatus=0'
+    pub struct StatusFooter(ObjectSubclass<imp::StatusFooter>)
+        @extends adw::Bin, gtk::Widget;
+}
+
+impl Default for StatusFooter {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
+
+impl StatusFooter {
+    pub fn set_nvim_tx(&self, tx: NvimBridge) {
+        self.imp().nvim_tx.set(tx).unwrap();
+    }
+
+    pub fn set_mode(&self, mode: Mode) {
+        self.imp().mode.set_text(mode.label());
+    }
+
+    pub fn set_cursor(&self, line: u64, column: u64, line_count: u64) {
+        self.imp()
+            .position
+            .set_text(&format!("{line}:{column} / {}", locale::number(line_count)));
+    }
+
+    pub fn set_buffer(&self, buffer: &BufferInfo) {
+        let imp = self.imp();
+        imp.filetype.set_text(&buffer.filetype);
+        imp.filetype.set_visible(!buffer.filetype.is_empty());
+
+        let encoding = match (buffer.encoding.as_str(), buffer.fileformat.as_str()) {
+            ("", "") => String::new(),
+            ("", format) => format.to_string(),
+            (encoding, "") => encoding.to_string(),
+            (encoding, format) => format!("{encoding} [{format}]"),
+        };
+        imp.encoding.set_text(&encoding);
+        imp.encoding_button.set_visible(!encoding.is_empty());
+    }
+}
@@ -1,140 +1,2724 @@
-use std::{cell::Cell, rc::Rc};
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{gdk, gio, glib};
+use nvim_rs::Value;
+
+use crate::{
+    autosave::Autosave,
+    clock::{self, Timer},
+    frame_skip::{self, FrameSkip},
+    input::{ImFilter, PinchAccumulator, ScrollAccumulator},
+    latency::LatencyTracer,
+    launcher::{self, LauncherState},
+    locale, notify,
+    nvim::{
+        bridge, event,
+        health::{self, HealthLevel, HealthSection},
+        protocol,
+        request::{self, FileDialogOptions},
+        BufferInfo, Color, ExtLineGrid, GtkToNvimEvent, GuiRequest, Mode, NvimBridge,
+        NvimMouseAction, NvimMouseButton, ProgressAction, QuickfixList, Register, Responder,
+        Viewport,
+    },
+    options::WindowOptions,
+    project::{self, ProjectConfig},
+    recent,
+    session::{self, SessionState},
+    settings,
+    theme::{ModeColors, ScrollbarColors, ThemeSync},
+    title, widgets,
+};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[template(resource = "/io/github/polymeilex/vimdicator/widgets/window.ui")]
+    pub struct VimdicatorWindow {
+        #[template_child]
+        pub header_bar_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub header_bar: TemplateChild<gtk::HeaderBar>,
+        #[template_child]
+        pub main_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub open_button: TemplateChild<adw::SplitButton>,
+        #[template_child]
+        pub locations_popover: TemplateChild<widgets::LocationsPopover>,
+
+        #[template_child]
+        pub renderer_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub unresponsive_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub debug_overlay: TemplateChild<widgets::DebugOverlay>,
+        #[template_child]
+        pub burst_indicator: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub file_preview: TemplateChild<widgets::FilePreview>,
+        #[template_child]
+        pub presentation_progress: TemplateChild<gtk::ProgressBar>,
+
+        #[template_child]
+        pub progress_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub progress_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub connection_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub connection_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub path_bar_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub path_bar: TemplateChild<widgets::PathBar>,
+        #[template_child]
+        pub find_bar: TemplateChild<gtk::SearchBar>,
+        #[template_child]
+        pub find_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub find_count: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub find_previous: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub find_next: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub find_jump: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub quickfix_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub quickfix_panel: TemplateChild<widgets::QuickfixPanel>,
+        #[template_child]
+        pub registers_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub registers_panel: TemplateChild<widgets::RegistersPanel>,
+        #[template_child]
+        pub search_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub search_panel: TemplateChild<widgets::SearchPanel>,
+        #[template_child]
+        pub ext_line_grid: TemplateChild<widgets::ExtLineGrid>,
+        #[template_child]
+        pub gutter: TemplateChild<widgets::Gutter>,
+        #[template_child]
+        pub ext_popup_menu: TemplateChild<widgets::ExtPopupMenu>,
+        #[template_child]
+        pub status_footer: TemplateChild<widgets::StatusFooter>,
+        #[template_child]
+        pub ext_tabline: TemplateChild<widgets::ExtTabLine>,
+
+        pub buffer: RefCell<BufferInfo>,
+        pub autosave: OnceCell<Rc<Autosave>>,
+        pub theme: OnceCell<Rc<ThemeSync>>,
+        pub mode: Cell<Mode>,
+        pub mode_colors: OnceCell<ModeColors>,
+        pub scrollbar_colors: ScrollbarColors,
+        pub oldfiles: RefCell<Vec<String>>,
+        /// Set by nvim when 'title'/'icon' are enabled, take precedence over the buffer name
+        pub nvim_title: RefCell<String>,
+        pub nvim_icon: RefCell<String>,
+        /// Path and contents of the `.vimdicator.toml` in effect, see `set_project_config`
+        pub project_file: RefCell<(String, String)>,
+        pub project: RefCell<ProjectConfig>,
+        /// 'mousefocus', the grid takes keyboard focus when the pointer enters it
+        pub mousefocus: Cell<bool>,
+        /// Rows of `progress_box` by job id
+        pub progress: RefCell<HashMap<String, ProgressRow>>,
+        pub hover: OnceCell<Hover>,
+        pub signature_help: OnceCell<SignatureHelp>,
+        pub latency: RefCell<LatencyTracer>,
+        pub im_context: OnceCell<gtk::IMMulticontext>,
+        pub key_controller: OnceCell<gtk::EventControllerKey>,
+        pub nvim: OnceCell<NvimBridge>,
+        /// Watches the underline settings, see `init_decoration_overrides`
+        pub decoration_settings: OnceCell<gio::Settings>,
+        /// Watches the render cache budget, see `init_render_cache`
+        pub cache_settings: OnceCell<gio::Settings>,
+        /// Watches `default-font`, see `init_default_font`
+        pub font_settings: OnceCell<gio::Settings>,
+        /// Watches the cursor overrides, see `init_cursor_style`
+        pub cursor_settings: OnceCell<gio::Settings>,
+        /// Watches the unfocused grid dimming, see `init_unfocused_dim`
+        pub dim_settings: OnceCell<gio::Settings>,
+        /// Watches the high contrast mode, see `init_high_contrast`
+        pub contrast_settings: OnceCell<gio::Settings>,
+        /// Watches the free form settings, see `init_settings_check`
+        pub checked_settings: OnceCell<gio::Settings>,
+        /// Watches the title format, see `init_title_format`
+        pub title_settings: OnceCell<gio::Settings>,
+        /// Watches the overflow hints, see `init_overflow_hints`
+        pub overflow_settings: OnceCell<gio::Settings>,
+        /// Watches the session autosave interval, see `init_session_autosave`
+        pub session_settings: OnceCell<gio::Settings>,
+        pub session_timer: RefCell<Option<Timer>>,
+        /// Address of the `--server`, empty for the embedded nvim
+        pub server: RefCell<String>,
+        /// Set while in presentation mode, see `set_presentation`
+        pub presentation: Cell<Option<Presentation>>,
+        /// Font zoom by pinching, in steps of `ZOOM_STEP`
+        pub zoom: Cell<i32>,
+        /// Watches the burst thresholds, see `init_frame_skip`
+        pub frame_skip_settings: OnceCell<gio::Settings>,
+        pub frame_skip: RefCell<Option<FrameSkip>>,
+        /// Latest grid not shown during a burst, shown when it's over
+        pub skipped_grid: RefCell<Option<ExtLineGrid>>,
+        /// Runs every frame during a burst
+        pub burst_timer: RefCell<Option<Timer>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VimdicatorWindow {
+        const NAME: &'static str = "VimdicatorWindow";
+        type Type = super::VimdicatorWindow;
+        type ParentType = adw::ApplicationWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            widgets::ExtTabLine::static_type();
+            widgets::ExtPopupMenu::static_type();
+            widgets::ExtLineGrid::static_type();
+            widgets::DebugOverlay::static_type();
+            widgets::StatusFooter::static_type();
+            widgets::Gutter::static_type();
+            widgets::SearchPanel::static_type();
+            widgets::QuickfixPanel::static_type();
+            widgets::RegistersPanel::static_type();
+            widgets::LocationsPopover::static_type();
+            widgets::FilePreview::static_type();
+            widgets::PathBar::static_type();
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VimdicatorWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.update_recent_menu();
+            obj.set_icon_name(Some(launcher::ICON));
+
+            gtk::RecentManager::default().connect_changed(glib::clone!(@weak obj => move |_| {
+                obj.update_recent_menu();
+            }));
+
+            self.renderer_banner
+                .connect_button_clicked(|banner| banner.set_revealed(false));
+            obj.connect_realize(|window| window.check_renderer());
+
+            // Minimized windows show nvim's icon title, like terminals do
+            obj.connect_realize(|window| {
+                if let Ok(toplevel) = window.surface().downcast::<gdk::Toplevel>() {
+                    toplevel.connect_state_notify(glib::clone!(@weak window => move |_| {
+                        window.update_title();
+                    }));
+                }
+            });
+
+            let debug_overlay = gio::ActionEntry::builder("debug-overlay")
+                .state(false.to_variant())
+                .activate(|window: &super::VimdicatorWindow, action, _| {
+                    let enabled = !action.state().and_then(|s| s.get()).unwrap_or(false);
+                    action.set_state(enabled.to_variant());
+                    window.imp().debug_overlay.set_enabled(enabled);
+                    window.imp().latency.borrow_mut().set_enabled(enabled);
+                })
+                .build();
+            let find = gio::ActionEntry::builder("find")
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    let imp = window.imp();
+                    imp.find_bar.set_search_mode(true);
+                    imp.find_entry.grab_focus();
+                    imp.find_entry.select_region(0, -1);
+                })
+                .build();
+            let protocol_diagnostics = gio::ActionEntry::builder("protocol-diagnostics")
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    window.show_protocol_diagnostics();
+                })
+                .build();
+            let colorschemes = gio::ActionEntry::builder("colorschemes")
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    window.show_colorschemes();
+                })
+                .build();
+            let health = gio::ActionEntry::builder("health")
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    window.show_health();
+                })
+                .build();
+            let search_panel = gio::ActionEntry::builder("search-panel")
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    window.toggle_search_panel();
+                })
+                .build();
+            let quickfix_panel = gio::ActionEntry::builder("quickfix-panel")
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    window.toggle_quickfix_panel();
+                })
+                .build();
+            let registers_panel = gio::ActionEntry::builder("registers-panel")
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    window.toggle_registers_panel();
+                })
+                .build();
+            let presentation = gio::ActionEntry::builder("presentation")
+                .state(false.to_variant())
+                .activate(|window: &super::VimdicatorWindow, _, _| {
+                    window.toggle_presentation();
+                })
+                .build();
+            obj.add_action_entries([
+                debug_overlay,
+                find,
+                protocol_diagnostics,
+                colorschemes,
+                health,
+                search_panel,
+                quickfix_panel,
+                registers_panel,
+                presentation,
+            ]);
+        }
+    }
+    impl WidgetImpl for VimdicatorWindow {}
+    impl WindowImpl for VimdicatorWindow {}
+    impl ApplicationWindowImpl for VimdicatorWindow {}
+    impl AdwApplicationWindowImpl for VimdicatorWindow {}
+}
+
+glib::wrapper! {
+    pub struct VimdicatorWindow(ObjectSubclass<imp::VimdicatorWindow>)
+        @extends gtk::Widget, gtk::Window, gtk::ApplicationWindow, adw::ApplicationWindow,        @implements gio::ActionGroup, gio::ActionMap, gtk::Native;
+}
+
+impl VimdicatorWindow {
+    pub fn new<P: glib::IsA<gtk::Application>>(application: &P) -> Self {
+        glib::Object::builder()
+            .property("application", application)
+            .build()
+    }
+
+    pub fn connect(&self, nvim_tx: NvimBridge) {
+        let window = self.clone();
+
+        window.ext_line_grid().set_nvim_tx(nvim_tx.clone());
+        window.ext_popup_menu().set_nvim_tx(nvim_tx.clone());
+        self.imp().nvim.set(nvim_tx.clone()).unwrap();
+
+        let autosave = Rc::new(Autosave::new(settings::settings(), nvim_tx.clone()));
+        self.imp().autosave.set(autosave.clone()).unwrap();
+
+        let theme = ThemeSync::new(settings::settings(), nvim_tx.clone());
+        self.imp().theme.set(theme).unwrap();
+
+        let mode_colors = ModeColors::new(settings::settings());
+        self.imp().mode_colors.set(mode_colors).unwrap();
+        self.set_mode(Mode::Normal);
+
+        window.connect_is_active_notify({
+            let autosave = autosave.clone();
+            move |window| {
+                if !window.is_active() {
+                    autosave.focus_lost();
+                }
+            }
+        });
+
+        // Any input restarts the idle timer, clicks, scrolling, pasting and drops as well as keys
+        let input_controller = gtk::EventControllerLegacy::new();
+        input_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        input_controller.connect_event(move |_, event| {
+            use gdk::EventType;
+
+            if matches!(
+                event.event_type(),
+                EventType::KeyPress
+                    | EventType::ButtonPress
+                    | EventType::Scroll
+                    | EventType::TouchBegin
+                    | EventType::TouchpadPinch
+                    | EventType::DropStart
+            ) {
+                autosave.reset_idle();
+            }
+            gtk::Inhibit(false)
+        });
+        window.add_controller(input_controller);
+
+        let im_filter = Rc::new(RefCell::new(ImFilter::default()));
+
+        // The IM context filters key presses before key-pressed is emitted
+        let im_context = gtk::IMMulticontext::new();
+        im_context.set_use_preedit(false);
+        im_context.set_client_widget(Some(&window));
+        im_context.connect_commit({
+            let tx = nvim_tx.clone();
+            let im_filter = im_filter.clone();
+            let weak_window = window.downgrade();
+            move |_, text| {
+                let Some(window) = weak_window.upgrade() else { return; };
+                let time = window
+                    .imp()
+                    .key_controller
+                    .get()
+                    .map_or(0, |c| c.current_event_time());
+
+                let input = im_filter.borrow_mut().commit(time, text);
+                window.imp().latency.borrow_mut().input();
+                tx.send(GtkToNvimEvent::Input(input)).unwrap();
+            }
+        });
+        window.connect_is_active_notify(glib::clone!(@weak im_context => move |window| {
+            if window.is_active() {
+                im_context.focus_in();
+            } else {
+                im_context.focus_out();
+            }
+        }));
+        self.imp().im_context.set(im_context.clone()).unwrap();
+
+        let tx = nvim_tx.clone();
+        let weak_window = window.downgrade();
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_name(Some("vim"));
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        key_controller.set_im_context(Some(&im_context));
+        let settings = settings::settings();
+        key_controller.connect_key_pressed(move |controller, key, _, modifiers| {
+            use crate::input;
+
+            let Some(window) = weak_window.upgrade() else {
+                return gtk::Inhibit(false);
+            };
+
+            if window.is_typing_in_panel() {
+                return gtk::Inhibit(false);
+            }
+
+            let shortcut_modifiers =
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK;
+            if key.to_lower() == gdk::Key::f && modifiers.contains(shortcut_modifiers) {
+                WidgetExt::activate_action(&window, "win.find", None).ok();
+                return gtk::Inhibit(true);
+            }
+
+            if key.to_lower() == gdk::Key::g && modifiers.contains(shortcut_modifiers) {
+                WidgetExt::activate_action(&window, "win.search-panel", None).ok();
+                return gtk::Inhibit(true);
+            }
+
+            if key.to_lower() == gdk::Key::c && modifiers.contains(shortcut_modifiers) {
+                let grid = window.ext_line_grid();
+                if let Some(text) = grid.selection_text() {
+                    grid.clipboard().set_text(&text);
+                    grid.clear_selection();
+                    return gtk::Inhibit(true);
+                }
+            }
+
+            // Paging through the completion popup moves by the rows the GUI shows
+            let popup_menu = window.ext_popup_menu();
+            let navigation = widgets::ext_popup_menu::Navigation::from_key(key);
+            let plain = (modifiers & gtk::accelerator_get_default_mod_mask()).is_empty();
+            if let (Some(navigation), true, true) = (navigation, plain, popup_menu.is_visible()) {
+                if let Some(input) = popup_menu.navigation_input(navigation) {
+                    tx.send(GtkToNvimEvent::Input(input)).unwrap();
+                }
+                return gtk::Inhibit(true);
+            }
+
+            if input::is_reserved(key, modifiers, &settings.strv("super-key-reserved")) {
+                return gtk::Inhibit(false);
+            }
+
+            let super_key = input::SuperKey::from_setting(&settings.string("super-key"));
+            let (inhibit, input) = input::gtk_key_press_to_vim_input(key, modifiers, super_key);
+
+            let text = key.to_unicode().map(String::from);
+            let input = input.and_then(|input| {
+                im_filter.borrow_mut().key_pressed(
+                    controller.current_event_time(),
+                    text.as_deref(),
+                    input,
+                )
+            });
+
+            if let Some(input) = input {
+                window.imp().latency.borrow_mut().input();
+
+                // Typing returns to nvim's screen, like terminals do
+                let grid = window.ext_line_grid();
+                grid.clear_selection();
+                grid.reset_scrollback();
+
+                tx.send(GtkToNvimEvent::Input(input)).unwrap();
+            }
+
+            inhibit
+        });
+        let imp = self.imp();
+        imp.key_controller.set(key_controller.clone()).unwrap();
+        window.add_controller(key_controller);
+
+        let state = Rc::new(MouseState::new());
+
+        init_focus_tracking(window.clone(), nvim_tx.clone());
+        init_mousefocus(&window);
+        init_hover(&window, nvim_tx.clone());
+        init_signature_help(&window);
+        init_status_footer(&window, nvim_tx.clone());
+        init_find_bar(&window, nvim_tx.clone());
+        init_search_panel(&window, nvim_tx.clone());
+        init_gutter(&window, nvim_tx.clone());
+        init_file_drop(&window, nvim_tx.clone());
+        init_default_font(&window);
+        init_decoration_overrides(&window);
+        init_render_cache(&window);
+        init_cursor_style(&window);
+        init_unfocused_dim(&window);
+        init_high_contrast(&window);
+        init_settings_check(&window);
+        init_title_format(&window);
+        init_overflow_hints(&window);
+        init_session_autosave(&window);
+        init_frame_skip(&window);
+        init_motion_controller(window.clone(), nvim_tx.clone(), state.clone());
+        init_scroll_controller(window.ext_line_grid(), nvim_tx.clone(), state.clone());
+        init_pinch_controller(&window, nvim_tx.clone());
+        init_gesture_controller(window.ext_line_grid(), nvim_tx, state);
+    }
+
+    /// Report the GSK renderer in use, cairo makes redrawing large grids slow
+    fn check_renderer(&self) {
+        let renderer = widgets::debug_overlay::renderer_name(&self.renderer());
+        log::info!("GSK renderer: {renderer}");
+
+        self.imp().debug_overlay.set_renderer(&renderer);
+
+        if renderer == "cairo" {
+            log::warn!("Falling back to the cairo renderer, set GSK_RENDERER or pass --renderer to override");
+            self.imp().renderer_banner.set_revealed(true);
+        }
+    }
+
+    /// Keys go to the find bar or the search panel instead of nvim
+    fn is_typing_in_panel(&self) -> bool {
+        let imp = self.imp();
+        self.focus_widget().is_some_and(|widget| {
+            widget.is_ancestor(&imp.find_bar.get())
+                || widget.is_ancestor(&imp.search_revealer.get())
+        })
+    }
+
+    /// nvim's quickfix list changed, `show` also opens the panel
+    pub fn set_quickfix(&self, list: QuickfixList, show: bool) {
+        let imp = self.imp();
+        imp.quickfix_panel.set_list(list);
+        if show {
+            imp.quickfix_revealer.set_reveal_child(true);
+        }
+    }
+
+    fn toggle_quickfix_panel(&self) {
+        let imp = self.imp();
+        if imp.quickfix_revealer.reveals_child() {
+            imp.quickfix_revealer.set_reveal_child(false);
+            self.ext_line_grid().grab_focus();
+        } else if let Some(nvim) = imp.nvim.get() {
+            nvim.send(GtkToNvimEvent::ExecLua(
+                "require('vimdicator').sync_quickfix(true)".to_string(),
+            ))
+            .unwrap();
+        }
+    }
+
+    /// nvim's registers changed, `show` also opens the panel
+    pub fn set_registers(&self, registers: Vec<Register>, show: bool) {
+        let imp = self.imp();
+        imp.registers_panel.set_registers(registers);
+        if show {
+            imp.registers_revealer.set_reveal_child(true);
+        }
+    }
+
+    fn toggle_registers_panel(&self) {
+        let imp = self.imp();
+        if imp.registers_revealer.reveals_child() {
+            imp.registers_revealer.set_reveal_child(false);
+            self.ext_line_grid().grab_focus();
+        } else if let Some(nvim) = imp.nvim.get() {
+            nvim.send(GtkToNvimEvent::ExecLua(
+                "require('vimdicator').sync_registers(true)".to_string(),
+            ))
+            .unwrap();
+        }
+    }
+
+    /// Show the search panel with its query focused, or hide it and go back to the grid
+    pub fn toggle_search_panel(&self) {
+        let imp = self.imp();
+        let reveal = !imp.search_revealer.reveals_child() || !self.is_typing_in_panel();
+        imp.search_revealer.set_reveal_child(reveal);
+        if reveal {
+            imp.search_panel.focus_entry();
+        } else {
+            imp.search_panel.cancel();
+            self.ext_line_grid().grab_focus();
+        }
+    }
+
+    /// Open or close a panel for a plugin, `None` toggles it
+    fn set_panel(&self, panel: request::Panel, open: Option<bool>) {
+        let imp = self.imp();
+        let revealer = match panel {
+            request::Panel::Quickfix => imp.quickfix_revealer.get(),
+            request::Panel::Registers => imp.registers_revealer.get(),
+            request::Panel::Search => imp.search_revealer.get(),
+        };
+        let open = open.unwrap_or(!revealer.reveals_child());
+        if open == revealer.reveals_child() {
+            return;
+        }
+
+        match panel {
+            request::Panel::Quickfix => self.toggle_quickfix_panel(),
+            request::Panel::Registers => self.toggle_registers_panel(),
+            request::Panel::Search if open => self.toggle_search_panel(),
+            request::Panel::Search => {
+                revealer.set_reveal_child(false);
+                imp.search_panel.cancel();
+                self.ext_line_grid().grab_focus();
+            }
+        }
+    }
+
+    /// Native file dialog of `require("vimdicator.ui")`, answered with the picked path, a list
+    /// of them when `multiple` or nil when dismissed
+    fn show_file_dialog(&self, options: FileDialogOptions, responder: Responder) {
+        let title = match options.title {
+            Some(title) => title,
+            None if options.save => gettext("Save File"),
+            None if options.multiple => gettext("Open Files"),
+            None => gettext("Open File"),
+        };
+        let dialog = gtk::FileDialog::builder().title(title).modal(true).build();
+        if let Some(folder) = &options.folder {
+            dialog.set_initial_folder(Some(&gio::File::for_path(folder)));
+        }
+        if let Some(name) = &options.name {
+            dialog.set_initial_name(Some(name));
+        }
+        if !options.filters.is_empty() {
+            let filters = gio::ListStore::new(gtk::FileFilter::static_type());
+            for filter in &options.filters {
+                let file_filter = gtk::FileFilter::new();
+                file_filter.set_name(Some(&filter.name));
+                for pattern in &filter.patterns {
+                    file_filter.add_pattern(pattern);
+                }
+                filters.append(&file_filter);
+            }
+            dialog.set_filters(&filters);
+        }
+
+        // Files from the portal are local, others are passed on as URIs like nvim's `:e` takes them
+        fn path(file: &gio::File) -> Value {
+            match file.path() {
+                Some(path) => path.to_string_lossy().as_ref().into(),
+                None => file.uri().as_str().into(),
+            }
+        }
+        // nvim may have stopped waiting, nobody is left to answer then
+        let answer = move |res: Result<Value, glib::Error>| {
+            let _ = responder.send(match res {
+                Ok(value) => Ok(value),
+                Err(err) if err.matches(gtk::DialogError::Dismissed) => Ok(Value::Nil),
+                Err(err) => Err(Value::from(err.to_string())),
+            });
+        };
+
+        if options.save {
+            dialog.save(Some(self), gio::Cancellable::NONE, move |res| {
+                answer(res.map(|file| path(&file)))
+            });
+        } else if options.multiple {
+            dialog.open_multiple(Some(self), gio::Cancellable::NONE, move |res| {
+                answer(res.map(|files| {
+                    let files = (0..files.n_items())
+                        .filter_map(|i| files.item(i).and_downcast::<gio::File>())
+                        .map(|file| path(&file));
+                    Value::Array(files.collect())
+                }))
+            });
+        } else {
+            dialog.open(Some(self), gio::Cancellable::NONE, move |res| {
+                answer(res.map(|file| path(&file)))
+            });
+        }
+    }
+
+    /// Color dialog of `require("vimdicator.ui")`, answered with `#rrggbb` or nil when
+    /// dismissed. The eyedropper falls back to the dialog without a portal for it.
+    fn show_color_picker(&self, initial: Option<String>, eyedropper: bool, responder: Responder) {
+        let initial = initial.and_then(|initial| gdk::RGBA::parse(initial.as_str()).ok());
+        let connection = self.application().and_then(|app| app.dbus_connection());
+        if let (true, Some(connection)) = (eyedropper, connection) {
+            let window = self.downgrade();
+            crate::portal::pick_color(&connection, move |res| match res {
+                Ok(color) => {
+                    let _ = responder.send(Ok(
+                        color.map_or(Value::Nil, |color| Value::from(color_hex(&color)))
+                    ));
+                }
+                Err(err) => {
+                    log::info!("No eyedropper, showing the color dialog: {err}");
+                    if let Some(window) = window.upgrade() {
+                        window.show_color_picker(initial.map(|c| color_hex(&c)), false, responder);
+                    }
+                }
+            });
+            return;
+        }
+
+        let dialog = gtk::ColorDialog::builder()
+            .title(gettext("Pick a Color"))
+            .modal(true)
+            .with_alpha(false)
+            .build();
+        dialog.choose_rgba(
+            Some(self),
+            initial.as_ref(),
+            gio::Cancellable::NONE,
+            move |res| {
+                // nvim may have stopped waiting, nobody is left to answer then
+                let _ = responder.send(match res {
+                    Ok(color) => Ok(Value::from(color_hex(&color))),
+                    Err(err) if err.matches(gtk::DialogError::Dismissed) => Ok(Value::Nil),
+                    Err(err) => Err(Value::from(err.to_string())),
+                });
+            },
+        );
+    }
+
+    /// Answer a request of `require("vimdicator.api")`
+    pub fn handle_request(&self, request: GuiRequest, responder: Responder) {
+        let imp = self.imp();
+        let answer = match request {
+            GuiRequest::WindowSize => {
+                let (columns, rows) = imp.ext_line_grid.grid_size().unwrap_or_default();
+                Ok(Value::Map(vec![
+                    ("columns".into(), columns.into()),
+                    ("rows".into(), rows.into()),
+                    ("width".into(), self.width().into()),
+                    ("height".into(), self.height().into()),
+                ]))
+            }
+            GuiRequest::FileDialog(options) => {
+                self.show_file_dialog(options, responder);
+                return;
+            }
+            GuiRequest::ColorPicker {
+                initial,
+                eyedropper,
+            } => {
+                self.show_color_picker(initial, eyedropper, responder);
+                return;
+            }
+            GuiRequest::Panel { panel, open } => {
+                self.set_panel(panel, open);
+                Ok(Value::Nil)
+            }
+            GuiRequest::Toast { message, timeout } => {
+                let toast = adw::Toast::new(&message);
+                toast.set_timeout(timeout);
+                self.add_toast(toast);
+                Ok(Value::Nil)
+            }
+            GuiRequest::SetClipboard(text) => {
+                self.clipboard().set_text(&text);
+                Ok(Value::Nil)
+            }
+            GuiRequest::Unknown(name) => Err(Value::from(format!("Unknown request {name}"))),
+        };
+        let _ = responder.send(answer);
+    }
+
+    /// Show the default grid of a flush, unless nvim floods the screen and the flush is skipped
+    pub fn show_grid(&self, grid: ExtLineGrid) {
+        let imp = self.imp();
+        let (show, bursting) = match imp.frame_skip.borrow_mut().as_mut() {
+            Some(skip) => (skip.flush(Instant::now()), skip.is_bursting()),
+            None => (true, false),
+        };
+        if !show {
+            imp.skipped_grid.replace(Some(grid));
+            return;
+        }
+        imp.skipped_grid.replace(None);
+
+        if bursting && imp.burst_timer.borrow().is_none() {
+            imp.burst_indicator.set_visible(true);
+            let timer = clock::timeout(
+                frame_skip::FRAME,
+                glib::clone!(@weak self as window => @default-return glib::Continue(false), move || {
+                    window.burst_tick()
+                }),
+            );
+            imp.burst_timer.replace(Some(timer));
+        }
+
+        imp.ext_line_grid.set_grid(grid);
+        self.update_find_count();
+        imp.gutter.queue_draw();
+    }
+
+    fn burst_tick(&self) -> glib::Continue {
+        let imp = self.imp();
+        let skipped = imp
+            .frame_skip
+            .borrow_mut()
+            .as_mut()
+            .map_or(Some(0), FrameSkip::tick);
+        let Some(skipped) = skipped else {
+            return glib::Continue(true);
+        };
+
+        log::info!("Output burst over, skipped {skipped} flushes");
+        imp.burst_timer.replace(None);
+        imp.burst_indicator.set_visible(false);
+        if let Some(grid) = imp.skipped_grid.take() {
+            self.show_grid(grid);
+        }
+        glib::Continue(false)
+    }
+
+    /// Show the selected find bar match and the number of matches
+    pub fn update_find_count(&self) {
+        let imp = self.imp();
+        if !imp.find_bar.is_search_mode() {
+            return;
+        }
+
+        let text = match imp.ext_line_grid.match_position() {
+            _ if imp.find_entry.text().is_empty() => String::new(),
+            (_, 0) => gettext("No matches"),
+            (current, count) => gettext("{current} of {count}")
+                .replace("{current}", &locale::count(current.unwrap_or(0)))
+                .replace("{count}", &locale::count(count)),
+        };
+        imp.find_count.set_text(&text);
+    }
+
+    /// A flush redrew the cursor, completes the latency samples of pending key presses
+    pub fn cursor_flushed(&self) {
+        let mut latency = self.imp().latency.borrow_mut();
+        latency.flushed();
+        self.imp().debug_overlay.set_latency(latency.percentiles());
+
+        // Candidate windows of the input method follow the cursor
+        let grid = self.ext_line_grid();
+        if let (Some(im_context), Some(rect)) = (self.imp().im_context.get(), grid.cursor_rect()) {
+            let (x, y) = (rect.x() as f64, rect.y() as f64);
+            if let Some((x, y)) = grid.translate_coordinates(self, x, y) {
+                im_context.set_cursor_location(&gdk::Rectangle::new(
+                    x as i32,
+                    y as i32,
+                    rect.width(),
+                    rect.height(),
+                ));
+            }
+        }
+    }
+
+    /// Start, update or remove the progress bar of a job in the header bar popover
+    pub fn set_progress(
+        &self,
+        action: ProgressAction,
+        id: String,
+        percent: Option<f64>,
+        title: &str,
+    ) {
+        let imp = self.imp();
+        let mut progress = imp.progress.borrow_mut();
+
+        if action == ProgressAction::Stop {
+            if let Some(row) = progress.remove(&id) {
+                imp.progress_box.remove(&row.container);
+            }
+        } else {
+            let row = progress.entry(id).or_insert_with(|| {
+                let row = ProgressRow::new();
+                imp.progress_box.append(&row.container);
+                row
+            });
+            row.update(percent, title);
+        }
+
+        imp.progress_button.set_visible(!progress.is_empty());
+        if progress.is_empty() {
+            imp.progress_button.popdown();
+        }
+        drop(progress);
+        self.update_launcher();
+    }
+
+    /// Progress of nvim's jobs and the running search on the dock or taskbar entry
+    pub fn update_launcher(&self) {
+        let imp = self.imp();
+        let Some(app) = self.application() else {
+            return;
+        };
+
+        let progress = imp.progress.borrow();
+        let state = LauncherState {
+            progress: launcher::combine(progress.values().map(ProgressRow::fraction)),
+            // A search can't tell how far it is, the matches found so far show it is going on
+            count: imp.search_panel.running_matches(),
+        };
+        launcher::update(app.upcast_ref(), state);
+    }
+
+    /// Cover the grid while waiting for the `--server` to come up
+    pub fn set_connecting(&self, addr: &str, attempt: u32) {
+        let description = gettext("Waiting for nvim at {addr}… (attempt {attempt})")
+            .replace("{addr}", addr)
+            .replace("{attempt}", &attempt.to_string());
+
+        self.show_connection_page(
+            &gettext("Waiting for Neovim"),
+            &description,
+            &gettext("Cancel"),
+        );
+    }
+
+    /// Grey out the editor while a request to nvim is stuck
+    pub fn set_unresponsive(&self, unresponsive: bool) {
+        let imp = self.imp();
+        imp.unresponsive_banner.set_revealed(unresponsive);
+        imp.header_bar.set_sensitive(!unresponsive);
+        imp.ext_tabline.set_sensitive(!unresponsive);
+        imp.ext_line_grid.set_sensitive(!unresponsive);
+    }
+
+    pub fn set_connected(&self) {
+        self.imp().connection_page.set_visible(false);
+    }
+
+    pub fn set_connect_failed(&self, addr: &str, error: &str) {
+        let description = gettext("No nvim at {addr}: {error}")
+            .replace("{addr}", addr)
+            .replace("{error}", error);
+
+        self.show_connection_page(
+            &gettext("Could Not Connect to Neovim"),
+            &description,
+            &gettext("Close"),
+        );
+    }
+
+    fn show_connection_page(&self, title: &str, description: &str, button: &str) {
+        let imp = self.imp();
+        imp.connection_page.set_title(title);
+        imp.connection_page
+            .set_description(Some(&glib::markup_escape_text(description)));
+        imp.connection_button.set_label(button);
+        imp.connection_page.set_visible(true);
+    }
+
+    pub fn add_toast(&self, toast: adw::Toast) {
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Open `path` in nvim, files over the `large-file-size` setting ask first
+    pub fn open_file(&self, path: String, line: Option<u64>) {
+        if widgets::hex_view::data::is_binary_file(std::path::Path::new(&path)) {
+            self.show_hex_view(&path);
+            return;
+        }
+
+        let limit = u64::from(settings::settings().uint("large-file-size")) * 1024 * 1024;
+        // Paths on the other side of a `--server` aren't checked
+        let size = std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        if limit == 0 || size <= limit {
+            if let Some(nvim) = self.imp().nvim.get() {
+                nvim.send(GtkToNvimEvent::OpenFile { path, line }).unwrap();
+            }
+            return;
+        }
+
+        let window = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            window.open_large_file(path, line, size).await;
+        });
+    }
+
+    async fn open_large_file(&self, path: String, line: Option<u64>, size: u64) {
+        let Some(nvim) = self.imp().nvim.get().cloned() else {
+            return;
+        };
+        let name = std::path::Path::new(&path)
+            .file_name()
+            .map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned());
+
+        let dialog = adw::MessageDialog::builder()
+            .heading(gettext("Open Large File?"))
+            .body(
+                gettext("“{name}” is {size}, loading it may take a while and use a lot of memory. Opening it without swap file and highlighting is faster.")
+                    .replace("{name}", &name)
+                    .replace("{size}", &locale::size(size)),
+            )
+            .modal(true)
+            .transient_for(self)
+            .build();
+        dialog.add_responses(&[
+            ("cancel", &gettext("_Cancel")),
+            ("open", &gettext("_Open")),
+            ("plain", &gettext("Open _Plain")),
+        ]);
+        dialog.set_response_appearance("plain", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("plain"));
+        dialog.set_close_response("cancel");
+
+        let plain = match dialog.choose_future().await.as_str() {
+            "open" => false,
+            "plain" => true,
+            _ => return,
+        };
+
+        let toast = adw::Toast::builder()
+            .title(glib::markup_escape_text(
+                &gettext("Opening “{name}”…").replace("{name}", &name),
+            ))
+            .timeout(0)
+            .build();
+        self.add_toast(toast.clone());
+
+        let code = "require('vimdicator').open_large_file(...)";
+        let args = vec![path.into(), line.unwrap_or(0).into(), plain.into()];
+        if let Err(err) = nvim.exec_lua(code, args).await {
+            log::warn!("Opening {name} failed: {err}");
+        }
+        toast.dismiss();
+    }
+
+    /// Preview `path` at the bottom of the file browser window, which covers the grid `area`
+    /// as (row, col, rows, columns). An empty path hides the preview.
+    pub fn show_file_preview(&self, path: &str, area: (usize, usize, usize, usize)) {
+        let preview = self.imp().file_preview.get();
+        preview.set_path(path);
+        if path.is_empty() {
+            return;
+        }
+
+        let grid = self.ext_line_grid();
+        let Some(overlay) = preview.parent() else {
+            return;
+        };
+        let Some((grid_x, grid_y)) = grid.translate_coordinates(&overlay, 0.0, 0.0) else {
+            return;
+        };
+
+        let (row, col, rows, columns) = area;
+        let cell_metrics = grid.cell_metrics();
+        let (x, y) = cell_metrics.pixel_coords(col, row + rows);
+        let width = columns as f64 * cell_metrics.char_width;
+
+        // The grid isn't mirrored in right-to-left locales, but the overlay's start is the right
+        if preview.direction() == gtk::TextDirection::Rtl {
+            let right = overlay.width() as f64 - grid_x - x - width;
+            preview.set_margin_start((right as i32 + 6).max(0));
+        } else {
+            preview.set_margin_start((grid_x + x) as i32 + 6);
+        }
+        preview.set_margin_bottom((overlay.height() as f64 - grid_y - y) as i32 + 6);
+        preview.set_width_request((width as i32 - 12).max(160));
+    }
+
+    /// Breadcrumbs of the file browser's root above the grid, an empty root hides them
+    pub fn set_file_browser_root(&self, root: &str) {
+        let imp = self.imp();
+        imp.path_bar_revealer.set_reveal_child(!root.is_empty());
+        if !root.is_empty() {
+            imp.path_bar.set_root(root);
+        }
+    }
+
+    /// A file was written, the git status next to the breadcrumbs may be stale
+    pub fn refresh_git_status(&self) {
+        self.imp().path_bar.refresh_git_status();
+    }
+
+    /// Type or paste the path of a directory for the file browser to show
+    pub fn edit_file_browser_path(&self) {
+        let imp = self.imp();
+        imp.path_bar_revealer.set_reveal_child(true);
+        imp.path_bar.edit();
+    }
+
+    /// Show a `vim.notify` message, routed by level according to the notify-* settings
+    pub fn notify(&self, message: &str, level: u64) {
+        match notify::route(&settings::settings(), level, self.is_active()) {
+            notify::Route::Toast => {
+                let toast = adw::Toast::new(&glib::markup_escape_text(message));
+                if level >= 3 {
+                    toast.set_priority(adw::ToastPriority::High);
+                }
+                self.imp().toast_overlay.add_toast(toast);
+            }
+            notify::Route::Desktop => {
+                let Some(app) = self.application() else { return; };
+
+                let notification = gio::Notification::new("Neovim");
+                notification.set_body(Some(message));
+                if level >= 4 {
+                    notification.set_priority(gio::NotificationPriority::High);
+                }
+                app.send_notification(Some("nvim-notify"), &notification);
+            }
+            notify::Route::Ignore => {}
+        }
+    }
+
+    /// Scrollbar trough and slider colors of the completion popup, from PmenuSbar/PmenuThumb
+    pub fn set_scrollbar_colors(&self, trough: Option<Color>, slider: Option<Color>) {
+        self.imp().scrollbar_colors.set(trough, slider);
+    }
+
+    /// Reflect the nvim mode as a `mode-*` CSS class and in the status footer
+    pub fn set_mode(&self, mode: Mode) {
+        let previous = self.imp().mode.replace(mode);
+        self.remove_css_class(&previous.css_class());
+        self.add_css_class(&mode.css_class());
+
+        self.imp().status_footer.set_mode(mode);
+        self.ext_line_grid().set_mode(mode);
+        if previous != mode {
+            self.update_title();
+        }
+    }
+
+    pub fn set_nvim_background(&self, background: &str) {
+        if let Some(theme) = self.imp().theme.get() {
+            theme.set_background(background);
+        }
+    }
+
+    pub fn set_buffer(&self, buffer: BufferInfo) {
+        if let Some(autosave) = self.imp().autosave.get() {
+            autosave.set_buffer(&buffer);
+        }
+
+        self.imp().status_footer.set_buffer(&buffer);
+        self.set_icon_name(Some(launcher::icon_name(buffer.modified)));
+        *self.imp().buffer.borrow_mut() = buffer;
+        self.update_title();
+    }
+
+    pub fn set_oldfiles(&self, oldfiles: Vec<String>) {
+        *self.imp().oldfiles.borrow_mut() = oldfiles;
+        self.update_recent_menu();
+    }
+
+    fn update_recent_menu(&self) {
+        let menu = recent::build_menu(&self.imp().oldfiles.borrow());
+        self.imp().open_button.set_menu_model(Some(&menu));
+    }
+
+    /// Documentation for the cell under the pointer, dropped if the pointer moved on meanwhile
+    pub fn show_hover(&self, grid: u64, pos: (u64, u64), text: &str) {
+        let Some(hover) = self.imp().hover.get() else { return; };
+
+        let grid_widget = self.ext_line_grid();
+        if grid_widget.grid_id() != Some(grid) || hover.pos.get() != Some(pos) {
+            return;
+        }
+
+        let cell_metrics = grid_widget.cell_metrics();
+        let (x, y) = cell_metrics.pixel_coords(pos.0 as usize, pos.1 as usize);
+        hover.popover.set_pointing_to(Some(&gdk::Rectangle::new(
+            x as i32,
+            y as i32,
+            cell_metrics.char_width.ceil() as i32,
+            cell_metrics.line_height.ceil() as i32,
+        )));
+
+        hover.label.set_markup(&crate::markdown::to_pango(text));
+        hover.popover.popup();
+    }
+
+    /// Show the active LSP signature above the cursor, hidden when `label` is empty
+    pub fn show_signature_help(&self, label: &str, active: Option<(usize, usize)>) {
+        let Some(signature_help) = self.imp().signature_help.get() else { return; };
+
+        if label.is_empty() {
+            signature_help.popover.popdown();
+            return;
+        }
+
+        let parts = active.and_then(|(start, end)| {
+            Some((
+                label.get(..start)?,
+                label.get(start..end)?,
+                label.get(end..)?,
+            ))
+        });
+
+        let markup = match parts {
+            Some((before, param, after)) => format!(
+                "{}<b>{}</b>{}",
+                glib::markup_escape_text(before),
+                glib::markup_escape_text(param),
+                glib::markup_escape_text(after)
+            ),
+            None => glib::markup_escape_text(label).to_string(),
+        };
+        signature_help.label.set_markup(&markup);
+
+        let Some(rect) = self.ext_line_grid().cursor_rect() else { return; };
+        signature_help.popover.set_pointing_to(Some(&rect));
+        signature_help.popover.popup();
+    }
+
+    pub fn dismiss_signature_help(&self) {
+        if let Some(signature_help) = self.imp().signature_help.get() {
+            signature_help.popover.popdown();
+        }
+    }
+
+    pub fn set_mousefocus(&self, mousefocus: bool) {
+        self.imp().mousefocus.set(mousefocus);
+    }
+
+    pub fn set_nvim_title(&self, title: String) {
+        *self.imp().nvim_title.borrow_mut() = title;
+        self.update_title();
+    }
+
+    pub fn set_nvim_icon(&self, icon: String) {
+        *self.imp().nvim_icon.borrow_mut() = icon;
+        self.update_title();
+    }
+
+    fn is_minimized(&self) -> bool {
+        // No surface before the window is realized
+        if !self.is_realized() {
+            return false;
+        }
+
+        self.surface()
+            .downcast::<gdk::Toplevel>()
+            .is_ok_and(|toplevel| toplevel.state().contains(gdk::ToplevelState::MINIMIZED))
+    }
+
+    fn update_title(&self) {
+        let imp = self.imp();
+
+        let icon = imp.nvim_icon.borrow();
+        if !icon.is_empty() && self.is_minimized() {
+            self.set_title(Some(&icon));
+            return;
+        }
+
+        let title = imp.nvim_title.borrow();
+        if !title.is_empty() {
+            self.set_title(Some(&title));
+            return;
+        }
+
+        let buffer = imp.buffer.borrow();
+        let server = imp.server.borrow();
+        let context = title::Context {
+            buffer: &buffer,
+            server: &server,
+            mode: imp.mode.get(),
+        };
+
+        let title = match &imp.project.borrow().title_format {
+            Some(format) => title::format(format, &context),
+            None => match imp.title_settings.get() {
+                Some(settings) => title::format(&settings.string("title-format"), &context),
+                None => title::format(title::DEFAULT_FORMAT, &context),
+            },
+        };
+        self.set_title(Some(&title));
+    }
+
+    /// Address of the `--server` for the `{server}` title placeholder
+    pub fn set_server(&self, addr: &str) {
+        self.imp().server.replace(addr.to_string());
+        self.update_title();
+    }
+
+    /// Apply the overrides of a `.vimdicator.toml`, those of the previous one are reverted.
+    /// `reload` also reports invalid settings again, or confirms there are none.
+    pub fn set_project_config(&self, path: String, contents: &str, reload: bool) {
+        let imp = self.imp();
+        let file = (path, contents.to_string());
+        if *imp.project_file.borrow() == file && !reload {
+            return;
+        }
+
+        let (config, ignored) = project::parse(contents);
+        if !ignored.is_empty() {
+            self.add_toast(
+                adw::Toast::builder()
+                    .title(
+                        gettext("Ignored in {file}: {keys}")
+                            .replace("{file}", project::FILE_NAME)
+                            .replace("{keys}", &ignored.join(", ")),
+                    )
+                    .timeout(10)
+                    .build(),
+            );
+        }
+        if reload {
+            let invalid = settings::invalid_keys(&settings::settings());
+            if !invalid.is_empty() {
+                self.report_invalid_settings(&invalid);
+            } else if ignored.is_empty() {
+                self.add_toast(adw::Toast::new(&gettext("Settings reloaded")));
+            }
+        }
+
+        self.ext_line_grid().set_font_size(config.font_size);
+        self.gutter().set_visible(
+            config
+                .native_gutter
+                .unwrap_or_else(|| settings::settings().boolean("native-gutter")),
+        );
+        if config.sidebar != imp.project.borrow().sidebar {
+            let sidebar = config
+                .sidebar
+                .map_or("nil", |open| if open { "true" } else { "false" });
+            if let Some(nvim) = imp.nvim.get() {
+                nvim.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').set_sidebar({sidebar})"
+                )))
+                .unwrap();
+            }
+        }
+
+        imp.project_file.replace(file);
+        imp.project.replace(config);
+        self.update_title();
+    }
+
+    fn report_invalid_settings(&self, keys: &[&str]) {
+        self.add_toast(
+            adw::Toast::builder()
+                .title(
+                    gettext("Invalid settings ignored: {keys}").replace("{keys}", &keys.join(", ")),
+                )
+                .timeout(10)
+                .build(),
+        );
+    }
+
+    /// Initial state from the command line, see [`WindowOptions`]
+    pub fn apply_options(&self, options: WindowOptions) {
+        if let Some((cols, rows)) = options.geometry {
+            self.set_grid_size(cols, rows);
+        }
+        if options.maximized {
+            self.maximize();
+        }
+        if options.fullscreen {
+            self.fullscreen();
+        }
+    }
+
+    /// Size the window so the grid fits `cols` x `rows` cells
+    pub fn set_grid_size(&self, cols: u32, rows: u32) {
+        let grid = self.ext_line_grid();
+        let (width, height) = {
+            let metrics = grid.cell_metrics();
+            (
+                (cols as f64 * metrics.char_width).ceil() as i32,
+                (rows as f64 * metrics.line_height).ceil() as i32,
+            )
+        };
+
+        if grid.width() > 0 {
+            // Everything around the grid: header bar, tab line, footer, sidebar...
+            let chrome_width = self.width() - grid.width();
+            let chrome_height = self.height() - grid.height();
+            self.set_default_size(width + chrome_width, height + chrome_height);
+            return;
+        }
+
+        // A first guess, corrected once the grid is laid out and the chrome is known
+        self.set_default_size(width, height);
+        let window = self.clone();
+        grid.add_tick_callback(move |grid, _| {
+            if grid.width() == 0 {
+                return glib::Continue(true);
+            }
+            window.set_grid_size(cols, rows);
+            glib::Continue(false)
+        });
+    }
+
+    /// Snapshot of the window, its panels and nvim's tabs and windows, see [`session`]
+    fn save_session(&self) {
+        let imp = self.imp();
+        // Attached to a `--server`, the session file would end up on the other side
+        let vim_session = imp.server.borrow().is_empty();
+        if vim_session {
+            if let Some(nvim) = imp.nvim.get() {
+                nvim.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').save_session({:?})",
+                    session::vim_session_path().display()
+                )))
+                .unwrap();
+            }
+        }
+
+        let (width, height) = self.default_size();
+        let state = SessionState {
+            width,
+            height,
+            maximized: self.is_maximized(),
+            quickfix: imp.quickfix_revealer.reveals_child(),
+            registers: imp.registers_revealer.reveals_child(),
+            search: imp.search_revealer.reveals_child(),
+            vim_session,
+            saved: session::now(),
+        };
+        if let Err(err) = state.save() {
+            log::warn!("Could not save the session: {err}");
+        }
+    }
+
+    /// The last run did not exit cleanly, ask whether to bring back its last snapshot
+    pub fn offer_session_restore(&self) {
+        let Some(state) = SessionState::load() else {
+            return;
+        };
+
+        let window = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let saved = locale::date_time(state.saved as i64)
+                .unwrap_or_else(|| gettext("an unknown time"));
+            let dialog = adw::MessageDialog::builder()
+                .heading(gettext("Restore Previous Session?"))
+                .body(
+                    gettext("Vimdicator did not exit cleanly. The windows, tabs and panels saved at {time} can be restored.")
+                        .replace("{time}", &saved),
+                )
+                .modal(true)
+                .transient_for(&window)
+                .build();
+            dialog.add_responses(&[
+                ("discard", &gettext("_Discard")),
+                ("restore", &gettext("_Restore")),
+            ]);
+            dialog.set_response_appearance("restore", adw::ResponseAppearance::Suggested);
+            dialog.set_default_response(Some("restore"));
+            dialog.set_close_response("discard");
+
+            if dialog.choose_future().await == "restore" {
+                window.restore_session(state);
+            }
+        });
+    }
+
+    fn restore_session(&self, state: SessionState) {
+        let imp = self.imp();
+        self.set_default_size(state.width, state.height);
+        if state.maximized {
+            self.maximize();
+        }
+
+        let Some(nvim) = imp.nvim.get() else {
+            return;
+        };
+        let mut code = Vec::new();
+        if state.vim_session {
+            code.push(format!(
+                "require('vimdicator').restore_session({:?})",
+                session::vim_session_path().display()
+            ));
+        }
+        // The panels open once nvim sent their contents
+        if state.quickfix {
+            code.push("require('vimdicator').sync_quickfix(true)".to_string());
+        }
+        if state.registers {
+            code.push("require('vimdicator').sync_registers(true)".to_string());
+        }
+        if !code.is_empty() {
+            nvim.send(GtkToNvimEvent::ExecLua(code.join("\n"))).unwrap();
+        }
+        if state.search && !imp.search_revealer.reveals_child() {
+            self.toggle_search_panel();
+        }
+    }
+
+    pub fn header_bar_revealer(&self) -> gtk::Revealer {
+        self.imp().header_bar_revealer.clone()
+    }
+
+    pub fn ext_line_grid(&self) -> widgets::ExtLineGrid {
+        self.imp().ext_line_grid.clone()
+    }
+
+    pub fn main_box(&self) -> gtk::Box {
+        self.imp().main_box.clone()
+    }
+
+    pub fn ext_popup_menu(&self) -> widgets::ExtPopupMenu {
+        self.imp().ext_popup_menu.get()
+    }
+
+    pub fn ext_tabline(&self) -> widgets::ExtTabLine {
+        self.imp().ext_tabline.get()
+    }
+
+    pub fn status_footer(&self) -> widgets::StatusFooter {
+        self.imp().status_footer.get()
+    }
+
+    pub fn gutter(&self) -> widgets::Gutter {
+        self.imp().gutter.get()
+    }
+
+    /// The current window scrolled or its cursor moved, see `win_viewport`
+    pub fn set_viewport(&self, viewport: Viewport) {
+        let gutter = self.gutter();
+        let previous = gutter.viewport();
+        gutter.set_viewport(viewport);
+
+        if !viewport.same_lines(&previous) {
+            self.refresh_gutter();
+        }
+        // Horizontal scrolling moves the cursor, so any viewport change may reveal more text
+        self.refresh_overflow();
+
+        let imp = self.imp();
+        if imp.presentation.get().is_some() {
+            imp.presentation_progress.set_fraction(viewport.progress());
+        }
+    }
+
+    /// Presentation mode for demos and teaching: a bigger font (see `presentation-scale`),
+    /// margins around the grid and a progress bar through the buffer instead of the header
+    /// bar, panels and status footer. Leaving it puts everything back.
+    pub fn set_presentation(&self, enabled: bool) {
+        let imp = self.imp();
+        if enabled == imp.presentation.get().is_some() {
+            return;
+        }
+
+        let grid = self.ext_line_grid();
+        let revealers = [
+            imp.quickfix_revealer.get(),
+            imp.registers_revealer.get(),
+            imp.search_revealer.get(),
+        ];
+        if enabled {
+            let [quickfix, registers, search] = revealers.each_ref().map(|r| r.reveals_child());
+            imp.presentation.set(Some(Presentation {
+                quickfix,
+                registers,
+                search,
+            }));
+            for revealer in &revealers {
+                revealer.set_reveal_child(false);
+            }
+            imp.header_bar_revealer.set_reveal_child(false);
+            imp.status_footer.set_visible(false);
+            self.update_font_scale();
+            imp.presentation_progress
+                .set_fraction(self.gutter().viewport().progress());
+            imp.presentation_progress.set_visible(true);
+            grid.grab_focus();
+        } else if let Some(presentation) = imp.presentation.take() {
+            let shown = [
+                presentation.quickfix,
+                presentation.registers,
+                presentation.search,
+            ];
+            for (revealer, shown) in revealers.iter().zip(shown) {
+                revealer.set_reveal_child(shown);
+            }
+            imp.status_footer
+                .set_visible(settings::settings().boolean("status-footer"));
+            self.update_font_scale();
+            imp.presentation_progress.set_visible(false);
+        }
+
+        // The row of the gutter and grid, the margins center it
+        if let Some(row) = self.gutter().parent() {
+            let (horizontal, vertical) = if enabled {
+                PRESENTATION_MARGINS
+            } else {
+                (0, 0)
+            };
+            row.set_margin_start(horizontal);
+            row.set_margin_end(horizontal);
+            row.set_margin_top(vertical);
+            row.set_margin_bottom(vertical);
+        }
+
+        if let Some(action) = self.lookup_action("presentation") {
+            action.change_state(&enabled.to_variant());
+        }
+    }
+
+    /// Grow the font by `steps` of `ZOOM_STEP`, shrink it with negative ones
+    pub fn zoom(&self, steps: i32) {
+        let imp = self.imp();
+        imp.zoom
+            .set((imp.zoom.get() + steps).clamp(ZOOM_STEPS.0, ZOOM_STEPS.1));
+        self.update_font_scale();
+    }
+
+    /// Zoom times the presentation mode's scale
+    fn update_font_scale(&self) {
+        let imp = self.imp();
+        let zoom = imp.zoom.get();
+        let presentation = imp.presentation.get().is_some();
+
+        let scale = match (zoom, presentation) {
+            (0, false) => None,
+            _ => {
+                let presentation = if presentation {
+                    settings::settings().double("presentation-scale")
+                } else {
+                    1.0
+                };
+                Some(presentation * ZOOM_STEP.powi(zoom))
+            }
+        };
+        self.ext_line_grid().set_font_scale(scale);
+    }
+
+    pub fn toggle_presentation(&self) {
+        self.set_presentation(self.imp().presentation.get().is_none());
+    }
+
+    /// Ask nvim which rows continue past the edges of windows without 'wrap'
+    pub fn refresh_overflow(&self) {
+        let imp = self.imp();
+        let grid = self.ext_line_grid();
+        let Some(nvim) = imp.nvim.get().cloned() else { return; };
+        if !imp
+            .overflow_settings
+            .get()
+            .is_some_and(|settings| settings.boolean("overflow-hints"))
+        {
+            grid.set_overflow(Vec::new());
+            return;
+        }
+
+        bridge::spawn_local(
+            "Updating the overflow hints",
+            async move {
+                nvim.exec_lua_as("return require('vimdicator').overflow()", vec![])
+                    .await
+            },
+            move |windows| grid.set_overflow(windows),
+        );
+    }
+
+    /// Ask nvim for the line numbers and signs on screen
+    pub fn refresh_gutter(&self) {
+        let gutter = self.gutter();
+        let Some(nvim) = self.imp().nvim.get().cloned() else { return; };
+        if !gutter.is_visible() {
+            return;
+        }
+
+        bridge::spawn_local(
+            "Updating the gutter",
+            async move {
+                nvim.exec_lua_as("return require('vimdicator').gutter()", vec![])
+                    .await
+            },
+            move |lines| gutter.set_lines(lines),
+        );
+    }
+
+    /// UI events the GUI doesn't handle and malformed data it dropped, to spot protocol gaps
+    pub fn show_protocol_diagnostics(&self) {
+        let events = adw::PreferencesGroup::builder()
+            .title(gettext("Unhandled UI Events"))
+            .description(gettext(
+                "Events vimdicator does not know or does nothing with, and the first one received",
+            ))
+            .build();
+        let uncovered = protocol::uncovered_events();
+        for (name, uncovered) in &uncovered {
+            let count = format!("{} × {}", uncovered.coverage.name(), uncovered.count);
+            events.add(&diagnostics_row(name, &uncovered.first_payload, &count));
+        }
+        if uncovered.is_empty() {
+            events.add(&diagnostics_row(&gettext("None so far"), "", ""));
+        }
+
+        let malformed = adw::PreferencesGroup::builder()
+            .title(gettext("Malformed Data"))
+            .description(gettext("Values dropped while parsing what nvim sent"))
+            .build();
+        let warnings = event::parse_warnings();
+        for (what, count) in &warnings {
+            malformed.add(&diagnostics_row(what, "", &format!("× {count}")));
+        }
+        if warnings.is_empty() {
+            malformed.add(&diagnostics_row(&gettext("None so far"), "", ""));
+        }
+
+        let page = adw::PreferencesPage::new();
+        page.set_vexpand(true);
+        page.add(&events);
+        page.add(&malformed);
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        content.append(&adw::HeaderBar::new());
+        content.append(&page);
+
+        adw::Window::builder()
+            .transient_for(self)
+            .title(gettext("Protocol Diagnostics"))
+            .default_width(640)
+            .default_height(480)
+            .content(&content)
+            .build()
+            .present();
+    }
+
+    /// Binary `path` in the hex viewer, nvim gets it if it can't be read
+    pub fn show_hex_view(&self, path: &str) {
+        let Some(nvim) = self.imp().nvim.get().cloned() else {
+            return;
+        };
+
+        let view = widgets::HexView::default();
+        if let Err(err) = view.set_path(path) {
+            log::warn!("Could not open {path} in the hex viewer: {err}");
+            nvim.send(GtkToNvimEvent::OpenFile {
+                path: path.to_string(),
+                line: None,
+            })
+            .unwrap();
+            return;
+        }
+        view.set_transient_for(Some(self));
+        view.set_nvim_tx(nvim);
+        view.present();
+    }
+
+    pub fn show_colorschemes(&self) {
+        let Some(nvim) = self.imp().nvim.get().cloned() else {
+            return;
+        };
+
+        let dialog = widgets::ColorschemeDialog::default();
+        dialog.set_transient_for(Some(self));
+        dialog.set_nvim_tx(nvim);
+        dialog.present();
+    }
+
+    /// `:checkhealth` as a list of checks with their status, instead of the report buffer
+    pub fn show_health(&self) {
+        let Some(nvim) = self.imp().nvim.get().cloned() else {
+            return;
+        };
+
+        let copy_all = gtk::Button::builder()
+            .icon_name("edit-copy-symbolic")
+            .tooltip_text(gettext("Copy Report"))
+            .sensitive(false)
+            .build();
+        let header_bar = adw::HeaderBar::new();
+        header_bar.pack_start(&copy_all);
+
+        let running = adw::StatusPage::builder()
+            .title(gettext("Running Health Checks…"))
+            .child(&gtk::Spinner::builder().spinning(true).build())
+            .vexpand(true)
+            .build();
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        content.append(&header_bar);
+        content.append(&running);
+
+        let dialog = adw::Window::builder()
+            .transient_for(self)
+            .title(gettext("Health Check"))
+            .default_width(720)
+            .default_height(560)
+            .content(&content)
+            .build();
+        dialog.present();
+
+        bridge::spawn_local(
+            "Running :checkhealth",
+            async move {
+                nvim.exec_lua_as::<Vec<String>>(
+                    "return require('vimdicator').checkhealth()",
+                    vec![],
+                )
+                .await
+            },
+            move |lines| {
+                let sections = health::parse(&lines);
+                content.remove(&running);
+
+                if sections.is_empty() {
+                    content.append(
+                        &adw::StatusPage::builder()
+                            .icon_name("dialog-warning-symbolic")
+                            .title(gettext("No Health Checks"))
+                            .description(gettext(":checkhealth did not report anything"))
+                            .vexpand(true)
+                            .build(),
+                    );
+                    return;
+                }
+
+                let page = adw::PreferencesPage::new();
+                page.set_vexpand(true);
+                for section in &sections {
+                    page.add(&health_group(section));
+                }
+                content.append(&page);
+
+                let report = lines.join("\n");
+                copy_all.set_sensitive(true);
+                copy_all.connect_clicked(move |button| button.clipboard().set_text(&report));
+            },
+        );
+    }
+
+    /// Answer `:VimdicatorCacheStats` with a message in nvim
+    pub fn report_cache_stats(&self) {
+        self.echo(self.ext_line_grid().cache_stats(), "Normal");
+    }
+
+    /// `:VimdicatorCursor`, changes are written to the cursor settings
+    pub fn apply_cursor_command(&self, args: &str) {
+        use widgets::ext_line_grid::cursor::{self, CursorChange};
+
+        let changes = cursor::parse_command(args).and_then(|changes| {
+            match changes.iter().find_map(|change| match change {
+                CursorChange::Color(Some(color)) => gdk::RGBA::parse(color).err(),
+                _ => None,
+            }) {
+                Some(_) => Err("Invalid cursor color, expected e.g. #ff8800".to_string()),
+                None => Ok(changes),
+            }
+        });
+
+        let changes = match changes {
+            Ok(changes) => changes,
+            Err(err) => {
+                self.echo(format!("VimdicatorCursor: {err}"), "ErrorMsg");
+                return;
+            }
+        };
+
+        let settings = settings::settings();
+        let mut shapes: HashMap<String, String> = settings.get("cursor-shapes");
+        for change in changes {
+            match change {
+                CursorChange::Shape(mode, Some(shape)) => {
+                    shapes.insert(mode.name().to_string(), shape.name().to_string());
+                }
+                CursorChange::Shape(mode, None) => {
+                    shapes.remove(mode.name());
+                }
+                CursorChange::Size(size) => settings.set_string("cursor-size", &size).unwrap(),
+                CursorChange::Color(color) => settings
+                    .set_string("cursor-color", color.as_deref().unwrap_or_default())
+                    .unwrap(),
+                CursorChange::Reset => {
+                    shapes.clear();
+                    settings.reset("cursor-size");
+                    settings.reset("cursor-color");
+                }
+            }
+        }
+        settings.set("cursor-shapes", shapes).unwrap();
+    }
+
+    /// Show a message in nvim's message area with the `highlight` group
+    fn echo(&self, message: String, highlight: &str) {
+        let Some(nvim) = self.imp().nvim.get().cloned() else { return; };
+        let highlight = highlight.to_string();
+
+        bridge::spawn_local(
+            "Echoing a message",
+            async move {
+                nvim.exec_lua(
+                    "vim.api.nvim_echo({ { ... } }, true, {})",
+                    vec![message.into(), highlight.into()],
+                )
+                .await
+            },
+            |_| {},
+        );
+    }
+}
+
+fn diagnostics_row(title: &str, subtitle: &str, count: &str) -> adw::ActionRow {
+    let row = adw::ActionRow::builder()
+        .title(title)
+        .subtitle(subtitle)
+        .subtitle_lines(3)
+        .use_markup(false)
+        .build();
+
+    let count = gtk::Label::new(Some(count));
+    count.add_css_class("dim-label");
+    row.add_suffix(&count);
+
+    row
+}
+
+fn health_badge(level: HealthLevel) -> Option<gtk::Label> {
+    let (label, class) = match level {
+        HealthLevel::Info => return None,
+        HealthLevel::Ok => (gettext("OK"), "success"),
+        HealthLevel::Warning => (gettext("Warning"), "warning"),
+        HealthLevel::Error => (gettext("Error"), "error"),
+    };
+    Some(
+        gtk::Label::builder()
+            .label(label)
+            .valign(gtk::Align::Center)
+            .css_classes(["caption-heading", class])
+            .build(),
+    )
+}
+
+/// A health check with a row per heading, headings with problems start expanded
+fn health_group(section: &HealthSection) -> adw::PreferencesGroup {
+    let group = adw::PreferencesGroup::builder()
+        .title(glib::markup_escape_text(&section.name))
+        .build();
+
+    let copy = gtk::Button::builder()
+        .icon_name("edit-copy-symbolic")
+        .tooltip_text(gettext("Copy"))
+        .valign(gtk::Align::Center)
+        .css_classes(["flat"])
+        .build();
+    let report = section.report.clone();
+    copy.connect_clicked(move |button| button.clipboard().set_text(&report));
+
+    let suffix = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    if let Some(badge) = health_badge(section.level()) {
+        suffix.append(&badge);
+    }
+    suffix.append(&copy);
+    group.set_header_suffix(Some(&suffix));
+
+    for health_group in &section.groups {
+        let title = if health_group.title.is_empty() {
+            &section.name
+        } else {
+            &health_group.title
+        };
+        let level = health_group.level();
+
+        let row = adw::ExpanderRow::builder()
+            .title(glib::markup_escape_text(title))
+            .expanded(level >= HealthLevel::Warning)
+            .build();
+        if let Some(badge) = health_badge(level) {
+            row.add_action(&badge);
+        }
+
+        for item in &health_group.items {
+            let item_row = adw::ActionRow::builder()
+                .title(&item.text)
+                .subtitle(item.advice.join("\n"))
+                .use_markup(false)
+                .build();
+            if let Some(badge) = health_badge(item.level) {
+                item_row.add_suffix(&badge);
+            }
+            row.add_row(&item_row);
+        }
+        group.add(&row);
+    }
+
+    group
+}
+
+struct MouseState {
+    pos: Cell<Option<(u64, u64)>>,
+    is_pressed: Cell<bool>,
+    /// Button 1 drags a GUI side selection instead of being sent to nvim
+    selecting: Cell<bool>,
+    scroll_x: Cell<ScrollAccumulator>,
+    scroll_y: Cell<ScrollAccumulator>,
+}
+
+impl MouseState {
+    fn new() -> Self {
+        Self {
+            pos: Cell::new(None),
+            is_pressed: Cell::new(false),
+            selecting: Cell::new(false),
+            scroll_x: Cell::new(ScrollAccumulator::default()),
+            scroll_y: Cell::new(ScrollAccumulator::default()),
+        }
+    }
+
+    fn scroll_steps(&self, dx: f64, dy: f64) -> (i64, i64) {
+        let mut scroll_x = self.scroll_x.get();
+        let mut scroll_y = self.scroll_y.get();
+
+        let steps = (scroll_x.accumulate(dx), scroll_y.accumulate(dy));
+
+        self.scroll_x.set(scroll_x);
+        self.scroll_y.set(scroll_y);
+
+        steps
+    }
+
+    fn reset_scroll(&self) {
+        self.scroll_x.set(ScrollAccumulator::default());
+        self.scroll_y.set(ScrollAccumulator::default());
+    }
+}
+
+/// Focus changes are forwarded only once they settle, so rapid flaps
+/// (e.g. popovers grabbing focus) don't queue redundant autocmds.
+const FOCUS_SETTLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+fn init_focus_tracking(window: widgets::VimdicatorWindow, tx: NvimBridge) {
+    let pending = Rc::new(RefCell::new(None::<Timer>));
+    let last_sent = Rc::new(Cell::new(None::<bool>));
+
+    window.connect_is_active_notify(move |window| {
+        window.ext_line_grid().set_focused(window.is_active());
+
+        if let Some(timer) = pending.take() {
+            timer.cancel();
+        }
+
+        let timer = clock::timeout_once(FOCUS_SETTLE_TIMEOUT, {
+            let window = window.downgrade();
+            let pending = pending.clone();
+            let last_sent = last_sent.clone();
+            let tx = tx.clone();
+
+            move || {
+                pending.take();
+
+                let Some(window) = window.upgrade() else { return; };
+                let focused = window.is_active();
+
+                if last_sent.get() != Some(focused) {
+                    last_sent.set(Some(focused));
+                    tx.send(GtkToNvimEvent::Focus(focused)).unwrap();
+                }
+            }
+        });
+
+        *pending.borrow_mut() = Some(timer);
+    });
+}
 
-use adw::prelude::*;
-use adw::subclass::prelude::*;
-use gtk::{gio, glib};
-use tokio::sync::mpsc::UnboundedSender;
+/// Horizontal and vertical margins around the grid in presentation mode, in pixels
+const PRESENTATION_MARGINS: (i32, i32) = (64, 32);
 
-use crate::{
-    nvim::{GtkToNvimEvent, NvimMouseAction, NvimMouseButton},
-    widgets,
-};
+/// Factor of one font zoom step
+const ZOOM_STEP: f64 = 1.1;
+/// Fewest and most zoom steps, about half and two and a half times the size
+const ZOOM_STEPS: (i32, i32) = (-7, 10);
 
-mod imp {
-    use super::*;
+/// Panels that were open before presentation mode hid them
+#[derive(Debug, Clone, Copy)]
+pub struct Presentation {
+    quickfix: bool,
+    registers: bool,
+    search: bool,
+}
 
-    #[derive(Debug, Default, gtk::CompositeTemplate)]
-    #[template(resource = "/io/github/polymeilex/vimdicator/widgets/window.ui")]
-    pub struct VimdicatorWindow {
-        #[template_child]
-        pub header_bar_revealer: TemplateChild<gtk::Revealer>,
-        #[template_child]
-        pub header_bar: TemplateChild<gtk::HeaderBar>,
-        #[template_child]
-        pub main_box: TemplateChild<gtk::Box>,
+/// Popover showing LSP hover documentation after the pointer rested on a cell
+#[derive(Debug)]
+pub struct Hover {
+    popover: gtk::Popover,
+    label: gtk::Label,
+    /// Cell the pending or shown documentation is for
+    pos: Cell<Option<(u64, u64)>>,
+    timeout: RefCell<Option<Timer>>,
+}
 
-        #[template_child]
-        pub ext_line_grid: TemplateChild<widgets::ExtLineGrid>,
-        #[template_child]
-        pub ext_popup_menu: TemplateChild<widgets::ExtPopupMenu>,
-        #[template_child]
-        pub ext_tabline: TemplateChild<widgets::ExtTabLine>,
+impl Hover {
+    fn dismiss(&self) {
+        if let Some(timer) = self.timeout.take() {
+            timer.cancel();
+        }
+        self.pos.set(None);
+        self.popover.popdown();
     }
+}
 
-    #[glib::object_subclass]
-    impl ObjectSubclass for VimdicatorWindow {
-        const NAME: &'static str = "VimdicatorWindow";
-        type Type = super::VimdicatorWindow;
-        type ParentType = adw::ApplicationWindow;
+/// The footer replaces nvim's statusline, hide that one while the footer is shown
+fn init_status_footer(window: &widgets::VimdicatorWindow, tx: NvimBridge) {
+    let footer = window.status_footer();
+    footer.set_nvim_tx(tx.clone());
 
-        fn class_init(klass: &mut Self::Class) {
-            widgets::ExtTabLine::static_type();
-            widgets::ExtPopupMenu::static_type();
-            widgets::ExtLineGrid::static_type();
-            klass.bind_template();
-        }
+    // The user's 'laststatus' is kept in nvim while the footer replaces the statusline
+    footer.connect_visible_notify(move |footer| {
+        let code = if footer.is_visible() {
+            r#"
+                if vim.g.vimdicator_laststatus == nil then
+                    vim.g.vimdicator_laststatus = vim.o.laststatus
+                end
+                vim.o.laststatus = 0
+            "#
+        } else {
+            r#"
+                if vim.g.vimdicator_laststatus ~= nil then
+                    vim.o.laststatus = vim.g.vimdicator_laststatus
+                    vim.g.vimdicator_laststatus = nil
+                end
+            "#
+        };
+        tx.send(GtkToNvimEvent::ExecLua(code.to_string())).unwrap();
+    });
 
-        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
-            obj.init_template();
+    settings::settings()
+        .bind("status-footer", &footer, "visible")
+        .get_only()
+        .build();
+}
+
+fn init_default_font(window: &widgets::VimdicatorWindow) {
+    let settings = settings::settings();
+    let grid = window.ext_line_grid();
+
+    // Detected once, the stored value is the user's to change from then on
+    if settings.string("default-font").is_empty() {
+        let font = grid.detect_default_font();
+        log::info!("Detected default font {font:?}");
+        if let Err(err) = settings.set_string("default-font", &font) {
+            log::warn!("Failed to store the default font: {err}");
         }
     }
 
-    impl ObjectImpl for VimdicatorWindow {}
-    impl WidgetImpl for VimdicatorWindow {}
-    impl WindowImpl for VimdicatorWindow {}
-    impl ApplicationWindowImpl for VimdicatorWindow {}
-    impl AdwApplicationWindowImpl for VimdicatorWindow {}
+    let load = move |settings: &gio::Settings| {
+        grid.set_default_font(&settings.string("default-font"));
+    };
+    load(&settings);
+    settings.connect_changed(Some("default-font"), move |settings, _| load(settings));
+    window.imp().font_settings.set(settings).unwrap();
 }
 
-glib::wrapper! {
-    pub struct VimdicatorWindow(ObjectSubclass<imp::VimdicatorWindow>)
-        @extends gtk::Widget, gtk::Window, gtk::ApplicationWindow, adw::ApplicationWindow,        @implements gio::ActionGroup, gio::ActionMap;
+fn init_decoration_overrides(window: &widgets::VimdicatorWindow) {
+    const KEYS: [&str; 3] = [
+        "underline-thickness",
+        "underline-offset",
+        "undercurl-thickness",
+    ];
+
+    let grid = window.ext_line_grid();
+    let load = move |settings: &gio::Settings| {
+        grid.set_decoration_overrides(widgets::ext_line_grid::DecorationOverrides {
+            underline_thickness: settings.double("underline-thickness"),
+            underline_offset: settings.double("underline-offset"),
+            undercurl_thickness: settings.double("undercurl-thickness"),
+        });
+    };
+
+    let settings = settings::settings();
+    load(&settings);
+    settings.connect_changed(None, move |settings, key| {
+        if KEYS.contains(&key) {
+            load(settings);
+        }
+    });
+    window.imp().decoration_settings.set(settings).unwrap();
 }
 
-impl VimdicatorWindow {
-    pub fn new<P: glib::IsA<gtk::Application>>(application: &P) -> Self {
-        glib::Object::builder()
-            .property("application", application)
-            .build()
-    }
+fn init_render_cache(window: &widgets::VimdicatorWindow) {
+    let grid = window.ext_line_grid();
+    let load = move |settings: &gio::Settings| {
+        let megabytes = settings.uint("render-cache-mb") as usize;
+        grid.set_cache_budget(megabytes * 1024 * 1024);
+    };
 
-    pub fn connect(&self, nvim_tx: UnboundedSender<GtkToNvimEvent>) {
-        let window = self.clone();
+    let settings = settings::settings();
+    load(&settings);
+    settings.connect_changed(Some("render-cache-mb"), move |settings, _| load(settings));
+    window.imp().cache_settings.set(settings).unwrap();
+}
 
-        window.ext_line_grid().set_nvim_tx(nvim_tx.clone());
+fn init_unfocused_dim(window: &widgets::VimdicatorWindow) {
+    let grid = window.ext_line_grid();
+    let load = move |settings: &gio::Settings| {
+        grid.set_dim_amount(settings.double("unfocused-dim"));
+    };
 
-        let tx = nvim_tx.clone();
-        let key_controller = gtk::EventControllerKey::new();
-        key_controller.set_name(Some("vim"));
-        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
-        key_controller.connect_key_pressed(move |_, key, _, modifiers| {
-            use crate::input;
+    let settings = settings::settings();
+    load(&settings);
+    settings.connect_changed(Some("unfocused-dim"), move |settings, _| load(settings));
+    window.imp().dim_settings.set(settings).unwrap();
+}
 
-            let (inhibit, input) = input::gtk_key_press_to_vim_input(key, modifiers);
+fn init_title_format(window: &widgets::VimdicatorWindow) {
+    let settings = settings::settings();
+    settings.connect_changed(
+        Some("title-format"),
+        glib::clone!(@weak window => move |_, _| window.update_title()),
+    );
+    window.imp().title_settings.set(settings).unwrap();
+    window.update_title();
+}
 
-            if let Some(input) = input {
-                tx.send(GtkToNvimEvent::Input(input)).unwrap();
+fn init_overflow_hints(window: &widgets::VimdicatorWindow) {
+    let settings = settings::settings();
+    settings.connect_changed(
+        Some("overflow-hints"),
+        glib::clone!(@weak window => move |_, _| window.refresh_overflow()),
+    );
+    window.imp().overflow_settings.set(settings).unwrap();
+}
+
+fn init_frame_skip(window: &widgets::VimdicatorWindow) {
+    const KEYS: [&str; 2] = ["burst-flushes", "burst-render-every"];
+
+    let load = glib::clone!(@weak window => move |settings: &gio::Settings| {
+        let skip = FrameSkip::new(
+            settings.uint("burst-flushes"),
+            settings.uint("burst-render-every"),
+        );
+        window.imp().frame_skip.replace(Some(skip));
+    });
+
+    let settings = settings::settings();
+    load(&settings);
+    settings.connect_changed(None, move |settings, key| {
+        if KEYS.contains(&key) {
+            load(settings);
+        }
+    });
+    window.imp().frame_skip_settings.set(settings).unwrap();
+}
+
+fn init_session_autosave(window: &widgets::VimdicatorWindow) {
+    fn load(window: &widgets::VimdicatorWindow) {
+        let imp = window.imp();
+        if let Some(timer) = imp.session_timer.take() {
+            timer.cancel();
+        }
+
+        let interval = imp
+            .session_settings
+            .get()
+            .unwrap()
+            .uint("session-autosave-interval");
+        if interval == 0 {
+            return;
+        }
+        let timer = clock::timeout(
+            Duration::from_secs(interval.into()),
+            glib::clone!(@weak window => @default-return glib::Continue(false), move || {
+                window.save_session();
+                glib::Continue(true)
+            }),
+        );
+        imp.session_timer.replace(Some(timer));
+    }
+
+    let settings = settings::settings();
+    settings.connect_changed(
+        Some("session-autosave-interval"),
+        glib::clone!(@weak window => move |_, _| load(&window)),
+    );
+    window.imp().session_settings.set(settings).unwrap();
+    load(window);
+}
+
+fn init_high_contrast(window: &widgets::VimdicatorWindow) {
+    fn load(window: &widgets::VimdicatorWindow) {
+        let settings = window.imp().contrast_settings.get().unwrap();
+        let enabled = match settings.string("high-contrast").as_str() {
+            "on" => true,
+            "off" => false,
+            _ => adw::StyleManager::default().is_high_contrast(),
+        };
+
+        let min_contrast = enabled.then(|| settings.double("high-contrast-ratio"));
+        window.ext_line_grid().set_min_contrast(min_contrast);
+    }
+
+    let settings = settings::settings();
+    settings.connect_changed(
+        None,
+        glib::clone!(@weak window => move |_, key| {
+            if key.starts_with("high-contrast") {
+                load(&window);
             }
+        }),
+    );
+    window.imp().contrast_settings.set(settings).unwrap();
 
-            inhibit
+    adw::StyleManager::default()
+        .connect_high_contrast_notify(glib::clone!(@weak window => move |_| load(&window)));
+    load(window);
+}
+
+fn init_cursor_style(window: &widgets::VimdicatorWindow) {
+    use widgets::ext_line_grid::cursor::{CursorShape, CursorSize, CursorStyle};
+
+    const KEYS: [&str; 5] = [
+        "cursor-shapes",
+        "cursor-size",
+        "cursor-color",
+        "cursor-line",
+        "cursor-column",
+    ];
+
+    let grid = window.ext_line_grid();
+    let load = move |settings: &gio::Settings| {
+        let shapes: HashMap<String, String> = settings.get("cursor-shapes");
+        let shapes = Mode::ALL
+            .into_iter()
+            .filter_map(|mode| {
+                let shape = CursorShape::from_name(shapes.get(mode.name())?)?;
+                Some((mode.name(), shape))
+            })
+            .collect();
+
+        let size = settings.string("cursor-size");
+        let color = settings.string("cursor-color");
+
+        grid.set_cursor_style(CursorStyle {
+            shapes,
+            size: CursorSize::parse(&size).unwrap_or_default(),
+            color: gdk::RGBA::parse(color.as_str()).ok().map(|rgba| Color {
+                r: rgba.red(),
+                g: rgba.green(),
+                b: rgba.blue(),
+            }),
+            line: settings.boolean("cursor-line"),
+            column: settings.boolean("cursor-column"),
         });
-        window.add_controller(key_controller);
+    };
 
-        let state = Rc::new(MouseState::new());
+    let settings = settings::settings();
+    load(&settings);
+    settings.connect_changed(None, move |settings, key| {
+        if KEYS.contains(&key) {
+            load(settings);
+        }
+    });
+    window.imp().cursor_settings.set(settings).unwrap();
+}
 
-        init_motion_controller(window.clone(), nvim_tx.clone(), state.clone());
-        init_scroll_controller(window.ext_line_grid(), nvim_tx.clone(), state.clone());
-        init_gesture_controller(window.ext_line_grid(), nvim_tx, state);
-    }
+/// Tell about malformed values of free form settings, e.g. changed with dconf or gsettings
+fn init_settings_check(window: &widgets::VimdicatorWindow) {
+    let settings = settings::settings();
+    settings.connect_changed(
+        None,
+        glib::clone!(@weak window => move |settings, key| {
+            if let Some(key) = settings::FREE_FORM_KEYS.iter().find(|k| **k == key) {
+                if !settings::is_valid(settings, key) {
+                    window.report_invalid_settings(&[key]);
+                }
+            }
+        }),
+    );
 
-    pub fn header_bar_revealer(&self) -> gtk::Revealer {
-        self.imp().header_bar_revealer.clone()
+    let invalid = settings::invalid_keys(&settings);
+    if !invalid.is_empty() {
+        window.report_invalid_settings(&invalid);
     }
+    window.imp().checked_settings.set(settings).unwrap();
+}
 
-    pub fn ext_line_grid(&self) -> widgets::ExtLineGrid {
-        self.imp().ext_line_grid.clone()
+fn init_gutter(window: &widgets::VimdicatorWindow, tx: NvimBridge) {
+    let gutter = window.gutter();
+    gutter.set_grid_widget(&window.ext_line_grid());
+    // The gutter stands in for nvim's sign column, which stays left of the text in
+    // right-to-left locales as the grid isn't mirrored
+    if let Some(row) = gutter.parent() {
+        row.set_direction(gtk::TextDirection::Ltr);
     }
 
-    pub fn main_box(&self) -> gtk::Box {
-        self.imp().main_box.clone()
+    gutter.connect_visible_notify(glib::clone!(@weak window => move |_| {
+        window.refresh_gutter();
+    }));
+
+    let click = gtk::GestureClick::new();
+    click.connect_pressed(glib::clone!(@weak gutter => move |_, _, x, y| {
+        let Some((line, on_sign)) = gutter.line_at(x, y) else { return; };
+
+        let tx = tx.clone();
+        bridge::spawn_local(
+            "Clicking the gutter",
+            async move {
+                tx.exec_lua(
+                    "require('vimdicator').gutter_click(...)",
+                    vec![line.into(), on_sign.into()],
+                )
+                .await
+            },
+            |_| {},
+        );
+    }));
+    gutter.add_controller(click);
+
+    settings::settings()
+        .bind("native-gutter", &gutter, "visible")
+        .get_only()
+        .build();
+}
+
+/// `#rrggbb` of `color`, the way colorschemes and CSS write it
+fn color_hex(color: &gdk::RGBA) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.red()),
+        channel(color.green()),
+        channel(color.blue())
+    )
+}
+
+/// Title and bar of a job in the progress popover
+#[derive(Debug)]
+pub struct ProgressRow {
+    container: gtk::Box,
+    title: gtk::Label,
+    bar: gtk::ProgressBar,
+    /// Of the last update, `None` while the job can't tell
+    percent: Cell<Option<f64>>,
+}
+
+impl ProgressRow {
+    fn new() -> Self {
+        let title = gtk::Label::builder()
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        let bar = gtk::ProgressBar::new();
+
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        container.append(&title);
+        container.append(&bar);
+
+        Self {
+            container,
+            title,
+            bar,
+            percent: Cell::new(None),
+        }
     }
 
-    pub fn ext_popup_menu(&self) -> widgets::ExtPopupMenu {
-        self.imp().ext_popup_menu.get()
+    fn fraction(&self) -> Option<f64> {
+        self.percent.get().map(|percent| percent / 100.0)
     }
 
-    pub fn ext_tabline(&self) -> widgets::ExtTabLine {
-        self.imp().ext_tabline.get()
+    fn update(&self, percent: Option<f64>, title: &str) {
+        if !title.is_empty() {
+            self.title.set_text(title);
+        }
+        self.percent.set(percent);
+
+        match percent {
+            Some(percent) => self.bar.set_fraction(percent.clamp(0.0, 100.0) / 100.0),
+            None => self.bar.pulse(),
+        }
     }
 }
 
-struct MouseState {
-    pos: Cell<Option<(u64, u64)>>,
-    is_pressed: Cell<bool>,
+/// Popover above the cursor with the LSP signature being typed
+#[derive(Debug)]
+pub struct SignatureHelp {
+    popover: gtk::Popover,
+    label: gtk::Label,
 }
 
-impl MouseState {
-    fn new() -> Self {
-        Self {
+/// Files dropped on the grid are opened, with Ctrl held their paths are inserted at the cursor
+fn init_file_drop(window: &widgets::VimdicatorWindow, tx: NvimBridge) {
+    let target = gtk::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+
+    target.connect_drop(
+        glib::clone!(@weak window => @default-return false, move |_, value, _, _| {
+            let Ok(files) = value.get::<gdk::FileList>() else { return false; };
+            let paths: Vec<String> = files
+                .files()
+                .iter()
+                .filter_map(|file| file.path())
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            if paths.is_empty() {
+                return false;
+            }
+
+            let insert = window
+                .display()
+                .default_seat()
+                .and_then(|seat| seat.keyboard())
+                .is_some_and(|keyboard| {
+                    keyboard
+                        .modifier_state()
+                        .contains(gdk::ModifierType::CONTROL_MASK)
+                });
+
+            if insert {
+                tx.send(GtkToNvimEvent::InsertPaths(paths)).unwrap();
+            } else {
+                for path in paths {
+                    window.open_file(path, None);
+                }
+            }
+            window.present();
+            true
+        }),
+    );
+
+    window.ext_line_grid().add_controller(target);
+}
+
+fn init_search_panel(window: &widgets::VimdicatorWindow, tx: NvimBridge) {
+    let imp = window.imp();
+    imp.search_panel.set_nvim_tx(tx.clone());
+    imp.quickfix_panel.set_nvim_tx(tx.clone());
+    imp.registers_panel.set_nvim_tx(tx.clone());
+    imp.locations_popover.set_nvim_tx(tx.clone());
+    imp.file_preview.set_nvim_tx(tx.clone());
+    imp.path_bar.set_nvim_tx(tx);
+
+    // Back to typing into nvim once a path was entered or dismissed
+    imp.path_bar
+        .connect_editing_stopped(glib::clone!(@weak window => move |_| {
+            window.ext_line_grid().grab_focus();
+        }));
+    imp.path_bar
+        .connect_filter_done(glib::clone!(@weak window => move |_| {
+            window.ext_line_grid().grab_focus();
+        }));
+
+    // Escape stops the search, or closes the panel when there is none
+    let escape = gtk::EventControllerKey::new();
+    escape.set_propagation_phase(gtk::PropagationPhase::Capture);
+    escape.connect_key_pressed(
+        glib::clone!(@weak window => @default-return gtk::Inhibit(false), move |_, key, _, _| {
+            if key != gdk::Key::Escape {
+                return gtk::Inhibit(false);
+            }
+            if !window.imp().search_panel.cancel() {
+                window.toggle_search_panel();
+            }
+            gtk::Inhibit(true)
+        }),
+    );
+    imp.search_revealer.add_controller(escape);
+}
+
+fn init_find_bar(window: &widgets::VimdicatorWindow, tx: NvimBridge) {
+    let imp = window.imp();
+    let grid = window.ext_line_grid();
+
+    imp.find_entry
+        .connect_search_changed(glib::clone!(@weak window, @weak grid => move |entry| {
+            grid.set_search(&entry.text());
+            window.update_find_count();
+        }));
+
+    let select = move |window: &widgets::VimdicatorWindow, backward: bool| {
+        window.ext_line_grid().select_match(backward);
+        window.update_find_count();
+    };
+    imp.find_entry
+        .connect_activate(glib::clone!(@weak window => move |_| select(&window, false)));
+    imp.find_entry
+        .connect_next_match(glib::clone!(@weak window => move |_| select(&window, false)));
+    imp.find_entry
+        .connect_previous_match(glib::clone!(@weak window => move |_| select(&window, true)));
+    imp.find_next
+        .connect_clicked(glib::clone!(@weak window => move |_| select(&window, false)));
+    imp.find_previous
+        .connect_clicked(glib::clone!(@weak window => move |_| select(&window, true)));
+
+    imp.find_entry
+        .connect_stop_search(glib::clone!(@weak window => move |_| {
+            window.imp().find_bar.set_search_mode(false);
+        }));
+
+    imp.find_jump
+        .connect_clicked(glib::clone!(@weak window => move |_| {
+            let grid = window.ext_line_grid();
+            if let Some(pattern) = grid.search_pattern() {
+                let cell = grid
+                    .selected_match()
+                    .map_or("nil, nil".to_string(), |(row, col)| format!("{row}, {col}"));
+                tx.send(GtkToNvimEvent::ExecLua(format!(
+                    "require('vimdicator').find_jump({pattern:?}, {cell})"
+                )))
+                .unwrap();
+            }
+            window.imp().find_bar.set_search_mode(false);
+        }));
+
+    imp.find_bar
+        .connect_search_mode_enabled_notify(glib::clone!(@weak window => move |find_bar| {
+            if !find_bar.is_search_mode() {
+                let grid = window.ext_line_grid();
+                grid.set_search("");
+                grid.grab_focus();
+            }
+        }));
+
+    // The input method commits to nvim, so it only follows the keyboard while the grid has it
+    window.connect_focus_widget_notify(|window| {
+        let imp = window.imp();
+        let (Some(key_controller), Some(im_context)) =
+            (imp.key_controller.get(), imp.im_context.get())
+        else {
+            return;
+        };
+
+        if window.is_typing_in_panel() {
+            im_context.focus_out();
+            key_controller.set_im_context(None::<&gtk::IMContext>);
+        } else if key_controller.im_context().is_none() {
+            key_controller.set_im_context(Some(im_context));
+            im_context.focus_in();
+        }
+    });
+}
+
+fn init_signature_help(window: &widgets::VimdicatorWindow) {
+    let label = gtk::Label::builder()
+        .wrap(true)
+        .max_width_chars(100)
+        .xalign(0.0)
+        .selectable(false)
+        .build();
+    label.add_css_class("monospace");
+
+    let popover = gtk::Popover::builder()
+        .child(&label)
+        .position(gtk::PositionType::Top)
+        .autohide(false)
+        .has_arrow(false)
+        .can_target(false)
+        .build();
+    popover.set_parent(&window.ext_line_grid());
+
+    window
+        .imp()
+        .signature_help
+        .set(SignatureHelp { popover, label })
+        .unwrap();
+}
+
+fn init_hover(window: &widgets::VimdicatorWindow, tx: NvimBridge) {
+    let ext_line_grid = window.ext_line_grid();
+
+    let label = gtk::Label::builder()
+        .wrap(true)
+        .max_width_chars(80)
+        .xalign(0.0)
+        .selectable(false)
+        .build();
+
+    let scrolled = gtk::ScrolledWindow::builder()
+        .child(&label)
+        .propagate_natural_width(true)
+        .propagate_natural_height(true)
+        .max_content_height(300)
+        .build();
+
+    let popover = gtk::Popover::builder()
+        .child(&scrolled)
+        .autohide(false)
+        .has_arrow(true)
+        .can_target(false)
+        .build();
+    popover.set_parent(&ext_line_grid);
+
+    window
+        .imp()
+        .hover
+        .set(Hover {
+            popover,
+            label,
             pos: Cell::new(None),
-            is_pressed: Cell::new(false),
+            timeout: RefCell::new(None),
+        })
+        .unwrap();
+
+    let settings = settings::settings();
+    let controller = gtk::EventControllerMotion::new();
+
+    controller.connect_motion(glib::clone!(@weak window => move |_, x, y| {
+        let hover = window.imp().hover.get().unwrap();
+        let grid = window.ext_line_grid();
+        let pos = grid.cell_metrics().cell_cords(x, y);
+
+        if hover.pos.get() == Some(pos) {
+            return;
         }
-    }
+        hover.dismiss();
+
+        if !settings.boolean("hover") {
+            return;
+        }
+
+        hover.pos.set(Some(pos));
+
+        let delay = Duration::from_millis(settings.uint("hover-delay").into());
+        let request = glib::clone!(@weak window, @strong tx => move || {
+            let hover = window.imp().hover.get().unwrap();
+            hover.timeout.take();
+
+            let grid = window.ext_line_grid().grid_id();
+            let (Some(grid), Some((col, row))) = (grid, hover.pos.get()) else { return; };
+
+            tx.send(GtkToNvimEvent::ExecLua(format!(
+                "require('vimdicator').hover({grid}, {row}, {col})"
+            )))
+            .unwrap();
+        });
+        *hover.timeout.borrow_mut() = Some(clock::timeout_once(delay, request));
+    }));
+
+    controller.connect_leave(glib::clone!(@weak window => move |_| {
+        window.imp().hover.get().unwrap().dismiss();
+    }));
+
+    ext_line_grid.add_controller(controller);
+}
+
+fn init_mousefocus(window: &widgets::VimdicatorWindow) {
+    let controller = gtk::EventControllerMotion::new();
+
+    controller.connect_enter(glib::clone!(@weak window => move |_, _, _| {
+        if window.imp().mousefocus.get() {
+            window.ext_line_grid().grab_focus();
+        }
+    }));
+
+    window.ext_line_grid().add_controller(controller);
 }
 
 fn init_motion_controller(
     window: widgets::VimdicatorWindow,
-    tx: UnboundedSender<GtkToNvimEvent>,
+    tx: NvimBridge,
     mouse_state: Rc<MouseState>,
 ) {
     let motion_controller = gtk::EventControllerMotion::new();
@@ -151,12 +2735,17 @@ fn init_motion_controller(
 
             let pos = ext_line_grid.cell_metrics().cell_cords(x, y);
 
-            if y < 0.0 {
+            if y < 0.0 && window.imp().presentation.get().is_none() {
                 window.header_bar_revealer().set_reveal_child(true);
             } else {
                 window.header_bar_revealer().set_reveal_child(false);
             }
 
+            if mouse_state.selecting.get() {
+                ext_line_grid.update_selection(x, y);
+                return;
+            }
+
             let pos = if Some(pos) != mouse_state.pos.get() {
                 mouse_state.pos.set(Some(pos));
                 Some(pos)
@@ -182,50 +2771,137 @@ fn init_motion_controller(
 
 fn init_scroll_controller(
     ext_line_grid: widgets::ExtLineGrid,
-    tx: UnboundedSender<GtkToNvimEvent>,
+    tx: NvimBridge,
     mouse_state: Rc<MouseState>,
 ) {
-    let scroll_controller = gtk::EventControllerScroll::new(
-        gtk::EventControllerScrollFlags::VERTICAL | gtk::EventControllerScrollFlags::DISCRETE,
-    );
+    let scroll_controller =
+        gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::BOTH_AXES);
+
+    scroll_controller.connect_scroll_begin({
+        let mouse_state = mouse_state.clone();
+        move |_| mouse_state.reset_scroll()
+    });
+
+    let settings = settings::settings();
+    let ext_line_grid_weak = ext_line_grid.downgrade();
+
+    scroll_controller.connect_scroll(move |controller, dx, dy| {
+        let Some(ext_line_grid) = ext_line_grid_weak.upgrade() else {
+            return gtk::Inhibit(false);
+        };
 
-    let grid = ext_line_grid.grid_id();
-    scroll_controller.connect_scroll(move |controller, _dx, dy| {
-        let dy = dy.round();
+        let speed = f64::from(settings.uint("scroll-speed").max(1));
+        let direction = if settings.boolean("scroll-natural") {
+            -1.0
+        } else {
+            1.0
+        };
+        let dx = if settings.boolean("scroll-horizontal") {
+            dx
+        } else {
+            0.0
+        };
 
-        let action = match dy.total_cmp(&0.0) {
-            std::cmp::Ordering::Less => NvimMouseAction::Up,
-            std::cmp::Ordering::Greater => NvimMouseAction::Down,
-            std::cmp::Ordering::Equal => return gtk::Inhibit(false),
+        // Wheel ticks send `scroll-speed` events each, touchpads one per cell swiped
+        let (dx, dy) = match controller.unit() {
+            gdk::ScrollUnit::Surface => {
+                let cell_metrics = ext_line_grid.cell_metrics();
+                (
+                    dx / cell_metrics.char_width.max(1.0),
+                    dy / cell_metrics.line_height.max(1.0),
+                )
+            }
+            _ => (dx * speed, dy * speed),
         };
 
+        let (steps_x, steps_y) = mouse_state.scroll_steps(dx * direction, dy * direction);
+
         let state = controller.current_event_state();
-        let modifier = crate::input::keyval_to_input_string("", state);
 
-        let dy = dy.abs() as usize;
+        // Shift+wheel browses the GUI scrollback, any wheel does once it is open
+        let history = ext_line_grid.is_scrolled_back()
+            || (state.contains(gdk::ModifierType::SHIFT_MASK)
+                && settings.uint("scrollback-lines") > 0);
+        if history {
+            ext_line_grid.scroll_history(-steps_y as isize);
+            return gtk::Inhibit(true);
+        }
+
+        let modifier = crate::input::keyval_to_input_string("", state);
 
+        let grid = ext_line_grid.grid_id();
         let pos = mouse_state.pos.get();
 
-        for _ in 0..dy {
-            tx.send(GtkToNvimEvent::InputMouse {
-                button: NvimMouseButton::Wheel,
-                action,
-                modifier: modifier.clone(),
-                grid,
-                pos,
-            })
-            .unwrap();
+        let vertical = if steps_y < 0 {
+            NvimMouseAction::Up
+        } else {
+            NvimMouseAction::Down
+        };
+        let horizontal = if steps_x < 0 {
+            NvimMouseAction::Left
+        } else {
+            NvimMouseAction::Right
+        };
+
+        for (action, steps) in [(vertical, steps_y), (horizontal, steps_x)] {
+            for _ in 0..steps.unsigned_abs() {
+                tx.send(GtkToNvimEvent::InputMouse {
+                    button: NvimMouseButton::Wheel,
+                    action,
+                    modifier: modifier.clone(),
+                    grid,
+                    pos,
+                })
+                .unwrap();
+            }
         }
 
-        gtk::Inhibit(false)
+        gtk::Inhibit(steps_x != 0 || steps_y != 0)
     });
 
     ext_line_grid.add_controller(scroll_controller);
 }
 
+/// Pinching zooms the font or sends keys for mappings, see the `pinch-action` setting
+fn init_pinch_controller(window: &widgets::VimdicatorWindow, tx: NvimBridge) {
+    let zoom_controller = gtk::GestureZoom::new();
+    let pinch = Rc::new(Cell::new(PinchAccumulator::default()));
+
+    zoom_controller.connect_begin({
+        let pinch = pinch.clone();
+        move |_, _| pinch.set(PinchAccumulator::default())
+    });
+
+    let settings = settings::settings();
+    zoom_controller.connect_scale_changed(glib::clone!(@weak window => move |_, scale| {
+        let mut accumulator = pinch.get();
+        let steps = accumulator.accumulate(scale);
+        pinch.set(accumulator);
+        if steps == 0 {
+            return;
+        }
+
+        match settings.string("pinch-action").as_str() {
+            "zoom" => window.zoom(steps),
+            "keys" => {
+                let key = if steps > 0 {
+                    "<Plug>(VimdicatorScaleUp)"
+                } else {
+                    "<Plug>(VimdicatorScaleDown)"
+                };
+                tx.send(GtkToNvimEvent::Input(key.repeat(steps.unsigned_abs() as usize)))
+                    .unwrap();
+            }
+            _ => {}
+        }
+    }));
+
+    window.ext_line_grid().add_controller(zoom_controller);
+}
+
 fn init_gesture_controller(
     ext_line_grid: widgets::ExtLineGrid,
-    tx: UnboundedSender<GtkToNvimEvent>,
+    tx: NvimBridge,
     mouse_state: Rc<MouseState>,
 ) {
     let click_controller = gtk::GestureClick::builder().n_points(1).button(0).build();
@@ -247,7 +2923,17 @@ fn init_gesture_controller(
             mouse_state.pos.set(Some(pos));
 
             match btn {
+                1 if ext_line_grid.is_scrolled_back()
+                    || state.contains(
+                        gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+                    ) =>
+                {
+                    let block = state.contains(gdk::ModifierType::ALT_MASK);
+                    ext_line_grid.start_selection(x, y, block);
+                    mouse_state.selecting.set(true);
+                }
                 1 => {
+                    ext_line_grid.clear_selection();
                     mouse_state.is_pressed.set(true);
 
                     tx.send(GtkToNvimEvent::InputMouse {
@@ -281,6 +2967,14 @@ fn init_gesture_controller(
             mouse_state.pos.set(Some(pos));
 
             match btn {
+                1 if mouse_state.selecting.get() => {
+                    mouse_state.selecting.set(false);
+                    ext_line_grid.update_selection(x, y);
+
+                    if let Some(text) = ext_line_grid.selection_text() {
+                        ext_line_grid.primary_clipboard().set_text(&text);
+                    }
+                }
                 1 => {
                     mouse_state.is_pressed.set(false);
 